@@ -194,6 +194,7 @@ impl Compile {
 					}
 				}
 				Some("json") | Some("dzn") => data.push(f.clone()),
+				None if f.as_os_str() == shackle::STDIN_PATH => data.push(f.clone()),
 				_ => {
 					return Err(Report::msg(format!(
 						"file `{}' has an unsupported file type",
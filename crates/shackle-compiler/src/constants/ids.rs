@@ -95,6 +95,14 @@ id_registry!(
 	minus: "-",
 	plus: "+",
 	times: "*",
+	div,
+	mod_: "mod",
+	ne: "!=",
+	lt: "<",
+	le: "<=",
+	gt: ">",
+	ge: ">=",
+	not,
 	sum,
 	product,
 	erase_enum,
@@ -133,4 +141,9 @@ id_registry!(
 	mzn_show_record_access,
 	return_value: "<return value>",
 	mzn_inline_call_by_name,
+	int_search,
+	bool_search,
+	seq_search,
+	input_order,
+	indomain_min,
 );
@@ -10,12 +10,22 @@ use std::{
 	sync::Arc,
 };
 
+use miette::SourceSpan;
+
 use crate::{
 	constants::TypeRegistry,
-	diagnostics::FileError,
-	file::{DefaultFileHandler, FileHandler, FileRef, FileRefData, InputFile, ModelRef},
-	hir::db::{Hir, HirStorage},
-	syntax::db::{SourceParser, SourceParserStorage},
+	diagnostics::{Error, FileError, InvalidSettings, UnknownSetting, Warning},
+	file::{
+		DefaultFileHandler, FileHandler, FileRef, FileRefData, InputFile, ModelRef, SourceFile,
+	},
+	hir::{
+		cache::deserialize_lowered,
+		db::{Hir, HirStorage},
+	},
+	syntax::db::{
+		HasIncrementalParses, IncrementalParses, ParseContentsQuery, SourceParser,
+		SourceParserStorage, PARSE_CONTENTS_LRU_CAPACITY,
+	},
 	thir::db::{Thir, ThirStorage},
 	ty::{NewType, NewTypeData, Ty, TyData},
 };
@@ -52,6 +62,32 @@ pub trait CompilerSettings: Inputs {
 
 	/// Get all of the directories to search for includes
 	fn include_search_dirs(&self) -> Arc<Vec<PathBuf>>;
+
+	/// Set whether to fold constant arithmetic expressions during lowering
+	#[salsa::input]
+	fn fold_constants(&self) -> bool;
+
+	/// Set whether to warn on uses of `==` (which is equivalent to `=`)
+	#[salsa::input]
+	fn warn_double_equals(&self) -> bool;
+
+	/// Set whether a constraint which is always false is an error
+	///
+	/// If not, then a warning is given instead.
+	#[salsa::input]
+	fn unsatisfiable_constraint_error(&self) -> bool;
+
+	/// Set whether reversed ranges (e.g. `5..1`) in data files are allowed
+	///
+	/// If allowed, reversed ranges are empty sets (and a warning is given), otherwise they are an error.
+	#[salsa::input]
+	fn allow_reversed_ranges(&self) -> bool;
+
+	/// Set the maximum nesting depth of expressions
+	///
+	/// Expressions nested more deeply than this are a syntax error.
+	#[salsa::input]
+	fn max_expression_depth(&self) -> usize;
 }
 
 fn share_directory(db: &dyn CompilerSettings) -> crate::Result<Arc<PathBuf>> {
@@ -186,6 +222,7 @@ impl From<InternedStringData> for String {
 pub struct CompilerDatabase {
 	storage: salsa::Storage<CompilerDatabase>,
 	file_handler: Box<dyn FileHandler + RefUnwindSafe>,
+	incremental_parses: IncrementalParses,
 }
 
 impl Default for CompilerDatabase {
@@ -200,6 +237,7 @@ impl CompilerDatabase {
 		let mut db = Self {
 			storage: Default::default(),
 			file_handler,
+			incremental_parses: IncrementalParses::default(),
 		};
 		let stdlib_dir = std::env::var("MZN_STDLIB_DIR")
 			.ok()
@@ -209,9 +247,122 @@ impl CompilerDatabase {
 		db.set_globals_directory(None);
 		db.set_search_directories(Arc::new(Vec::new()));
 		db.set_ignore_stdlib(false);
+		db.set_fold_constants(false);
+		db.set_warn_double_equals(false);
+		db.set_unsatisfiable_constraint_error(false);
+		db.set_allow_reversed_ranges(true);
+		db.set_max_expression_depth(100_000);
+		db.set_cached_models(Default::default());
+		ParseContentsQuery
+			.in_db_mut(&mut db)
+			.set_lru_capacity(PARSE_CONTENTS_LRU_CAPACITY);
 		db
 	}
 
+	/// Load compiler settings from a JSON configuration file (modelled on MiniZinc's `.mpc` files)
+	///
+	/// The supported keys are `includePaths` (an array of search directories), `stdlibDir`,
+	/// `globalsDir` and `ignoreStdlib`. Relative `includePaths` and `stdlibDir` are resolved
+	/// against the directory containing the configuration file. Unknown keys are ignored, and a
+	/// warning is returned for each of them.
+	///
+	/// The whole file is checked before any settings are changed, so no settings are changed if
+	/// the file is invalid.
+	pub fn load_settings(&mut self, path: &Path) -> crate::Result<Vec<Warning>> {
+		let src = SourceFile::try_from(path)?;
+		let config: serde_json::Value =
+			serde_json::from_str(src.contents()).map_err(|e| Error::from_serde_json(e, &src))?;
+		let key_span = |key: &str| -> SourceSpan {
+			src.contents()
+				.find(&format!("\"{}\"", key))
+				.map(|start| (start, key.len() + 2))
+				.unwrap_or((0, src.contents().len()))
+				.into()
+		};
+		let invalid = |key: &str, expected: &str| -> Error {
+			InvalidSettings {
+				src: src.clone(),
+				msg: format!("Expected {} for '{}'", expected, key),
+				span: key_span(key),
+			}
+			.into()
+		};
+		let config = config.as_object().ok_or_else(|| InvalidSettings {
+			src: src.clone(),
+			msg: "Expected settings object".to_owned(),
+			span: (0, src.contents().len()).into(),
+		})?;
+		let base = path.parent().unwrap_or_else(|| Path::new(""));
+		let mut search_directories = None;
+		let mut stdlib_directory = None;
+		let mut globals_directory = None;
+		let mut ignore_stdlib = None;
+		let mut warnings = Vec::new();
+		for (key, value) in config.iter() {
+			match key.as_str() {
+				"includePaths" => {
+					let dirs = value
+						.as_array()
+						.and_then(|dirs| {
+							dirs.iter()
+								.map(|d| d.as_str().map(|d| base.join(d)))
+								.collect::<Option<Vec<_>>>()
+						})
+						.ok_or_else(|| invalid(key, "array of strings"))?;
+					search_directories = Some(dirs);
+				}
+				"stdlibDir" => {
+					let dir = value.as_str().ok_or_else(|| invalid(key, "string"))?;
+					stdlib_directory = Some(base.join(dir));
+				}
+				"globalsDir" => {
+					let dir = value.as_str().ok_or_else(|| invalid(key, "string"))?;
+					globals_directory = Some(PathBuf::from(dir));
+				}
+				"ignoreStdlib" => {
+					let ignore = value.as_bool().ok_or_else(|| invalid(key, "boolean"))?;
+					ignore_stdlib = Some(ignore);
+				}
+				_ => warnings.push(
+					UnknownSetting {
+						src: src.clone(),
+						key: key.clone(),
+						span: key_span(key),
+					}
+					.into(),
+				),
+			}
+		}
+
+		if let Some(dirs) = search_directories {
+			self.set_search_directories(Arc::new(dirs));
+		}
+		if let Some(dir) = stdlib_directory {
+			self.set_stdlib_directory(Some(Arc::new(dir)));
+		}
+		if let Some(dir) = globals_directory {
+			self.set_globals_directory(Some(Arc::new(dir)));
+		}
+		if let Some(ignore) = ignore_stdlib {
+			self.set_ignore_stdlib(ignore);
+		}
+		Ok(warnings)
+	}
+
+	/// Load a lowered model serialized using [`crate::hir::cache::serialize_lowered`], so that it
+	/// is used instead of lowering the model from source.
+	///
+	/// Fails if the data is invalid, or if the model's source text or the compiler settings have
+	/// changed since it was serialized. The cached model is ignored if the source text changes
+	/// after loading.
+	pub fn load_lowered(&mut self, model: ModelRef, bytes: &[u8]) -> crate::Result<()> {
+		let cached = deserialize_lowered(self, model, bytes)?;
+		let mut models = (*self.cached_models()).clone();
+		models.insert(model, Arc::new(cached));
+		self.set_cached_models(Arc::new(models));
+		Ok(())
+	}
+
 	/// Snapshot the database
 	pub fn snapshot(&self) -> salsa::Snapshot<Self> {
 		salsa::ParallelDatabase::snapshot(self)
@@ -237,6 +388,7 @@ impl salsa::ParallelDatabase for CompilerDatabase {
 		salsa::Snapshot::new(Self {
 			storage: self.storage.snapshot(),
 			file_handler: self.file_handler.snapshot(),
+			incremental_parses: self.incremental_parses.clone(),
 		})
 	}
 }
@@ -265,6 +417,12 @@ impl HasFileHandler for CompilerDatabase {
 	}
 }
 
+impl HasIncrementalParses for CompilerDatabase {
+	fn incremental_parses(&self) -> &IncrementalParses {
+		&self.incremental_parses
+	}
+}
+
 /// Trait for upcasting the database
 pub trait Upcast<T: ?Sized> {
 	/// Perform upcast
@@ -289,3 +447,48 @@ impl_upcast!(CompilerDatabase, SourceParser);
 impl_upcast!(CompilerDatabase, Interner);
 impl_upcast!(CompilerDatabase, Hir);
 impl_upcast!(CompilerDatabase, Thir);
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use super::{CompilerDatabase, CompilerSettings, Inputs, Warning};
+
+	#[test]
+	fn test_load_settings() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("settings.mpc");
+		std::fs::write(
+			&path,
+			r#"{
+				"includePaths": ["a", "b"],
+				"stdlibDir": "share/minizinc",
+				"ignoreStdlib": true,
+				"unknownKey": 1
+			}"#,
+		)
+		.unwrap();
+		let mut db = CompilerDatabase::default();
+		let warnings = db.load_settings(&path).unwrap();
+		assert_eq!(warnings.len(), 1);
+		let Warning::UnknownSetting(w) = &warnings[0] else {
+			panic!("expected unknown setting warning")
+		};
+		assert_eq!(w.key, "unknownKey");
+		assert!(db.ignore_stdlib());
+		assert_eq!(
+			db.include_search_dirs(),
+			Arc::new(vec![
+				dir.path().join("a"),
+				dir.path().join("b"),
+				dir.path().join("share/minizinc").join("std"),
+			])
+		);
+
+		// Settings before the invalid one are not applied
+		std::fs::write(&path, r#"{"ignoreStdlib": false, "stdlibDir": 1}"#).unwrap();
+		let err = db.load_settings(&path).unwrap_err();
+		assert_eq!(err.code(), "shackle::invalid_settings");
+		assert!(db.ignore_stdlib());
+	}
+}
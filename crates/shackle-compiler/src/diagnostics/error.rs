@@ -48,7 +48,7 @@ impl Display for InternalError {
 /// Multiple errors
 #[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
 #[error("Multiple errors")]
-#[diagnostic()]
+#[diagnostic(code(shackle::multiple_errors))]
 pub struct MultipleErrors {
 	/// The errors
 	#[related]
@@ -134,6 +134,19 @@ pub struct AdditionalSolveItem {
 	pub span: SourceSpan,
 }
 
+/// Constraint which is always false
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Model is unsatisfiable")]
+#[diagnostic(code(shackle::unsatisfiable_constraint))]
+pub struct UnsatisfiableConstraint {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the constraint
+	#[label("This constraint is always false")]
+	pub span: SourceSpan,
+}
+
 /// Multiple assignments to same variable error
 #[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
 #[error("The variable '{variable}' is already assigned")]
@@ -213,6 +226,27 @@ pub struct IdentifierAlreadyDefined {
 	pub identifier: String,
 }
 
+/// A reference to a let declaration which has not been declared yet
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Identifier used before its declaration")]
+#[diagnostic(
+	code(shackle::forward_reference),
+	help("Declarations in a let expression can only refer to earlier declarations.")
+)]
+pub struct ForwardReference {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the reference
+	#[label("{identifier} is used here")]
+	pub span: SourceSpan,
+	/// The span of the later declaration
+	#[label("{identifier} is declared here")]
+	pub declaration: SourceSpan,
+	/// The identifier which is referenced
+	pub identifier: String,
+}
+
 /// An invalid pattern error
 #[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
 #[error("Invalid pattern used")]
@@ -258,10 +292,44 @@ pub struct TypeMismatch {
 	pub span: SourceSpan,
 }
 
+/// A predicate called where a par bool is required
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Predicate called in a par context")]
+#[diagnostic(
+	code(shackle::predicate_in_par_context),
+	help("Predicates always return 'var bool'. If '{name}' should be par, try declaring it as a test instead.")
+)]
+pub struct PredicateInParContext {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The name of the predicate
+	pub name: String,
+	/// The span of the predicate call
+	#[label("Expected 'bool' but '{name}' returns 'var bool'")]
+	pub span: SourceSpan,
+}
+
+/// A range with both endpoints infinite
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Range cannot have two infinite endpoints")]
+#[diagnostic(
+	code(shackle::infinite_range),
+	help("At most one endpoint of a range can be 'infinity'.")
+)]
+pub struct InfiniteRange {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the range
+	#[label("This range has no finite endpoint")]
+	pub span: SourceSpan,
+}
+
 /// A mismatch in branch/arm types
 #[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
 #[error("Type mismatch")]
-#[diagnostic(code(shackle::type_mismatch))]
+#[diagnostic(code(shackle::branch_mismatch))]
 pub struct BranchMismatch {
 	/// The source code
 	#[source_code]
@@ -445,6 +513,24 @@ pub struct InvalidFieldAccess {
 	pub span: SourceSpan,
 }
 
+/// Duplicate field in a record literal or record type
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Duplicate record field")]
+#[diagnostic(
+	code(shackle::duplicate_record_field),
+	help("Try removing or renaming this field.")
+)]
+pub struct DuplicateRecordField {
+	/// The name of the field
+	pub field: String,
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span associated with the error
+	#[label("Field {field} is already defined")]
+	pub span: SourceSpan,
+}
+
 /// Non-exhaustive case expression pattern matching
 #[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
 #[error("Non-exhaustive pattern matching")]
@@ -490,6 +576,153 @@ pub struct TypeSpecialisationRecursionLimit {
 	pub span: SourceSpan,
 }
 
+/// Reversed range in a data file
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Reversed range")]
+#[diagnostic(code(shackle::reversed_range))]
+pub struct ReversedRange {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The reversed range
+	pub range: String,
+	/// The span associated with the error
+	#[label("The range {range} is reversed")]
+	pub span: SourceSpan,
+}
+
+/// A set bound in a data file is not a constant literal
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Invalid set bound")]
+#[diagnostic(
+	code(shackle::invalid_set_bound),
+	help("Set bounds in data must be constant literals of the element type.")
+)]
+pub struct InvalidSetBound {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The element type of the set
+	pub ty: String,
+	/// The span of the bound
+	#[label("Expected a constant '{ty}' literal")]
+	pub span: SourceSpan,
+}
+
+/// A value could not be converted into the requested type
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Expected {expected} but found {value}")]
+#[diagnostic(code(shackle::value_conversion))]
+pub struct ValueConversionError {
+	/// The value which could not be converted
+	pub value: String,
+	/// The type that was requested
+	pub expected: String,
+}
+
+/// A value could not be constructed because its parts are invalid
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Invalid value: {msg}")]
+#[diagnostic(code(shackle::invalid_value))]
+pub struct InvalidValue {
+	/// Description of the problem
+	pub msg: String,
+}
+
+/// Data was given in a language which cannot be used for data
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("{lang} is not a supported language for {input}")]
+#[diagnostic(
+	code(shackle::unsupported_data_language),
+	help("The supported languages are {supported}.")
+)]
+pub struct UnsupportedDataLanguage {
+	/// The language which was given
+	pub lang: String,
+	/// Description of the data input
+	pub input: String,
+	/// The languages which are supported for the data input
+	pub supported: String,
+}
+
+/// A constant is defined before a parameter with the same name is declared
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("The constant '{name}' redefines a parameter")]
+#[diagnostic(
+	code(shackle::parameter_redefinition),
+	help("Declare the parameter before giving it a value.")
+)]
+pub struct ParameterRedefinition {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The name of the constant and parameter
+	pub name: String,
+	/// The span of the constant definition
+	#[label("{name} is defined here")]
+	pub span: SourceSpan,
+	/// The span of the parameter declaration
+	#[label("{name} is declared as a parameter here")]
+	pub parameter: SourceSpan,
+}
+
+/// An enum with no definition is used as an array index set
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("The enum '{name}' has no definition but is used as an array index set")]
+#[diagnostic(
+	code(shackle::undefined_enum_index_set),
+	help("Give '{name}' a definition in the model, or assign it in the data.")
+)]
+pub struct UndefinedEnumIndexSet {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The name of the enum
+	pub name: String,
+	/// The span of the index set
+	#[label("{name} is used as an index set here")]
+	pub span: SourceSpan,
+}
+
+/// A serialized lowered model could not be loaded
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Invalid cached model: {msg}")]
+#[diagnostic(code(shackle::invalid_cache))]
+pub struct InvalidCache {
+	/// Description of the problem
+	pub msg: String,
+}
+
+/// An identifier could not be renamed
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Cannot rename identifier")]
+#[diagnostic(code(shackle::invalid_rename))]
+pub struct InvalidRename {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The error message
+	pub msg: String,
+	/// The span associated with the error
+	#[label("{msg}")]
+	pub span: SourceSpan,
+}
+
+/// A compiler settings file is invalid
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Invalid settings")]
+#[diagnostic(code(shackle::invalid_settings))]
+pub struct InvalidSettings {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The error message
+	pub msg: String,
+	/// The span associated with the error
+	#[label("{msg}")]
+	pub span: SourceSpan,
+}
+
 /// Main Shackle error type
 #[derive(Error, Diagnostic, Debug, PartialEq, Eq, Clone)]
 pub enum Error {
@@ -517,6 +750,10 @@ pub enum Error {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	MultipleSolveItems(#[from] MultipleSolveItems),
+	/// Constraint which is always false
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	UnsatisfiableConstraint(#[from] UnsatisfiableConstraint),
 	/// Multiple assignments to same variable
 	#[error(transparent)]
 	#[diagnostic(transparent)]
@@ -533,6 +770,10 @@ pub enum Error {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	UndefinedIdentifier(#[from] UndefinedIdentifier),
+	/// Reference to a later let declaration
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	ForwardReference(#[from] ForwardReference),
 	/// Invalid pattern
 	#[error(transparent)]
 	#[diagnostic(transparent)]
@@ -545,6 +786,14 @@ pub enum Error {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	TypeMismatch(#[from] TypeMismatch),
+	/// Predicate called in a par context
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	PredicateInParContext(#[from] PredicateInParContext),
+	/// Range with two infinite endpoints
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	InfiniteRange(#[from] InfiniteRange),
 	/// Branch mismatch
 	#[error(transparent)]
 	#[diagnostic(transparent)]
@@ -581,6 +830,10 @@ pub enum Error {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	InvalidFieldAccess(#[from] InvalidFieldAccess),
+	/// Duplicate record field
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	DuplicateRecordField(#[from] DuplicateRecordField),
 	/// Non-exhaustive pattern matching
 	#[error(transparent)]
 	#[diagnostic(transparent)]
@@ -593,8 +846,49 @@ pub enum Error {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	TypeSpecialisationRecursionLimit(#[from] TypeSpecialisationRecursionLimit),
+	/// Reversed range in a data file
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	ReversedRange(#[from] ReversedRange),
+	/// Non-literal set bound in a data file
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	InvalidSetBound(#[from] InvalidSetBound),
+	/// Value conversion error
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	ValueConversionError(#[from] ValueConversionError),
+	/// Invalid value construction
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	InvalidValue(#[from] InvalidValue),
+	/// Data given in an unsupported language
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	UnsupportedDataLanguage(#[from] UnsupportedDataLanguage),
+	/// Constant defined before a parameter of the same name
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	ParameterRedefinition(#[from] ParameterRedefinition),
+	/// An enum with no definition is used as an array index set
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	UndefinedEnumIndexSet(#[from] UndefinedEnumIndexSet),
+	/// Invalid rename of an identifier
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	InvalidRename(#[from] InvalidRename),
+	/// Invalid serialized lowered model
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	InvalidCache(#[from] InvalidCache),
+	/// Invalid compiler settings file
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	InvalidSettings(#[from] InvalidSettings),
 	/// An internal error
 	#[error("Internal Error - Please report this issue to the Shackle developers")]
+	#[diagnostic(code(shackle::internal_error))]
 	InternalError(#[from] InternalError),
 }
 
@@ -632,6 +926,102 @@ impl TryFrom<Diagnostics<Error>> for Error {
 }
 
 impl Error {
+	/// Get the stable code identifying the kind of this error.
+	///
+	/// This is the same code shown when the error is rendered.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Error::MultipleErrors(_) => "shackle::multiple_errors",
+			Error::FileError(_) => "shackle::io_error",
+			Error::SyntaxError(_) => "shackle::syntax_error",
+			Error::StandardLibraryNotFound => "shackle::stdlib_not_found",
+			Error::IncludeError(_) => "shackle::include_error",
+			Error::MultipleSolveItems(_) => "shackle::multiple_solve_items",
+			Error::UnsatisfiableConstraint(_) => "shackle::unsatisfiable_constraint",
+			Error::MultipleAssignments(_) => "shackle::multiple_assignments",
+			Error::CyclicDefinition(_) => "shackle::cyclic_definition",
+			Error::IdentifierAlreadyDefined(_) => "shackle::identifier_already_defined",
+			Error::UndefinedIdentifier(_) => "shackle::undefined_identifier",
+			Error::ForwardReference(_) => "shackle::forward_reference",
+			Error::InvalidPattern(_) => "shackle::invalid_pattern",
+			Error::IllegalType(_) => "shackle::illegal_type",
+			Error::TypeMismatch(_) => "shackle::type_mismatch",
+			Error::PredicateInParContext(_) => "shackle::predicate_in_par_context",
+			Error::InfiniteRange(_) => "shackle::infinite_range",
+			Error::BranchMismatch(_) => "shackle::branch_mismatch",
+			Error::InvalidArrayLiteral(_) => "shackle::invalid_array_literal",
+			Error::NoMatchingFunction(_) => "shackle::no_matching_fn",
+			Error::AmbiguousCall(_) => "shackle::ambiguous_call",
+			Error::IllegalOverloading(_) => "shackle::illegal_overload",
+			Error::FunctionAlreadyDefined(_) => "shackle::function_already_defined",
+			Error::ConstructorAlreadyDefined(_) => "shackle::constructor_already_defined",
+			Error::TypeInferenceFailure(_) => "shackle::type_inference_failure",
+			Error::InvalidFieldAccess(_) => "shackle::invalid_field_access",
+			Error::DuplicateRecordField(_) => "shackle::duplicate_record_field",
+			Error::NonExhaustivePatternMatching(_) => "shackle::non_exhaustive_pattern_matching",
+			Error::InvalidNumericLiteral(_) => "shackle::invalid_numeric_literal",
+			Error::TypeSpecialisationRecursionLimit(_) => "shackle::instantiation_recursion_limit",
+			Error::ReversedRange(_) => "shackle::reversed_range",
+			Error::InvalidSetBound(_) => "shackle::invalid_set_bound",
+			Error::ValueConversionError(_) => "shackle::value_conversion",
+			Error::InvalidValue(_) => "shackle::invalid_value",
+			Error::UnsupportedDataLanguage(_) => "shackle::unsupported_data_language",
+			Error::ParameterRedefinition(_) => "shackle::parameter_redefinition",
+			Error::UndefinedEnumIndexSet(_) => "shackle::undefined_enum_index_set",
+			Error::InvalidRename(_) => "shackle::invalid_rename",
+			Error::InvalidCache(_) => "shackle::invalid_cache",
+			Error::InvalidSettings(_) => "shackle::invalid_settings",
+			Error::InternalError(_) => "shackle::internal_error",
+		}
+	}
+
+	/// Get the source file and primary span of this error, if it has one
+	pub fn source_span(&self) -> Option<(&SourceFile, SourceSpan)> {
+		match self {
+			Error::SyntaxError(e) => Some((&e.src, e.span)),
+			Error::IncludeError(e) => Some((&e.src, e.span)),
+			Error::MultipleSolveItems(e) => Some((&e.src, e.span)),
+			Error::UnsatisfiableConstraint(e) => Some((&e.src, e.span)),
+			Error::MultipleAssignments(e) => Some((&e.src, e.span)),
+			Error::CyclicDefinition(e) => Some((&e.src, e.span)),
+			Error::IdentifierAlreadyDefined(e) => Some((&e.src, e.span)),
+			Error::UndefinedIdentifier(e) => Some((&e.src, e.span)),
+			Error::ForwardReference(e) => Some((&e.src, e.span)),
+			Error::InvalidPattern(e) => Some((&e.src, e.span)),
+			Error::IllegalType(e) => Some((&e.src, e.span)),
+			Error::TypeMismatch(e) => Some((&e.src, e.span)),
+			Error::PredicateInParContext(e) => Some((&e.src, e.span)),
+			Error::InfiniteRange(e) => Some((&e.src, e.span)),
+			Error::BranchMismatch(e) => Some((&e.src, e.span)),
+			Error::InvalidArrayLiteral(e) => Some((&e.src, e.span)),
+			Error::NoMatchingFunction(e) => Some((&e.src, e.span)),
+			Error::AmbiguousCall(e) => Some((&e.src, e.span)),
+			Error::IllegalOverloading(e) => Some((&e.src, e.span)),
+			Error::FunctionAlreadyDefined(e) => Some((&e.src, e.span)),
+			Error::ConstructorAlreadyDefined(e) => Some((&e.src, e.span)),
+			Error::TypeInferenceFailure(e) => Some((&e.src, e.span)),
+			Error::InvalidFieldAccess(e) => Some((&e.src, e.span)),
+			Error::DuplicateRecordField(e) => Some((&e.src, e.span)),
+			Error::NonExhaustivePatternMatching(e) => Some((&e.src, e.span)),
+			Error::InvalidNumericLiteral(e) => Some((&e.src, e.span)),
+			Error::TypeSpecialisationRecursionLimit(e) => Some((&e.src, e.span)),
+			Error::ReversedRange(e) => Some((&e.src, e.span)),
+			Error::InvalidSetBound(e) => Some((&e.src, e.span)),
+			Error::ParameterRedefinition(e) => Some((&e.src, e.span)),
+			Error::UndefinedEnumIndexSet(e) => Some((&e.src, e.span)),
+			Error::InvalidRename(e) => Some((&e.src, e.span)),
+			Error::InvalidSettings(e) => Some((&e.src, e.span)),
+			Error::MultipleErrors(_)
+			| Error::FileError(_)
+			| Error::StandardLibraryNotFound
+			| Error::ValueConversionError(_)
+			| Error::InvalidValue(_)
+			| Error::UnsupportedDataLanguage(_)
+			| Error::InvalidCache(_)
+			| Error::InternalError(_) => None,
+		}
+	}
+
 	/// Parse an error from JSON
 	pub fn from_serde_json(err: serde_json::Error, src: &SourceFile) -> Self {
 		use serde_json::error::Category;
@@ -662,3 +1052,302 @@ impl Error {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use expect_test::expect;
+	use rustc_hash::FxHashSet;
+
+	use super::*;
+
+	#[test]
+	fn test_error_codes() {
+		let src = SourceFile::introduced("test.mzn");
+		let span = SourceSpan::from((0, 0));
+		let msg = String::new();
+		let errors: Vec<Error> = vec![
+			MultipleErrors { errors: Vec::new() }.into(),
+			FileError {
+				file: PathBuf::from("test.mzn"),
+				message: msg.clone(),
+				other: Vec::new(),
+			}
+			.into(),
+			SyntaxError {
+				src: src.clone(),
+				span,
+				msg: msg.clone(),
+				other: Vec::new(),
+			}
+			.into(),
+			Error::StandardLibraryNotFound,
+			IncludeError {
+				include: msg.clone(),
+				src: src.clone(),
+				span,
+			}
+			.into(),
+			MultipleSolveItems {
+				src: src.clone(),
+				span,
+				others: Vec::new(),
+			}
+			.into(),
+			UnsatisfiableConstraint {
+				src: src.clone(),
+				span,
+			}
+			.into(),
+			MultipleAssignments {
+				src: src.clone(),
+				span,
+				variable: msg.clone(),
+				others: Vec::new(),
+			}
+			.into(),
+			CyclicDefinition {
+				variable: msg.clone(),
+				src: src.clone(),
+				span,
+			}
+			.into(),
+			IdentifierAlreadyDefined {
+				src: src.clone(),
+				span,
+				identifier: msg.clone(),
+			}
+			.into(),
+			UndefinedIdentifier {
+				src: src.clone(),
+				span,
+				identifier: msg.clone(),
+			}
+			.into(),
+			ForwardReference {
+				src: src.clone(),
+				span,
+				declaration: span,
+				identifier: msg.clone(),
+			}
+			.into(),
+			InvalidPattern {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			IllegalType {
+				src: src.clone(),
+				span,
+				ty: msg.clone(),
+			}
+			.into(),
+			TypeMismatch {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			PredicateInParContext {
+				src: src.clone(),
+				name: msg.clone(),
+				span,
+			}
+			.into(),
+			InfiniteRange {
+				src: src.clone(),
+				span,
+			}
+			.into(),
+			BranchMismatch {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+				original_span: span,
+			}
+			.into(),
+			InvalidArrayLiteral {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			NoMatchingFunction {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			AmbiguousCall {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			IllegalOverloading {
+				src: src.clone(),
+				span,
+				others: Vec::new(),
+			}
+			.into(),
+			FunctionAlreadyDefined {
+				src: src.clone(),
+				signature: msg.clone(),
+				span,
+				others: Vec::new(),
+			}
+			.into(),
+			ConstructorAlreadyDefined {
+				src: src.clone(),
+				span,
+				others: Vec::new(),
+			}
+			.into(),
+			TypeInferenceFailure {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			InvalidFieldAccess {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			DuplicateRecordField {
+				field: msg.clone(),
+				src: src.clone(),
+				span,
+			}
+			.into(),
+			NonExhaustivePatternMatching {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			InvalidNumericLiteral {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			TypeSpecialisationRecursionLimit {
+				src: src.clone(),
+				name: msg.clone(),
+				span,
+			}
+			.into(),
+			ReversedRange {
+				src: src.clone(),
+				range: msg.clone(),
+				span,
+			}
+			.into(),
+			InvalidSetBound {
+				src: src.clone(),
+				ty: msg.clone(),
+				span,
+			}
+			.into(),
+			ValueConversionError {
+				value: msg.clone(),
+				expected: msg.clone(),
+			}
+			.into(),
+			InvalidValue { msg: msg.clone() }.into(),
+			UnsupportedDataLanguage {
+				lang: msg.clone(),
+				input: msg.clone(),
+				supported: msg.clone(),
+			}
+			.into(),
+			ParameterRedefinition {
+				src: src.clone(),
+				name: msg.clone(),
+				span,
+				parameter: span,
+			}
+			.into(),
+			UndefinedEnumIndexSet {
+				src: src.clone(),
+				name: msg.clone(),
+				span,
+			}
+			.into(),
+			InvalidRename {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			InvalidCache { msg: msg.clone() }.into(),
+			InvalidSettings {
+				src: src.clone(),
+				msg: msg.clone(),
+				span,
+			}
+			.into(),
+			InternalError::new(msg).into(),
+		];
+
+		let mut seen = FxHashSet::default();
+		for e in errors.iter() {
+			// Code must match the one used when rendering
+			assert_eq!(
+				Diagnostic::code(e).map(|c| c.to_string()).as_deref(),
+				Some(e.code())
+			);
+			assert!(seen.insert(e.code()), "Duplicate code {}", e.code());
+		}
+
+		let codes = errors.iter().map(|e| e.code()).collect::<Vec<_>>();
+		expect![[r#"
+		    shackle::multiple_errors
+		    shackle::io_error
+		    shackle::syntax_error
+		    shackle::stdlib_not_found
+		    shackle::include_error
+		    shackle::multiple_solve_items
+		    shackle::unsatisfiable_constraint
+		    shackle::multiple_assignments
+		    shackle::cyclic_definition
+		    shackle::identifier_already_defined
+		    shackle::undefined_identifier
+		    shackle::forward_reference
+		    shackle::invalid_pattern
+		    shackle::illegal_type
+		    shackle::type_mismatch
+		    shackle::predicate_in_par_context
+		    shackle::infinite_range
+		    shackle::branch_mismatch
+		    shackle::invalid_array_literal
+		    shackle::no_matching_fn
+		    shackle::ambiguous_call
+		    shackle::illegal_overload
+		    shackle::function_already_defined
+		    shackle::constructor_already_defined
+		    shackle::type_inference_failure
+		    shackle::invalid_field_access
+		    shackle::duplicate_record_field
+		    shackle::non_exhaustive_pattern_matching
+		    shackle::invalid_numeric_literal
+		    shackle::instantiation_recursion_limit
+		    shackle::reversed_range
+		    shackle::invalid_set_bound
+		    shackle::value_conversion
+		    shackle::invalid_value
+		    shackle::unsupported_data_language
+		    shackle::parameter_redefinition
+		    shackle::undefined_enum_index_set
+		    shackle::invalid_rename
+		    shackle::invalid_cache
+		    shackle::invalid_settings
+		    shackle::internal_error
+		"#]]
+		.assert_eq(&codes.join("\n"));
+	}
+}
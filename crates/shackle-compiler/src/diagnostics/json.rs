@@ -0,0 +1,140 @@
+//! Machine-readable output of diagnostics as newline-delimited JSON
+//!
+//! Each diagnostic is output as a single line containing an object of the form
+//!
+//! ```json
+//! {"code":"shackle::type_mismatch","detail":"Expected 'int' but got 'float'","file":"model.mzn","help":null,"message":"Type mismatch","severity":"error","span":{"end":{"column":12,"line":0},"start":{"column":9,"line":0}}}
+//! ```
+//!
+//! The `message` is the title of the diagnostic, the `detail` is the text of its labels (one per
+//! line) and the `help` is its help text, which are `null` if not present.
+//!
+//! Lines and columns are zero-based, and the end of the span is exclusive
+//! (see [`SourceFile::span_line_cols`]). The `file` and `span` are `null` if the
+//! diagnostic is not associated with a location in a source file.
+
+use std::fmt::Write;
+
+use miette::{Diagnostic, SourceSpan};
+use serde_json::{json, Value};
+
+use super::{Diagnostics, Error, Warning};
+use crate::file::SourceFile;
+
+impl Diagnostics<Error> {
+	/// Render these errors as newline-delimited JSON objects
+	pub fn to_json_lines(&self) -> String {
+		let mut out = String::new();
+		for e in self.iter() {
+			write_error(&mut out, e);
+		}
+		out
+	}
+}
+
+impl Diagnostics<Warning> {
+	/// Render these warnings as newline-delimited JSON objects
+	pub fn to_json_lines(&self) -> String {
+		let mut out = String::new();
+		for w in self.iter() {
+			write_line(&mut out, "warning", w.code(), w, Some(w.source_span()));
+		}
+		out
+	}
+}
+
+fn write_error(out: &mut String, e: &Error) {
+	if let Error::MultipleErrors(es) = e {
+		for e in es.errors.iter() {
+			write_error(out, e);
+		}
+		return;
+	}
+	write_line(out, "error", e.code(), e, e.source_span());
+}
+
+fn write_line(
+	out: &mut String,
+	severity: &str,
+	code: &str,
+	diagnostic: &dyn Diagnostic,
+	location: Option<(&SourceFile, SourceSpan)>,
+) {
+	let detail = diagnostic
+		.labels()
+		.map(|labels| {
+			labels
+				.filter_map(|l| l.label().map(|l| l.to_owned()))
+				.collect::<Vec<_>>()
+				.join("\n")
+		})
+		.filter(|detail| !detail.is_empty());
+	let help = diagnostic.help().map(|help| help.to_string());
+	let (file, span) = match location {
+		Some((src, span)) => {
+			let (start_line, start_column, end_line, end_column) = src.span_line_cols(span);
+			(
+				src.name().map(Value::String).unwrap_or(Value::Null),
+				json!({
+					"start": { "line": start_line, "column": start_column },
+					"end": { "line": end_line, "column": end_column },
+				}),
+			)
+		}
+		None => (Value::Null, Value::Null),
+	};
+	let object = json!({
+		"file": file,
+		"span": span,
+		"severity": severity,
+		"code": code,
+		"message": diagnostic.to_string(),
+		"detail": detail,
+		"help": help,
+	});
+	writeln!(out, "{}", object).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use expect_test::expect;
+
+	use crate::{
+		diagnostics::{Diagnostics, DoubleEquals, Error, TypeMismatch, Warning},
+		file::SourceFile,
+	};
+
+	#[test]
+	fn test_json_lines() {
+		let src = SourceFile::stdin(Arc::new("int: x = 1.5;\nconstraint x == 1;\n".to_owned()));
+
+		let mut errors = Diagnostics::<Error>::default();
+		errors.push(
+			TypeMismatch {
+				src: src.clone(),
+				msg: "Expected 'int' but got 'float'".to_owned(),
+				span: (9, 3).into(),
+			}
+			.into(),
+		);
+		expect![[r#"
+		    {"code":"shackle::type_mismatch","detail":"Expected 'int' but got 'float'","file":"<stdin>","help":null,"message":"Type mismatch","severity":"error","span":{"end":{"column":12,"line":0},"start":{"column":9,"line":0}}}
+		"#]]
+		.assert_eq(&errors.to_json_lines());
+
+		let mut warnings = Diagnostics::<Warning>::default();
+		warnings.push(
+			DoubleEquals {
+				src,
+				span: (27, 2).into(),
+			}
+			.into(),
+		);
+		expect![[r#"
+		    {"code":"shackle::double_equals","detail":"== is equivalent to =","file":"<stdin>","help":"Try using = instead.","message":"Use of ==","severity":"warning","span":{"end":{"column":15,"line":1},"start":{"column":13,"line":1}}}
+		"#]]
+		.assert_eq(&warnings.to_json_lines());
+	}
+}
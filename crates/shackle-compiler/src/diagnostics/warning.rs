@@ -36,6 +36,117 @@ pub struct UnreachablePattern {
 	pub span: SourceSpan,
 }
 
+/// Recursive function without a terminating branch
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Unguarded recursion")]
+#[diagnostic(
+	code(shackle::unguarded_recursion),
+	severity(Warning),
+	help("Try adding an if-then-else or case branch which does not recurse.")
+)]
+pub struct UnguardedRecursion {
+	/// The name of the function
+	pub name: String,
+	/// The functions which form the recursive cycle
+	pub cycle: String,
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the function name
+	#[label("{name} recurses through {cycle} without a base case")]
+	pub span: SourceSpan,
+}
+
+/// Use of `==` instead of `=`
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Use of ==")]
+#[diagnostic(
+	code(shackle::double_equals),
+	severity(Warning),
+	help("Try using = instead.")
+)]
+pub struct DoubleEquals {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the operator
+	#[label("== is equivalent to =")]
+	pub span: SourceSpan,
+}
+
+/// Constraint which is a Boolean literal
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Constraint is always {value}")]
+#[diagnostic(code(shackle::constant_constraint), severity(Warning))]
+pub struct ConstantConstraint {
+	/// The value of the constraint
+	pub value: bool,
+	/// The effect of the constraint
+	pub effect: String,
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the constraint
+	#[label("{effect}")]
+	pub span: SourceSpan,
+}
+
+/// Reversed range in a data file, which is an empty set
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Empty range")]
+#[diagnostic(code(shackle::empty_range), severity(Warning))]
+pub struct EmptyRange {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The reversed range
+	pub range: String,
+	/// The span of the value containing the range
+	#[label("The range {range} is reversed, so it is an empty set")]
+	pub span: SourceSpan,
+}
+
+/// Item other than an assignment in a model file used as data
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Ignored data item")]
+#[diagnostic(code(shackle::ignored_data_item), severity(Warning))]
+pub struct IgnoredDataItem {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the item
+	#[label("Only assignment items are used from a model file given as data")]
+	pub span: SourceSpan,
+}
+
+/// Essence' heuristic which is not supported, and so is ignored
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Ignored heuristic")]
+#[diagnostic(code(shackle::ignored_heuristic), severity(Warning))]
+pub struct IgnoredHeuristic {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The span of the heuristic
+	#[label("Heuristics are not supported, so this heuristic is ignored")]
+	pub span: SourceSpan,
+}
+
+/// Unknown key in a compiler settings file, which is ignored
+#[derive(Error, Debug, Diagnostic, PartialEq, Eq, Clone)]
+#[error("Unknown setting")]
+#[diagnostic(code(shackle::unknown_setting), severity(Warning))]
+pub struct UnknownSetting {
+	/// The source code
+	#[source_code]
+	pub src: SourceFile,
+	/// The name of the setting
+	pub key: String,
+	/// The span of the setting's key
+	#[label("The setting '{key}' is not supported, so it is ignored")]
+	pub span: SourceSpan,
+}
+
 /// Shackle warning type
 #[derive(Error, Diagnostic, Debug, PartialEq, Eq, Clone)]
 pub enum Warning {
@@ -47,4 +158,66 @@ pub enum Warning {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	UnreachablePattern(#[from] UnreachablePattern),
+	/// Recursive function without a terminating branch
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	UnguardedRecursion(#[from] UnguardedRecursion),
+	/// Use of `==` instead of `=`
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	DoubleEquals(#[from] DoubleEquals),
+	/// Constraint which is a Boolean literal
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	ConstantConstraint(#[from] ConstantConstraint),
+	/// Reversed range in a data file
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	EmptyRange(#[from] EmptyRange),
+	/// Item ignored in a model file used as data
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	IgnoredDataItem(#[from] IgnoredDataItem),
+	/// Essence' heuristic which is ignored
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	IgnoredHeuristic(#[from] IgnoredHeuristic),
+	/// Unknown key in a compiler settings file
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	UnknownSetting(#[from] UnknownSetting),
+}
+
+impl Warning {
+	/// Get the stable code identifying the kind of this warning.
+	///
+	/// This is the same code shown when the warning is rendered.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Warning::IdentifierShadowing(_) => "shackle::shadowed_variable",
+			Warning::UnreachablePattern(_) => "shackle::unreachable_pattern",
+			Warning::UnguardedRecursion(_) => "shackle::unguarded_recursion",
+			Warning::DoubleEquals(_) => "shackle::double_equals",
+			Warning::ConstantConstraint(_) => "shackle::constant_constraint",
+			Warning::EmptyRange(_) => "shackle::empty_range",
+			Warning::IgnoredDataItem(_) => "shackle::ignored_data_item",
+			Warning::IgnoredHeuristic(_) => "shackle::ignored_heuristic",
+			Warning::UnknownSetting(_) => "shackle::unknown_setting",
+		}
+	}
+
+	/// Get the source file and primary span of this warning
+	pub fn source_span(&self) -> (&SourceFile, SourceSpan) {
+		match self {
+			Warning::IdentifierShadowing(w) => (&w.src, w.span),
+			Warning::UnreachablePattern(w) => (&w.src, w.span),
+			Warning::UnguardedRecursion(w) => (&w.src, w.span),
+			Warning::DoubleEquals(w) => (&w.src, w.span),
+			Warning::ConstantConstraint(w) => (&w.src, w.span),
+			Warning::EmptyRange(w) => (&w.src, w.span),
+			Warning::IgnoredDataItem(w) => (&w.src, w.span),
+			Warning::IgnoredHeuristic(w) => (&w.src, w.span),
+			Warning::UnknownSetting(w) => (&w.src, w.span),
+		}
+	}
 }
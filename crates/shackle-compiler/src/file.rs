@@ -4,14 +4,16 @@
 
 use std::{
 	ffi::OsStr,
-	fs::read_to_string,
+	hash::{Hash, Hasher},
+	io::Read,
 	ops::Deref,
 	panic::{RefUnwindSafe, UnwindSafe},
 	path::{Path, PathBuf},
-	sync::Arc,
+	sync::{Arc, OnceLock},
 };
 
-use miette::{MietteSpanContents, SourceCode};
+use flate2::read::MultiGzDecoder;
+use miette::{MietteSpanContents, SourceCode, SourceSpan};
 
 use crate::{db::FileReader, diagnostics::FileError};
 
@@ -33,7 +35,7 @@ impl InputFile {
 }
 
 /// Input languages
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum InputLang {
 	/// MiniZinc modelling language
 	MiniZinc,
@@ -55,7 +57,7 @@ impl InputLang {
 		};
 		match ext.to_str() {
 			Some("mzn") => Self::MiniZinc,
-			Some("eprime") => Self::EPrime,
+			Some("eprime" | "param") => Self::EPrime,
 			Some("dzn") => Self::DataZinc,
 			Some("json") => Self::Json,
 			_ => Self::MiniZinc,
@@ -63,6 +65,9 @@ impl InputLang {
 	}
 }
 
+/// The magic bytes at the start of a gzip-compressed file
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 /// Source file/text for error reporting
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct SourceFile(SourceFileInner);
@@ -72,10 +77,39 @@ enum SourceFileInner {
 	Text {
 		name: Option<PathBuf>,
 		source: Arc<String>,
+		line_starts: LineStarts,
 	},
 	Introduced(&'static str),
 }
 
+/// Byte offsets of the start of each line in a source file, computed when first needed
+///
+/// These are derived from the source text, so they are ignored when comparing and hashing.
+#[derive(Clone, Default)]
+struct LineStarts(Arc<OnceLock<Box<[usize]>>>);
+
+impl LineStarts {
+	fn get(&self, source: &str) -> &[usize] {
+		self.0.get_or_init(|| {
+			std::iter::once(0)
+				.chain(source.match_indices('\n').map(|(i, _)| i + 1))
+				.collect()
+		})
+	}
+}
+
+impl PartialEq for LineStarts {
+	fn eq(&self, _: &Self) -> bool {
+		true
+	}
+}
+
+impl Eq for LineStarts {}
+
+impl Hash for LineStarts {
+	fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
 impl std::fmt::Debug for SourceFile {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("SourceFile")
@@ -94,6 +128,16 @@ impl SourceFile {
 		Self(SourceFileInner::Text {
 			name: file.path(db),
 			source: file.contents(db).unwrap_or_default(),
+			line_starts: LineStarts::default(),
+		})
+	}
+
+	/// Create a source file for text read from the standard input stream (named `<stdin>`)
+	pub fn stdin(source: Arc<String>) -> Self {
+		Self(SourceFileInner::Text {
+			name: Some(PathBuf::from("<stdin>")),
+			source,
+			line_starts: LineStarts::default(),
 		})
 	}
 
@@ -134,27 +178,72 @@ impl SourceFile {
 			SourceFileInner::Introduced(_) => "",
 		}
 	}
+
+	/// Get the zero-based line and column of the given byte offset
+	///
+	/// Columns are counted in characters. The line starts are computed on first use and shared
+	/// between clones of this source file, so repeated conversions are cheap.
+	pub fn line_col(&self, offset: usize) -> (usize, usize) {
+		let starts = match &self.0 {
+			SourceFileInner::Text {
+				source,
+				line_starts,
+				..
+			} => line_starts.get(source),
+			SourceFileInner::Introduced(_) => &[0],
+		};
+		let line = starts.partition_point(|s| *s <= offset) - 1;
+		let column = self.contents()[starts[line]..offset].chars().count();
+		(line, column)
+	}
+
+	/// Get the zero-based `(start_line, start_column, end_line, end_column)` of the given span
+	///
+	/// The end is exclusive (see [`SourceFile::line_col`]).
+	pub fn span_line_cols(&self, span: SourceSpan) -> (usize, usize, usize, usize) {
+		let (start_line, start_column) = self.line_col(span.offset());
+		let (end_line, end_column) = self.line_col(span.offset() + span.len());
+		(start_line, start_column, end_line, end_column)
+	}
 }
 
 impl TryFrom<&Path> for SourceFile {
 	type Error = FileError;
 
+	/// Read the source file at the given path.
+	///
+	/// Files starting with the gzip magic bytes are decompressed, including any further gzip members
+	/// concatenated to the first. The contents (and therefore the spans of diagnostics) refer to
+	/// the decompressed text.
 	fn try_from(path: &Path) -> Result<Self, Self::Error> {
-		let content = read_to_string(path).map_err(|err| FileError {
+		let io_error = |err: std::io::Error| FileError {
 			file: path.to_path_buf(),
 			message: err.to_string(),
 			other: Vec::new(),
-		})?;
+		};
+		let bytes = std::fs::read(path).map_err(io_error)?;
+		let mut content = String::new();
+		if bytes.starts_with(&GZIP_MAGIC) {
+			MultiGzDecoder::new(bytes.as_slice()).read_to_string(&mut content)
+		} else {
+			bytes.as_slice().read_to_string(&mut content)
+		}
+		.map_err(io_error)?;
 		Ok(Self(SourceFileInner::Text {
 			name: Some(path.to_owned()),
 			source: content.into(),
+			line_starts: LineStarts::default(),
 		}))
 	}
 }
 
 impl From<Arc<String>> for SourceFile {
 	fn from(source: Arc<String>) -> Self {
-		Self(SourceFileInner::Text { name: None, source })
+		Self(SourceFileInner::Text {
+			name: None,
+			source,
+			line_starts: LineStarts::default(),
+		})
 	}
 }
 
@@ -357,3 +446,27 @@ impl FileHandler for DefaultFileHandler {
 		Box::new(self.clone())
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use super::SourceFile;
+
+	#[test]
+	fn test_span_line_cols() {
+		let src = SourceFile::from(Arc::new("int: x;\nconstraint\n  x > 1;\n".to_owned()));
+		assert_eq!(src.line_col(0), (0, 0));
+		assert_eq!(src.line_col(5), (0, 5));
+		// Start of the second line
+		assert_eq!(src.line_col(8), (1, 0));
+		// `x` on the third line
+		assert_eq!(src.span_line_cols((21, 1).into()), (2, 2, 2, 3));
+		// `constraint\n  x` crosses a newline
+		assert_eq!(src.span_line_cols((8, 14).into()), (1, 0, 2, 3));
+		// End of the file
+		assert_eq!(src.line_col(28), (3, 0));
+		// Clones share the computed line starts
+		assert_eq!(src.clone().line_col(21), (2, 2));
+	}
+}
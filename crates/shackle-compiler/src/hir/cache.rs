@@ -0,0 +1,1073 @@
+//! Serialization of lowered HIR models for caching
+//!
+//! Lowering does not depend on any other model, so the lowered form of a model which does not
+//! change (such as the standard library) can be saved using [`serialize_lowered`] and loaded
+//! into a new database using `CompilerDatabase::load_lowered` rather than lowering it again.
+//!
+//! The format consists of a header (containing the format version, the compiler settings which
+//! affect lowering and a hash of the source text), a table of strings, the model and then its
+//! source map. Interned strings are not stable between databases, so identifiers and string
+//! literals are stored as indices into the string table and are re-interned when loaded.
+//! Similarly, CST node IDs are not stable between parses, so the origins in the source map
+//! instead record how to find their node again in the reparsed CST.
+
+use std::{any::TypeId, sync::Arc};
+
+use rustc_hash::FxHashMap;
+
+use super::{
+	db::Hir,
+	ids::{EntityRef, ItemRef, LocalEntityRef, LocalItemRef, NodeRef},
+	source::{Origin, SourceMap},
+	*,
+};
+use crate::{
+	diagnostics::InvalidCache,
+	file::ModelRef,
+	syntax::cst::Cst,
+	utils::arena::{Arena, ArenaIndex, ArenaMap},
+	Error, Result,
+};
+
+/// Magic bytes at the start of a serialized model
+const MAGIC: &[u8; 4] = b"SHKH";
+
+/// Version of the serialization format (must be incremented whenever the HIR changes)
+const FORMAT_VERSION: u64 = 3;
+
+/// A lowered model loaded from a cache
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedModel {
+	/// Hash of the source text the model was lowered from
+	pub content_hash: u64,
+	/// Whether constant folding was enabled when the model was lowered
+	pub fold_constants: bool,
+	/// The maximum expression nesting depth when the model was lowered
+	pub max_expression_depth: usize,
+	/// The lowered model
+	pub model: Arc<Model>,
+	/// The source map of the lowered model
+	pub source_map: Arc<SourceMap>,
+}
+
+impl CachedModel {
+	/// Whether this cached model can be used in place of lowering the given source text
+	pub fn is_valid(&self, db: &dyn Hir, contents: &str) -> bool {
+		self.content_hash == content_hash(contents)
+			&& self.fold_constants == db.fold_constants()
+			&& self.max_expression_depth == db.max_expression_depth()
+	}
+}
+
+/// Hash source text (using 64-bit FNV-1a, which is stable between runs and platforms)
+pub fn content_hash(contents: &str) -> u64 {
+	contents.bytes().fold(0xcbf29ce484222325, |hash, b| {
+		(hash ^ b as u64).wrapping_mul(0x100000001b3)
+	})
+}
+
+/// Serialize the lowered form of the given model.
+///
+/// Models with lowering errors or warnings cannot be cached, as the diagnostics are not stored.
+pub fn serialize_lowered(db: &dyn Hir, model: ModelRef) -> Result<Vec<u8>> {
+	let (hir, source_map, errors, warnings) = db.lower_items(model);
+	if !errors.is_empty() {
+		return Err(Error::try_from(errors.to_vec()).unwrap());
+	}
+	if !warnings.is_empty() {
+		return Err(InvalidCache {
+			msg: "models with lowering warnings cannot be cached".to_owned(),
+		}
+		.into());
+	}
+	encode_lowered(db, model, &hir, &source_map)
+}
+
+/// Encode the given lowered form of a model, without checking that it is the result of lowering
+fn encode_lowered(
+	db: &dyn Hir,
+	model: ModelRef,
+	hir: &Model,
+	source_map: &SourceMap,
+) -> Result<Vec<u8>> {
+	let contents = model.contents(db.upcast())?;
+	let cst = db.cst(*model)?;
+	let mut encoder = Encoder {
+		db,
+		model,
+		cst: &cst,
+		buf: Vec::new(),
+		strings: FxHashMap::default(),
+		string_table: Vec::new(),
+	};
+	hir.encode(&mut encoder);
+	encoder.encode_source_map(source_map);
+
+	// The string table is only known after encoding, but must come first
+	let body = std::mem::replace(&mut encoder.buf, MAGIC.to_vec());
+	let string_table = std::mem::take(&mut encoder.string_table);
+	encoder.write_u64(FORMAT_VERSION);
+	encoder.write_bool(db.fold_constants());
+	encoder.write_u64(db.max_expression_depth() as u64);
+	encoder.buf.extend(content_hash(&contents).to_le_bytes());
+	encoder.write_u64(string_table.len() as u64);
+	for s in string_table {
+		encoder.write_u64(s.len() as u64);
+		encoder.buf.extend(s.as_bytes());
+	}
+	encoder.buf.extend(body);
+	Ok(encoder.buf)
+}
+
+/// Load a lowered model serialized using [`serialize_lowered`].
+///
+/// Fails if the data is invalid, or was produced from different source text or with different
+/// compiler settings.
+pub fn deserialize_lowered(db: &dyn Hir, model: ModelRef, bytes: &[u8]) -> Result<CachedModel> {
+	let contents = model.contents(db.upcast())?;
+	let cst = db.cst(*model)?;
+	let mut decoder = Decoder {
+		db,
+		model,
+		cst: &cst,
+		data: bytes,
+		pos: 0,
+		strings: Vec::new(),
+		scopes: Vec::new(),
+	};
+	if decoder.read_slice(MAGIC.len())? != MAGIC {
+		return Err(decoder.error("not a serialized model").into());
+	}
+	if decoder.read_u64()? != FORMAT_VERSION {
+		return Err(decoder.error("unsupported format version").into());
+	}
+	let fold_constants = decoder.read_bool()?;
+	let max_expression_depth = decoder.read_u64()? as usize;
+	if fold_constants != db.fold_constants() || max_expression_depth != db.max_expression_depth() {
+		return Err(decoder
+			.error("model was lowered with different compiler settings")
+			.into());
+	}
+	let hash = u64::from_le_bytes(decoder.read_slice(8)?.try_into().unwrap());
+	if hash != content_hash(&contents) {
+		return Err(decoder.error("model source has changed").into());
+	}
+	let len = decoder.read_u64()?;
+	for _ in 0..len {
+		let s = decoder.read_str()?.to_owned();
+		decoder.strings.push(s);
+	}
+	decoder.begin_scope();
+	let hir = Model::decode(&mut decoder)?;
+	decoder.end_scope()?;
+	let source_map = decoder.decode_source_map(&hir)?;
+	if decoder.pos != bytes.len() {
+		return Err(decoder.error("unexpected trailing data").into());
+	}
+	Ok(CachedModel {
+		content_hash: hash,
+		fold_constants,
+		max_expression_depth,
+		model: Arc::new(hir),
+		source_map: Arc::new(source_map),
+	})
+}
+
+struct Encoder<'a> {
+	db: &'a dyn Hir,
+	model: ModelRef,
+	cst: &'a Cst,
+	buf: Vec<u8>,
+	strings: FxHashMap<String, u64>,
+	string_table: Vec<String>,
+}
+
+impl Encoder<'_> {
+	fn write_u8(&mut self, v: u8) {
+		self.buf.push(v);
+	}
+
+	fn write_bool(&mut self, v: bool) {
+		self.write_u8(v as u8);
+	}
+
+	/// Write an unsigned integer as a LEB128 variable length integer
+	fn write_u64(&mut self, mut v: u64) {
+		while v >= 0x80 {
+			self.buf.push((v as u8) | 0x80);
+			v >>= 7;
+		}
+		self.buf.push(v as u8);
+	}
+
+	/// Write a signed integer using zigzag encoding
+	fn write_i64(&mut self, v: i64) {
+		self.write_u64(((v << 1) ^ (v >> 63)) as u64);
+	}
+
+	/// Write a string as an index into the string table
+	fn write_string(&mut self, s: String) {
+		let table = &mut self.string_table;
+		let index = *self.strings.entry(s).or_insert_with_key(|s| {
+			table.push(s.clone());
+			table.len() as u64 - 1
+		});
+		self.write_u64(index);
+	}
+
+	fn encode_source_map(&mut self, source_map: &SourceMap) {
+		let entries = source_map.iter().collect::<Vec<_>>();
+		self.write_u64(entries.len() as u64);
+		for (node, origin) in entries {
+			match node {
+				NodeRef::Model(m) => {
+					debug_assert_eq!(m, self.model);
+					self.write_u8(0);
+				}
+				NodeRef::Item(i) => {
+					debug_assert_eq!(i.model_ref(self.db), self.model);
+					self.write_u8(1);
+					i.local_item_ref(self.db).encode(self);
+				}
+				NodeRef::Entity(e) => {
+					let item = e.item(self.db);
+					debug_assert_eq!(item.model_ref(self.db), self.model);
+					self.write_u8(2);
+					item.local_item_ref(self.db).encode(self);
+					e.entity(self.db).encode(self);
+				}
+			}
+			debug_assert_eq!(origin.file, *self.model);
+			self.write_u64(origin.range.start as u64);
+			self.write_u64(origin.range.end as u64);
+			// The origin node is an ancestor of the smallest node spanning its range
+			let mut ancestors = 0;
+			let mut current = self
+				.cst
+				.root_node()
+				.descendant_for_byte_range(origin.range.start, origin.range.end);
+			while let Some(n) = current {
+				if n.id() == origin.node_id {
+					break;
+				}
+				ancestors += 1;
+				current = n.parent();
+			}
+			self.write_u64(if current.is_some() { ancestors } else { 0 });
+		}
+	}
+}
+
+struct Decoder<'a> {
+	db: &'a dyn Hir,
+	model: ModelRef,
+	cst: &'a Cst,
+	data: &'a [u8],
+	pos: usize,
+	strings: Vec<String>,
+	scopes: Vec<IndexScope>,
+}
+
+/// The arena indices decoded within a model or item, and the lengths of the arenas it contains
+///
+/// Indices may be decoded before the arena they refer to, so they are checked once the whole
+/// model or item has been decoded.
+#[derive(Default)]
+struct IndexScope {
+	lengths: FxHashMap<TypeId, u32>,
+	indices: Vec<(TypeId, u32)>,
+}
+
+impl<'a> Decoder<'a> {
+	fn error(&self, msg: &str) -> InvalidCache {
+		InvalidCache {
+			msg: format!("{} (at byte {})", msg, self.pos),
+		}
+	}
+
+	fn read_slice(&mut self, len: usize) -> Result<&'a [u8], InvalidCache> {
+		let end = self
+			.pos
+			.checked_add(len)
+			.filter(|end| *end <= self.data.len())
+			.ok_or_else(|| self.error("unexpected end of data"))?;
+		let slice = &self.data[self.pos..end];
+		self.pos = end;
+		Ok(slice)
+	}
+
+	fn read_u8(&mut self) -> Result<u8, InvalidCache> {
+		Ok(self.read_slice(1)?[0])
+	}
+
+	fn read_bool(&mut self) -> Result<bool, InvalidCache> {
+		match self.read_u8()? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(self.error("invalid boolean")),
+		}
+	}
+
+	fn read_u64(&mut self) -> Result<u64, InvalidCache> {
+		let mut result = 0;
+		let mut shift = 0;
+		loop {
+			let byte = self.read_u8()?;
+			if shift >= 64 || (shift == 63 && byte > 1) {
+				return Err(self.error("integer too large"));
+			}
+			result |= ((byte & 0x7f) as u64) << shift;
+			if byte & 0x80 == 0 {
+				return Ok(result);
+			}
+			shift += 7;
+		}
+	}
+
+	fn read_i64(&mut self) -> Result<i64, InvalidCache> {
+		let v = self.read_u64()?;
+		Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+	}
+
+	fn read_usize(&mut self) -> Result<usize, InvalidCache> {
+		let v = self.read_u64()?;
+		usize::try_from(v).map_err(|_| self.error("integer too large"))
+	}
+
+	fn read_str(&mut self) -> Result<&'a str, InvalidCache> {
+		let len = self.read_usize()?;
+		let bytes = self.read_slice(len)?;
+		std::str::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 string"))
+	}
+
+	/// Read a string given as an index into the string table
+	fn read_string(&mut self) -> Result<&str, InvalidCache> {
+		let index = self.read_usize()?;
+		match self.strings.get(index) {
+			Some(s) => Ok(s),
+			None => Err(self.error("invalid string index")),
+		}
+	}
+
+	/// Start collecting the arena indices and arena lengths of a model or item
+	fn begin_scope(&mut self) {
+		self.scopes.push(IndexScope::default());
+	}
+
+	/// Check that the arena indices decoded since the matching call to `begin_scope` refer to
+	/// elements of the arenas decoded in that time
+	fn end_scope(&mut self) -> Result<(), InvalidCache> {
+		let scope = self.scopes.pop().expect("no scope to end");
+		for (ty, index) in scope.indices {
+			if scope.lengths.get(&ty).map_or(true, |len| index > *len) {
+				return Err(self.error("invalid arena index"));
+			}
+		}
+		Ok(())
+	}
+
+	/// Whether the given index refers to an element of an arena already decoded in this scope
+	fn in_bounds<T: 'static>(&self, index: ArenaIndex<T>) -> bool {
+		self.scopes
+			.last()
+			.and_then(|scope| scope.lengths.get(&TypeId::of::<T>()))
+			.map_or(false, |len| u32::from(index) <= *len)
+	}
+
+	fn decode_source_map(&mut self, model: &Model) -> Result<SourceMap, InvalidCache> {
+		let mut source_map = SourceMap::default();
+		let len = self.read_u64()?;
+		for _ in 0..len {
+			let node: NodeRef = match self.read_u8()? {
+				0 => self.model.into(),
+				1 => {
+					let item = LocalItemRef::decode(self)?;
+					if item_data(model, item).is_none() {
+						return Err(self.error("invalid source map item"));
+					}
+					ItemRef::new(self.db, self.model, item).into()
+				}
+				2 => {
+					let item = LocalItemRef::decode(self)?;
+					let entity = LocalEntityRef::decode(self)?;
+					let valid = item_data(model, item).map_or(false, |data| match entity {
+						LocalEntityRef::Expression(e) => data.expressions.get(e).is_some(),
+						LocalEntityRef::Type(t) => data.types.get(t).is_some(),
+						LocalEntityRef::Pattern(p) => data.patterns.get(p).is_some(),
+					});
+					if !valid {
+						return Err(self.error("invalid source map entity"));
+					}
+					let item = ItemRef::new(self.db, self.model, item);
+					EntityRef::new(self.db, item, entity).into()
+				}
+				_ => return Err(self.error("invalid source map entry")),
+			};
+			let start = self.read_usize()?;
+			let end = self.read_usize()?;
+			let ancestors = self.read_u64()?;
+			let mut current = self.cst.root_node().descendant_for_byte_range(start, end);
+			for _ in 0..ancestors {
+				current = current.and_then(|n| n.parent());
+				if current.is_none() {
+					break;
+				}
+			}
+			let node_id = current
+				.ok_or_else(|| self.error("origin not found in source"))?
+				.id();
+			source_map.insert(
+				node,
+				Origin {
+					file: *self.model,
+					range: start..end,
+					node_id,
+				},
+			);
+		}
+		Ok(source_map)
+	}
+}
+
+/// Get the data of the given item in `model`, if the item exists
+fn item_data(model: &Model, item: LocalItemRef) -> Option<&ItemData> {
+	match item {
+		LocalItemRef::Annotation(i) => model.annotations.get(i).map(|i| &i.data),
+		LocalItemRef::Assignment(i) => model.assignments.get(i).map(|i| &i.data),
+		LocalItemRef::Constraint(i) => model.constraints.get(i).map(|i| &i.data),
+		LocalItemRef::Declaration(i) => model.declarations.get(i).map(|i| &i.data),
+		LocalItemRef::Enumeration(i) => model.enumerations.get(i).map(|i| &i.data),
+		LocalItemRef::EnumAssignment(i) => model.enum_assignments.get(i).map(|i| &i.data),
+		LocalItemRef::Function(i) => model.functions.get(i).map(|i| &i.data),
+		LocalItemRef::Output(i) => model.outputs.get(i).map(|i| &i.data),
+		LocalItemRef::Solve(i) => model.solves.get(i).map(|i| &i.data),
+		LocalItemRef::TypeAlias(i) => model.type_aliases.get(i).map(|i| &i.data),
+	}
+}
+
+trait Encode {
+	fn encode(&self, e: &mut Encoder);
+}
+
+trait Decode: Sized {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache>;
+}
+
+impl Encode for bool {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_bool(*self);
+	}
+}
+
+impl Decode for bool {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		d.read_bool()
+	}
+}
+
+impl Encode for usize {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_u64(*self as u64);
+	}
+}
+
+impl Decode for usize {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		d.read_usize()
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode(&self, e: &mut Encoder) {
+		match self {
+			Some(v) => {
+				e.write_u8(1);
+				v.encode(e);
+			}
+			None => e.write_u8(0),
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		match d.read_bool()? {
+			true => Ok(Some(T::decode(d)?)),
+			false => Ok(None),
+		}
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_u64(self.len() as u64);
+		for v in self.iter() {
+			v.encode(e);
+		}
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let len = d.read_u64()?;
+		// Don't trust the length for preallocation since the data may be invalid
+		let mut result = Vec::new();
+		for _ in 0..len {
+			result.push(T::decode(d)?);
+		}
+		Ok(result)
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode(&self, e: &mut Encoder) {
+		self[..].encode(e);
+	}
+}
+
+impl<T: Encode> Encode for Box<[T]> {
+	fn encode(&self, e: &mut Encoder) {
+		self[..].encode(e);
+	}
+}
+
+impl<T: Decode> Decode for Box<[T]> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		Ok(Vec::decode(d)?.into_boxed_slice())
+	}
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode(&self, e: &mut Encoder) {
+		self.0.encode(e);
+		self.1.encode(e);
+	}
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		Ok((A::decode(d)?, B::decode(d)?))
+	}
+}
+
+impl<T> Encode for ArenaIndex<T> {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_u64(u32::from(*self) as u64);
+	}
+}
+
+impl<T: 'static> Decode for ArenaIndex<T> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let raw = d.read_u64()?;
+		let index = u32::try_from(raw)
+			.ok()
+			.and_then(ArenaIndex::from_raw)
+			.ok_or_else(|| d.error("invalid arena index"))?;
+		if let Some(scope) = d.scopes.last_mut() {
+			scope.indices.push((TypeId::of::<T>(), raw as u32));
+		}
+		Ok(index)
+	}
+}
+
+impl<T: Encode> Encode for Arena<T> {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_u64(self.len() as u64);
+		for v in self.values() {
+			v.encode(e);
+		}
+	}
+}
+
+impl<T: Decode + 'static> Decode for Arena<T> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let len = d.read_u64()?;
+		let mut arena = Arena::new();
+		for _ in 0..len {
+			arena.insert(T::decode(d)?);
+		}
+		if let Some(scope) = d.scopes.last_mut() {
+			scope.lengths.insert(TypeId::of::<T>(), arena.len());
+		}
+		Ok(arena)
+	}
+}
+
+impl<K, V: Encode> Encode for ArenaMap<K, V> {
+	fn encode(&self, e: &mut Encoder) {
+		let entries = self.iter().collect::<Vec<_>>();
+		e.write_u64(entries.len() as u64);
+		for (k, v) in entries {
+			k.encode(e);
+			v.encode(e);
+		}
+	}
+}
+
+impl<K: 'static, V: Decode> Decode for ArenaMap<K, V> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let len = d.read_u64()?;
+		let mut map = ArenaMap::new();
+		for _ in 0..len {
+			let k = ArenaIndex::decode(d)?;
+			// Inserting resizes the map to fit the key, so it must be checked first (the arena of
+			// keys is always decoded before the map)
+			if !d.in_bounds(k) {
+				return Err(d.error("invalid arena index"));
+			}
+			map.insert(k, V::decode(d)?);
+		}
+		Ok(map)
+	}
+}
+
+impl<T: Encode> Encode for Item<T> {
+	fn encode(&self, e: &mut Encoder) {
+		(**self).encode(e);
+		self.data.encode(e);
+	}
+}
+
+impl<T: Decode> Decode for Item<T> {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		// The indices used in the item refer to the arenas in its data
+		d.begin_scope();
+		let item = T::decode(d)?;
+		let data = ItemData::decode(d)?;
+		d.end_scope()?;
+		Ok(Item::new(item, data))
+	}
+}
+
+impl Encode for Identifier {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_string(self.lookup(e.db));
+	}
+}
+
+impl Decode for Identifier {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let db = d.db;
+		Ok(Identifier::new(d.read_string()?, db))
+	}
+}
+
+impl Encode for StringLiteral {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_string(self.value(e.db));
+	}
+}
+
+impl Decode for StringLiteral {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let db = d.db;
+		Ok(StringLiteral::new(d.read_string()?, db))
+	}
+}
+
+impl Encode for IntegerLiteral {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_i64(self.0);
+	}
+}
+
+impl Decode for IntegerLiteral {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		Ok(IntegerLiteral(d.read_i64()?))
+	}
+}
+
+impl Encode for BooleanLiteral {
+	fn encode(&self, e: &mut Encoder) {
+		e.write_bool(self.0);
+	}
+}
+
+impl Decode for BooleanLiteral {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		Ok(BooleanLiteral(d.read_bool()?))
+	}
+}
+
+impl Encode for FloatLiteral {
+	fn encode(&self, e: &mut Encoder) {
+		e.buf.extend(self.value().to_bits().to_le_bytes());
+	}
+}
+
+impl Decode for FloatLiteral {
+	fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+		let bits = u64::from_le_bytes(d.read_slice(8)?.try_into().unwrap());
+		Ok(FloatLiteral::new(f64::from_bits(bits)))
+	}
+}
+
+/// Implement `Encode` and `Decode` for a struct by encoding each of its fields in order
+macro_rules! codec_struct {
+	($($ty:ident { $($field:ident),* $(,)? })*) => {
+		$(
+			impl Encode for $ty {
+				fn encode(&self, e: &mut Encoder) {
+					$(self.$field.encode(e);)*
+				}
+			}
+
+			impl Decode for $ty {
+				fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+					Ok($ty {
+						$($field: Decode::decode(d)?,)*
+					})
+				}
+			}
+		)*
+	};
+}
+
+/// Implement `Encode` and `Decode` for an enum by encoding the given tag followed by the
+/// fields of the variant in order
+macro_rules! codec_enum {
+	($($ty:ident {
+		$($tag:literal => $variant:ident $({ $($sf:ident),* $(,)? })? $(($($tf:ident),*))?),* $(,)?
+	})*) => {
+		$(
+			impl Encode for $ty {
+				fn encode(&self, e: &mut Encoder) {
+					match self {
+						$($ty::$variant $({ $($sf),* })? $(($($tf),*))? => {
+							e.write_u8($tag);
+							$($($sf.encode(e);)*)?
+							$($($tf.encode(e);)*)?
+						})*
+					}
+				}
+			}
+
+			impl Decode for $ty {
+				fn decode(d: &mut Decoder) -> Result<Self, InvalidCache> {
+					match d.read_u8()? {
+						$($tag => {
+							$($(let $sf = Decode::decode(d)?;)*)?
+							$($(let $tf = Decode::decode(d)?;)*)?
+							Ok($ty::$variant $({ $($sf),* })? $(($($tf),*))?)
+						})*
+						_ => Err(d.error(concat!("invalid ", stringify!($ty)))),
+					}
+				}
+			}
+		)*
+	};
+}
+
+codec_struct! {
+	Model {
+		items,
+		annotations,
+		assignments,
+		enum_assignments,
+		constraints,
+		declarations,
+		enumerations,
+		functions,
+		outputs,
+		solves,
+		type_aliases,
+	}
+	ItemData { expressions, types, patterns, annotations }
+	Annotation { constructor }
+	Assignment { assignee, definition }
+	Constraint { expression, annotations }
+	Declaration { declared_type, pattern, definition, annotations }
+	ConstructorParameter { declared_type, pattern }
+	Enumeration { pattern, definition, annotations }
+	EnumAssignment { assignee, definition }
+	Function { return_type, pattern, type_inst_vars, parameters, body, annotations }
+	TypeInstIdentifierDeclaration { name, anonymous, is_enum, is_varifiable, is_indexable }
+	Parameter { declared_type, pattern, annotations }
+	Output { section, expression }
+	Solve { goal, annotations }
+	TypeAlias { name, aliased_type, annotations }
+	SetLiteral { members }
+	ArrayLiteral { members }
+	ArrayLiteral2D { rows, columns, members }
+	IndexedArrayLiteral { indices, members }
+	ArrayAccess { collection, indices }
+	ArrayComprehension { template, indices, generators }
+	SetComprehension { template, generators }
+	TupleLiteral { fields }
+	RecordLiteral { fields }
+	IfThenElse { branches, else_result }
+	Branch { condition, result }
+	Call { function, arguments }
+	Case { expression, cases }
+	CaseItem { pattern, value }
+	Let { items, in_expression }
+	TupleAccess { tuple, field }
+	RecordAccess { record, field }
+	Lambda { return_type, parameters, body }
+}
+
+codec_enum! {
+	LocalItemRef {
+		0 => Annotation(i),
+		1 => Assignment(i),
+		2 => Constraint(i),
+		3 => Declaration(i),
+		4 => Enumeration(i),
+		5 => EnumAssignment(i),
+		6 => Function(i),
+		7 => Output(i),
+		8 => Solve(i),
+		9 => TypeAlias(i),
+	}
+	LocalEntityRef {
+		0 => Expression(e),
+		1 => Type(t),
+		2 => Pattern(p),
+	}
+	Constructor {
+		0 => Atom { pattern },
+		1 => Function { constructor, destructor, parameters },
+	}
+	EnumConstructor {
+		0 => Anonymous { pattern, parameters },
+		1 => Named(c),
+	}
+	Goal {
+		0 => Satisfy,
+		1 => Maximize { pattern, objective },
+		2 => Minimize { pattern, objective },
+	}
+	Expression {
+		0 => IntegerLiteral(v),
+		1 => FloatLiteral(v),
+		2 => SetLiteral(v),
+		3 => BooleanLiteral(v),
+		4 => StringLiteral(v),
+		5 => Identifier(v),
+		6 => Absent,
+		7 => Infinity,
+		8 => TupleLiteral(v),
+		9 => RecordLiteral(v),
+		10 => ArrayLiteral(v),
+		11 => ArrayLiteral2D(v),
+		12 => IndexedArrayLiteral(v),
+		13 => ArrayAccess(v),
+		14 => ArrayComprehension(v),
+		15 => SetComprehension(v),
+		16 => IfThenElse(v),
+		17 => Call(v),
+		18 => Case(v),
+		19 => Let(v),
+		20 => TupleAccess(v),
+		21 => RecordAccess(v),
+		22 => Lambda(v),
+		23 => Slice(v),
+		24 => Missing,
+		25 => Anonymous,
+	}
+	MaybeIndexSet {
+		0 => NonIndexed(n),
+		1 => Indexed(v),
+	}
+	Generator {
+		0 => Iterator { patterns, collection, where_clause },
+		1 => Assignment { pattern, value, where_clause },
+	}
+	LetItem {
+		0 => Declaration(v),
+		1 => Constraint(v),
+	}
+	Type {
+		0 => Primitive { inst, opt, primitive_type },
+		1 => Bounded { inst, opt, domain },
+		2 => Array { opt, dimensions, element },
+		3 => Set { inst, opt, element },
+		4 => Tuple { opt, fields },
+		5 => Record { opt, fields },
+		6 => Operation { opt, return_type, parameter_types },
+		7 => AnonymousTypeInstVar { inst, opt, pattern },
+		8 => Any,
+		9 => Missing,
+	}
+	Pattern {
+		0 => Identifier(v),
+		1 => Anonymous,
+		2 => Absent,
+		3 => Boolean(v),
+		4 => Float { negated, value },
+		5 => Integer { negated, value },
+		6 => Infinity { negated },
+		7 => String(v),
+		8 => Call { function, arguments },
+		9 => Tuple { fields },
+		10 => Record { fields },
+		11 => Missing,
+	}
+	VarType {
+		0 => Par,
+		1 => Var,
+	}
+	OptType {
+		0 => NonOpt,
+		1 => Opt,
+	}
+	PrimitiveType {
+		0 => Ann,
+		1 => Bool,
+		2 => Float,
+		3 => Int,
+		4 => String,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use super::{encode_lowered, serialize_lowered};
+	use crate::{
+		db::{CompilerDatabase, CompilerSettings, FileReader, Inputs},
+		file::{InputFile, InputLang},
+		hir::{db::Hir, ids::LocalItemRef, source::item_doc_comment, Model},
+		utils::{arena::ArenaIndex, DebugPrint},
+		Error,
+	};
+
+	const SOURCE: &str = r#"
+enum Colour = {Red, Green, Blue};
+% The number of things
+int: n = 3;
+array [1..n] of var Colour: x;
+tuple(int, float): t = (-1, 2.5);
+record(string: name, bool: b): r = (name: "foo", b: true);
+function var int: double(var int: v) = 2 * v;
+constraint forall (i in 2..n) (x[i] != x[i - 1]);
+constraint let { var int: y = double(n) } in y > 0 :: "named";
+output ["\(x)"];
+solve satisfy;
+"#;
+
+	fn database(source: &str) -> CompilerDatabase {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			source.to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		db
+	}
+
+	fn summary(db: &CompilerDatabase) -> (Vec<String>, Vec<Error>, Option<String>) {
+		let items = db.lookup_items(db.input_models()[0]);
+		(
+			items.iter().map(|i| i.debug_print(db)).collect(),
+			db.all_errors().iter().cloned().collect(),
+			item_doc_comment(db, items[1]),
+		)
+	}
+
+	#[test]
+	fn test_serialize_round_trip() {
+		let db = database(SOURCE);
+		let bytes = serialize_lowered(&db, db.input_models()[0]).unwrap();
+
+		let mut cached = database(SOURCE);
+		let model = cached.input_models()[0];
+		cached.load_lowered(model, &bytes).unwrap();
+		assert!(Arc::ptr_eq(
+			&cached.lookup_model(model),
+			&cached.cached_models()[&model].model
+		));
+		let expected = summary(&db);
+		assert_eq!(expected.2.as_deref(), Some("The number of things"));
+		assert_eq!(summary(&cached), expected);
+
+		// Cached model is ignored once the source changes
+		cached.set_input_files(Arc::new(vec![InputFile::String(
+			"int: n = 3;".to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		assert_eq!(cached.lookup_items(model).len(), 1);
+	}
+
+	#[test]
+	fn test_load_invalid_cache() {
+		let db = database(SOURCE);
+		let bytes = serialize_lowered(&db, db.input_models()[0]).unwrap();
+
+		let mut changed = database("int: n = 3;");
+		let model = changed.input_models()[0];
+		let err = changed.load_lowered(model, &bytes).unwrap_err();
+		assert!(
+			err.to_string().contains("model source has changed"),
+			"{}",
+			err
+		);
+
+		let mut truncated = database(SOURCE);
+		let model = truncated.input_models()[0];
+		assert!(matches!(
+			truncated.load_lowered(model, &bytes[..bytes.len() - 1]),
+			Err(Error::InvalidCache(_))
+		));
+		assert!(truncated.cached_models().is_empty());
+
+		// Models lowered with a different expression depth limit may have different errors
+		let mut limited = database(SOURCE);
+		limited.set_max_expression_depth(3);
+		let model = limited.input_models()[0];
+		let err = limited.load_lowered(model, &bytes).unwrap_err();
+		assert!(
+			err.to_string().contains("different compiler settings"),
+			"{}",
+			err
+		);
+
+		let mut cached = database(SOURCE);
+		let model = cached.input_models()[0];
+		cached.load_lowered(model, &bytes).unwrap();
+		cached.set_max_expression_depth(3);
+		assert!(!Arc::ptr_eq(
+			&cached.lookup_model(model),
+			&cached.cached_models()[&model].model
+		));
+	}
+
+	#[test]
+	fn test_load_corrupted_cache() {
+		let db = database(SOURCE);
+		let model = db.input_models()[0];
+		let (hir, source_map, _, _) = db.lower_items(model);
+		let check_rejected = |corrupt: &dyn Fn(&mut Model)| {
+			let mut hir = (*hir).clone();
+			corrupt(&mut hir);
+			let bytes = encode_lowered(&db, model, &hir, &source_map).unwrap();
+
+			let mut corrupted = database(SOURCE);
+			let model = corrupted.input_models()[0];
+			assert!(matches!(
+				corrupted.load_lowered(model, &bytes),
+				Err(Error::InvalidCache(_))
+			));
+			assert!(corrupted.cached_models().is_empty());
+		};
+
+		// Expression outside of the item's expression arena
+		check_rejected(&|hir| {
+			let constraint = hir.constraints.values_mut().next().unwrap();
+			let expression = ArenaIndex::from_raw(constraint.data.expressions.len() + 1).unwrap();
+			constraint.expression = expression;
+		});
+		// Annotations for an expression which does not exist
+		check_rejected(&|hir| {
+			let data = &mut hir.constraints.values_mut().next().unwrap().data;
+			let expression = ArenaIndex::from_raw(data.expressions.len() + 1).unwrap();
+			data.annotations.insert(expression, Box::new([]));
+		});
+		// Item which does not exist
+		check_rejected(&|hir| {
+			let solve = ArenaIndex::from_raw(hir.solves.len() + 1).unwrap();
+			hir.items.push(LocalItemRef::Solve(solve));
+		});
+	}
+}
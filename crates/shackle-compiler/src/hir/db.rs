@@ -2,28 +2,40 @@
 
 //! Salsa database for HIR operations
 
-use std::{collections::HashSet, path::Path, sync::Arc};
+use std::{
+	collections::HashSet,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
 
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use super::{
-	ids::{EntityRef, EntityRefData, ItemRef, ItemRefData, PatternRef},
+	cache::CachedModel,
+	ids::{
+		EntityRef, EntityRefData, ItemRef, ItemRefData, LocalEntityRef, LocalItemRef, NodeRef,
+		PatternRef,
+	},
 	scope::{ScopeData, ScopeResult},
 	source::SourceMap,
-	typecheck::{BodyTypes, SignatureTypes, TypeDiagnostics, TypeResult},
-	Identifier, Model, ScopeCollectorResult,
+	typecheck::{
+		BodyTypes, FunctionSignature, PatternTy, SignatureTypes, TypeDiagnostics, TypeResult,
+	},
+	Expression, FloatLiteral, Identifier, IntegerLiteral, ItemData, LetItem, Model, Pattern,
+	ScopeCollectorResult, SolveMethod, Type,
 };
 use crate::{
 	constants::IdentifierRegistry,
 	db::{CompilerSettings, FileReader, Interner, Upcast},
-	diagnostics::{Diagnostics, IncludeError, MultipleErrors},
+	diagnostics::{Diagnostics, DoubleEquals, IncludeError, MultipleErrors, UndefinedEnumIndexSet},
 	file::{FileRef, ModelRef, SourceFile},
 	syntax::{
 		ast::{AstNode, ConstraintModel},
 		db::SourceParser,
-		minizinc,
+		eprime, minizinc,
 	},
-	ty::EnumRef,
+	ty::{EnumRef, OverloadedFunction, Ty},
+	utils::arena::ArenaIndex,
 	Error, Result, Warning,
 };
 
@@ -50,18 +62,52 @@ pub trait Hir:
 	/// If resolving files fails, then abort (but collect as many errors as possible).
 	fn resolve_includes(&self) -> Result<Arc<Vec<ModelRef>>>;
 
+	/// Get the targets of the include items in the given model (in order), without resolving them.
+	///
+	/// Only MiniZinc models have include items, so this is empty for other models (and for models
+	/// which cannot be read).
+	fn model_includes(&self, model: ModelRef) -> Arc<Vec<String>>;
+
+	/// Get the models transitively included by the given model (starting with the model itself).
+	///
+	/// Unlike `resolve_includes`, this does not add the standard library, and includes which cannot
+	/// be found are ignored.
+	fn include_closure(&self, model: ModelRef) -> Arc<Vec<ModelRef>>;
+
 	/// Get the syntax errors (only allowed if resolving includes succeeds)
 	fn syntax_errors(&self) -> Arc<Vec<Error>>;
 
 	/// Get the names of the enumeration items
 	fn enumeration_names(&self) -> Arc<HashSet<Identifier>>;
 
+	/// Get the names of the parameters declared using `given` in Essence' models
+	fn eprime_parameter_names(&self) -> Arc<HashSet<Identifier>>;
+
+	/// Lowered models loaded from a cache, which are used instead of lowering the source
+	/// (see `CompilerDatabase::load_lowered`)
+	#[salsa::input]
+	fn cached_models(&self) -> Arc<FxHashMap<ModelRef, Arc<CachedModel>>>;
+
 	/// Lower the items of the given model to HIR.
 	///
 	/// Avoid using this query directly, and instead use `lookup_model` to retrieve the lowered model
 	/// without the source map/diagnostics.
+	///
+	/// Lowering is performed for a whole model at once rather than per item. Any edit produces a new
+	/// CST for the whole file, so the origins in the source map of every item would have to be
+	/// recomputed anyway. The lowered model itself contains no source locations, so if an edit does
+	/// not change it (e.g. whitespace or comments), it compares equal to the previous result and
+	/// queries which only depend on `lookup_model` are not re-run.
 	#[salsa::invoke(super::lower::lower_items)]
-	fn lower_items(&self, model: ModelRef) -> (Arc<Model>, Arc<SourceMap>, Arc<Vec<Error>>);
+	fn lower_items(
+		&self,
+		model: ModelRef,
+	) -> (
+		Arc<Model>,
+		Arc<SourceMap>,
+		Arc<Vec<Error>>,
+		Arc<Vec<Warning>>,
+	);
 
 	/// Get the HIR for the given model
 	fn lookup_model(&self, model: ModelRef) -> Arc<Model>;
@@ -69,9 +115,24 @@ pub trait Hir:
 	fn lookup_source_map(&self, model: ModelRef) -> Arc<SourceMap>;
 	/// Get the lowering diagnostics for the given model
 	fn lookup_lowering_errors(&self, model: ModelRef) -> Arc<Vec<Error>>;
+	/// Get the lowering warnings for the given model
+	fn lookup_lowering_warnings(&self, model: ModelRef) -> Arc<Vec<Warning>>;
 	/// Get the items for the given model
 	fn lookup_items(&self, model: ModelRef) -> Arc<Vec<ItemRef>>;
 
+	/// Get the annotations attached to the given expression in an item
+	fn expression_annotations(
+		&self,
+		item: ItemRef,
+		expression: ArenaIndex<Expression>,
+	) -> Arc<Vec<ArenaIndex<Expression>>>;
+
+	/// Get the identifiers of all annotations used in the model (including included files).
+	///
+	/// This includes annotations on items, declarations in let expressions and expressions, as
+	/// well as annotations nested in the arguments of other annotations (e.g. in `seq_search`).
+	fn required_annotations(&self) -> Arc<FxHashSet<Identifier>>;
+
 	/// Collect the identifiers in global scope.
 	///
 	/// Avoid using this query directly, and instead use `lookup_global_variable` or
@@ -130,6 +191,11 @@ pub trait Hir:
 	/// Get the diagnostics produced when computing the signature of this item.
 	fn lookup_item_signature_errors(&self, item: ItemRef) -> Arc<Vec<Error>>;
 
+	/// Whether the given pattern is declared by a declaration item annotated with `output_only`.
+	///
+	/// Such declarations are only computed for output, and are not part of the problem.
+	fn is_output_only(&self, decl: PatternRef) -> bool;
+
 	/// Compute the types of RHS expressions in this item.
 	/// Panics if item does not have a body.
 	///
@@ -153,11 +219,64 @@ pub trait Hir:
 	#[salsa::invoke(super::typecheck::TypeDiagnostics::new)]
 	fn lookup_item_type_errors(&self, item: ItemRef) -> TypeDiagnostics;
 
+	/// Get the type of the global variable with the given identifier.
+	///
+	/// Returns `None` if there is no such variable.
+	fn global_variable_type(&self, identifier: Identifier) -> Option<Ty>;
+
+	/// Get the parameter and return types of the function declared by the given pattern.
+	///
+	/// Enumerable type-inst variables (`$$E`) are resolved to their bound of `int`, while other
+	/// type-inst variables are left as they are. Returns `None` if the pattern does not declare
+	/// a function.
+	fn function_signature(&self, func: PatternRef) -> Option<FunctionSignature>;
+
+	/// Get the pattern which declares the identifier referenced by the given entity.
+	///
+	/// For an identifier expression, this is the declaration it resolves to (a local variable,
+	/// function parameter or global declaration). For a pattern, this is the declaration it refers to
+	/// (e.g. an enum constructor), or the pattern itself if it declares a new identifier.
+	fn definition_site(&self, entity: EntityRef) -> Option<PatternRef>;
+
+	/// Get the identifier expressions across all models which refer to the given declaration.
+	///
+	/// Identifiers which refer to a different declaration with the same name (e.g. a local
+	/// variable shadowing a global one) are not included.
+	fn references(&self, target: PatternRef) -> Arc<Vec<EntityRef>>;
+
+	/// Get the global parameters which have no definition in the model, and are not among
+	/// the given identifiers assigned in data.
+	fn unassigned_parameters(&self, assigned: Arc<Vec<Identifier>>) -> Arc<Vec<PatternRef>>;
+
+	/// Get errors for enums which have no definition in the model and are not among the given
+	/// identifiers assigned in data, but are used as array index sets.
+	fn undefined_enum_index_sets(&self, assigned: Arc<Vec<Identifier>>) -> Arc<Vec<Error>>;
+
+	/// Get the global `var` declarations and their types, in declaration order.
+	///
+	/// Parameters, functions and local declarations are not included.
+	fn decision_variables(&self) -> Arc<Vec<(Identifier, Ty)>>;
+
+	/// Get the global `var` declarations whose domain contains exactly one value, and are
+	/// therefore effectively constants.
+	///
+	/// Identifiers used as domains are resolved using the definitions of the declarations they
+	/// refer to. Only domains whose bounds are (possibly negated) literals are detected, and
+	/// parameters assigned in data are not taken into account, so a domain such as `n..n` is never
+	/// reported if `n` is only given in data.
+	fn singleton_domain_variables(&self) -> Arc<Vec<PatternRef>>;
+
 	/// Topologically sort items
 	///
 	/// Use `lookup_topological_sorted_items` to remain diagnostics independent.
 	#[salsa::invoke(super::typecheck::topological_sort)]
-	fn topological_sort_items(&self) -> (Arc<Vec<ItemRef>>, Arc<Vec<Error>>);
+	fn topological_sort_items(
+		&self,
+	) -> (
+		Arc<Vec<ItemRef>>,
+		Arc<Vec<Error>>,
+		Arc<FxHashMap<ItemRef, Arc<Vec<ItemRef>>>>,
+	);
 
 	/// Lookup the topologically sorted item order
 	fn lookup_topological_sorted_items(&self) -> Arc<Vec<ItemRef>>;
@@ -165,6 +284,40 @@ pub trait Hir:
 	/// Lookup errors from topologically sorting items
 	fn lookup_topological_sorted_items_errors(&self) -> Arc<Vec<Error>>;
 
+	/// Get the items which the given item directly depends on (as used for topological sorting)
+	fn item_dependencies(&self, item: ItemRef) -> Arc<Vec<ItemRef>>;
+
+	/// Check for uses of `==` in the given model (if enabled by `warn_double_equals`)
+	fn check_double_equals(&self, model: ModelRef) -> Arc<Vec<Warning>>;
+
+	/// Check for recursive functions which have no terminating branch
+	#[salsa::invoke(super::typecheck::check_function_recursion)]
+	fn check_function_recursion(&self) -> Arc<Vec<Warning>>;
+
+	/// Get the solve item across all included models (if there is one)
+	///
+	/// Gives an error if there are multiple solve items.
+	#[salsa::invoke(super::validate::solve_item)]
+	fn solve_item(&self) -> Result<Option<ItemRef>, Arc<Vec<Error>>>;
+
+	/// Get whether the model is a satisfaction, minimization or maximization problem
+	///
+	/// Gives `None` if there is no solve item, and an error if there are multiple solve items.
+	#[salsa::invoke(super::validate::solve_method)]
+	fn solve_method(&self) -> Result<Option<SolveMethod>, Arc<Vec<Error>>>;
+
+	/// Check for constraints which are Boolean literals in the given model
+	///
+	/// `constraint false` is an error if `unsatisfiable_constraint_error` is set, otherwise a warning.
+	#[salsa::invoke(super::validate::check_constant_constraints)]
+	fn check_constant_constraints(&self, model: ModelRef) -> (Arc<Vec<Error>>, Arc<Vec<Warning>>);
+
+	/// Lookup errors from checking for constant constraints
+	fn lookup_constant_constraint_errors(&self, model: ModelRef) -> Arc<Vec<Error>>;
+
+	/// Lookup warnings from checking for constant constraints
+	fn lookup_constant_constraint_warnings(&self, model: ModelRef) -> Arc<Vec<Warning>>;
+
 	/// Validate HIR
 	#[salsa::invoke(super::validate::validate_hir)]
 	fn validate_hir(&self) -> Arc<Vec<Error>>;
@@ -194,6 +347,13 @@ pub trait Hir:
 	#[salsa::invoke(super::pattern_matching::lookup_enum_constructors)]
 	fn lookup_enum_constructors(&self, e: EnumRef) -> Option<Arc<Vec<PatternRef>>>;
 
+	/// Get every enum type declared in the model (in declaration order) along with its
+	/// constructor patterns.
+	///
+	/// Enums which are never defined have no constructors.
+	#[salsa::invoke(super::pattern_matching::all_enums)]
+	fn all_enums(&self) -> Arc<Vec<(EnumRef, Arc<Vec<PatternRef>>)>>;
+
 	/// Get the items in the given model which contain a case expression
 	///
 	/// Allows us to only perform case exhaustiveness checking if there are actually
@@ -212,6 +372,11 @@ pub trait Hir:
 
 	/// Get counts of entities across all models
 	fn entity_counts(&self) -> Arc<EntityCounts>;
+
+	/// Get the documentation comment for the given item, made up of the line comments
+	/// immediately preceding it (without the comment markers).
+	#[salsa::invoke(super::source::item_doc_comment)]
+	fn item_doc_comment(&self, item: ItemRef) -> Option<String>;
 }
 
 fn run_hir_phase(db: &dyn Hir) -> Result<Arc<Vec<ItemRef>>, Arc<Diagnostics<Error>>> {
@@ -259,6 +424,21 @@ fn run_hir_phase(db: &dyn Hir) -> Result<Arc<Vec<ItemRef>>, Arc<Diagnostics<Erro
 	}
 }
 
+fn model_includes(db: &dyn Hir, model: ModelRef) -> Arc<Vec<String>> {
+	let Ok(ConstraintModel::MznModel(model)) = db.ast(*model) else {
+		return Arc::new(Vec::new());
+	};
+	Arc::new(
+		model
+			.items()
+			.filter_map(|item| match item {
+				minizinc::Item::Include(i) => Some(i.file().value()),
+				_ => None,
+			})
+			.collect(),
+	)
+}
+
 fn identifier_registry(db: &dyn Hir) -> Arc<IdentifierRegistry> {
 	Arc::new(IdentifierRegistry::new(db))
 }
@@ -340,45 +520,22 @@ fn resolve_includes(db: &dyn Hir) -> Result<Arc<Vec<ModelRef>>> {
 		for item in model.items() {
 			if let minizinc::Item::Include(i) = item {
 				let value = i.file().value();
-				let included = Path::new(&value);
-
-				let resolved_file = if included.is_absolute() {
-					included.to_owned()
-				} else {
-					// Resolve relative to search directories, then current file
-					let file_dir = model
-						.cst()
-						.file()
-						.path(db.upcast())
-						.and_then(|p| p.parent().map(|p| p.to_owned()));
-
-					let resolved = if included.starts_with("./") {
-						file_dir.map(|p| p.join(included)).filter(|p| p.exists())
-					} else {
-						search_dirs
-							.iter()
-							.chain(file_dir.iter())
-							.map(|p| p.join(included))
-							.find(|p| p.exists())
-					};
-
-					match resolved {
-						Some(r) => r,
-						None => {
-							let (src, span) = i.cst_node().source_span(db.upcast());
-							errors.push(
-								IncludeError {
-									src,
-									span,
-									include: value,
-								}
-								.into(),
-							);
-							continue;
-						}
+				match resolve_include_path(db, &model, Path::new(&value)) {
+					Some(resolved_file) => {
+						todo.push(FileRef::new(&resolved_file, db.upcast()).into())
 					}
-				};
-				todo.push(FileRef::new(&resolved_file, db.upcast()).into());
+					None => {
+						let (src, span) = i.cst_node().source_span(db.upcast());
+						errors.push(
+							IncludeError {
+								src,
+								span,
+								include: value,
+							}
+							.into(),
+						);
+					}
+				}
 			}
 		}
 	}
@@ -392,6 +549,65 @@ fn resolve_includes(db: &dyn Hir) -> Result<Arc<Vec<ModelRef>>> {
 	}
 }
 
+/// Find the file referred to by an include item in the given model
+fn resolve_include_path(
+	db: &dyn Hir,
+	model: &minizinc::MznModel,
+	included: &Path,
+) -> Option<PathBuf> {
+	if included.is_absolute() {
+		return Some(included.to_owned());
+	}
+	// Resolve relative to search directories, then current file
+	let file_dir = model
+		.cst()
+		.file()
+		.path(db.upcast())
+		.and_then(|p| p.parent().map(|p| p.to_owned()));
+	if included.starts_with("./") {
+		file_dir.map(|p| p.join(included)).filter(|p| p.exists())
+	} else {
+		db.include_search_dirs()
+			.iter()
+			.chain(file_dir.iter())
+			.map(|p| p.join(included))
+			.find(|p| p.exists())
+	}
+}
+
+fn include_closure(db: &dyn Hir, model: ModelRef) -> Arc<Vec<ModelRef>> {
+	let mut models = Vec::new();
+	let mut seen = FxHashSet::default();
+	let mut todo = vec![model];
+	while let Some(file) = todo.pop() {
+		if let Some(path) = file
+			.path(db.upcast())
+			.map(|p| p.canonicalize().unwrap_or(p))
+		{
+			if !seen.insert(path) {
+				continue;
+			}
+		}
+		models.push(file);
+		let Ok(ConstraintModel::MznModel(ast)) = db.ast(*file) else {
+			continue;
+		};
+		let includes = ast
+			.items()
+			.filter_map(|item| match item {
+				minizinc::Item::Include(i) => {
+					let path = resolve_include_path(db, &ast, Path::new(&i.file().value()))?;
+					Some(ModelRef::from(FileRef::new(&path, db.upcast())))
+				}
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		// Visit includes in the order they appear
+		todo.extend(includes.into_iter().rev());
+	}
+	Arc::new(models)
+}
+
 fn enumeration_names(db: &dyn Hir) -> Arc<HashSet<Identifier>> {
 	// When lowering we need to know the enumeration item names so that we can
 	// correctly handle assignments to them
@@ -410,6 +626,24 @@ fn enumeration_names(db: &dyn Hir) -> Arc<HashSet<Identifier>> {
 	Arc::new(result)
 }
 
+fn eprime_parameter_names(db: &dyn Hir) -> Arc<HashSet<Identifier>> {
+	// When lowering Essence' models we need to know the parameters declared in other models, so
+	// that constant definitions in separate param files become assignments to them
+	let mut result = HashSet::default();
+	let models = db.resolve_includes().unwrap();
+	for model in models.iter() {
+		let ast = db.ast(**model).unwrap();
+		if let ConstraintModel::EPrimeModel(ast) = ast {
+			for item in ast.items() {
+				if let eprime::Item::ParamDeclaration(p) = item {
+					result.extend(p.names().map(|n| Identifier::new(n.name(), db)));
+				}
+			}
+		}
+	}
+	Arc::new(result)
+}
+
 fn lookup_model(db: &dyn Hir, model: ModelRef) -> Arc<Model> {
 	db.lower_items(model).0
 }
@@ -422,6 +656,10 @@ fn lookup_lowering_errors(db: &dyn Hir, model: ModelRef) -> Arc<Vec<Error>> {
 	db.lower_items(model).2
 }
 
+fn lookup_lowering_warnings(db: &dyn Hir, model: ModelRef) -> Arc<Vec<Warning>> {
+	db.lower_items(model).3
+}
+
 fn lookup_items(db: &dyn Hir, model: ModelRef) -> Arc<Vec<ItemRef>> {
 	Arc::new(
 		db.lookup_model(model)
@@ -432,6 +670,92 @@ fn lookup_items(db: &dyn Hir, model: ModelRef) -> Arc<Vec<ItemRef>> {
 	)
 }
 
+fn expression_annotations(
+	db: &dyn Hir,
+	item: ItemRef,
+	expression: ArenaIndex<Expression>,
+) -> Arc<Vec<ArenaIndex<Expression>>> {
+	let model = item.model(db);
+	let data = item.local_item_ref(db).data(&model);
+	Arc::new(data.annotations(expression).collect())
+}
+
+fn required_annotations(db: &dyn Hir) -> Arc<FxHashSet<Identifier>> {
+	fn collect_annotation(
+		data: &ItemData,
+		e: ArenaIndex<Expression>,
+		result: &mut FxHashSet<Identifier>,
+	) {
+		match &data[e] {
+			Expression::Identifier(i) => {
+				result.insert(*i);
+			}
+			Expression::Call(c) => {
+				if let Expression::Identifier(i) = &data[c.function] {
+					result.insert(*i);
+				}
+				for arg in c.arguments.iter() {
+					match &data[*arg] {
+						Expression::Call(_) => collect_annotation(data, *arg, result),
+						Expression::ArrayLiteral(al) => {
+							for m in al.members.iter() {
+								if let Expression::Call(_) = &data[*m] {
+									collect_annotation(data, *m, result);
+								}
+							}
+						}
+						_ => (),
+					}
+				}
+			}
+			_ => (),
+		}
+	}
+
+	let mut result = FxHashSet::default();
+	for m in db.resolve_includes().unwrap().iter() {
+		let model = db.lookup_model(*m);
+		for item in db.lookup_items(*m).iter() {
+			let local_item = item.local_item_ref(db);
+			let data = local_item.data(&model);
+			let mut annotations = match local_item {
+				LocalItemRef::Constraint(c) => model[c].annotations.to_vec(),
+				LocalItemRef::Declaration(d) => model[d].annotations.to_vec(),
+				LocalItemRef::Enumeration(e) => model[e].annotations.to_vec(),
+				LocalItemRef::Function(f) => model[f]
+					.annotations
+					.iter()
+					.chain(
+						model[f]
+							.parameters
+							.iter()
+							.flat_map(|p| p.annotations.iter()),
+					)
+					.copied()
+					.collect(),
+				LocalItemRef::Solve(s) => model[s].annotations.to_vec(),
+				LocalItemRef::TypeAlias(t) => model[t].annotations.to_vec(),
+				_ => Vec::new(),
+			};
+			annotations.extend(data.annotations.values().flat_map(|anns| anns.iter()));
+			for e in data.expressions.values() {
+				if let Expression::Let(l) = e {
+					for i in l.items.iter() {
+						match i {
+							LetItem::Constraint(c) => annotations.extend(c.annotations.iter()),
+							LetItem::Declaration(d) => annotations.extend(d.annotations.iter()),
+						}
+					}
+				}
+			}
+			for ann in annotations {
+				collect_annotation(data, ann, &mut result);
+			}
+		}
+	}
+	Arc::new(result)
+}
+
 fn lookup_global_scope(db: &dyn Hir) -> Arc<ScopeData> {
 	db.collect_global_scope().0
 }
@@ -453,6 +777,341 @@ fn lookup_global_function(db: &dyn Hir, identifier: Identifier) -> Arc<Vec<Patte
 	Arc::new(fns)
 }
 
+fn global_variable_type(db: &dyn Hir, identifier: Identifier) -> Option<Ty> {
+	let pattern = db.lookup_global_variable(identifier)?;
+	let types = db.lookup_item_types(pattern.item());
+	match types.get_pattern(pattern.pattern())? {
+		PatternTy::Variable(ty) => Some(*ty),
+		_ => None,
+	}
+}
+
+fn function_signature(db: &dyn Hir, func: PatternRef) -> Option<FunctionSignature> {
+	let signature = db.lookup_item_signature(func.item());
+	let PatternTy::Function(f) = signature.patterns.get(&func)? else {
+		return None;
+	};
+	let (params, return_type) = match &f.overload {
+		OverloadedFunction::Function(f) => (f.params.clone(), f.return_type),
+		OverloadedFunction::PolymorphicFunction(p) => {
+			let par_int = db.type_registry().par_int;
+			let bounds: FxHashMap<_, _> = signature
+				.patterns
+				.values()
+				.filter_map(|t| match t {
+					PatternTy::TyVar(tv) if tv.enumerable && p.ty_params.contains(&tv.ty_var) => {
+						Some((tv.ty_var, par_int))
+					}
+					_ => None,
+				})
+				.collect();
+			let f = p.instantiate(db.upcast(), &bounds);
+			(f.params, f.return_type)
+		}
+	};
+	Some(FunctionSignature {
+		params,
+		return_type,
+	})
+}
+
+fn definition_site(db: &dyn Hir, entity: EntityRef) -> Option<PatternRef> {
+	let item = entity.item(db);
+	match entity.entity(db) {
+		LocalEntityRef::Expression(e) => db.lookup_item_types(item).name_resolution(e),
+		LocalEntityRef::Pattern(p) => Some(
+			db.lookup_item_types(item)
+				.pattern_resolution(p)
+				.unwrap_or_else(|| PatternRef::new(item, p)),
+		),
+		_ => None,
+	}
+}
+
+fn references(db: &dyn Hir, target: PatternRef) -> Arc<Vec<EntityRef>> {
+	let Ok(models) = db.resolve_includes() else {
+		return Arc::new(Vec::new());
+	};
+	let name = target.identifier(db);
+	let mut references = Vec::new();
+	for m in models.iter() {
+		for item in db.lookup_items(*m).iter() {
+			let model = item.model(db);
+			let data = item.local_item_ref(db).data(&model);
+			let mut types = None;
+			for (e, expression) in data.expressions.iter() {
+				if !matches!(expression, Expression::Identifier(i) if Some(*i) == name) {
+					continue;
+				}
+				// Only typecheck items which use the name
+				let types = types.get_or_insert_with(|| db.lookup_item_types(*item));
+				if types.name_resolution(e) == Some(target) {
+					references.push(EntityRef::new(db, *item, e));
+				}
+			}
+		}
+	}
+	Arc::new(references)
+}
+
+fn unassigned_parameters(db: &dyn Hir, assigned: Arc<Vec<Identifier>>) -> Arc<Vec<PatternRef>> {
+	let Ok(models) = db.resolve_includes() else {
+		return Arc::new(Vec::new());
+	};
+	let items = models
+		.iter()
+		.flat_map(|m| db.lookup_items(*m).iter().copied().collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	// Declarations assigned using an assignment item in the model
+	let mut model_assigned = FxHashSet::default();
+	for item in items.iter() {
+		if let LocalItemRef::Assignment(a) = item.local_item_ref(db) {
+			let model = item.model(db);
+			let types = db.lookup_item_types(*item);
+			if let Some(p) = types.name_resolution(model[a].assignee) {
+				model_assigned.insert(p);
+			}
+		}
+	}
+	let mut unassigned = Vec::new();
+	for item in items.iter() {
+		let LocalItemRef::Declaration(d) = item.local_item_ref(db) else {
+			continue;
+		};
+		let model = item.model(db);
+		if model[d].definition.is_some() {
+			continue;
+		}
+		let data = item.local_item_ref(db).data(&model);
+		let types = db.lookup_item_types(*item);
+		for p in Pattern::identifiers(model[d].pattern, data) {
+			let pattern = PatternRef::new(*item, p);
+			let is_par = matches!(
+				types.get_pattern(p),
+				Some(PatternTy::Variable(ty)) if ty.known_par(db.upcast())
+			);
+			let in_data = data[p]
+				.identifier()
+				.map(|i| assigned.contains(&i))
+				.unwrap_or(false);
+			if is_par && !in_data && !model_assigned.contains(&pattern) {
+				unassigned.push(pattern);
+			}
+		}
+	}
+	Arc::new(unassigned)
+}
+
+fn undefined_enum_index_sets(db: &dyn Hir, assigned: Arc<Vec<Identifier>>) -> Arc<Vec<Error>> {
+	let Ok(models) = db.resolve_includes() else {
+		return Arc::new(Vec::new());
+	};
+	let items = models
+		.iter()
+		.flat_map(|m| db.lookup_items(*m).iter().copied().collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let mut defined = FxHashSet::default();
+	let mut undefined = FxHashSet::default();
+	for item in items.iter() {
+		let model = item.model(db);
+		match item.local_item_ref(db) {
+			LocalItemRef::Enumeration(e) => {
+				let pattern = PatternRef::new(*item, model[e].pattern);
+				if model[e].definition.is_some() {
+					defined.insert(pattern);
+				} else {
+					undefined.insert(pattern);
+				}
+			}
+			LocalItemRef::EnumAssignment(a) => {
+				let types = db.lookup_item_types(*item);
+				if let Some(p) = types.name_resolution(model[a].assignee) {
+					defined.insert(p);
+				}
+			}
+			_ => (),
+		}
+	}
+	undefined.retain(|p| {
+		!defined.contains(p)
+			&& !p
+				.identifier(db)
+				.map(|i| assigned.contains(&i))
+				.unwrap_or(false)
+	});
+	if undefined.is_empty() {
+		return Arc::new(Vec::new());
+	}
+	let mut errors = Vec::new();
+	for item in items.iter() {
+		let model = item.model(db);
+		let data = item.local_item_ref(db).data(&model);
+		let types = db.lookup_item_types(*item);
+		for (_, t) in data.types.iter() {
+			let Type::Array { dimensions, .. } = t else {
+				continue;
+			};
+			let dims = match &data[*dimensions] {
+				Type::Tuple { fields, .. } => fields.to_vec(),
+				_ => vec![*dimensions],
+			};
+			for dim in dims {
+				let Type::Bounded { domain, .. } = &data[dim] else {
+					continue;
+				};
+				match types.name_resolution(*domain) {
+					Some(p) if undefined.contains(&p) => {
+						let (src, span) =
+							NodeRef::from(EntityRef::new(db, *item, *domain)).source_span(db);
+						errors.push(
+							UndefinedEnumIndexSet {
+								src,
+								span,
+								name: p.identifier(db).unwrap().lookup(db),
+							}
+							.into(),
+						);
+					}
+					_ => (),
+				}
+			}
+		}
+	}
+	Arc::new(errors)
+}
+
+fn decision_variables(db: &dyn Hir) -> Arc<Vec<(Identifier, Ty)>> {
+	let Ok(models) = db.resolve_includes() else {
+		return Arc::new(Vec::new());
+	};
+	let mut variables = Vec::new();
+	for m in models.iter() {
+		for item in db.lookup_items(*m).iter() {
+			let LocalItemRef::Declaration(d) = item.local_item_ref(db) else {
+				continue;
+			};
+			let model = item.model(db);
+			let data = item.local_item_ref(db).data(&model);
+			let types = db.lookup_item_types(*item);
+			for p in Pattern::identifiers(model[d].pattern, data) {
+				if let (Some(i), Some(PatternTy::Variable(ty))) =
+					(data[p].identifier(), types.get_pattern(p))
+				{
+					if !ty.known_par(db.upcast()) {
+						variables.push((i, *ty));
+					}
+				}
+			}
+		}
+	}
+	Arc::new(variables)
+}
+
+fn singleton_domain_variables(db: &dyn Hir) -> Arc<Vec<PatternRef>> {
+	let Ok(models) = db.resolve_includes() else {
+		return Arc::new(Vec::new());
+	};
+	let mut variables = Vec::new();
+	for m in models.iter() {
+		for item in db.lookup_items(*m).iter() {
+			let LocalItemRef::Declaration(d) = item.local_item_ref(db) else {
+				continue;
+			};
+			let model = item.model(db);
+			let data = item.local_item_ref(db).data(&model);
+			let Type::Bounded { domain, .. } = data[model[d].declared_type] else {
+				continue;
+			};
+			let types = db.lookup_item_types(*item);
+			let is_var = matches!(
+				types.get_pattern(model[d].pattern),
+				Some(PatternTy::Variable(ty)) if !ty.known_par(db.upcast())
+			);
+			if is_var && is_singleton_domain(db, *item, domain) {
+				variables.push(PatternRef::new(*item, model[d].pattern));
+			}
+		}
+	}
+	Arc::new(variables)
+}
+
+/// Whether the given domain expression is a range or set literal containing exactly one value
+fn is_singleton_domain(
+	db: &dyn Hir,
+	mut item: ItemRef,
+	mut domain: ArenaIndex<Expression>,
+) -> bool {
+	let ids = db.identifier_registry();
+	let mut visited = FxHashSet::default();
+	loop {
+		let model = item.model(db);
+		let data = item.local_item_ref(db).data(&model);
+		match &data[domain] {
+			Expression::Identifier(_) => {
+				// Follow the identifier to the definition of its declaration
+				let Some(p) = db.lookup_item_types(item).name_resolution(domain) else {
+					return false;
+				};
+				let LocalItemRef::Declaration(d) = p.item().local_item_ref(db) else {
+					return false;
+				};
+				let Some(definition) = p.item().model(db)[d].definition else {
+					return false;
+				};
+				if !visited.insert(p) {
+					return false;
+				}
+				item = p.item();
+				domain = definition;
+			}
+			Expression::SetLiteral(sl) => return sl.members.len() == 1,
+			Expression::Call(c) => {
+				let is_range = matches!(
+					&data[c.function],
+					Expression::Identifier(i) if *i == ids.dot_dot
+				);
+				return match &c.arguments[..] {
+					[a, b] if is_range => {
+						let lower = range_bound(&ids, data, *a);
+						lower.is_some() && lower == range_bound(&ids, data, *b)
+					}
+					_ => false,
+				};
+			}
+			_ => return false,
+		}
+	}
+}
+
+/// Get the integer or float literal given as a range bound, evaluating any negation (which is
+/// only folded into the literal during lowering if constant folding is enabled)
+fn range_bound(
+	ids: &IdentifierRegistry,
+	data: &ItemData,
+	bound: ArenaIndex<Expression>,
+) -> Option<Expression> {
+	match &data[bound] {
+		Expression::IntegerLiteral(_) | Expression::FloatLiteral(_) => Some(data[bound].clone()),
+		Expression::Call(c) => {
+			let is_negation = matches!(
+				&data[c.function],
+				Expression::Identifier(i) if *i == ids.minus
+			);
+			match &c.arguments[..] {
+				[a] if is_negation => match range_bound(ids, data, *a)? {
+					Expression::IntegerLiteral(IntegerLiteral(v)) => {
+						Some(IntegerLiteral(v.checked_neg()?).into())
+					}
+					Expression::FloatLiteral(v) => Some(FloatLiteral::new(-v.value()).into()),
+					_ => None,
+				},
+				_ => None,
+			}
+		}
+		_ => None,
+	}
+}
+
 fn lookup_item_scope(db: &dyn Hir, item: ItemRef) -> Arc<ScopeResult> {
 	db.collect_item_scope(item).result
 }
@@ -469,6 +1128,20 @@ fn lookup_item_signature(db: &dyn Hir, item: ItemRef) -> Arc<SignatureTypes> {
 	db.collect_item_signature(item).0
 }
 
+fn is_output_only(db: &dyn Hir, decl: PatternRef) -> bool {
+	let item = decl.item();
+	let LocalItemRef::Declaration(d) = item.local_item_ref(db) else {
+		return false;
+	};
+	let model = item.model(db);
+	let it = &model[d];
+	let ids = db.identifier_registry();
+	it.annotations.iter().any(|ann| match &it.data[*ann] {
+		Expression::Identifier(i) => *i == ids.output_only,
+		_ => false,
+	})
+}
+
 fn lookup_item_signature_errors(db: &dyn Hir, item: ItemRef) -> Arc<Vec<Error>> {
 	db.collect_item_signature(item).1
 }
@@ -489,6 +1162,14 @@ fn lookup_topological_sorted_items_errors(db: &dyn Hir) -> Arc<Vec<Error>> {
 	db.topological_sort_items().1
 }
 
+fn item_dependencies(db: &dyn Hir, item: ItemRef) -> Arc<Vec<ItemRef>> {
+	db.topological_sort_items()
+		.2
+		.get(&item)
+		.cloned()
+		.unwrap_or_default()
+}
+
 fn items_with_case(db: &dyn Hir, model: ModelRef) -> Arc<Vec<ItemRef>> {
 	let source_map = db.lookup_source_map(model);
 	let cst = db.cst(*model).unwrap();
@@ -528,6 +1209,14 @@ fn lookup_case_exhaustiveness_warnings(db: &dyn Hir, item: ItemRef) -> Arc<Vec<W
 	db.check_case_exhaustiveness(item).1
 }
 
+fn lookup_constant_constraint_errors(db: &dyn Hir, model: ModelRef) -> Arc<Vec<Error>> {
+	db.check_constant_constraints(model).0
+}
+
+fn lookup_constant_constraint_warnings(db: &dyn Hir, model: ModelRef) -> Arc<Vec<Warning>> {
+	db.check_constant_constraints(model).1
+}
+
 fn syntax_errors(db: &dyn Hir) -> Arc<Vec<Error>> {
 	let errors = db
 		.resolve_includes()
@@ -565,6 +1254,8 @@ fn all_errors(db: &dyn Hir) -> Arc<Diagnostics<Error>> {
 					// Collect pattern matching exhaustiveness errors
 					diagnostics.extend(db.lookup_case_exhaustiveness_errors(*i));
 				}
+				// Collect constant constraint errors
+				diagnostics.extend(db.lookup_constant_constraint_errors(*m));
 			}
 			// Collect global scope errors
 			diagnostics.extend(db.lookup_global_scope_errors());
@@ -578,10 +1269,54 @@ fn all_errors(db: &dyn Hir) -> Arc<Diagnostics<Error>> {
 	Arc::new(diagnostics)
 }
 
+fn check_double_equals(db: &dyn Hir, model: ModelRef) -> Arc<Vec<Warning>> {
+	let mut warnings = Vec::new();
+	if !db.warn_double_equals() {
+		return Arc::new(warnings);
+	}
+	// Don't warn for the standard library
+	if let (Some(path), Ok(share)) = (model.path(db.upcast()), db.share_directory()) {
+		if path.starts_with(&*share) {
+			return Arc::new(warnings);
+		}
+	}
+	let ast = match db.ast(*model) {
+		Ok(ConstraintModel::MznModel(ast)) => ast,
+		_ => return Arc::new(warnings),
+	};
+	let cst = ast.cst();
+	let mut cursor = cst.root_node().walk();
+	loop {
+		let node = cursor.node();
+		if node.kind() == "infix_operator" {
+			if let Some(operator) = node
+				.child_by_field_name("operator")
+				.filter(|o| o.kind() == "==")
+			{
+				let (src, span) = cst.node(operator).source_span(db.upcast());
+				warnings.push(DoubleEquals { src, span }.into());
+			}
+		}
+		if !cursor.goto_first_child() {
+			while !cursor.goto_next_sibling() {
+				if !cursor.goto_parent() {
+					return Arc::new(warnings);
+				}
+			}
+		}
+	}
+}
+
 fn all_warnings(db: &dyn Hir) -> Arc<Diagnostics<Warning>> {
 	let mut diagnostics = Diagnostics::default();
 	if let Ok(r) = db.resolve_includes() {
 		for m in r.iter() {
+			// Collect lowering warnings
+			diagnostics.extend(db.lookup_lowering_warnings(*m));
+			// Collect double equals warnings
+			diagnostics.extend(db.check_double_equals(*m));
+			// Collect constant constraint warnings
+			diagnostics.extend(db.lookup_constant_constraint_warnings(*m));
 			for i in db.lookup_items(*m).iter() {
 				// Collect scoping warnings
 				diagnostics.extend(db.lookup_item_scope_warnings(*i));
@@ -589,6 +1324,8 @@ fn all_warnings(db: &dyn Hir) -> Arc<Diagnostics<Warning>> {
 				diagnostics.extend(db.lookup_case_exhaustiveness_warnings(*i));
 			}
 		}
+		// Collect recursion warnings
+		diagnostics.extend(db.check_function_recursion());
 	}
 	Arc::new(diagnostics)
 }
@@ -622,6 +1359,14 @@ pub struct EntityCounts {
 	pub types: u32,
 	/// Pattern count
 	pub patterns: u32,
+	/// Let expression item count
+	pub let_items: u32,
+	/// Comprehension generator count
+	pub generators: u32,
+	/// Case expression branch count
+	pub case_branches: u32,
+	/// Array and set comprehension count
+	pub comprehensions: u32,
 }
 
 fn entity_counts(db: &dyn Hir) -> Arc<EntityCounts> {
@@ -638,24 +1383,822 @@ fn entity_counts(db: &dyn Hir) -> Arc<EntityCounts> {
 		counts.outputs += model.outputs.len();
 		counts.solves += model.solves.len();
 		counts.type_aliases += model.type_aliases.len();
-		let data = model
-			.annotations
-			.values()
-			.map(|v| &v.data)
-			.chain(model.assignments.values().map(|v| &v.data))
-			.chain(model.enum_assignments.values().map(|v| &v.data))
-			.chain(model.constraints.values().map(|v| &v.data))
-			.chain(model.declarations.values().map(|v| &v.data))
-			.chain(model.enumerations.values().map(|v| &v.data))
-			.chain(model.functions.values().map(|v| &v.data))
-			.chain(model.outputs.values().map(|v| &v.data))
-			.chain(model.solves.values().map(|v| &v.data))
-			.chain(model.type_aliases.values().map(|v| &v.data));
-		for d in data {
+		for (_, item) in model.iter_items() {
+			let d = item.data();
 			counts.expressions += d.expressions.len();
 			counts.types += d.types.len();
 			counts.patterns += d.patterns.len();
+			for e in d.expressions.values() {
+				match e {
+					Expression::Let(l) => counts.let_items += l.items.len() as u32,
+					Expression::Case(c) => counts.case_branches += c.cases.len() as u32,
+					Expression::ArrayComprehension(c) => {
+						counts.comprehensions += 1;
+						counts.generators += c.generators.len() as u32;
+					}
+					Expression::SetComprehension(c) => {
+						counts.comprehensions += 1;
+						counts.generators += c.generators.len() as u32;
+					}
+					_ => (),
+				}
+			}
 		}
 	}
 	Arc::new(counts)
 }
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use expect_test::expect;
+
+	use crate::{
+		db::{CompilerDatabase, CompilerSettings, FileReader, Inputs},
+		file::{InputFile, InputLang},
+		hir::{
+			db::Hir,
+			ids::{EntityRef, ItemKind, ItemRef, LocalItemRef},
+			Expression, Identifier, ModelItem, SolveMethod,
+		},
+		Error, Warning,
+	};
+
+	/// Create a database containing the given MiniZinc model, without the standard library
+	fn model_db(model: &str) -> CompilerDatabase {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			model.to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		db
+	}
+
+	#[test]
+	fn test_iter_items() {
+		let db = model_db(
+			r#"
+			int: x;
+			constraint x > 1;
+			function int: f(int: a) = a;
+			int: y = f(x);
+			x = 2;
+			solve satisfy;
+			"#,
+		);
+		let model = db.lookup_model(db.input_models()[0]);
+		let items = model.iter_items().map(|(i, _)| i).collect::<Vec<_>>();
+		assert_eq!(items, model.items);
+		let kinds = model
+			.iter_items()
+			.map(|(_, item)| match item {
+				ModelItem::Assignment(_) => "assignment",
+				ModelItem::Constraint(_) => "constraint",
+				ModelItem::Declaration(_) => "declaration",
+				ModelItem::Function(_) => "function",
+				ModelItem::Solve(_) => "solve",
+				_ => unreachable!(),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			kinds,
+			[
+				"declaration",
+				"constraint",
+				"function",
+				"declaration",
+				"assignment",
+				"solve"
+			]
+		);
+		let declarations = model
+			.items_of_kind(ItemKind::Declaration)
+			.map(|(i, _)| i)
+			.collect::<Vec<_>>();
+		assert_eq!(declarations, [model.items[0], model.items[3]]);
+		assert_eq!(model.items_of_kind(ItemKind::Output).count(), 0);
+	}
+
+	#[test]
+	fn test_entity_counts() {
+		let db = model_db(
+			r#"
+			enum Foo = {A, B};
+			array [int] of int: x = [i | i in 1..3, j in 1..2 where i = j];
+			any: y = let { int: a = 1; int: b = 2; } in {a, b};
+			any: z = case A of A => 1, B => 2 endcase;
+			"#,
+		);
+		let counts = db.entity_counts();
+		expect!([r#"
+    (
+        2,
+        2,
+        2,
+        1,
+    )
+"#])
+		.assert_debug_eq(&(
+			counts.let_items,
+			counts.generators,
+			counts.case_branches,
+			counts.comprehensions,
+		));
+	}
+
+	#[test]
+	fn test_solve_item() {
+		let mut db = model_db("var 1..3: x;\nsolve maximize x;");
+		assert!(db.solve_item().unwrap().is_some());
+		db.set_input_files(Arc::new(vec![
+			InputFile::String(
+				"var 1..3: x;\nsolve maximize x;".to_owned(),
+				InputLang::MiniZinc,
+			),
+			InputFile::String("solve satisfy;".to_owned(), InputLang::MiniZinc),
+		]));
+		let errors = db.solve_item().unwrap_err();
+		assert_eq!(errors.len(), 1);
+		match &errors[0] {
+			Error::MultipleSolveItems(e) => {
+				let mut offsets = std::iter::once(e.span.offset())
+					.chain(e.others.iter().map(|o| o.span.offset()))
+					.collect::<Vec<_>>();
+				offsets.sort();
+				assert_eq!(offsets, vec![0, 13]);
+			}
+			_ => unreachable!(),
+		}
+	}
+
+	#[test]
+	fn test_multiple_assignments() {
+		let check = |source: &str| {
+			let db = model_db(source);
+			db.validate_hir()
+				.iter()
+				.filter_map(|e| match e {
+					Error::MultipleAssignments(e) => Some((
+						e.variable.clone(),
+						std::iter::once(e.span.offset())
+							.chain(e.others.iter().map(|o| o.span.offset()))
+							.collect::<Vec<_>>(),
+					)),
+					_ => None,
+				})
+				.collect::<Vec<_>>()
+		};
+		// Declaration with a definition followed by an assignment
+		assert_eq!(
+			check("int: x = 1;\nx = 2;"),
+			vec![("x".to_owned(), vec![9, 12])]
+		);
+		// Two assignment items
+		assert_eq!(
+			check("int: x;\nx = 1;\nx = 2;"),
+			vec![("x".to_owned(), vec![8, 15])]
+		);
+		// Enum definition followed by an enum assignment
+		let enums = check("enum E = {A};\nE = {B};");
+		assert_eq!(enums.len(), 1);
+		assert_eq!(enums[0].0, "E");
+		assert_eq!(enums[0].1.len(), 2);
+		assert_eq!(enums[0].1[1], 14);
+		// A single assignment is fine
+		assert!(check("int: x;\nx = 1;\nenum E;\nE = {A};").is_empty());
+	}
+
+	#[test]
+	fn test_all_enums() {
+		let db = model_db(
+			r#"
+			enum Foo = {A, B};
+			enum Bar = _(1..2) ++ {C};
+			enum Qux;
+			"#,
+		);
+		let enums = db
+			.all_enums()
+			.iter()
+			.map(|(e, cs)| {
+				let constructors = cs
+					.iter()
+					.map(|c| {
+						c.identifier(&db)
+							.map(|i| i.pretty_print(&db))
+							.unwrap_or_else(|| "_".to_owned())
+					})
+					.collect::<Vec<_>>();
+				format!("{}: {}", e.pretty_print(&db), constructors.join(", "))
+			})
+			.collect::<Vec<_>>();
+		expect!([r#"
+    [
+        "Foo: A, B",
+        "Bar: _, C",
+        "Qux: ",
+    ]
+"#])
+		.assert_debug_eq(&enums);
+	}
+
+	#[test]
+	fn test_required_annotations() {
+		let db = model_db(
+			r#"
+			annotation my_hint(int: x);
+			array [1..3] of var 1..3: x :: is_defined_var;
+			constraint foo(x) :: my_hint(1);
+			any: y = let { var int: z :: is_defined_var; } in z;
+			solve :: seq_search([int_search(x, input_order, indomain_min)]) satisfy;
+			"#,
+		);
+		let mut annotations = db
+			.required_annotations()
+			.iter()
+			.map(|i| i.pretty_print(&db))
+			.collect::<Vec<_>>();
+		annotations.sort();
+		expect!([r#"
+    [
+        "int_search",
+        "is_defined_var",
+        "my_hint",
+        "seq_search",
+    ]
+"#])
+		.assert_debug_eq(&annotations);
+	}
+
+	#[test]
+	fn test_search_annotations() {
+		let db = model_db(
+			r#"
+			solve
+				:: seq_search([
+					int_search(x, input_order, indomain_min),
+					seq_search([bool_search(b, first_fail, indomain_max, complete)]),
+				])
+				:: my_hint
+				satisfy;
+			"#,
+		);
+		let item = db.lookup_items(db.input_models()[0])[0];
+		let model = item.model(&db);
+		let LocalItemRef::Solve(s) = item.local_item_ref(&db) else {
+			unreachable!()
+		};
+		let searches = model[s]
+			.search_annotations(&db, &model[s].data)
+			.into_iter()
+			.map(|search| {
+				let Expression::Identifier(variables) = model[s].data[search.variables] else {
+					unreachable!()
+				};
+				format!(
+					"{}({}, {}, {})",
+					search.function.pretty_print(&db),
+					variables.pretty_print(&db),
+					search.var_select.pretty_print(&db),
+					search.value_select.pretty_print(&db)
+				)
+			})
+			.collect::<Vec<_>>();
+		expect!([r#"
+    [
+        "int_search(x, input_order, indomain_min)",
+        "bool_search(b, first_fail, indomain_max)",
+    ]
+"#])
+		.assert_debug_eq(&searches);
+	}
+
+	#[test]
+	fn test_expression_annotations() {
+		let db = model_db("constraint foo(bar(1) :: domain_consistency);");
+		let item = db.lookup_items(db.input_models()[0])[0];
+		let model = item.model(&db);
+		let data = item.local_item_ref(&db).data(&model);
+		let call = |name: &str| {
+			let function = Expression::Identifier(Identifier::new(name, &db));
+			data.expressions
+				.iter()
+				.find(|(_, e)| matches!(e, Expression::Call(c) if data[c.function] == function))
+				.unwrap()
+				.0
+		};
+		assert!(db.expression_annotations(item, call("foo")).is_empty());
+		let annotations = db.expression_annotations(item, call("bar"));
+		assert_eq!(annotations.len(), 1);
+		assert_eq!(
+			data[annotations[0]],
+			Expression::Identifier(Identifier::new("domain_consistency", &db))
+		);
+	}
+
+	#[test]
+	fn test_global_variable_type() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			"array [1..3] of var int: x;".to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let x = db.global_variable_type(Identifier::new("x", &db)).unwrap();
+		expect!("array [int] of var int").assert_eq(&x.pretty_print(&db));
+		assert_eq!(db.global_variable_type(Identifier::new("y", &db)), None);
+	}
+
+	#[test]
+	fn test_function_signature() {
+		let db = model_db(
+			r#"
+			function array [$$E] of $T: reverse(array [$$E] of $T: x);
+			predicate p(var int: x, set of int: s);
+			int: y;
+			"#,
+		);
+		let signature = |name: &str| {
+			let f = db.lookup_global_function(Identifier::new(name, &db))[0];
+			let s = db.function_signature(f).unwrap();
+			format!(
+				"({}) -> {}",
+				s.params
+					.iter()
+					.map(|p| p.pretty_print(&db))
+					.collect::<Vec<_>>()
+					.join(", "),
+				s.return_type.pretty_print(&db)
+			)
+		};
+		expect!("(array [int] of $T) -> array [int] of $T").assert_eq(&signature("reverse"));
+		expect!("(var int, set of int) -> var bool").assert_eq(&signature("p"));
+
+		let y = db
+			.lookup_global_variable(Identifier::new("y", &db))
+			.unwrap();
+		assert_eq!(db.function_signature(y), None);
+	}
+
+	#[test]
+	fn test_definition_site() {
+		let db = model_db(
+			r#"
+			int: g = 1;
+			function tuple(int, int): f(int: a) = (a, g);
+			any: y = let { int: l = 2 } in l;
+			"#,
+		);
+		let models = db.input_models();
+		let items = db.lookup_items(models[0]);
+		// Resolve the use of the given identifier in the given item
+		let resolve = |item: ItemRef, name: &str| {
+			let model = item.model(&db);
+			let data = item.local_item_ref(&db).data(&model);
+			let (e, _) = data
+				.expressions
+				.iter()
+				.find(|(_, e)| matches!(e, Expression::Identifier(i) if i.lookup(&db) == name))
+				.unwrap();
+			let p = db.definition_site(EntityRef::new(&db, item, e)).unwrap();
+			assert_eq!(p.identifier(&db).unwrap().lookup(&db), name);
+			p.item()
+		};
+		// Function parameter
+		assert_eq!(resolve(items[1], "a"), items[1]);
+		// Global declaration
+		assert_eq!(resolve(items[1], "g"), items[0]);
+		// Let-bound local
+		assert_eq!(resolve(items[2], "l"), items[2]);
+	}
+
+	#[test]
+	fn test_references() {
+		let db = model_db(
+			r#"
+			int: x = 1;
+			any: a = (x, 1);
+			any: b = let { int: x = 2 } in x;
+			any: c = [x];
+			"#,
+		);
+		let models = db.input_models();
+		let items = db.lookup_items(models[0]);
+		let x = db
+			.lookup_global_variable(Identifier::new("x", &db))
+			.unwrap();
+		let references = db.references(x);
+		assert_eq!(references.len(), 2);
+		assert_eq!(references[0].item(&db), items[1]);
+		assert_eq!(references[1].item(&db), items[3]);
+	}
+
+	#[test]
+	fn test_generator_shadowing() {
+		let source = r#"
+			any: a = [i | i in {1, 2}, j in {3}, i in {4}];
+			any: b = [i + j | i in {1, 2}, j in {3}];
+			"#;
+		let db = model_db(source);
+		let models = db.input_models();
+		let items = db.lookup_items(models[0]);
+
+		assert!(db.lookup_item_scope_errors(items[0]).is_empty());
+		let warnings = db.lookup_item_scope_warnings(items[0]);
+		assert_eq!(warnings.len(), 1);
+		let Warning::IdentifierShadowing(w) = &warnings[0] else {
+			panic!("expected shadowing warning");
+		};
+		assert_eq!(w.original.offset(), source.find("| i").unwrap() + 2);
+		assert_eq!(w.span.offset(), source.find(", i").unwrap() + 2);
+
+		assert!(db.lookup_item_scope_errors(items[1]).is_empty());
+		assert!(db.lookup_item_scope_warnings(items[1]).is_empty());
+	}
+
+	#[test]
+	fn test_let_forward_reference() {
+		let source = r#"
+			any: a = let { int: x = 1; int: y = x + 1; } in y;
+			any: b = let { int: x = y + 1; int: y = 1; } in x;
+			function int: f(int: n) = let { int: m = f(n - 1); int: f = 2; } in m + f;
+			"#;
+		let db = model_db(source);
+		let models = db.input_models();
+		let items = db.lookup_items(models[0]);
+
+		assert!(db.lookup_item_scope_errors(items[0]).is_empty());
+
+		let errors = db.lookup_item_scope_errors(items[1]);
+		assert_eq!(errors.len(), 1);
+		let Error::ForwardReference(e) = &errors[0] else {
+			panic!("expected forward reference error");
+		};
+		assert_eq!(e.identifier, "y");
+		assert_eq!(e.span.offset(), source.find("y + 1").unwrap());
+		assert_eq!(e.declaration.offset(), source.find("y = 1").unwrap());
+
+		assert!(db.lookup_item_scope_errors(items[2]).is_empty());
+	}
+
+	#[test]
+	fn test_decision_variables() {
+		let db = model_db(
+			r#"
+			int: n;
+			var bool: b;
+			set of int: s = {1, 2};
+			array [1..3] of var int: x;
+			any: y = x;
+			function var int: f(var int: a) = a;
+			"#,
+		);
+		let variables = db
+			.decision_variables()
+			.iter()
+			.map(|(i, ty)| format!("{}: {}", i.pretty_print(&db), ty.pretty_print(&db)))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			variables,
+			[
+				"b: var bool",
+				"x: array [int] of var int",
+				"y: array [int] of var int"
+			]
+		);
+	}
+
+	#[test]
+	fn test_solve_method() {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		let mut check = |source: &str, lang: InputLang| {
+			db.set_input_files(Arc::new(vec![InputFile::String(source.to_owned(), lang)]));
+			db.solve_method().unwrap()
+		};
+		assert_eq!(check("var 1..3: x;", InputLang::MiniZinc), None);
+		assert_eq!(
+			check("var 1..3: x;\nsolve satisfy;", InputLang::MiniZinc),
+			Some(SolveMethod::Satisfy)
+		);
+		assert_eq!(
+			check("var 1..3: x;\nsolve minimize x;", InputLang::MiniZinc),
+			Some(SolveMethod::Minimize)
+		);
+		assert_eq!(
+			check("var 1..3: x;\nsolve maximize x;", InputLang::MiniZinc),
+			Some(SolveMethod::Maximize)
+		);
+		// EPrime models without an objective get a synthesized satisfy item
+		assert_eq!(
+			check("find x : int(1..3)", InputLang::EPrime),
+			Some(SolveMethod::Satisfy)
+		);
+		assert_eq!(
+			check("find x : int(1..3)\nminimising x", InputLang::EPrime),
+			Some(SolveMethod::Minimize)
+		);
+		assert_eq!(
+			check("find x : int(1..3)\nmaximising x", InputLang::EPrime),
+			Some(SolveMethod::Maximize)
+		);
+
+		db.set_input_files(Arc::new(vec![
+			InputFile::String(
+				"var 1..3: x;\nsolve maximize x;".to_owned(),
+				InputLang::MiniZinc,
+			),
+			InputFile::String("solve satisfy;".to_owned(), InputLang::MiniZinc),
+		]));
+		assert!(db.solve_method().is_err());
+	}
+
+	#[test]
+	fn test_objective_undefined_identifier() {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		let mut check = |source: &str, lang: InputLang| {
+			db.set_input_files(Arc::new(vec![InputFile::String(source.to_owned(), lang)]));
+			let errors = db.all_errors();
+			assert_eq!(errors.len(), 1, "{:?}", errors);
+			match errors.iter().next().unwrap() {
+				Error::UndefinedIdentifier(e) => {
+					assert_eq!(e.identifier, "totl");
+					assert_eq!(e.span.offset(), source.find("totl").unwrap());
+					assert_eq!(e.span.len(), 4);
+				}
+				e => panic!("Expected undefined identifier error, got {:?}", e),
+			}
+		};
+		check(
+			"var 1..3: total;\nsolve minimize totl;",
+			InputLang::MiniZinc,
+		);
+		// Objective of synthesized solve item
+		check("find total : int(1..3)\nminimising totl", InputLang::EPrime);
+
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			"solve minimize \"total\";".to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let errors = db.all_errors();
+		assert_eq!(errors.len(), 1, "{:?}", errors);
+		assert!(matches!(
+			errors.iter().next().unwrap(),
+			Error::TypeMismatch(e) if e.msg == "Objective must be numeric, but got 'string'"
+		));
+	}
+
+	#[test]
+	fn test_unassigned_parameters() {
+		let db = model_db(
+			r#"
+			int: n;
+			int: m;
+			int: k = 1;
+			int: j;
+			j = 2;
+			var int: x;
+			"#,
+		);
+		let unassigned = db.unassigned_parameters(Arc::new(vec![Identifier::new("n", &db)]));
+		let names = unassigned
+			.iter()
+			.map(|p| p.identifier(&db).unwrap().lookup(&db))
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["m"]);
+	}
+
+	#[test]
+	fn test_singleton_domain_variables() {
+		let db = model_db(
+			r#"
+			var 5..5: x;
+			var 1..10: y;
+			set of int: S = 2..2;
+			var S: z;
+			var {3}: w;
+			5..5: p;
+			var -5..-5: u;
+			var -5..5: v;
+			var -1.5..-1.5: f;
+			"#,
+		);
+		let variables = db.singleton_domain_variables();
+		let names = variables
+			.iter()
+			.map(|p| p.identifier(&db).unwrap().lookup(&db))
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["x", "z", "w", "u", "f"]);
+	}
+
+	#[test]
+	fn test_undefined_enum_index_sets() {
+		let db = model_db(
+			r#"
+			enum E;
+			enum F;
+			enum G;
+			enum H = {A};
+			G = {B, C};
+			array [E] of int: a;
+			array [F, 1..3] of int: b;
+			array [G] of int: c;
+			array [H] of int: d;
+			"#,
+		);
+		let errors = db.undefined_enum_index_sets(Arc::new(vec![]));
+		let names = errors
+			.iter()
+			.map(|e| match e {
+				Error::UndefinedEnumIndexSet(e) => e.name.clone(),
+				_ => unreachable!(),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["E", "F"]);
+		let errors = db.undefined_enum_index_sets(Arc::new(vec![Identifier::new("F", &db)]));
+		assert_eq!(errors.len(), 1);
+	}
+
+	#[test]
+	fn test_eprime_param_file() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![
+			InputFile::String(
+				"given n: int\nfind x: int(1..n)".to_owned(),
+				InputLang::EPrime,
+			),
+			InputFile::String("letting n be 5".to_owned(), InputLang::EPrime),
+		]));
+		assert!(db.all_errors().is_empty());
+		let models = db.input_models();
+		let items = db.lookup_items(models[1]);
+		assert_eq!(items.len(), 1);
+		assert!(matches!(
+			items[0].local_item_ref(&db),
+			LocalItemRef::Assignment(_)
+		));
+	}
+
+	#[test]
+	fn test_eprime_domain_alias_chain() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			r#"
+			given n: int
+			letting D be domain int(1..n)
+			letting E be domain D
+			find x: E
+			"#
+			.to_owned(),
+			InputLang::EPrime,
+		)]));
+		assert!(db.all_errors().is_empty());
+	}
+
+	#[test]
+	fn test_eprime_cyclic_domain_alias() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			r#"
+			letting A be domain B
+			letting B be domain A
+			"#
+			.to_owned(),
+			InputLang::EPrime,
+		)]));
+		assert!(db
+			.all_errors()
+			.iter()
+			.any(|e| matches!(e, Error::CyclicDefinition(_))));
+	}
+
+	#[test]
+	fn test_warn_double_equals() {
+		let mut db = model_db(
+			r#"
+			constraint 1 == 1;
+			constraint 2 = 2;
+			"#,
+		);
+		let double_equals = |db: &CompilerDatabase| {
+			db.all_warnings()
+				.iter()
+				.filter_map(|w| match w {
+					Warning::DoubleEquals(w) => Some((w.span.offset(), w.span.len())),
+					_ => None,
+				})
+				.collect::<Vec<_>>()
+		};
+		assert!(double_equals(&db).is_empty());
+		db.set_warn_double_equals(true);
+		assert_eq!(double_equals(&db), vec![(17, 2)]);
+	}
+
+	#[test]
+	fn test_is_output_only() {
+		let db = model_db(
+			r#"
+			annotation output_only;
+			int: x :: output_only = 3;
+			int: y;
+			int: z :: output_only;
+			"#,
+		);
+		let output_only = |name| {
+			let p = db
+				.lookup_global_variable(Identifier::new(name, &db))
+				.unwrap();
+			db.is_output_only(p)
+		};
+		assert!(output_only("x"));
+		assert!(!output_only("y"));
+		assert!(output_only("z"));
+		let errors = db
+			.all_errors()
+			.iter()
+			.filter_map(|e| match e {
+				Error::SyntaxError(e) => Some(e.msg.clone()),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			errors,
+			vec!["'output_only' declarations must have a right-hand side.".to_owned()]
+		);
+	}
+
+	#[test]
+	fn test_include_closure() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path().canonicalize().unwrap();
+		std::fs::write(dir.join("a.mzn"), "include \"b.mzn\";\nint: a;").unwrap();
+		std::fs::write(dir.join("b.mzn"), "include \"./c.mzn\";\nint: b;").unwrap();
+		std::fs::write(dir.join("c.mzn"), "include \"a.mzn\";\nint: c;").unwrap();
+		std::fs::write(dir.join("d.mzn"), "int: d;").unwrap();
+
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![
+			InputFile::Path(dir.join("b.mzn"), InputLang::MiniZinc),
+			InputFile::Path(dir.join("d.mzn"), InputLang::MiniZinc),
+		]));
+		let models = db.input_models();
+		let closure = db
+			.include_closure(models[0])
+			.iter()
+			.map(|m| m.path(&db).unwrap().file_name().unwrap().to_owned())
+			.collect::<Vec<_>>();
+		assert_eq!(closure, vec!["b.mzn", "c.mzn", "a.mzn"]);
+		assert_eq!(*db.include_closure(models[1]), vec![models[1]]);
+	}
+
+	#[test]
+	fn test_model_includes() {
+		let db = model_db(
+			r#"
+			include "globals.mzn";
+			int: x;
+			include "./data/extra.mzn";
+			"#,
+		);
+		let model = db.input_models()[0];
+		assert_eq!(
+			*db.model_includes(model),
+			vec!["globals.mzn".to_owned(), "./data/extra.mzn".to_owned()]
+		);
+	}
+
+	#[test]
+	fn test_constant_constraints() {
+		let mut db = model_db(
+			r#"
+			var bool: b;
+			constraint true;
+			constraint false;
+			constraint b;
+			"#,
+		);
+		let constant_constraints = |db: &CompilerDatabase| {
+			db.all_warnings()
+				.iter()
+				.filter_map(|w| match w {
+					Warning::ConstantConstraint(w) => Some(w.value),
+					_ => None,
+				})
+				.collect::<Vec<_>>()
+		};
+		let unsatisfiable = |db: &CompilerDatabase| {
+			db.all_errors()
+				.iter()
+				.filter(|e| matches!(e, Error::UnsatisfiableConstraint(_)))
+				.count()
+		};
+		assert_eq!(constant_constraints(&db), vec![true, false]);
+		assert_eq!(unsatisfiable(&db), 0);
+		db.set_unsatisfiable_constraint_error(true);
+		assert_eq!(constant_constraints(&db), vec![true]);
+		assert_eq!(unsatisfiable(&db), 1);
+	}
+}
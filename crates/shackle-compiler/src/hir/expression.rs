@@ -29,6 +29,8 @@ pub enum Expression {
 	Identifier(Identifier),
 	/// Absent `<>`
 	Absent,
+	/// Anonymous variable `_`
+	Anonymous,
 	/// Infinity
 	Infinity,
 	/// Tuple literal
@@ -82,6 +84,7 @@ impl Expression {
 			}
 			match &data[e] {
 				Expression::Absent
+				| Expression::Anonymous
 				| Expression::BooleanLiteral(_)
 				| Expression::FloatLiteral(_)
 				| Expression::Identifier(_)
@@ -211,6 +214,7 @@ impl fmt::Debug for Expression {
 			Expression::StringLiteral(x) => fmt::Debug::fmt(x, f),
 			Expression::Identifier(x) => fmt::Debug::fmt(x, f),
 			Expression::Absent => f.write_str("Absent"),
+			Expression::Anonymous => f.write_str("Anonymous"),
 			Expression::Infinity => f.write_str("Infinity"),
 			Expression::TupleLiteral(x) => fmt::Debug::fmt(x, f),
 			Expression::RecordLiteral(x) => fmt::Debug::fmt(x, f),
@@ -55,6 +55,47 @@ impl LocalItemRef {
 			LocalItemRef::TypeAlias(i) => &model[i].data,
 		}
 	}
+
+	/// Get the kind of this item
+	pub fn kind(&self) -> ItemKind {
+		match self {
+			LocalItemRef::Annotation(_) => ItemKind::Annotation,
+			LocalItemRef::Assignment(_) => ItemKind::Assignment,
+			LocalItemRef::Constraint(_) => ItemKind::Constraint,
+			LocalItemRef::Declaration(_) => ItemKind::Declaration,
+			LocalItemRef::Enumeration(_) => ItemKind::Enumeration,
+			LocalItemRef::EnumAssignment(_) => ItemKind::EnumAssignment,
+			LocalItemRef::Function(_) => ItemKind::Function,
+			LocalItemRef::Output(_) => ItemKind::Output,
+			LocalItemRef::Solve(_) => ItemKind::Solve,
+			LocalItemRef::TypeAlias(_) => ItemKind::TypeAlias,
+		}
+	}
+}
+
+/// The kind of an item
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ItemKind {
+	/// Annotation item
+	Annotation,
+	/// Assignment item
+	Assignment,
+	/// Constraint item
+	Constraint,
+	/// Declaration item
+	Declaration,
+	/// Enumeration item
+	Enumeration,
+	/// Enum assignment item
+	EnumAssignment,
+	/// Function item
+	Function,
+	/// Output item
+	Output,
+	/// Solve item
+	Solve,
+	/// Type alias item
+	TypeAlias,
 }
 
 impl_enum_from!(LocalItemRef::Annotation(ArenaIndex<Item<Annotation>>));
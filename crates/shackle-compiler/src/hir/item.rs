@@ -19,7 +19,7 @@ use std::{
 	ops::{Deref, DerefMut},
 };
 
-use super::{db::Hir, source::Origin, Expression, Pattern, Type};
+use super::{db::Hir, source::Origin, Expression, Identifier, Pattern, Type};
 use crate::utils::{
 	arena::{Arena, ArenaIndex, ArenaMap},
 	debug_print_strings, impl_enum_from, impl_index, DebugPrint,
@@ -350,7 +350,7 @@ pub struct Parameter {
 /// Output item
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Output {
-	/// Section (always a `StringLiteral` or `None`)
+	/// Section (always a `StringLiteral` or `None` for the `default` section)
 	pub section: Option<ArenaIndex<Expression>>,
 	/// Output value
 	pub expression: ArenaIndex<Expression>,
@@ -365,6 +365,74 @@ pub struct Solve {
 	pub annotations: Box<[ArenaIndex<Expression>]>,
 }
 
+impl Solve {
+	/// Get the recognised search annotations (`int_search` and `bool_search`) of this solve item,
+	/// in the order they are applied.
+	///
+	/// The searches inside `seq_search` annotations are included in order, and any other
+	/// annotations are ignored.
+	pub fn search_annotations(&self, db: &dyn Hir, data: &ItemData) -> Vec<SearchAnnotation> {
+		let mut result = Vec::new();
+		for ann in self.annotations.iter() {
+			SearchAnnotation::collect(db, data, *ann, &mut result);
+		}
+		result
+	}
+}
+
+/// A search annotation over a collection of variables (`int_search` or `bool_search`)
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SearchAnnotation {
+	/// The search annotation (`int_search` or `bool_search`)
+	pub function: Identifier,
+	/// The collection of variables to search over
+	pub variables: ArenaIndex<Expression>,
+	/// The variable selection strategy
+	pub var_select: Identifier,
+	/// The value selection strategy
+	pub value_select: Identifier,
+}
+
+impl SearchAnnotation {
+	fn collect(
+		db: &dyn Hir,
+		data: &ItemData,
+		annotation: ArenaIndex<Expression>,
+		result: &mut Vec<SearchAnnotation>,
+	) {
+		let ids = db.identifier_registry();
+		let Expression::Call(c) = &data[annotation] else {
+			return;
+		};
+		let Expression::Identifier(function) = data[c.function] else {
+			return;
+		};
+		if function == ids.seq_search {
+			if let [searches] = &*c.arguments {
+				if let Expression::ArrayLiteral(al) = &data[*searches] {
+					for search in al.members.iter() {
+						Self::collect(db, data, *search, result);
+					}
+				}
+			}
+		} else if function == ids.int_search || function == ids.bool_search {
+			// The optional fourth argument is the (deprecated) exploration strategy
+			if let [variables, var_select, value_select, ..] = &*c.arguments {
+				if let (Expression::Identifier(var_select), Expression::Identifier(value_select)) =
+					(&data[*var_select], &data[*value_select])
+				{
+					result.push(SearchAnnotation {
+						function,
+						variables: *variables,
+						var_select: *var_select,
+						value_select: *value_select,
+					});
+				}
+			}
+		}
+	}
+}
+
 /// Solve method and objective
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Goal {
@@ -386,6 +454,28 @@ pub enum Goal {
 	},
 }
 
+impl Goal {
+	/// Get the solve method for this goal
+	pub fn method(&self) -> SolveMethod {
+		match self {
+			Goal::Satisfy => SolveMethod::Satisfy,
+			Goal::Maximize { .. } => SolveMethod::Maximize,
+			Goal::Minimize { .. } => SolveMethod::Minimize,
+		}
+	}
+}
+
+/// Solve method (without the objective)
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SolveMethod {
+	/// Satisfaction problem
+	Satisfy,
+	/// Minimization problem
+	Minimize,
+	/// Maximization problem
+	Maximize,
+}
+
 /// Type alias item
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct TypeAlias {
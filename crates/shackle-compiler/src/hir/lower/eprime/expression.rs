@@ -2,7 +2,7 @@ use std::iter;
 
 use crate::{
 	db::InternedStringData,
-	diagnostics::InvalidArrayLiteral,
+	diagnostics::{InvalidArrayLiteral, SyntaxError},
 	hir::{db::Hir, source::Origin, *},
 	syntax::{ast::AstNode, eprime::{self, MatrixComprehension}},
 	utils::arena::ArenaIndex,
@@ -45,17 +45,17 @@ impl ExpressionCollector<'_> {
             eprime::Expression::Identifier(i) => Identifier::new(i.name(), self.db).into(),
             eprime::Expression::ArrayAccess(aa) => self.collect_array_access(aa).into(),
             eprime::Expression::InfixOperator(o) => 
-				self.collect_operator_call(o.operator().name(), vec![o.left(), o.right()].into_iter(), origin.clone()).into(),
+				self.collect_operator(o.operator(), vec![o.left(), o.right()].into_iter(), origin.clone()),
             eprime::Expression::PrefixOperator(o) => 
-				self.collect_operator_call(o.operator().name(), iter::once(o.operand()), origin.clone()).into(),
+				self.collect_operator(o.operator(), iter::once(o.operand()), origin.clone()),
 			eprime::Expression::UnarySetConstructor(o) => 
-				self.collect_operator_call(o.operator().name(), iter::once(o.operand()), origin.clone()).into(),
+				self.collect_operator(o.operator(), iter::once(o.operand()), origin.clone()),
             eprime::Expression::Quantification(q) => self.collect_quantification(q).into(),
             eprime::Expression::MatrixComprehension(m) => return self.collect_matrix_comprehension(m),
             eprime::Expression::AbsoluteOperator(a) => 
 				self.collect_operator_call("abs", iter::once(a.operand()), origin.clone()).into(),
 			eprime::Expression::SetConstructor(o) =>  
-				self.collect_operator_call(o.operator().name(), vec![o.left(), o.right()].into_iter(), origin.clone()).into(),
+				self.collect_operator(o.operator(), vec![o.left(), o.right()].into_iter(), origin.clone()),
         };
         self.alloc_expression(origin, collected)
     }
@@ -213,6 +213,29 @@ impl ExpressionCollector<'_> {
 		})
 	}
 
+	/// Lower an operator into a call to the MiniZinc function implementing it
+	fn collect_operator(&mut self, o: eprime::Operator, args: impl Iterator<Item = eprime::Expression>, origin: Origin) -> Expression {
+		let Some(name) = operator_function(o.name()) else {
+			let (src, span) = o.cst_node().source_span(self.db.upcast());
+			self.add_diagnostic(SyntaxError {
+				src,
+				span,
+				msg: format!("Unsupported operator '{}'", o.name()),
+				other: Vec::new(),
+			});
+			return Expression::Missing;
+		};
+		let arguments = args
+			.map(|a| self.collect_expression(a))
+			.collect::<Box<_>>();
+		let function = self.ident_exp(origin, name);
+		Call {
+			function,
+			arguments,
+		}
+		.into()
+	}
+
 	fn collect_operator_call(&mut self, o: &str, args: impl Iterator<Item = eprime::Expression>, origin: Origin) -> Call {
 		let arguments = args
 			.into_iter()
@@ -220,16 +243,8 @@ impl ExpressionCollector<'_> {
 			.collect::<Box<_>>();
 		let function = self.ident_exp(
 			origin.clone(),
-			// Convert Eprime operators to MiniZinc ones
+			// Convert Eprime functions to MiniZinc ones
 			match o {
-				"==" => "eq",
-				"%" => "mod",
-				"<lex" => "lex_less",
-				"<=lex" => "lex_lesseq",
-				">lex" => "lex_greater",
-				">=lex" => "lex_greatereq",
-				"!" => "not",
-				"/" => "div",
 				"toInt" => "booltoint",
 				"toSet" => "arraytoset",
 				"and" => "forall",
@@ -491,6 +506,75 @@ impl ExpressionCollector<'_> {
 		self.alloc_expression(origin, Identifier::new(id, self.db))
 	}
 
+	/// Collect the annotations given by an annotation pragma
+	pub fn collect_pragma(&mut self, p: eprime::Pragma) -> Vec<ArenaIndex<Expression>> {
+		let origin = Origin::new(&p);
+		let mut annotations = Vec::new();
+		for text in p.annotations() {
+			if let Some(ann) = self.collect_pragma_term(origin.clone(), text) {
+				annotations.push(ann);
+			} else {
+				let (src, span) = p.cst_node().source_span(self.db.upcast());
+				self.add_diagnostic(SyntaxError {
+					src,
+					span,
+					msg: format!("Invalid annotation '{}' in pragma", text),
+					other: Vec::new(),
+				});
+			}
+		}
+		annotations
+	}
+
+	/// Lower a term in an annotation pragma.
+	///
+	/// Terms are identifiers, calls, or integer, Boolean and string literals.
+	fn collect_pragma_term(
+		&mut self,
+		origin: Origin,
+		text: &str,
+	) -> Option<ArenaIndex<Expression>> {
+		let is_identifier = |s: &str| {
+			s.starts_with(|c: char| c.is_ascii_alphabetic())
+				&& s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+		};
+		let text = text.trim();
+		if let Some(s) = text.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+			return Some(self.alloc_expression(origin, StringLiteral::new(s, self.db)));
+		}
+		if let Ok(b) = text.parse::<bool>() {
+			return Some(self.alloc_expression(origin, BooleanLiteral(b)));
+		}
+		if let Ok(i) = text.parse::<i64>() {
+			return Some(self.alloc_expression(origin, IntegerLiteral(i)));
+		}
+		if is_identifier(text) {
+			return Some(self.ident_exp(origin, text));
+		}
+		let (name, args) = text.split_once('(')?;
+		let name = name.trim();
+		let args = args.strip_suffix(')')?;
+		if !is_identifier(name) {
+			return None;
+		}
+		let arguments = if args.trim().is_empty() {
+			Box::new([]) as Box<[_]>
+		} else {
+			eprime::split_top_level(args)
+				.into_iter()
+				.map(|a| self.collect_pragma_term(origin.clone(), a))
+				.collect::<Option<_>>()?
+		};
+		let function = self.ident_exp(origin.clone(), name);
+		Some(self.alloc_expression(
+			origin,
+			Call {
+				function,
+				arguments,
+			},
+		))
+	}
+
 	/// Add a diagnostic
 	pub fn add_diagnostic<E: Into<Error>>(&mut self, error: E) {
 		self.diagnostics.push(error.into());
@@ -556,6 +640,46 @@ impl ExpressionCollector<'_> {
 	}
 }
 
+/// Get the name of the MiniZinc function implementing an Essence' operator
+///
+/// Integer division (`/`) and modulo (`%`) round towards negative infinity, unlike MiniZinc's `div`
+/// and `mod`, so are mapped to the `eprime_div` and `eprime_mod` functions from
+/// `eprime_globals.mzn`.
+/// Returns `None` if the operator is not supported.
+pub(super) fn operator_function(operator: &str) -> Option<&'static str> {
+	Some(match operator {
+		// Arithmetic
+		"+" => "+",
+		"-" => "-",
+		"*" => "*",
+		"/" => "eprime_div",
+		"%" => "eprime_mod",
+		"**" => "pow",
+		// Comparison
+		"=" => "=",
+		"!=" => "!=",
+		"<" => "<",
+		"<=" => "<=",
+		">" => ">",
+		">=" => ">=",
+		"<lex" => "lex_less",
+		"<=lex" => "lex_lesseq",
+		">lex" => "lex_greater",
+		">=lex" => "lex_greatereq",
+		// Logical
+		"!" => "not",
+		"/\\" => "/\\",
+		"\\/" => "\\/",
+		"->" | "=>" => "->",
+		"<->" | "<=>" => "<->",
+		// Sets
+		"in" => "in",
+		".." => "..",
+		"..o" => "..o",
+		_ => return None,
+	})
+}
+
 /// Represents a collected domain in the expression collector
 /// Preserves relevant information depending on the type of domain
 pub(super) enum CollectedDomain {
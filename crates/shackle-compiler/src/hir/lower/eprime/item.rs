@@ -10,8 +10,9 @@ use crate::{
 		source::{Origin, SourceMap},
 		*,
 	},
-	syntax::eprime,
-	Error,
+	diagnostics::{IgnoredHeuristic, ParameterRedefinition},
+	syntax::{ast::AstNode, eprime},
+	Error, Warning,
 };
 
 /// Collects AST items into an HIR model
@@ -21,9 +22,11 @@ pub struct ItemCollector<'a> {
 	model: Model,
 	source_map: SourceMap,
 	diagnostics: Vec<Error>,
+	warnings: Vec<Warning>,
 	owner: ModelRef,
 	branching_annotations: Option<eprime::MatrixLiteral>, // Used to store branching annotations
 	goal: eprime::Goal,                                   // Used to store goal of solve
+	has_decisions: bool,                                  // Whether the model declares any decision variables
 }
 
 impl ItemCollector<'_> {
@@ -39,9 +42,11 @@ impl ItemCollector<'_> {
 			model: Model::default(),
 			source_map: SourceMap::default(),
 			diagnostics: Vec::new(),
+			warnings: Vec::new(),
 			owner,
 			branching_annotations: None,
 			goal: eprime::Goal::Satisfy,
+			has_decisions: false,
 		}
 	}
 
@@ -61,7 +66,12 @@ impl ItemCollector<'_> {
 				self.branching_annotations = Some(b.branching_array());
 				return;
 			},
-			eprime::Item::Heuristic(_) => return, // Currently not supported
+			eprime::Item::Heuristic(h) => {
+				// Currently not supported
+				let (src, span) = h.cst_node().source_span(self.db.upcast());
+				self.warnings.push(IgnoredHeuristic { src, span }.into());
+				return;
+			}
 			eprime::Item::Output(i) => self.collect_output(i),
 		};
 		self.source_map.insert(it.into(), Origin::new(&item));
@@ -69,13 +79,20 @@ impl ItemCollector<'_> {
 	}
 
 	/// Finish lowering
-	pub fn finish(self) -> (Model, SourceMap, Vec<Error>) {
-		(self.model, self.source_map, self.diagnostics)
+	pub fn finish(self) -> (Model, SourceMap, Vec<Error>, Vec<Warning>) {
+		(self.model, self.source_map, self.diagnostics, self.warnings)
 	}
 
-	/// Checks if a solve item exists, if not, adds satisfy solve
+	/// Checks if a solve item exists, if not, adds satisfy solve (unless there is nothing to solve)
 	/// TODO: Broken SourceMap
 	pub fn add_solve(&mut self) {
+		if !self.has_decisions
+			&& self.branching_annotations.is_none()
+			&& matches!(self.goal, eprime::Goal::Satisfy)
+		{
+			// Nothing to solve (e.g. this is a param file)
+			return;
+		}
 		let mut ctx = ExpressionCollector::new(self.db, &mut self.diagnostics);
 
 		let annotations = match &self.branching_annotations {
@@ -83,11 +100,10 @@ impl ItemCollector<'_> {
 				let origin = Origin::new(b);
 				let arguments = Box::new([
 					ctx.collect_matrix_literal(b.clone(), false),
-					ctx.alloc_expression(origin.clone(), Identifier::new("input_order", self.db)),
-					ctx.alloc_expression(origin.clone(), Identifier::new("indomain_min", self.db)),
+					ctx.alloc_expression(origin.clone(), self.identifiers.input_order),
+					ctx.alloc_expression(origin.clone(), self.identifiers.indomain_min),
 				]);
-				let function =
-					ctx.alloc_expression(origin.clone(), Identifier::new("int_search", self.db));
+				let function = ctx.alloc_expression(origin.clone(), self.identifiers.int_search);
 				Box::new([ctx.alloc_expression(
 					origin.clone(),
 					Call {
@@ -171,11 +187,12 @@ impl ItemCollector<'_> {
 
 		// Collect where expressions as constraints
 		for w in p.wheres() {
-			self.collect_constraint_expression(w);
+			self.collect_constraint_expression(w, Vec::new());
 		}
 	}
 
 	fn collect_decision_declaration(&mut self, d: eprime::DecisionDeclaration) {
+		self.has_decisions = true;
 		self.collect_declarations(d.names(), Some(d.domain()), false, None, VarType::Var);
 	}
 
@@ -243,19 +260,32 @@ impl ItemCollector<'_> {
 		}
 	}
 
+	/// Collect a constraint item
+	///
+	/// Each constraint expression is lowered into its own constraint item, annotated using the
+	/// annotation pragmas (`$:: ann`) directly preceding it.
 	fn collect_constraint(&mut self, c: eprime::Constraint) {
 		for expr in c.expressions() {
-			self.collect_constraint_expression(expr);
+			let pragmas = c.pragmas(&expr);
+			self.collect_constraint_expression(expr, pragmas);
 		}
 	}
 
-	fn collect_constraint_expression(&mut self, expr: eprime::Expression) {
+	fn collect_constraint_expression(
+		&mut self,
+		expr: eprime::Expression,
+		pragmas: Vec<eprime::Pragma>,
+	) {
 		let mut ctx = ExpressionCollector::new(self.db, &mut self.diagnostics);
+		let annotations = pragmas
+			.into_iter()
+			.flat_map(|p| ctx.collect_pragma(p))
+			.collect();
 		let expression = ctx.collect_expression(expr.clone());
 		let (data, sm) = ctx.finish();
 		let index = self.model.constraints.insert(Item::new(
 			Constraint {
-				annotations: Box::new([]),
+				annotations,
 				expression,
 			},
 			data,
@@ -266,6 +296,10 @@ impl ItemCollector<'_> {
 		self.source_map.add_from_item_data(self.db, it, &sm);
 	}
 
+	/// Collect an output item
+	///
+	/// Essence' has no syntax for output sections, so all output items are placed in the
+	/// default section (represented by `None` as for MiniZinc output items without a section).
 	fn collect_output(&mut self, i: eprime::Output) -> (ItemRef, ItemDataSourceMap) {
 		let mut ctx = ExpressionCollector::new(self.db, &mut self.diagnostics);
 		let expression = ctx.collect_expression(i.expression());
@@ -283,34 +317,65 @@ impl ItemCollector<'_> {
 
 	/// Preprocess the model to collect parameter index sets, and ensure constants are declared
 	pub fn preprocess(&mut self, items: impl Iterator<Item = eprime::Item>) {
-		let mut parameter_identifiers = Vec::new();
-		let mut parameter_index_set_map = HashMap::new();
-		for item in items {
-			match item {
-				eprime::Item::ParamDeclaration(p) => {
-					for name in p.names() {
-						let n = name.name().to_string();
-						parameter_identifiers.push(n.clone());
-						if let eprime::Domain::MatrixDomain(m) = p.domain()  {
-							parameter_index_set_map.insert(n, m.indexes().collect());
-						}
-					}
-				},
-				eprime::Item::ConstDefinition(c) => {
-					// If the constant definition isn't a parameter assignment give it a declaration
-					// Otherwise give it an assignment
-					let name = match c.name() {
-						eprime::Expression::Identifier(i) => i,
-						_ => continue,
+		let items = items.collect::<Vec<_>>();
+		// Map from parameter names to their position, identifier and index sets
+		let mut parameters = HashMap::new();
+		for (position, item) in items.iter().enumerate() {
+			if let eprime::Item::ParamDeclaration(p) = item {
+				for name in p.names() {
+					let indexes = match p.domain() {
+						eprime::Domain::MatrixDomain(m) => Some(m.indexes().collect::<Vec<_>>()),
+						_ => None,
 					};
-					let name_str = &name.name().to_string();
-					if !parameter_identifiers.contains(name_str) {
-						self.collect_declarations(iter::once(name), c.domain(), false, Some(c.definition()), VarType::Par);
-					} else {
-						self.collect_const_definition(c, parameter_index_set_map.get(name_str));
+					parameters
+						.entry(name.name().to_string())
+						.or_insert((position, name, indexes));
+				}
+			}
+		}
+		for (position, item) in items.into_iter().enumerate() {
+			let c = match item {
+				eprime::Item::ConstDefinition(c) => c,
+				_ => continue,
+			};
+			let name = match c.name() {
+				eprime::Expression::Identifier(i) => i,
+				_ => continue,
+			};
+			// If the constant definition isn't a parameter assignment give it a declaration
+			// Otherwise give it an assignment
+			match parameters.get(name.name()) {
+				None if self
+					.db
+					.eprime_parameter_names()
+					.contains(&Identifier::new(name.name(), self.db)) =>
+				{
+					// Parameter declared in another model (e.g. when this is a param file)
+					self.collect_const_definition(c, None)
+				}
+				None => self.collect_declarations(
+					iter::once(name),
+					c.domain(),
+					false,
+					Some(c.definition()),
+					VarType::Par,
+				),
+				Some((parameter_position, parameter, indexes)) => {
+					if *parameter_position > position {
+						let (src, span) = name.cst_node().source_span(self.db.upcast());
+						let (_, parameter) = parameter.cst_node().source_span(self.db.upcast());
+						self.diagnostics.push(
+							ParameterRedefinition {
+								src,
+								name: name.name().to_string(),
+								span,
+								parameter,
+							}
+							.into(),
+						);
 					}
-				},
-				_ => {},
+					self.collect_const_definition(c, indexes.as_ref());
+				}
 			}
 		}
 	}
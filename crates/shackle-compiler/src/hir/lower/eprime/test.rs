@@ -1,6 +1,10 @@
 use expect_test::expect;
 
-use crate::hir::lower::test::check_lower_item_eprime;
+use super::expression::operator_function;
+use crate::hir::lower::test::{
+	check_lower_errors_eprime, check_lower_item, check_lower_item_eprime,
+	check_lower_warnings_eprime,
+};
 
 #[test]
 fn test_lower_integer_domain() {
@@ -151,6 +155,29 @@ fn test_lower_indexed_access() {
 	);
 }
 
+#[test]
+fn test_lower_indexed_access_slice() {
+	check_lower_item_eprime(
+		r#"
+      letting column = m[.., j]
+      "#,
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::5>), annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("..")
+        <Expression::2>: Identifier("j")
+        <Expression::3>: TupleLiteral { fields: [<Expression::1>, <Expression::2>] }
+        <Expression::4>: Identifier("m")
+        <Expression::5>: ArrayAccess { collection: <Expression::4>, indices: <Expression::3> }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("column"))
+      Annotations:
+    "#]],
+	);
+}
+
 #[test]
 fn test_lower_infix_operator() {
 	check_lower_item_eprime(
@@ -194,6 +221,86 @@ fn test_lower_prefix_operator() {
 	);
 }
 
+#[test]
+fn test_lower_arithmetic_operators() {
+	check_lower_item_eprime(
+		"letting remainder = 7 % 2",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::4>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(7)
+        <Expression::2>: IntegerLiteral(2)
+        <Expression::3>: Identifier("eprime_mod")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("remainder"))
+      Annotations:
+    "#]],
+	);
+	check_lower_item_eprime(
+		"letting power = 2 ** 3",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::4>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(2)
+        <Expression::2>: IntegerLiteral(3)
+        <Expression::3>: Identifier("pow")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("power"))
+      Annotations:
+    "#]],
+	);
+	// Division of integers is integer division, rounding towards negative infinity
+	check_lower_item_eprime(
+		"letting quotient = 7 / 2",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::4>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(7)
+        <Expression::2>: IntegerLiteral(2)
+        <Expression::3>: Identifier("eprime_div")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("quotient"))
+      Annotations:
+    "#]],
+	);
+	check_lower_item_eprime(
+		"letting negative = (-7) / 2",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::6>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(7)
+        <Expression::2>: Identifier("-")
+        <Expression::3>: Call { function: <Expression::2>, arguments: [<Expression::1>] }
+        <Expression::4>: IntegerLiteral(2)
+        <Expression::5>: Identifier("eprime_div")
+        <Expression::6>: Call { function: <Expression::5>, arguments: [<Expression::3>, <Expression::4>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("negative"))
+      Annotations:
+    "#]],
+	);
+}
+
+#[test]
+fn test_operator_function() {
+	assert_eq!(operator_function("%"), Some("eprime_mod"));
+	assert_eq!(operator_function("**"), Some("pow"));
+	assert_eq!(operator_function("/"), Some("eprime_div"));
+	assert_eq!(operator_function("=>"), Some("->"));
+	assert_eq!(operator_function("^"), None);
+}
+
 #[test]
 fn test_lower_quantification() {
 	check_lower_item_eprime(
@@ -219,6 +326,99 @@ fn test_lower_quantification() {
 	);
 }
 
+#[test]
+fn test_lower_quantification_guard() {
+	// A guard is part of the body of the quantifier, so it is an implication for forAll
+	check_lower_item_eprime(
+		"letting guarded = forAll i : int(1..3) . i > 1 -> i < 3",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::17>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(1)
+        <Expression::2>: IntegerLiteral(3)
+        <Expression::3>: Identifier("..")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+        <Expression::5>: Identifier("i")
+        <Expression::6>: IntegerLiteral(1)
+        <Expression::7>: Identifier(">")
+        <Expression::8>: Call { function: <Expression::7>, arguments: [<Expression::5>, <Expression::6>] }
+        <Expression::9>: Identifier("i")
+        <Expression::10>: IntegerLiteral(3)
+        <Expression::11>: Identifier("<")
+        <Expression::12>: Call { function: <Expression::11>, arguments: [<Expression::9>, <Expression::10>] }
+        <Expression::13>: Identifier("->")
+        <Expression::14>: Call { function: <Expression::13>, arguments: [<Expression::8>, <Expression::12>] }
+        <Expression::15>: ArrayComprehension { template: <Expression::14>, indices: None, generators: [Iterator { patterns: [<Pattern::2>], collection: <Expression::4>, where_clause: None }] }
+        <Expression::16>: Identifier("forall")
+        <Expression::17>: Call { function: <Expression::16>, arguments: [<Expression::15>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("guarded"))
+        <Pattern::2>: Identifier(Identifier("i"))
+      Annotations:
+    "#]],
+	);
+	// and a conjunction for exists
+	check_lower_item_eprime(
+		"letting guarded = exists i : int(1..3) . i > 1 /\\ i < 3",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::17>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(1)
+        <Expression::2>: IntegerLiteral(3)
+        <Expression::3>: Identifier("..")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+        <Expression::5>: Identifier("i")
+        <Expression::6>: IntegerLiteral(1)
+        <Expression::7>: Identifier(">")
+        <Expression::8>: Call { function: <Expression::7>, arguments: [<Expression::5>, <Expression::6>] }
+        <Expression::9>: Identifier("i")
+        <Expression::10>: IntegerLiteral(3)
+        <Expression::11>: Identifier("<")
+        <Expression::12>: Call { function: <Expression::11>, arguments: [<Expression::9>, <Expression::10>] }
+        <Expression::13>: Identifier("/\\")
+        <Expression::14>: Call { function: <Expression::13>, arguments: [<Expression::8>, <Expression::12>] }
+        <Expression::15>: ArrayComprehension { template: <Expression::14>, indices: None, generators: [Iterator { patterns: [<Pattern::2>], collection: <Expression::4>, where_clause: None }] }
+        <Expression::16>: Identifier("exists")
+        <Expression::17>: Call { function: <Expression::16>, arguments: [<Expression::15>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("guarded"))
+        <Pattern::2>: Identifier(Identifier("i"))
+      Annotations:
+    "#]],
+	);
+	// Whereas a matrix comprehension condition filters the generated elements
+	check_lower_item_eprime(
+		"letting filtered = [ i < 3 | i : int(1..3), i > 1 ]",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::13>), annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("i")
+        <Expression::2>: IntegerLiteral(1)
+        <Expression::3>: Identifier(">")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+        <Expression::5>: IntegerLiteral(1)
+        <Expression::6>: IntegerLiteral(3)
+        <Expression::7>: Identifier("..")
+        <Expression::8>: Call { function: <Expression::7>, arguments: [<Expression::5>, <Expression::6>] }
+        <Expression::9>: Identifier("i")
+        <Expression::10>: IntegerLiteral(3)
+        <Expression::11>: Identifier("<")
+        <Expression::12>: Call { function: <Expression::11>, arguments: [<Expression::9>, <Expression::10>] }
+        <Expression::13>: ArrayComprehension { template: <Expression::12>, indices: None, generators: [Iterator { patterns: [<Pattern::2>], collection: <Expression::8>, where_clause: Some(<Expression::4>) }] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("filtered"))
+        <Pattern::2>: Identifier(Identifier("i"))
+      Annotations:
+    "#]],
+	);
+}
+
 #[test]
 fn test_lower_matrix_comprehension() {
 	check_lower_item_eprime(
@@ -232,7 +432,7 @@ fn test_lower_matrix_comprehension() {
         <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
         <Expression::5>: Identifier("num")
         <Expression::6>: IntegerLiteral(2)
-        <Expression::7>: Identifier("**")
+        <Expression::7>: Identifier("pow")
         <Expression::8>: Call { function: <Expression::7>, arguments: [<Expression::5>, <Expression::6>] }
         <Expression::9>: ArrayComprehension { template: <Expression::8>, indices: None, generators: [Iterator { patterns: [<Pattern::2>], collection: <Expression::4>, where_clause: None }] }
       Types:
@@ -355,6 +555,31 @@ fn test_lower_matrix_comprehension() {
 	);
 }
 
+#[test]
+fn test_lower_matrix_comprehension_default_index() {
+	// Without nested comprehensions there is no explicit index, so indices default to None
+	check_lower_item_eprime(
+		"letting pairs = [ i | i, j : int(1..2) ]",
+		expect![[r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::6>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(1)
+        <Expression::2>: IntegerLiteral(2)
+        <Expression::3>: Identifier("..")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+        <Expression::5>: Identifier("i")
+        <Expression::6>: ArrayComprehension { template: <Expression::5>, indices: None, generators: [Iterator { patterns: [<Pattern::2>, <Pattern::3>], collection: <Expression::4>, where_clause: None }] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("pairs"))
+        <Pattern::2>: Identifier(Identifier("i"))
+        <Pattern::3>: Identifier(Identifier("j"))
+      Annotations:
+    "#]],
+	);
+}
+
 #[test]
 fn test_lower_absolute() {
 	check_lower_item_eprime(
@@ -428,6 +653,43 @@ fn test_lower_param_declaration() {
 	);
 }
 
+#[test]
+fn test_lower_parameter_redefinition() {
+	check_lower_errors_eprime(
+		r#"
+      letting n be 5
+      given n: int
+    "#,
+		expect![[r#"
+    The constant 'n' redefines a parameter at `n`: n is defined here
+    The constant 'n' redefines a parameter at `n`: n is declared as a parameter here
+"#]],
+	);
+	check_lower_errors_eprime(
+		r#"
+      given n: int
+      letting n be 5
+      letting m be 5
+      letting s = sum n : int(1..2) . n
+    "#,
+		expect![[r#""#]],
+	);
+}
+
+#[test]
+fn test_lower_ignored_heuristic() {
+	check_lower_warnings_eprime(
+		r#"
+      find x: int(1..3)
+      heuristic static
+    "#,
+		expect![[r#"
+    Ignored heuristic at `heuristic static`: Heuristics are not supported, so this heuristic is ignored
+"#]],
+	);
+	check_lower_warnings_eprime("find x: int(1..3)", expect![[r#""#]]);
+}
+
 #[test]
 fn test_lower_domain_alias() {
 	check_lower_item_eprime(
@@ -563,6 +825,40 @@ fn test_lower_constraint() {
 	)
 }
 
+#[test]
+fn test_lower_constraint_pragma() {
+	check_lower_item_eprime(
+		r#"
+		such that
+			$:: domain, priority(2, "high")
+			x
+		"#,
+		expect![[r#"
+    Item: Constraint { expression: <Expression::6>, annotations: [<Expression::1>, <Expression::5>] }
+      Expressions:
+        <Expression::1>: Identifier("domain")
+        <Expression::2>: IntegerLiteral(2)
+        <Expression::3>: StringLiteral("high")
+        <Expression::4>: Identifier("priority")
+        <Expression::5>: Call { function: <Expression::4>, arguments: [<Expression::2>, <Expression::3>] }
+        <Expression::6>: Identifier("x")
+      Types:
+      Patterns:
+      Annotations:
+    "#]],
+	);
+	check_lower_errors_eprime(
+		r#"
+		such that
+			$:: priority(2
+			x
+		"#,
+		expect![[r#"
+    Syntax Error at `$:: priority(2`: Invalid annotation 'priority(2' in pragma
+"#]],
+	);
+}
+
 #[test]
 fn test_lower_matrix_literal() {
 	check_lower_item_eprime(
@@ -651,3 +947,30 @@ fn test_lower_output() {
 "#]],
 	)
 }
+
+#[test]
+fn test_lower_output_default_section() {
+	// Output items without a section are placed in the default section in both languages
+	check_lower_item_eprime(
+		r#"output "foo""#,
+		expect![[r#"
+    Item: Output { section: None, expression: <Expression::1> }
+      Expressions:
+        <Expression::1>: StringLiteral("foo")
+      Types:
+      Patterns:
+      Annotations:
+"#]],
+	);
+	check_lower_item(
+		r#"output "foo";"#,
+		expect![[r#"
+    Item: Output { section: None, expression: <Expression::1> }
+      Expressions:
+        <Expression::1>: StringLiteral("foo")
+      Types:
+      Patterns:
+      Annotations:
+"#]],
+	);
+}
@@ -1,9 +1,9 @@
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
 	constants::IdentifierRegistry,
 	db::InternedStringData,
-	diagnostics::{InvalidArrayLiteral, InvalidNumericLiteral, SyntaxError},
+	diagnostics::{DuplicateRecordField, InvalidArrayLiteral, InvalidNumericLiteral, SyntaxError},
 	hir::{db::Hir, source::Origin, *},
 	syntax::{ast::AstNode, minizinc},
 	utils::{arena::ArenaIndex, maybe_grow_stack},
@@ -17,6 +17,9 @@ pub struct ExpressionCollector<'a> {
 	data: ItemData,
 	source_map: ItemDataSourceMap,
 	diagnostics: &'a mut Vec<Error>,
+	depth: usize,
+	max_depth: usize,
+	depth_exceeded: bool,
 }
 
 impl ExpressionCollector<'_> {
@@ -32,6 +35,9 @@ impl ExpressionCollector<'_> {
 			data: ItemData::new(),
 			source_map: ItemDataSourceMap::new(),
 			diagnostics,
+			depth: 0,
+			max_depth: db.max_expression_depth(),
+			depth_exceeded: false,
 		}
 	}
 
@@ -45,7 +51,28 @@ impl ExpressionCollector<'_> {
 		&mut self,
 		expression: minizinc::Expression,
 	) -> ArenaIndex<Expression> {
-		maybe_grow_stack(|| self.collect_expression_inner(expression))
+		if self.depth >= self.max_depth {
+			let origin = Origin::new(&expression);
+			// Only report the first expression which is too deep
+			if !self.depth_exceeded {
+				self.depth_exceeded = true;
+				let (src, span) = expression.cst_node().source_span(self.db.upcast());
+				self.add_diagnostic(SyntaxError {
+					src,
+					span,
+					msg: format!(
+						"Expression nesting exceeds the maximum depth of {}",
+						self.max_depth
+					),
+					other: Vec::new(),
+				});
+			}
+			return self.alloc_expression(origin, Expression::Missing);
+		}
+		self.depth += 1;
+		let result = maybe_grow_stack(|| self.collect_expression_inner(expression));
+		self.depth -= 1;
+		result
 	}
 
 	fn collect_expression_inner(
@@ -90,17 +117,7 @@ impl ExpressionCollector<'_> {
 			minizinc::Expression::StringLiteral(s) => StringLiteral::new(s.value(), self.db).into(),
 			minizinc::Expression::Absent(_) => Expression::Absent,
 			minizinc::Expression::Infinity(_) => Expression::Infinity,
-			minizinc::Expression::Anonymous(a) => {
-				// No longer support anonymous variables, instead use opt
-				let (src, span) = a.cst_node().source_span(self.db.upcast());
-				self.add_diagnostic(SyntaxError {
-					src,
-					span,
-					msg: "Anonymous variables in expressions are not supported".to_string(),
-					other: Vec::new(),
-				});
-				Expression::Missing
-			}
+			minizinc::Expression::Anonymous(_) => Expression::Anonymous,
 			minizinc::Expression::Identifier(i) => Identifier::new(i.name(), self.db).into(),
 			minizinc::Expression::TupleLiteral(t) => self.collect_tuple_literal(t).into(),
 			minizinc::Expression::RecordLiteral(r) => self.collect_record_literal(r).into(),
@@ -195,23 +212,24 @@ impl ExpressionCollector<'_> {
 					.map(|f| self.collect_type_with_tiids(f, tiids, false, is_fn_parameter))
 					.collect(),
 			},
-			minizinc::Type::RecordType(r) => Type::Record {
-				opt: OptType::NonOpt,
-				fields: r
-					.fields()
-					.map(|f| {
-						(
-							self.collect_pattern(f.name().into()),
-							self.collect_type_with_tiids(
-								f.field_type(),
-								tiids,
-								false,
-								is_fn_parameter,
-							),
-						)
-					})
-					.collect(),
-			},
+			minizinc::Type::RecordType(r) => {
+				let mut seen = FxHashSet::default();
+				let mut fields = Vec::new();
+				for f in r.fields() {
+					if !self.is_new_record_field(&mut seen, f.name()) {
+						self.discarded().collect_type(f.field_type());
+						continue;
+					}
+					fields.push((
+						self.collect_pattern(f.name().into()),
+						self.collect_type_with_tiids(f.field_type(), tiids, false, is_fn_parameter),
+					));
+				}
+				Type::Record {
+					opt: OptType::NonOpt,
+					fields: fields.into_boxed_slice(),
+				}
+			}
 			minizinc::Type::OperationType(o) => Type::Operation {
 				opt: OptType::NonOpt,
 				return_type: self.collect_type_with_tiids(
@@ -496,7 +514,6 @@ impl ExpressionCollector<'_> {
 	}
 
 	fn collect_2d_array_literal(&mut self, al: minizinc::ArrayLiteral2D) -> ArenaIndex<Expression> {
-		// Desugar into array2d call
 		let origin = Origin::new(&al);
 		let col_indices = al
 			.column_indices()
@@ -508,6 +525,8 @@ impl ExpressionCollector<'_> {
 		let mut row_count = 0;
 		let mut values = Vec::new();
 		for row in al.rows() {
+			// Diagnostics point at the offending row rather than the whole literal
+			let (src, row_span) = row.cst_node().source_span(self.db.upcast());
 			let members = row
 				.members()
 				.map(|m| self.collect_expression(m))
@@ -522,29 +541,26 @@ impl ExpressionCollector<'_> {
 				first = false;
 
 				if !col_indices.is_empty() && col_count != col_indices.len() {
-					let (src, span) = al.cst_node().source_span(self.db.upcast());
 					self.add_diagnostic(InvalidArrayLiteral {
 						src,
-						span,
+						span: row_span,
 						msg: "2D array literal has different row length to index row".to_string(),
 					});
 					return self.alloc_expression(origin, Expression::Missing);
 				}
 			} else if members.len() != col_count {
-				let (src, span) = al.cst_node().source_span(self.db.upcast());
 				self.add_diagnostic(InvalidArrayLiteral {
 					src,
-					span,
+					span: row_span,
 					msg: "Non-uniform 2D array literal row length".to_string(),
 				});
 				return self.alloc_expression(origin, Expression::Missing);
 			}
 
 			if index.is_none() != row_indices.is_empty() {
-				let (src, span) = al.cst_node().source_span(self.db.upcast());
 				self.add_diagnostic(InvalidArrayLiteral {
 					src,
-					span,
+					span: row_span,
 					msg: "Mixing indexed and non-indexed rows not allowed".to_string(),
 				});
 				return self.alloc_expression(origin, Expression::Missing);
@@ -647,6 +663,11 @@ impl ExpressionCollector<'_> {
 	}
 
 	fn collect_infix_operator(&mut self, o: minizinc::InfixOperator) -> ArenaIndex<Expression> {
+		if self.db.fold_constants() {
+			if let Some(e) = self.fold_infix_operator(&o) {
+				return e;
+			}
+		}
 		let arguments = [o.left(), o.right()]
 			.into_iter()
 			.map(|a| self.collect_expression(a))
@@ -670,6 +691,47 @@ impl ExpressionCollector<'_> {
 		)
 	}
 
+	/// Fold constant integer/float arithmetic and simplify identity operations
+	fn fold_infix_operator(
+		&mut self,
+		o: &minizinc::InfixOperator,
+	) -> Option<ArenaIndex<Expression>> {
+		let operator = o.operator();
+		let left = fold_constant(&o.left());
+		let right = fold_constant(&o.right());
+		match (left, right) {
+			(Some(l), Some(r)) => match fold_arithmetic(operator.name(), l, r)? {
+				Ok(Constant::Integer(v)) => {
+					Some(self.alloc_expression(Origin::new(o), IntegerLiteral(v)))
+				}
+				Ok(Constant::Float(v)) => {
+					Some(self.alloc_expression(Origin::new(o), FloatLiteral::new(v)))
+				}
+				Err(msg) => {
+					// Leave the call in place rather than wrapping
+					let (src, span) = o.cst_node().source_span(self.db.upcast());
+					self.add_diagnostic(InvalidNumericLiteral { src, span, msg });
+					None
+				}
+			},
+			// Identity operations (only if the other operand is known to be numeric, since
+			// otherwise removing the operator could change its type)
+			(None, Some(Constant::Integer(0))) if matches!(operator.name(), "+" | "-") => {
+				is_arithmetic(&o.left()).then(|| self.collect_expression(o.left()))
+			}
+			(None, Some(Constant::Integer(1))) if matches!(operator.name(), "*" | "div") => {
+				is_arithmetic(&o.left()).then(|| self.collect_expression(o.left()))
+			}
+			(Some(Constant::Integer(0)), None) if operator.name() == "+" => {
+				is_arithmetic(&o.right()).then(|| self.collect_expression(o.right()))
+			}
+			(Some(Constant::Integer(1)), None) if operator.name() == "*" => {
+				is_arithmetic(&o.right()).then(|| self.collect_expression(o.right()))
+			}
+			_ => None,
+		}
+	}
+
 	fn collect_prefix_operator(&mut self, o: minizinc::PrefixOperator) -> ArenaIndex<Expression> {
 		let arguments = Box::new([self.collect_expression(o.operand())]);
 		let operator = o.operator();
@@ -807,30 +869,68 @@ impl ExpressionCollector<'_> {
 	}
 
 	fn collect_record_literal(&mut self, r: minizinc::RecordLiteral) -> RecordLiteral {
+		let mut seen = FxHashSet::default();
+		let mut fields = Vec::new();
+		for m in r.members() {
+			if !self.is_new_record_field(&mut seen, m.name()) {
+				self.discarded().collect_expression(m.value());
+				continue;
+			}
+			fields.push((
+				self.collect_pattern(m.name().into()),
+				self.collect_expression(m.value()),
+			));
+		}
 		RecordLiteral {
-			fields: r
-				.members()
-				.map(|m| {
-					(
-						self.collect_pattern(m.name().into()),
-						self.collect_expression(m.value()),
-					)
-				})
-				.collect(),
+			fields: fields.into_boxed_slice(),
 		}
 	}
 
+	/// Check that a record field name has not already been used, emitting an error if it has
+	fn is_new_record_field(
+		&mut self,
+		seen: &mut FxHashSet<Identifier>,
+		name: minizinc::Identifier,
+	) -> bool {
+		if seen.insert(Identifier::new(name.name(), self.db)) {
+			return true;
+		}
+		let (src, span) = name.cst_node().source_span(self.db.upcast());
+		self.add_diagnostic(DuplicateRecordField {
+			field: name.name().to_string(),
+			src,
+			span,
+		});
+		false
+	}
+
+	/// Create a collector for lowering a construct which is then discarded (e.g. the value of a
+	/// duplicate record field), so that errors inside it are still reported
+	fn discarded(&mut self) -> ExpressionCollector<'_> {
+		let mut collector = ExpressionCollector::new(self.db, self.identifiers, self.diagnostics);
+		collector.depth = self.depth;
+		collector.depth_exceeded = self.depth_exceeded;
+		collector
+	}
+
 	fn collect_tuple_access(&mut self, t: minizinc::TupleAccess) -> TupleAccess {
-		TupleAccess {
-			field: IntegerLiteral(t.field().value().unwrap_or_else(|e| {
+		let field = match t.field().value() {
+			Ok(i) if i >= 1 => i,
+			result => {
 				let (src, span) = t.field().cst_node().source_span(self.db.upcast());
 				self.add_diagnostic(InvalidNumericLiteral {
 					src,
 					span,
-					msg: e.to_string(),
+					msg: match result {
+						Ok(i) => format!("Invalid tuple field {}, tuple fields start at 1", i),
+						Err(e) => e.to_string(),
+					},
 				});
 				1
-			})),
+			}
+		};
+		TupleAccess {
+			field: IntegerLiteral(field),
 			tuple: self.collect_expression(t.tuple()),
 		}
 	}
@@ -872,9 +972,14 @@ impl ExpressionCollector<'_> {
 		let annotations = e
 			.annotations()
 			.map(|ann| self.collect_expression(ann))
-			.collect();
+			.collect::<Vec<_>>();
 		let idx = self.collect_expression(e.expression());
-		self.data.annotations.insert(idx, annotations);
+		// The lowered expression may already be annotated (e.g. for `(x :: a) :: b`, or if an
+		// operator call was folded into its operand), so keep any existing annotations
+		let existing = self.data.annotations.remove(idx).unwrap_or_default();
+		self.data
+			.annotations
+			.insert(idx, existing.iter().copied().chain(annotations).collect());
 		idx
 	}
 
@@ -913,6 +1018,87 @@ impl ExpressionCollector<'_> {
 	}
 }
 
+/// A constant value computed while folding arithmetic
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Constant {
+	Integer(i64),
+	Float(f64),
+}
+
+/// Compute the value of a constant arithmetic expression
+fn fold_constant(e: &minizinc::Expression) -> Option<Constant> {
+	match e {
+		minizinc::Expression::IntegerLiteral(i) => i.value().ok().map(Constant::Integer),
+		minizinc::Expression::FloatLiteral(f) => f.value().ok().map(Constant::Float),
+		minizinc::Expression::PrefixOperator(o) if o.operator().name() == "-" => {
+			match fold_constant(&o.operand())? {
+				Constant::Integer(v) => v.checked_neg().map(Constant::Integer),
+				Constant::Float(v) => Some(Constant::Float(-v)),
+			}
+		}
+		minizinc::Expression::InfixOperator(o) => {
+			let left = fold_constant(&o.left())?;
+			let right = fold_constant(&o.right())?;
+			fold_arithmetic(o.operator().name(), left, right)?.ok()
+		}
+		_ => None,
+	}
+}
+
+/// Apply an arithmetic operator to constant operands.
+///
+/// Returns `None` if the operation cannot be folded, or an error message if it overflows.
+fn fold_arithmetic(
+	operator: &str,
+	left: Constant,
+	right: Constant,
+) -> Option<Result<Constant, String>> {
+	match (left, right) {
+		(Constant::Integer(l), Constant::Integer(r)) => {
+			if matches!(operator, "div" | "mod") && r == 0 {
+				// Leave division by zero to be reported later
+				return None;
+			}
+			let result = match operator {
+				"+" => l.checked_add(r),
+				"-" => l.checked_sub(r),
+				"*" => l.checked_mul(r),
+				"div" => l.checked_div(r),
+				"mod" => l.checked_rem(r),
+				_ => return None,
+			};
+			Some(
+				result.map(Constant::Integer).ok_or_else(|| {
+					format!("Integer overflow when computing {} {} {}", l, operator, r)
+				}),
+			)
+		}
+		(Constant::Float(l), Constant::Float(r)) => {
+			let result = match operator {
+				"+" => l + r,
+				"-" => l - r,
+				"*" => l * r,
+				"/" => l / r,
+				_ => return None,
+			};
+			result.is_finite().then_some(Ok(Constant::Float(result)))
+		}
+		_ => None,
+	}
+}
+
+/// Whether this expression is an arithmetic operation or numeric literal
+fn is_arithmetic(e: &minizinc::Expression) -> bool {
+	match e {
+		minizinc::Expression::IntegerLiteral(_) | minizinc::Expression::FloatLiteral(_) => true,
+		minizinc::Expression::PrefixOperator(o) => o.operator().name() == "-",
+		minizinc::Expression::InfixOperator(o) => {
+			matches!(o.operator().name(), "+" | "-" | "*" | "/" | "div" | "mod")
+		}
+		_ => false,
+	}
+}
+
 /// Tracks type-inst identifiers used in a function item
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct TypeInstIdentifiers {
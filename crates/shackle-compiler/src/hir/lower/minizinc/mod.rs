@@ -6,6 +6,8 @@
 //! - prefix/infix/postfix operators rewritten as calls
 //! - generator calls rewritten as calls using array comprehensions
 //! - string interpolation rewritten into `concat` of `show` calls
+//! - constant integer/float arithmetic folded into literals (if the
+//!   `fold_constants` setting is enabled)
 //!
 //! Any performed desugaring steps need must be formulated to guarantee that no
 //! future error messages could refer to non-user-written constructs.
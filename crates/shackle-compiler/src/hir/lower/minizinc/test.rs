@@ -1,6 +1,12 @@
 use expect_test::expect;
 
-use crate::hir::lower::test::check_lower_item;
+use crate::{
+	db::{CompilerDatabase, CompilerSettings},
+	file::InputLang,
+	hir::lower::test::{
+		check_lower_errors, check_lower_errors_with_db, check_lower_item, check_lower_item_folded,
+	},
+};
 
 #[test]
 fn test_lower_assignment() {
@@ -433,3 +439,245 @@ fn test_lower_type_alias() {
 "#]),
 	);
 }
+
+#[test]
+fn test_lower_anonymous_variable() {
+	check_lower_item(
+		"constraint x = [1, _];",
+		expect!([r#"
+    Item: Constraint { expression: <Expression::6>, annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("x")
+        <Expression::2>: IntegerLiteral(1)
+        <Expression::3>: Anonymous
+        <Expression::4>: ArrayLiteral { members: [<Expression::2>, <Expression::3>] }
+        <Expression::5>: Identifier("=")
+        <Expression::6>: Call { function: <Expression::5>, arguments: [<Expression::1>, <Expression::4>] }
+      Types:
+      Patterns:
+      Annotations:
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_string_interpolation_text() {
+	// Text following an interpolated expression is kept as it is
+	check_lower_item(
+		r#"
+        output ["\(h):00 and \(x):1.5 units"];
+    "#,
+		expect!([r#"
+    Item: Output { section: None, expression: <Expression::12> }
+      Expressions:
+        <Expression::1>: Identifier("h")
+        <Expression::2>: Identifier("show")
+        <Expression::3>: Call { function: <Expression::2>, arguments: [<Expression::1>] }
+        <Expression::4>: StringLiteral(":00 and ")
+        <Expression::5>: Identifier("x")
+        <Expression::6>: Identifier("show")
+        <Expression::7>: Call { function: <Expression::6>, arguments: [<Expression::5>] }
+        <Expression::8>: StringLiteral(":1.5 units")
+        <Expression::9>: ArrayLiteral { members: [<Expression::3>, <Expression::4>, <Expression::7>, <Expression::8>] }
+        <Expression::10>: Identifier("concat")
+        <Expression::11>: Call { function: <Expression::10>, arguments: [<Expression::9>] }
+        <Expression::12>: ArrayLiteral { members: [<Expression::11>] }
+      Types:
+      Patterns:
+      Annotations:
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_2d_array_literal_errors() {
+	check_lower_errors(
+		"constraint x = [| 1, 2 | 3 |];",
+		expect!([r#"
+    Invalid array literal at `3`: Non-uniform 2D array literal row length
+"#]),
+	);
+	check_lower_errors(
+		"constraint x = [| 1: 2: 3: | 1, 2 | 3, 4 |];",
+		expect!([r#"
+    Invalid array literal at `1, 2`: 2D array literal has different row length to index row
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_expression_depth_limit() {
+	let mut db = CompilerDatabase::default();
+	db.set_max_expression_depth(3);
+	check_lower_errors_with_db(
+		db,
+		InputLang::MiniZinc,
+		"constraint not not not not true;",
+		expect!([r#"
+    Syntax Error at `not true`: Expression nesting exceeds the maximum depth of 3
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_constant_folding() {
+	check_lower_item_folded(
+		"constraint x = 2 * 3 + 1;",
+		expect!([r#"
+    Item: Constraint { expression: <Expression::4>, annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("x")
+        <Expression::2>: IntegerLiteral(7)
+        <Expression::3>: Identifier("=")
+        <Expression::4>: Call { function: <Expression::3>, arguments: [<Expression::1>, <Expression::2>] }
+      Types:
+      Patterns:
+      Annotations:
+"#]),
+	);
+	check_lower_item_folded(
+		"constraint x = (y + 1) * 1;",
+		expect!([r#"
+    Item: Constraint { expression: <Expression::7>, annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("x")
+        <Expression::2>: Identifier("y")
+        <Expression::3>: IntegerLiteral(1)
+        <Expression::4>: Identifier("+")
+        <Expression::5>: Call { function: <Expression::4>, arguments: [<Expression::2>, <Expression::3>] }
+        <Expression::6>: Identifier("=")
+        <Expression::7>: Call { function: <Expression::6>, arguments: [<Expression::1>, <Expression::5>] }
+      Types:
+      Patterns:
+      Annotations:
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_if_then_else_literal_condition() {
+	// Folding is only done once the branches have been typechecked (in THIR), so all branches
+	// are kept even with constant folding enabled
+	check_lower_item_folded(
+		"any: x = if true then a else b endif;",
+		expect!([r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::4>), annotations: [] }
+      Expressions:
+        <Expression::1>: BooleanLiteral(true)
+        <Expression::2>: Identifier("a")
+        <Expression::3>: Identifier("b")
+        <Expression::4>: IfThenElse { branches: [Branch { condition: <Expression::1>, result: <Expression::2> }], else_result: Some(<Expression::3>) }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("x"))
+      Annotations:
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_annotated_operator() {
+	check_lower_item(
+		"any: x = (a + b) :: bounds;",
+		expect!([r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::5>), annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("bounds")
+        <Expression::2>: Identifier("a")
+        <Expression::3>: Identifier("b")
+        <Expression::4>: Identifier("+")
+        <Expression::5>: Call { function: <Expression::4>, arguments: [<Expression::2>, <Expression::3>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("x"))
+      Annotations:
+        <Expression::5>: [<Expression::1>]
+"#]),
+	);
+	// Annotations of nested annotated expressions are combined
+	check_lower_item(
+		"any: x = ((a + b) :: foo) :: bar;",
+		expect!([r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::6>), annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("bar")
+        <Expression::2>: Identifier("foo")
+        <Expression::3>: Identifier("a")
+        <Expression::4>: Identifier("b")
+        <Expression::5>: Identifier("+")
+        <Expression::6>: Call { function: <Expression::5>, arguments: [<Expression::3>, <Expression::4>] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("x"))
+      Annotations:
+        <Expression::6>: [<Expression::2>, <Expression::1>]
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_duplicate_record_fields() {
+	check_lower_errors(
+		"any: x = (a: 1, b: 2, a: 3);",
+		expect!([r#"
+    Duplicate record field at `a`: Field a is already defined
+"#]),
+	);
+	check_lower_errors(
+		"record(int: a, bool: a): x;",
+		expect!([r#"
+    Duplicate record field at `a`: Field a is already defined
+"#]),
+	);
+	// Duplicate fields are still lowered so that their errors are reported
+	check_lower_errors(
+		"any: x = (a: 1, a: 99999999999999999999);",
+		expect!([r#"
+    Duplicate record field at `a`: Field a is already defined
+    Invalid numeric literal at `99999999999999999999`: number too large to fit in target type
+"#]),
+	);
+	check_lower_errors(
+		"record(int: a, 1..99999999999999999999: a): x;",
+		expect!([r#"
+    Duplicate record field at `a`: Field a is already defined
+    Invalid numeric literal at `99999999999999999999`: number too large to fit in target type
+"#]),
+	);
+	check_lower_item(
+		"any: x = (a: 1, a: 2);",
+		expect!([r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::2>), annotations: [] }
+      Expressions:
+        <Expression::1>: IntegerLiteral(1)
+        <Expression::2>: RecordLiteral { fields: [(<Pattern::2>, <Expression::1>)] }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("x"))
+        <Pattern::2>: Identifier(Identifier("a"))
+      Annotations:
+"#]),
+	);
+}
+
+#[test]
+fn test_lower_tuple_access() {
+	check_lower_item(
+		"any: x = y.2;",
+		expect!([r#"
+    Item: Declaration { declared_type: <Type::1>, pattern: <Pattern::1>, definition: Some(<Expression::2>), annotations: [] }
+      Expressions:
+        <Expression::1>: Identifier("y")
+        <Expression::2>: TupleAccess { tuple: <Expression::1>, field: IntegerLiteral(2) }
+      Types:
+        <Type::1>: Any
+      Patterns:
+        <Pattern::1>: Identifier(Identifier("x"))
+      Annotations:
+"#]),
+	);
+}
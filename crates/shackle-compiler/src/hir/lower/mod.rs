@@ -14,14 +14,42 @@ use crate::{
 	file::ModelRef,
 	hir::{db::Hir, source::SourceMap, *},
 	syntax::ast::ConstraintModel,
-	Error,
+	Error, Warning,
 };
 
 /// Lower a model to HIR
-pub fn lower_items(db: &dyn Hir, model: ModelRef) -> (Arc<Model>, Arc<SourceMap>, Arc<Vec<Error>>) {
+pub fn lower_items(
+	db: &dyn Hir,
+	model: ModelRef,
+) -> (
+	Arc<Model>,
+	Arc<SourceMap>,
+	Arc<Vec<Error>>,
+	Arc<Vec<Warning>>,
+) {
+	if let Some(cached) = db.cached_models().get(&model) {
+		if let Ok(contents) = model.contents(db.upcast()) {
+			if cached.is_valid(db, &contents) {
+				log::info!("Using cached lowered model");
+				return (
+					cached.model.clone(),
+					cached.source_map.clone(),
+					Default::default(),
+					Default::default(),
+				);
+			}
+		}
+	}
 	let ast = match db.ast(*model) {
 		Ok(m) => m,
-		Err(e) => return (Default::default(), Default::default(), Arc::new(vec![e])),
+		Err(e) => {
+			return (
+				Default::default(),
+				Default::default(),
+				Arc::new(vec![e]),
+				Default::default(),
+			)
+		}
 	};
 	let identifiers = IdentifierRegistry::new(db);
 	match ast {
@@ -31,7 +59,7 @@ pub fn lower_items(db: &dyn Hir, model: ModelRef) -> (Arc<Model>, Arc<SourceMap>
 				ctx.collect_item(item);
 			}
 			let (m, sm, e) = ctx.finish();
-			(Arc::new(m), Arc::new(sm), Arc::new(e))
+			(Arc::new(m), Arc::new(sm), Arc::new(e), Default::default())
 		}
 		ConstraintModel::EPrimeModel(ast) => {
 			let mut ctx = EPrimeItemCollector::new(db, &identifiers, model);
@@ -40,8 +68,8 @@ pub fn lower_items(db: &dyn Hir, model: ModelRef) -> (Arc<Model>, Arc<SourceMap>
 				ctx.collect_item(item);
 			}
 			ctx.add_solve();
-			let (m, sm, e) = ctx.finish();
-			(Arc::new(m), Arc::new(sm), Arc::new(e))
+			let (m, sm, e, w) = ctx.finish();
+			(Arc::new(m), Arc::new(sm), Arc::new(e), Arc::new(w))
 		}
 	}
 }
@@ -1,16 +1,25 @@
 use std::sync::Arc;
 
 use expect_test::Expect;
+use miette::Diagnostic;
 
 use crate::{
-	db::{CompilerDatabase, FileReader, Inputs},
+	db::{CompilerDatabase, CompilerSettings, FileReader, Inputs},
 	file::{InputFile, InputLang},
 	hir::db::Hir,
 	utils::DebugPrint,
 };
 
 pub fn check_lower_item_with_lang(language: InputLang, item: &str, expected: Expect) {
-	let mut db = CompilerDatabase::default();
+	check_lower_item_with_db(CompilerDatabase::default(), language, item, expected);
+}
+
+pub fn check_lower_item_with_db(
+	mut db: CompilerDatabase,
+	language: InputLang,
+	item: &str,
+	expected: Expect,
+) {
 	db.set_ignore_stdlib(true);
 	db.set_input_files(Arc::new(vec![InputFile::String(item.to_owned(), language)]));
 	let model = db.input_models();
@@ -27,3 +36,74 @@ pub fn check_lower_item(item: &str, expected: Expect) {
 pub fn check_lower_item_eprime(item: &str, expected: Expect) {
 	check_lower_item_with_lang(InputLang::EPrime, item, expected);
 }
+
+pub fn check_lower_item_folded(item: &str, expected: Expect) {
+	let mut db = CompilerDatabase::default();
+	db.set_fold_constants(true);
+	check_lower_item_with_db(db, InputLang::MiniZinc, item, expected);
+}
+
+/// Check the diagnostics produced when lowering, showing the source text each points at
+pub fn check_lower_errors_with_lang(language: InputLang, model: &str, expected: Expect) {
+	check_lower_errors_with_db(CompilerDatabase::default(), language, model, expected);
+}
+
+/// Check the diagnostics produced when lowering using the settings of the given database
+pub fn check_lower_errors_with_db(
+	db: CompilerDatabase,
+	language: InputLang,
+	model: &str,
+	expected: Expect,
+) {
+	let db = lowering_db(db, language, model);
+	let models = db.input_models();
+	let errors = db.lookup_lowering_errors(models[0]);
+	expected.assert_eq(&print_labels(model, errors.iter()));
+}
+
+/// Check the warnings produced when lowering, showing the source text each points at
+pub fn check_lower_warnings_with_lang(language: InputLang, model: &str, expected: Expect) {
+	let db = lowering_db(CompilerDatabase::default(), language, model);
+	let models = db.input_models();
+	let warnings = db.lookup_lowering_warnings(models[0]);
+	expected.assert_eq(&print_labels(model, warnings.iter()));
+}
+
+fn lowering_db(mut db: CompilerDatabase, language: InputLang, model: &str) -> CompilerDatabase {
+	db.set_ignore_stdlib(true);
+	db.set_input_files(Arc::new(vec![InputFile::String(
+		model.to_owned(),
+		language,
+	)]));
+	db
+}
+
+fn print_labels<'a, D: Diagnostic + 'a>(
+	model: &str,
+	diagnostics: impl Iterator<Item = &'a D>,
+) -> String {
+	let mut actual = String::new();
+	for e in diagnostics {
+		for l in e.labels().into_iter().flatten() {
+			actual.push_str(&format!(
+				"{} at `{}`: {}\n",
+				e,
+				&model[l.offset()..l.offset() + l.len()],
+				l.label().unwrap_or_default()
+			));
+		}
+	}
+	actual
+}
+
+pub fn check_lower_errors(model: &str, expected: Expect) {
+	check_lower_errors_with_lang(InputLang::MiniZinc, model, expected);
+}
+
+pub fn check_lower_errors_eprime(model: &str, expected: Expect) {
+	check_lower_errors_with_lang(InputLang::EPrime, model, expected);
+}
+
+pub fn check_lower_warnings_eprime(model: &str, expected: Expect) {
+	check_lower_warnings_with_lang(InputLang::EPrime, model, expected);
+}
@@ -15,6 +15,7 @@
 //!   module
 //! - Validation of whole program (see the `validate` module)
 
+pub mod cache;
 pub mod container;
 pub mod db;
 pub mod expression;
@@ -39,7 +40,7 @@ pub use scope::*;
 pub use typecheck::*;
 pub use types::*;
 
-use self::ids::LocalItemRef;
+use self::ids::{ItemKind, LocalItemRef};
 use crate::utils::{
 	arena::{Arena, ArenaIndex},
 	impl_index,
@@ -84,3 +85,77 @@ impl_index!(Model[self, index: ArenaIndex<Item<Function>>] -> Item<Function> { s
 impl_index!(Model[self, index: ArenaIndex<Item<Output>>] -> Item<Output> { self.outputs[index] });
 impl_index!(Model[self, index: ArenaIndex<Item<Solve>>] -> Item<Solve> { self.solves[index] });
 impl_index!(Model[self, index: ArenaIndex<Item<TypeAlias>>] -> Item<TypeAlias> { self.type_aliases[index] });
+
+impl Model {
+	/// Get the item with the given ID
+	pub fn item(&self, item: LocalItemRef) -> ModelItem<'_> {
+		match item {
+			LocalItemRef::Annotation(i) => ModelItem::Annotation(&self[i]),
+			LocalItemRef::Assignment(i) => ModelItem::Assignment(&self[i]),
+			LocalItemRef::Constraint(i) => ModelItem::Constraint(&self[i]),
+			LocalItemRef::Declaration(i) => ModelItem::Declaration(&self[i]),
+			LocalItemRef::Enumeration(i) => ModelItem::Enumeration(&self[i]),
+			LocalItemRef::EnumAssignment(i) => ModelItem::EnumAssignment(&self[i]),
+			LocalItemRef::Function(i) => ModelItem::Function(&self[i]),
+			LocalItemRef::Output(i) => ModelItem::Output(&self[i]),
+			LocalItemRef::Solve(i) => ModelItem::Solve(&self[i]),
+			LocalItemRef::TypeAlias(i) => ModelItem::TypeAlias(&self[i]),
+		}
+	}
+
+	/// Iterate over all items in their original order
+	pub fn iter_items(&self) -> impl Iterator<Item = (LocalItemRef, ModelItem<'_>)> + '_ {
+		self.items.iter().map(move |i| (*i, self.item(*i)))
+	}
+
+	/// Iterate over the items of the given kind in their original order
+	pub fn items_of_kind(
+		&self,
+		kind: ItemKind,
+	) -> impl Iterator<Item = (LocalItemRef, ModelItem<'_>)> + '_ {
+		self.iter_items().filter(move |(i, _)| i.kind() == kind)
+	}
+}
+
+/// An item of any kind in a model
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModelItem<'a> {
+	/// Annotation item
+	Annotation(&'a Item<Annotation>),
+	/// Assignment item
+	Assignment(&'a Item<Assignment>),
+	/// Constraint item
+	Constraint(&'a Item<Constraint>),
+	/// Declaration item
+	Declaration(&'a Item<Declaration>),
+	/// Enumeration item
+	Enumeration(&'a Item<Enumeration>),
+	/// Enum assignment item
+	EnumAssignment(&'a Item<EnumAssignment>),
+	/// Function item
+	Function(&'a Item<Function>),
+	/// Output item
+	Output(&'a Item<Output>),
+	/// Solve item
+	Solve(&'a Item<Solve>),
+	/// Type alias item
+	TypeAlias(&'a Item<TypeAlias>),
+}
+
+impl<'a> ModelItem<'a> {
+	/// Get the item data for this item
+	pub fn data(&self) -> &'a ItemData {
+		match self {
+			ModelItem::Annotation(i) => &i.data,
+			ModelItem::Assignment(i) => &i.data,
+			ModelItem::Constraint(i) => &i.data,
+			ModelItem::Declaration(i) => &i.data,
+			ModelItem::Enumeration(i) => &i.data,
+			ModelItem::EnumAssignment(i) => &i.data,
+			ModelItem::Function(i) => &i.data,
+			ModelItem::Output(i) => &i.data,
+			ModelItem::Solve(i) => &i.data,
+			ModelItem::TypeAlias(i) => &i.data,
+		}
+	}
+}
@@ -59,6 +59,21 @@ pub fn lookup_enum_constructors(db: &dyn Hir, e: EnumRef) -> Option<Arc<Vec<Patt
 	map.get(&e).cloned()
 }
 
+/// Get all enum types declared in the model along with their constructors
+pub fn all_enums(db: &dyn Hir) -> Arc<Vec<(EnumRef, Arc<Vec<PatternRef>>)>> {
+	let mut result = Vec::new();
+	for m in db.resolve_includes().unwrap().iter() {
+		let model = db.lookup_model(*m);
+		for (i, e) in model.enumerations.iter() {
+			let item = ItemRef::new(db, *m, i);
+			let enum_ref = EnumRef::new(db, PatternRef::new(item, e.pattern));
+			let constructors = db.lookup_enum_constructors(enum_ref).unwrap_or_default();
+			result.push((enum_ref, constructors));
+		}
+	}
+	Arc::new(result)
+}
+
 /// Check that all case statements in this item are exhaustive
 pub fn check_case_exhaustiveness(
 	db: &dyn Hir,
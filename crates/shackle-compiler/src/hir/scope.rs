@@ -9,7 +9,9 @@ use rustc_hash::{FxHashMap, FxHashSet};
 
 use super::{Constructor, EnumConstructor, Generator, MaybeIndexSet};
 use crate::{
-	diagnostics::{IdentifierAlreadyDefined, IdentifierShadowing, InvalidPattern},
+	diagnostics::{
+		ForwardReference, IdentifierAlreadyDefined, IdentifierShadowing, InvalidPattern,
+	},
 	hir::{
 		db::Hir,
 		ids::{EntityRef, ItemRef, LocalItemRef, NodeRef, PatternRef},
@@ -389,6 +391,8 @@ struct ScopeCollector<'a> {
 	current: ArenaIndex<Scope>,
 	generations: Vec<u32>,
 	expression_scope: ArenaMap<Expression, (ArenaIndex<Scope>, u32)>,
+	let_declarations: Vec<FxHashMap<Identifier, ArenaIndex<Pattern>>>,
+	forward_references: FxHashSet<ArenaIndex<Expression>>,
 	diagnostics: Vec<Error>,
 	warnings: Vec<Warning>,
 }
@@ -407,6 +411,8 @@ impl ScopeCollector<'_> {
 			current,
 			generations: vec![0],
 			expression_scope: ArenaMap::new(),
+			let_declarations: Vec::new(),
+			forward_references: FxHashSet::default(),
 			diagnostics: Vec::new(),
 			warnings: Vec::new(),
 		}
@@ -553,28 +559,26 @@ impl ScopeCollector<'_> {
 		let e = &self.data[index];
 		match e {
 			Expression::Absent
+			| Expression::Anonymous
 			| Expression::BooleanLiteral(_)
 			| Expression::FloatLiteral(_)
-			| Expression::Identifier(_)
 			| Expression::Infinity
 			| Expression::IntegerLiteral(_)
 			| Expression::Missing
 			| Expression::Slice(_)
 			| Expression::StringLiteral(_) => (),
+			Expression::Identifier(i) => self.check_forward_reference(index, *i),
 			Expression::ArrayAccess(aa) => {
 				self.collect_expression(aa.collection);
 				self.collect_expression(aa.indices);
 			}
 			Expression::ArrayComprehension(c) => {
-				self.push();
-				for generator in c.generators.iter() {
-					self.collect_generator(generator);
-				}
+				self.collect_generators(&c.generators);
 				if let Some(i) = c.indices {
 					self.collect_expression(i);
 				}
 				self.collect_expression(c.template);
-				self.pop();
+				self.pop_generators(&c.generators);
 			}
 			Expression::ArrayLiteral(al) => {
 				for e in al.members.iter() {
@@ -621,6 +625,16 @@ impl ScopeCollector<'_> {
 			}
 			Expression::Let(l) => {
 				self.push();
+				// Declarations which have not been reached yet
+				let mut later = FxHashMap::default();
+				for let_item in l.items.iter().rev() {
+					if let LetItem::Declaration(d) = let_item {
+						for p in Pattern::identifiers(d.pattern, self.data) {
+							later.insert(self.data[p].identifier().unwrap(), p);
+						}
+					}
+				}
+				self.let_declarations.push(later);
 				for let_item in l.items.iter() {
 					match let_item {
 						LetItem::Constraint(c) => {
@@ -638,19 +652,21 @@ impl ScopeCollector<'_> {
 								self.collect_expression(def)
 							}
 							self.collect_pattern(d.pattern, true);
+							let later = self.let_declarations.last_mut().unwrap();
+							for p in Pattern::identifiers(d.pattern, self.data) {
+								later.remove(&self.data[p].identifier().unwrap());
+							}
 						}
 					}
 				}
+				self.let_declarations.pop();
 				self.collect_expression(l.in_expression);
 				self.pop();
 			}
 			Expression::SetComprehension(c) => {
-				self.push();
-				for generator in c.generators.iter() {
-					self.collect_generator(generator);
-				}
+				self.collect_generators(&c.generators);
 				self.collect_expression(c.template);
-				self.pop();
+				self.pop_generators(&c.generators);
 			}
 			Expression::SetLiteral(s) => {
 				for e in s.members.iter() {
@@ -704,6 +720,79 @@ impl ScopeCollector<'_> {
 			.insert(index, (self.current, self.generation()));
 	}
 
+	/// Report a reference to a let declaration which has not been reached yet.
+	///
+	/// References which resolve to an identifier from an outer scope (e.g. a recursive call
+	/// to the enclosing function) are not forward references.
+	fn check_forward_reference(&mut self, expression: ArenaIndex<Expression>, i: Identifier) {
+		let pattern = match self
+			.let_declarations
+			.iter()
+			.rev()
+			.find_map(|later| later.get(&i))
+		{
+			Some(p) => *p,
+			None => return,
+		};
+		if self.is_defined(i) {
+			return;
+		}
+		let (src, span) =
+			NodeRef::from(EntityRef::new(self.db, self.item, expression)).source_span(self.db);
+		let (_, declaration) =
+			NodeRef::from(EntityRef::new(self.db, self.item, pattern)).source_span(self.db);
+		self.diagnostics.push(
+			ForwardReference {
+				src,
+				span,
+				declaration,
+				identifier: i.pretty_print(self.db),
+			}
+			.into(),
+		);
+		self.forward_references.insert(expression);
+	}
+
+	/// Whether the given identifier refers to a variable or function in the current scope
+	fn is_defined(&self, i: Identifier) -> bool {
+		let generation = self.generation();
+		let mut current = self.current;
+		loop {
+			match &self.scopes[current] {
+				Scope::Local { parent, scope } => {
+					if scope.find_variable(i, generation).is_some()
+						|| !scope.find_function(i, generation).is_empty()
+					{
+						return true;
+					}
+					current = *parent;
+				}
+				Scope::Global => {
+					return self.db.lookup_global_variable(i).is_some()
+						|| !self.db.lookup_global_function(i).is_empty()
+				}
+			}
+		}
+	}
+
+	/// Collect the generators of a comprehension.
+	///
+	/// Each generator is given its own scope, so a generator which reuses the name of an earlier
+	/// generator shadows it (and produces a warning) rather than redefining it.
+	fn collect_generators(&mut self, generators: &[Generator]) {
+		for generator in generators.iter() {
+			self.push();
+			self.collect_generator(generator);
+		}
+	}
+
+	/// Pop the scopes added by [`Self::collect_generators`]
+	fn pop_generators(&mut self, generators: &[Generator]) {
+		for _ in generators.iter() {
+			self.pop();
+		}
+	}
+
 	fn collect_generator(&mut self, generator: &Generator) {
 		match generator {
 			Generator::Iterator {
@@ -776,6 +865,7 @@ impl ScopeCollector<'_> {
 			result: Arc::new(ScopeResult {
 				scopes: self.scopes,
 				expression_scopes: self.expression_scope,
+				forward_references: self.forward_references,
 			}),
 			diagnostics: Arc::new(self.diagnostics),
 			warnings: Arc::new(self.warnings),
@@ -815,9 +905,15 @@ pub struct ScopeCollectorResult {
 pub struct ScopeResult {
 	scopes: Arena<Scope>,
 	expression_scopes: ArenaMap<Expression, (ArenaIndex<Scope>, u32)>,
+	forward_references: FxHashSet<ArenaIndex<Expression>>,
 }
 
 impl ScopeResult {
+	/// Whether the given identifier expression refers to a later let declaration
+	pub fn is_forward_reference(&self, e: ArenaIndex<Expression>) -> bool {
+		self.forward_references.contains(&e)
+	}
+
 	/// Return the function identifiers in scope for the given expression
 	///
 	/// Used for code completion
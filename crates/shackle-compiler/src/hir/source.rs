@@ -10,13 +10,15 @@ pub use tree_sitter::Point;
 
 use super::{
 	db::Hir,
-	ids::{EntityRef, ExpressionRef, ItemRef, LocalEntityRef, NodeRef},
-	ItemDataSourceMap, Type,
+	ids::{EntityRef, ExpressionRef, ItemRef, LocalEntityRef, NodeRef, PatternRef},
+	Identifier, ItemDataSourceMap, Type,
 };
 use crate::{
+	diagnostics::InvalidRename,
 	file::{FileRef, SourceFile},
 	syntax::{ast::AstNode, cst::CstNode},
-	utils::{debug_print_strings, DebugPrint},
+	utils::{debug_print_strings, pretty_print_identifier, DebugPrint},
+	Error,
 };
 
 /// Source mapping between HIR and AST nodes.
@@ -50,6 +52,11 @@ impl SourceMap {
 		self.map.get(&node)
 	}
 
+	/// Iterate over the nodes in the source map and their origins
+	pub fn iter(&self) -> impl '_ + Iterator<Item = (NodeRef, &Origin)> {
+		self.map.iter().map(|(k, v)| (*k, v))
+	}
+
 	/// Get the reference to the HIR node from the CST node ID
 	pub fn find_node<'a>(&self, cst_node: impl Into<Node<'a>>) -> Option<NodeRef> {
 		self.reverse.get(&cst_node.into().id()).copied()
@@ -146,12 +153,169 @@ pub fn find_expression(
 	})
 }
 
+/// Get the documentation comment for the given item.
+///
+/// This is made up of the line comments directly above the item, with their comment markers
+/// removed. Comments separated from the item by a blank line, or which trail another item, are
+/// not included.
+pub fn item_doc_comment(db: &dyn Hir, item: ItemRef) -> Option<String> {
+	let source_map = db.lookup_source_map(item.model_ref(db));
+	let origin = source_map.get_origin(item.into())?;
+	let cst = db.cst(origin.file).ok()?;
+	let mut node = cst
+		.root_node()
+		.descendant_for_byte_range(origin.range.start, origin.range.end)?;
+	while node.id() != origin.node_id {
+		node = node.parent()?;
+	}
+	let mut lines = Vec::new();
+	let mut row = node.start_position().row;
+	let mut prev = node.prev_sibling();
+	while let Some(comment) = prev.filter(|n| n.kind() == "line_comment") {
+		prev = comment.prev_sibling();
+		let trailing = prev.map_or(false, |p| {
+			p.end_position().row == comment.start_position().row
+		});
+		if trailing || comment.end_position().row + 1 != row {
+			break;
+		}
+		row = comment.start_position().row;
+		let text = &comment.utf8_text(cst.text().as_bytes()).unwrap()[1..];
+		lines.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+	}
+	if lines.is_empty() {
+		return None;
+	}
+	lines.reverse();
+	Some(lines.join("\n"))
+}
+
+/// Compute the textual edits needed to rename a declaration and all of its references.
+///
+/// The edits are only computed, not applied. Operators and reserved words cannot be renamed,
+/// and the new name must not collide with an identifier already visible where it is used.
+pub fn rename_edits(
+	db: &dyn Hir,
+	target: PatternRef,
+	new_name: &str,
+) -> Result<Vec<(FileRef, SourceSpan, String)>, Error> {
+	let origin = |entity: EntityRef| {
+		let source_map = db.lookup_source_map(entity.item(db).model_ref(db));
+		source_map
+			.get_origin(entity.into())
+			.expect("No origin for this node!")
+			.clone()
+	};
+	let declaration = origin(EntityRef::new(db, target.item(), target.pattern()));
+	let (src, span) = declaration.source_span(db);
+	let old = match target.identifier(db) {
+		Some(i) => i,
+		None => {
+			return Err(InvalidRename {
+				src,
+				span,
+				msg: "Only identifiers can be renamed".to_owned(),
+			}
+			.into())
+		}
+	};
+	let old_name = old.lookup(db);
+	if old_name.contains('\'') || pretty_print_identifier(&old_name) != old_name {
+		return Err(InvalidRename {
+			src,
+			span,
+			msg: "Operators and reserved words cannot be renamed".to_owned(),
+		}
+		.into());
+	}
+	if new_name.is_empty()
+		|| new_name.contains('\'')
+		|| pretty_print_identifier(new_name) != new_name
+	{
+		return Err(InvalidRename {
+			src,
+			span,
+			msg: format!("'{}' is not a valid name for an identifier", new_name),
+		}
+		.into());
+	}
+
+	let new = Identifier::new(new_name, db);
+	let collides = |variable: Option<PatternRef>, functions: &[PatternRef]| {
+		matches!(variable, Some(p) if p != target) || functions.iter().any(|p| *p != target)
+	};
+	let collision = |src: SourceFile, span: SourceSpan| -> Error {
+		InvalidRename {
+			src,
+			span,
+			msg: format!("'{}' is already defined in this scope", new_name),
+		}
+		.into()
+	};
+
+	// Check the scope of the declaration itself
+	let is_global = db.lookup_global_variable(old) == Some(target)
+		|| db.lookup_global_function(old).contains(&target);
+	if is_global {
+		if collides(
+			db.lookup_global_variable(new),
+			&db.lookup_global_function(new),
+		) {
+			return Err(collision(src, span));
+		}
+	} else {
+		// Conservatively disallow any other declaration of the new name in the same item
+		let model = target.item().model(db);
+		let data = target.item().local_item_ref(db).data(&model);
+		if data
+			.patterns
+			.iter()
+			.any(|(p, pattern)| p != target.pattern() && pattern.identifier() == Some(new))
+		{
+			return Err(collision(src, span));
+		}
+	}
+
+	let mut edits = Vec::new();
+	let mut push_edit = |origin: Origin, src: SourceFile, span: SourceSpan| {
+		// Skip nodes introduced through desugaring, which don't correspond to the identifier
+		// in the source text
+		if src.contents().get(origin.range.clone()) != Some(old_name.as_str()) {
+			return;
+		}
+		let edit = (origin.file, span, new_name.to_owned());
+		if !edits.contains(&edit) {
+			edits.push(edit);
+		}
+	};
+	push_edit(declaration, src, span);
+
+	// Check the scope of each reference
+	for reference in db.references(target).iter() {
+		let e = match reference.entity(db) {
+			LocalEntityRef::Expression(e) => e,
+			_ => continue,
+		};
+		let scope = db.lookup_item_scope(reference.item(db));
+		let origin = origin(*reference);
+		let (src, span) = origin.source_span(db);
+		if collides(
+			scope.find_variable(db, e, new),
+			&scope.find_function(db, e, new),
+		) {
+			return Err(collision(src, span));
+		}
+		push_edit(origin, src, span);
+	}
+	Ok(edits)
+}
+
 /// Origin of an HIR node.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Origin {
-	file: FileRef,
-	range: std::ops::Range<usize>,
-	node_id: usize,
+	pub(crate) file: FileRef,
+	pub(crate) range: std::ops::Range<usize>,
+	pub(crate) node_id: usize,
 }
 
 impl Origin {
@@ -189,3 +353,117 @@ impl<T: AstNode> From<&T> for Origin {
 		Self::new(node)
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use super::rename_edits;
+	use crate::{
+		db::{CompilerDatabase, CompilerSettings, FileReader, Inputs},
+		file::{InputFile, InputLang},
+		hir::{db::Hir, Identifier},
+		Error,
+	};
+
+	fn rename(source: &str, old_name: &str, new_name: &str) -> Result<String, Error> {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			source.to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let target = db
+			.lookup_global_variable(Identifier::new(old_name, &db))
+			.unwrap();
+		let mut edits = rename_edits(&db, target, new_name)?;
+		edits.sort_by_key(|(_, span, _)| std::cmp::Reverse(span.offset()));
+		let mut result = source.to_owned();
+		for (_, span, text) in edits {
+			result.replace_range(span.offset()..span.offset() + span.len(), &text);
+		}
+		Ok(result)
+	}
+
+	#[test]
+	fn test_rename_global() {
+		let result = rename(
+			r#"
+			int: x = 1;
+			any: a = (x, 2);
+			any: b = let { int: x = 3 } in [x];
+			any: c = [x, a.1];
+			"#,
+			"x",
+			"y",
+		)
+		.unwrap();
+		assert_eq!(
+			result,
+			r#"
+			int: y = 1;
+			any: a = (y, 2);
+			any: b = let { int: x = 3 } in [x];
+			any: c = [y, a.1];
+			"#
+		);
+	}
+
+	#[test]
+	fn test_rename_collision() {
+		let source = r#"
+			int: x = 1;
+			int: y = 2;
+			any: a = let { int: z = 3 } in (x, z);
+			"#;
+		assert!(matches!(
+			rename(source, "x", "y"),
+			Err(Error::InvalidRename(_))
+		));
+		assert!(matches!(
+			rename(source, "x", "z"),
+			Err(Error::InvalidRename(_))
+		));
+		assert!(matches!(
+			rename(source, "x", "constraint"),
+			Err(Error::InvalidRename(_))
+		));
+		assert!(rename(source, "x", "w").is_ok());
+	}
+
+	#[test]
+	fn test_item_doc_comment() {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			r#"% The number of items
+% (must be positive)
+int: n;
+
+% Not attached to anything
+
+int: m; % Trailing comment
+int: k;
+/* Block comment */
+int: j;
+"#
+			.to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let model = db.input_models()[0];
+		let docs = db
+			.lookup_items(model)
+			.iter()
+			.map(|i| db.item_doc_comment(*i))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			docs,
+			vec![
+				Some("The number of items\n(must be positive)".to_owned()),
+				None,
+				None,
+				None,
+			]
+		);
+	}
+}
@@ -114,12 +114,7 @@ impl BodyTypeContext {
 				// Declarations with incomplete types would have been done during signature typing
 				if data[it.declared_type].is_complete(data) {
 					if let Some(e) = it.definition {
-						let ids = db.identifier_registry();
-						let output_only = it.annotations.iter().any(|ann| match &it.data[*ann] {
-							crate::hir::Expression::Identifier(i) => *i == ids.output_only,
-							_ => false,
-						});
-						if output_only {
+						if db.is_output_only(PatternRef::new(item, it.pattern)) {
 							typer.typecheck_output(e, expected);
 						} else {
 							typer.typecheck_expression(e, expected);
@@ -222,6 +217,13 @@ impl TypeContext for BodyTypeContext {
 			expression
 		);
 	}
+	fn identifier_resolution(&self, expression: ExpressionRef) -> Option<PatternRef> {
+		assert_eq!(expression.item(), self.item);
+		self.data
+			.identifier_resolution
+			.get(&expression.expression())
+			.copied()
+	}
 	fn add_pattern_resolution(&mut self, pattern: PatternRef, resolution: PatternRef) {
 		assert_eq!(pattern.item(), self.item);
 		let old = self
@@ -51,11 +51,12 @@ use crate::{
 };
 
 mod body;
+mod recursion;
 mod signature;
 mod toposort;
 mod typer;
 
-pub use self::{body::*, signature::*, toposort::*, typer::*};
+pub use self::{body::*, recursion::*, signature::*, toposort::*, typer::*};
 
 /// Collected types for an item
 ///
@@ -380,6 +381,8 @@ pub trait TypeContext {
 	fn add_expression(&mut self, expression: ExpressionRef, ty: Ty);
 	/// Add identifier resolution
 	fn add_identifier_resolution(&mut self, expression: ExpressionRef, resolution: PatternRef);
+	/// Get the identifier resolution of an expression, if it has been added
+	fn identifier_resolution(&self, expression: ExpressionRef) -> Option<PatternRef>;
 	/// Add pattern resolution
 	fn add_pattern_resolution(&mut self, pattern: PatternRef, resolution: PatternRef);
 	/// Add an error
@@ -414,6 +417,15 @@ pub fn collect_item_body(db: &dyn Hir, item: ItemRef) -> (Arc<BodyTypes>, Arc<Ve
 	(Arc::new(s), Arc::new(e))
 }
 
+/// Signature of a function declaration (see `Hir::function_signature`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FunctionSignature {
+	/// Parameter types
+	pub params: Box<[Ty]>,
+	/// Return type
+	pub return_type: Ty,
+}
+
 /// Type of a pattern (usually a declaration)
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PatternTy {
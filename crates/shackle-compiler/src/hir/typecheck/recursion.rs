@@ -0,0 +1,280 @@
+//! Analysis of recursive functions.
+//!
+//! Builds the call graph of functions which have bodies and finds the cycles
+//! in it (strongly connected components). A cycle is considered guarded if at
+//! least one of its members contains an `if-then-else` or `case` branch which
+//! does not call back into the cycle, otherwise a warning is emitted for each
+//! member of the cycle.
+
+use std::sync::Arc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+	diagnostics::UnguardedRecursion,
+	hir::{
+		db::Hir,
+		ids::{ItemRef, LocalItemRef, NodeRef, PatternRef},
+		Expression,
+	},
+	utils::arena::ArenaIndex,
+	Warning,
+};
+
+/// Check for recursive functions which can never terminate
+pub fn check_function_recursion(db: &dyn Hir) -> Arc<Vec<Warning>> {
+	let models = match db.resolve_includes() {
+		Ok(m) => m,
+		Err(_) => return Arc::new(Vec::new()),
+	};
+
+	// Functions with bodies, indexed by the pattern which names them
+	let mut functions = FxHashMap::default();
+	let mut order = Vec::new();
+	for m in models.iter() {
+		let model = db.lookup_model(*m);
+		for item in db.lookup_items(*m).iter() {
+			if let LocalItemRef::Function(f) = item.local_item_ref(db) {
+				if model[f].body.is_some() {
+					functions.insert(PatternRef::new(*item, model[f].pattern), *item);
+					order.push(*item);
+				}
+			}
+		}
+	}
+
+	let mut graph = CallGraph::default();
+	for item in order.iter() {
+		let callees = graph.callees.entry(*item).or_default();
+		for callee in called_functions(db, *item, function_body(db, *item), &functions) {
+			if !callees.contains(&callee) {
+				callees.push(callee);
+			}
+		}
+	}
+
+	let mut warnings = Vec::new();
+	for component in graph.components(&order) {
+		let is_recursive =
+			component.len() > 1 || graph.callees[&component[0]].contains(&component[0]);
+		if !is_recursive {
+			continue;
+		}
+		let members = component.iter().copied().collect::<FxHashSet<_>>();
+		let targets = functions
+			.iter()
+			.filter(|(_, i)| members.contains(i))
+			.map(|(p, _)| *p)
+			.collect::<FxHashMap<_, _>>();
+		if component.iter().any(|f| has_base_case(db, *f, &targets)) {
+			continue;
+		}
+		let names = component
+			.iter()
+			.map(|f| function_name(db, *f))
+			.collect::<Vec<_>>();
+		let cycle = names.join(", ");
+		for (f, name) in component.iter().zip(names) {
+			let model = f.model(db);
+			let LocalItemRef::Function(i) = f.local_item_ref(db) else {
+				unreachable!()
+			};
+			let pattern = PatternRef::new(*f, model[i].pattern);
+			let (src, span) = NodeRef::from(pattern.into_entity(db)).source_span(db);
+			warnings.push(
+				UnguardedRecursion {
+					name,
+					cycle: cycle.clone(),
+					src,
+					span,
+				}
+				.into(),
+			);
+		}
+	}
+	Arc::new(warnings)
+}
+
+/// Call graph between functions
+#[derive(Default)]
+struct CallGraph {
+	callees: FxHashMap<ItemRef, Vec<ItemRef>>,
+}
+
+impl CallGraph {
+	/// Get the strongly connected components of the graph (using Kosaraju's algorithm)
+	fn components(&self, order: &[ItemRef]) -> Vec<Vec<ItemRef>> {
+		let position = order
+			.iter()
+			.enumerate()
+			.map(|(i, f)| (*f, i))
+			.collect::<FxHashMap<_, _>>();
+
+		// Post-order of depth first search
+		let mut visited = FxHashSet::default();
+		let mut finished = Vec::with_capacity(order.len());
+		for start in order.iter() {
+			if !visited.insert(*start) {
+				continue;
+			}
+			let mut stack = vec![(*start, 0)];
+			while let Some((node, next)) = stack.last_mut() {
+				if let Some(callee) = self.callees[node].get(*next) {
+					*next += 1;
+					if visited.insert(*callee) {
+						stack.push((*callee, 0));
+					}
+				} else {
+					finished.push(*node);
+					stack.pop();
+				}
+			}
+		}
+
+		// Search the transposed graph in reverse post-order
+		let mut callers: FxHashMap<ItemRef, Vec<ItemRef>> = FxHashMap::default();
+		for (caller, callees) in self.callees.iter() {
+			for callee in callees.iter() {
+				callers.entry(*callee).or_default().push(*caller);
+			}
+		}
+		let mut assigned = FxHashSet::default();
+		let mut components = Vec::new();
+		for start in finished.into_iter().rev() {
+			if !assigned.insert(start) {
+				continue;
+			}
+			let mut component = Vec::new();
+			let mut todo = vec![start];
+			while let Some(node) = todo.pop() {
+				component.push(node);
+				for caller in callers.get(&node).into_iter().flatten() {
+					if assigned.insert(*caller) {
+						todo.push(*caller);
+					}
+				}
+			}
+			// Keep members in declaration order
+			component.sort_by_key(|f| position[f]);
+			components.push(component);
+		}
+		components.sort_by_key(|c| position[&c[0]]);
+		components
+	}
+}
+
+fn function_body(db: &dyn Hir, item: ItemRef) -> ArenaIndex<Expression> {
+	let model = item.model(db);
+	match item.local_item_ref(db) {
+		LocalItemRef::Function(f) => model[f].body.unwrap(),
+		_ => unreachable!("Item is not a function"),
+	}
+}
+
+fn function_name(db: &dyn Hir, item: ItemRef) -> String {
+	let model = item.model(db);
+	match item.local_item_ref(db) {
+		LocalItemRef::Function(f) => model[f].data[model[f].pattern]
+			.identifier()
+			.unwrap()
+			.pretty_print(db),
+		_ => unreachable!("Item is not a function"),
+	}
+}
+
+/// Get the functions in `targets` called from the given expression
+fn called_functions(
+	db: &dyn Hir,
+	item: ItemRef,
+	expression: ArenaIndex<Expression>,
+	targets: &FxHashMap<PatternRef, ItemRef>,
+) -> Vec<ItemRef> {
+	let model = item.model(db);
+	let data = item.local_item_ref(db).data(&model);
+	let types = db.lookup_item_types(item);
+	Expression::walk(expression, data)
+		.filter_map(|e| match data[e] {
+			Expression::Identifier(_) => types
+				.name_resolution(e)
+				.and_then(|p| targets.get(&p).copied()),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Whether the function has an `if-then-else` or `case` branch which does not call any of `targets`
+fn has_base_case(db: &dyn Hir, item: ItemRef, targets: &FxHashMap<PatternRef, ItemRef>) -> bool {
+	let model = item.model(db);
+	let data = item.local_item_ref(db).data(&model);
+	let terminates = |e| called_functions(db, item, e, targets).is_empty();
+	Expression::walk(function_body(db, item), data).any(|e| match &data[e] {
+		Expression::IfThenElse(ite) => {
+			ite.else_result.map(terminates).unwrap_or(true)
+				|| ite.branches.iter().any(|b| terminates(b.result))
+		}
+		Expression::Case(c) => c.cases.iter().any(|i| terminates(i.value)),
+		_ => false,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::Arc;
+
+	use expect_test::{expect, Expect};
+
+	use crate::{
+		db::{CompilerDatabase, Inputs},
+		file::{InputFile, InputLang},
+		hir::db::Hir,
+		Warning,
+	};
+
+	fn check_recursion(model: &str, expected: Expect) {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			model.to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let mut actual = String::new();
+		for w in db.check_function_recursion().iter() {
+			if let Warning::UnguardedRecursion(w) = w {
+				actual.push_str(&format!("{}: {}\n", w.name, w.cycle));
+			}
+		}
+		expected.assert_eq(&actual);
+	}
+
+	#[test]
+	fn test_unguarded_recursion() {
+		check_recursion(
+			r#"
+			function int: dec(int: x);
+			function int: foo(int: x) = foo(dec(x));
+			function int: bar(int: x) = dec(baz(x));
+			function int: baz(int: x) = bar(x);
+		"#,
+			expect!([r#"
+    foo: foo
+    bar: bar, baz
+    baz: bar, baz
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_guarded_recursion() {
+		check_recursion(
+			r#"
+			function int: dec(int: x);
+			function bool: is_zero(int: x);
+			function int: fact(int: x) = if is_zero(x) then 1 else fact(dec(x)) endif;
+			function int: even(int: x) = if is_zero(x) then 1 else odd(dec(x)) endif;
+			function int: odd(int: x) = even(dec(x));
+			function int: quad(int: x) = dec(dec(x));
+		"#,
+			expect!([""]),
+		);
+	}
+}
@@ -657,6 +657,9 @@ impl TypeContext for SignatureTypeContext {
 			expression
 		);
 	}
+	fn identifier_resolution(&self, expression: ExpressionRef) -> Option<PatternRef> {
+		self.data.identifier_resolution.get(&expression).copied()
+	}
 	fn add_pattern_resolution(&mut self, pattern: PatternRef, resolution: PatternRef) {
 		let old = self.data.pattern_resolution.insert(pattern, resolution);
 		assert!(
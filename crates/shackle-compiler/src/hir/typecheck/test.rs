@@ -3,10 +3,11 @@ use std::sync::Arc;
 use expect_test::{expect, Expect};
 
 use crate::{
-	db::{CompilerDatabase, FileReader, Inputs},
+	db::{CompilerDatabase, CompilerSettings, FileReader, Inputs},
 	file::{InputFile, InputLang},
-	hir::{db::Hir, ids::LocalItemRef},
+	hir::{db::Hir, ids::LocalItemRef, Identifier, PatternTy},
 	ty::Ty,
+	Error,
 };
 
 #[derive(Default)]
@@ -28,6 +29,16 @@ impl TypeTester {
 	}
 
 	fn check_error(&mut self, model: &str, expected: Expect) {
+		let result = self
+			.collect_errors(model)
+			.iter()
+			.map(|e| e.to_string())
+			.collect::<Vec<_>>()
+			.join("\n");
+		expected.assert_eq(&result);
+	}
+
+	fn collect_errors(&mut self, model: &str) -> Vec<Error> {
 		self.db.set_input_files(Arc::new(vec![InputFile::String(
 			model.to_owned(),
 			InputLang::MiniZinc,
@@ -40,12 +51,7 @@ impl TypeTester {
 				}
 			}
 		}
-		let result = errors
-			.iter()
-			.map(|e| e.to_string())
-			.collect::<Vec<_>>()
-			.join("\n");
-		expected.assert_eq(&result);
+		errors
 	}
 
 	fn type_expression(&mut self, preamble: &str, expr: &str) -> Ty {
@@ -111,6 +117,14 @@ fn test_type_expressions() {
 		"let { var set of 1..3: s } in {i | i in s}",
 		expect!("var set of int"),
 	);
+	tester.check_expression(
+		"let { var bool: p } in [j | i in 1..3, j = i where p]",
+		expect!("array [int] of var opt int"),
+	);
+	tester.check_expression(
+		"let { var bool: p } in {j | i in 1..3, j = i where p}",
+		expect!("var set of int"),
+	);
 	tester.check_expression("let { any: x = (1, 2) } in x.1", expect!("int"));
 	tester.check_expression(
 		"let { any: x = (1, (1.5, 2)) } in x.2",
@@ -251,6 +265,109 @@ fn test_function_resolution() {
 	);
 }
 
+#[test]
+fn test_enum_reflection() {
+	let mut tester = TypeTester::default();
+	let preamble = r#"
+        enum Color = {Red, Green, Blue};
+        var Color: c;
+        "#;
+	tester.check_expression_preamble(preamble, "to_enum(Color, 1)", expect!("Color"));
+	tester.check_expression_preamble(
+		preamble,
+		"to_enum(Color, [1, 2])",
+		expect!("array [int] of Color"),
+	);
+	tester.check_expression_preamble(preamble, "enum_of(Red)", expect!("set of Color"));
+	tester.check_expression_preamble(preamble, "enum_of(c)", expect!("set of Color"));
+}
+
+/// Check the type of the top-level declaration `name` in the Essence' model
+fn check_eprime_declaration(model: &str, name: &str, expected: Expect) {
+	let mut db = CompilerDatabase::default();
+	db.set_input_files(Arc::new(vec![InputFile::String(
+		model.to_owned(),
+		InputLang::EPrime,
+	)]));
+	let p = db
+		.lookup_global_variable(Identifier::new(name, &db))
+		.unwrap();
+	let types = db.lookup_item_types(p.item());
+	let PatternTy::Variable(ty) = &types[p.pattern()] else {
+		unreachable!()
+	};
+	expected.assert_eq(&ty.pretty_print(&db));
+}
+
+#[test]
+fn test_eprime_integer_division() {
+	// Division and modulo use the floor division functions from eprime_globals.mzn
+	check_eprime_declaration("letting q = (-7) / 2", "q", expect!("int"));
+	check_eprime_declaration("letting r = (-7) % 2", "r", expect!("int"));
+
+	let mut db = CompilerDatabase::default();
+	db.set_input_files(Arc::new(vec![InputFile::String(
+		"find x : int(-3..3)\nsuch that x / 2 = -1 /\\ x % 2 = 1".to_owned(),
+		InputLang::EPrime,
+	)]));
+	assert!(db.all_errors().is_empty());
+}
+
+#[test]
+fn test_eprime_slice() {
+	let mut db = CompilerDatabase::default();
+	db.set_input_files(Arc::new(vec![InputFile::String(
+		r#"
+		given m : matrix indexed by [int(1..2), int(1..3)] of int
+		letting column = m[.., 2]
+		"#
+		.to_owned(),
+		InputLang::EPrime,
+	)]));
+	let p = db
+		.lookup_global_variable(Identifier::new("column", &db))
+		.unwrap();
+	let item = p.item();
+	let LocalItemRef::Declaration(d) = item.local_item_ref(&db) else {
+		unreachable!()
+	};
+	let e = item.model(&db)[d].definition.unwrap();
+	let ty = db.lookup_item_types(item)[e];
+	expect!("array [int] of int").assert_eq(&ty.pretty_print(&db));
+}
+
+#[test]
+fn test_if_then_else_literal_condition() {
+	// Branch types are unified even if the condition is known when constant folding is enabled
+	let mut tester = TypeTester::default();
+	tester.db.set_fold_constants(true);
+	tester.check_expression("if true then 1 else 2.5 endif", expect!("float"));
+	tester.check_expression("if false then 1 else 2.5 endif", expect!("float"));
+	tester.check_expression_preamble(
+		"var int: y;",
+		"if true then 1 else y endif",
+		expect!("var int"),
+	);
+	tester.check_expression_preamble(
+		"var bool: p;",
+		"if true then 1 elseif p then 2 else 3 endif",
+		expect!("var int"),
+	);
+	// Errors in branches which are not taken are still reported
+	tester.check_error(
+		r#"
+		any: x = if true then 1 else y endif;
+		"#,
+		expect!("Undefined identifier"),
+	);
+	tester.check_error(
+		r#"
+		any: x = if false then 1 else 2 endif + "a";
+		"#,
+		expect!("No matching function"),
+	);
+}
+
 #[test]
 fn test_type_errors() {
 	let mut tester = TypeTester::default();
@@ -278,6 +395,12 @@ fn test_type_errors() {
 		"#,
 		expect!("Invalid array literal"),
 	);
+	tester.check_error(
+		r#"
+		any: x = {j | i in 1..3, j = i where j};
+		"#,
+		expect!("Type mismatch"),
+	);
 	tester.check_error(
 		r#"
 		any: x = nope;
@@ -285,3 +408,159 @@ fn test_type_errors() {
 		expect!("Undefined identifier"),
 	);
 }
+
+#[test]
+fn test_assert() {
+	let mut tester = TypeTester::default();
+	tester.check_expression(r#"assert(true, "message")"#, expect!("bool"));
+	tester.check_expression(r#"assert(true, "message", 1)"#, expect!("int"));
+	tester.check_expression(
+		r#"let { var 1..3: x } in assert(true, "message", x)"#,
+		expect!("var int"),
+	);
+	tester.check_expression(
+		r#"assert(true, "message", [1.5, 2])"#,
+		expect!("array [int] of float"),
+	);
+	tester.check_error(
+		r#"
+		any: x = assert(true, 1, 2);
+		"#,
+		expect!("No matching function"),
+	);
+}
+
+#[test]
+fn test_trace() {
+	let mut tester = TypeTester::default();
+	tester.check_expression(r#"trace("message")"#, expect!("bool"));
+	tester.check_expression_preamble("var 1..3: x;", r#"trace("x=", x)"#, expect!("var int"));
+	tester.check_expression_preamble(
+		"array [1..3] of var bool: a;",
+		r#"trace_stdout("a=", a)"#,
+		expect!("array [int] of var bool"),
+	);
+	tester.check_expression(r#"trace_stdout("s=", {1, 2})"#, expect!("set of int"));
+	tester.check_error(
+		r#"
+		var 1..3: x;
+		any: y = trace(1, x);
+		"#,
+		expect!("No matching function"),
+	);
+}
+
+#[test]
+fn test_predicate_in_par_context() {
+	let mut tester = TypeTester::default();
+	tester.check_error(
+		r#"
+		predicate p(int: x) = x > 0;
+		bool: b = p(1);
+		"#,
+		expect!("Predicate called in a par context"),
+	);
+	tester.check_error(
+		r#"
+		predicate p(int: x) = x > 0;
+		constraint assert(p(1), "p(1) must hold");
+		"#,
+		expect!("Predicate called in a par context"),
+	);
+	tester.check_error(
+		r#"
+		test p(int: x) = x > 0;
+		bool: b = p(1);
+		constraint assert(p(1), "p(1) must hold");
+		"#,
+		expect![""],
+	);
+	// Operators and functions from the standard library cannot be declared as tests
+	tester.check_error(
+		r#"
+		var int: x;
+		bool: b = x > 0;
+		"#,
+		expect!("Type mismatch"),
+	);
+	tester.check_error(
+		r#"
+		var int: x;
+		bool: b = forall([x > 0]);
+		"#,
+		expect!("Type mismatch"),
+	);
+	tester.check_error(
+		r#"
+		var int: x;
+		constraint assert(x > 0, "x must be positive");
+		"#,
+		expect!("No matching function"),
+	);
+}
+
+#[test]
+fn test_infinite_range() {
+	let mut tester = TypeTester::default();
+	tester.check_expression("1..infinity", expect!("set of int"));
+	tester.check_expression("-infinity..5", expect!("set of int"));
+	tester.check_error(
+		r#"
+		set of int: a = 1..infinity;
+		set of int: b = -infinity..0;
+		"#,
+		expect![""],
+	);
+	tester.check_error(
+		r#"
+		set of int: s = infinity..infinity;
+		"#,
+		expect!("Range cannot have two infinite endpoints"),
+	);
+	tester.check_error(
+		r#"
+		set of int: s = -infinity..infinity;
+		"#,
+		expect!("Range cannot have two infinite endpoints"),
+	);
+}
+
+#[test]
+fn test_any_inference_failure() {
+	let mut tester = TypeTester::default();
+	for model in ["any: x;", "any: x = [];"] {
+		let errors = tester.collect_errors(model);
+		assert_eq!(errors.len(), 1, "{:?}", errors);
+		match &errors[0] {
+			Error::TypeInferenceFailure(e) => {
+				assert_eq!(e.msg, "Cannot infer type for 'x'; add a type annotation");
+				assert_eq!((e.span.offset(), e.span.len()), (5, 1));
+			}
+			e => panic!("Expected type inference failure, got {:?}", e),
+		}
+	}
+	tester.check_error("any: x = [1, 2];", expect![""]);
+	tester.check_expression("[1, 2]", expect!("array [int] of int"));
+}
+
+#[test]
+fn test_anonymous_variable() {
+	let mut tester = TypeTester::default();
+	tester.check_expression_preamble("var int: x;", "[x, _]", expect!("array [int] of var int"));
+	tester.check_expression("[_, 1.5]", expect!("array [int] of var float"));
+	tester.check_expression_preamble(
+		"function var int: f(var int: a, var int: b);",
+		"f(1, _)",
+		expect!("var int"),
+	);
+	tester.check_expression_preamble(
+		"predicate p(var bool: a); predicate p(var int: a, var int: b);",
+		"p(_)",
+		expect!("var bool"),
+	);
+	tester.check_error(
+		"var int: x; constraint x = [_, _][1];",
+		expect!["Type cannot be determined\nType cannot be determined"],
+	);
+	tester.check_error("var int: x = _;", expect!("Type cannot be determined"));
+}
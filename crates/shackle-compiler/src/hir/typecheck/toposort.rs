@@ -5,6 +5,9 @@
 //!
 //! Also ensures that globals (possibly transitively) used in function bodies
 //! appear before the function declaration.
+//!
+//! The dependency edges found while sorting are also recorded so that they can
+//! be queried for each item.
 
 use std::sync::Arc;
 
@@ -24,7 +27,13 @@ use crate::{
 };
 
 /// Topologically sort items
-pub fn topological_sort(db: &dyn Hir) -> (Arc<Vec<ItemRef>>, Arc<Vec<Error>>) {
+pub fn topological_sort(
+	db: &dyn Hir,
+) -> (
+	Arc<Vec<ItemRef>>,
+	Arc<Vec<Error>>,
+	Arc<FxHashMap<ItemRef, Arc<Vec<ItemRef>>>>,
+) {
 	log::info!("Topologically sorting items");
 	let models = db.resolve_includes().unwrap();
 	let mut items = Vec::with_capacity(models.iter().map(|m| db.lookup_items(*m).len()).sum());
@@ -55,8 +64,17 @@ pub fn topological_sort(db: &dyn Hir) -> (Arc<Vec<ItemRef>>, Arc<Vec<Error>>) {
 	for item in items.iter() {
 		topo_sorter.run(*item);
 	}
-	let (sorted, diagnostics) = topo_sorter.finish();
-	(Arc::new(sorted), Arc::new(diagnostics))
+	let (sorted, diagnostics, dependencies) = topo_sorter.finish();
+	(
+		Arc::new(sorted),
+		Arc::new(diagnostics),
+		Arc::new(
+			dependencies
+				.into_iter()
+				.map(|(k, v)| (k, Arc::new(v)))
+				.collect(),
+		),
+	)
 }
 
 /// Topological sorter
@@ -66,6 +84,8 @@ pub struct TopoSorter<'a> {
 	visited: FxHashSet<ItemRef>,
 	current: FxHashSet<PatternRef>,
 	assignments: FxHashMap<ItemRef, ItemRef>,
+	stack: Vec<ItemRef>,
+	dependencies: FxHashMap<ItemRef, Vec<ItemRef>>,
 	diagnostics: Vec<Error>,
 }
 
@@ -78,6 +98,8 @@ impl<'a> TopoSorter<'a> {
 			visited: FxHashSet::default(),
 			current: FxHashSet::default(),
 			assignments,
+			stack: Vec::new(),
+			dependencies: FxHashMap::default(),
 			diagnostics: Vec::new(),
 		}
 	}
@@ -88,6 +110,24 @@ impl<'a> TopoSorter<'a> {
 			return;
 		}
 		self.visited.insert(item);
+		self.dependencies.entry(item).or_default();
+		self.stack.push(item);
+		self.visit_item(item);
+		self.stack.pop();
+	}
+
+	/// Record that the item currently being visited depends on the given item, and sort it first
+	fn add_dependency(&mut self, dependency: ItemRef) {
+		if let Some(item) = self.stack.last() {
+			let dependencies = self.dependencies.entry(*item).or_default();
+			if *item != dependency && !dependencies.contains(&dependency) {
+				dependencies.push(dependency);
+			}
+		}
+		self.run(dependency);
+	}
+
+	fn visit_item(&mut self, item: ItemRef) {
 		let model = item.model(self.db);
 		let local_item = item.local_item_ref(self.db);
 		match local_item {
@@ -102,7 +142,7 @@ impl<'a> TopoSorter<'a> {
 			LocalItemRef::Assignment(a) => {
 				let types = self.db.lookup_item_types(item);
 				if let Some(p) = types.name_resolution(model[a].assignee) {
-					self.run(p.item());
+					self.add_dependency(p.item());
 					self.current.insert(p);
 					self.visit_expression(ExpressionRef::new(item, model[a].definition), None);
 					self.current.remove(&p);
@@ -178,7 +218,7 @@ impl<'a> TopoSorter<'a> {
 			LocalItemRef::EnumAssignment(e) => {
 				let types = self.db.lookup_item_types(item);
 				if let Some(p) = types.name_resolution(model[e].assignee) {
-					self.run(p.item());
+					self.add_dependency(p.item());
 					self.current.insert(p);
 					let data = local_item.data(&model);
 					for c in model[e].definition.iter() {
@@ -335,7 +375,7 @@ impl<'a> TopoSorter<'a> {
 								continue;
 							}
 						}
-						self.run(p.item());
+						self.add_dependency(p.item());
 					}
 				}
 			}
@@ -343,8 +383,8 @@ impl<'a> TopoSorter<'a> {
 	}
 
 	/// Get results of topological sorting
-	pub fn finish(self) -> (Vec<ItemRef>, Vec<Error>) {
-		(self.sorted, self.diagnostics)
+	pub fn finish(self) -> (Vec<ItemRef>, Vec<Error>, FxHashMap<ItemRef, Vec<ItemRef>>) {
+		(self.sorted, self.diagnostics, self.dependencies)
 	}
 }
 
@@ -360,6 +400,33 @@ mod test {
 		hir::db::Hir,
 	};
 
+	fn check_dependencies(model: &str, expected: Expect) {
+		let mut db = CompilerDatabase::default();
+		db.set_ignore_stdlib(true);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			model.to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let model = db.input_models()[0];
+		let sm = db.lookup_source_map(model);
+		let text = |item| {
+			let origin = sm.get_origin(item).unwrap();
+			let (source, span) = origin.source_span(&db);
+			source.contents()[span.offset()..span.offset() + span.len()].to_owned()
+		};
+		let mut actual = String::new();
+		for item in db.lookup_items(model).iter().copied() {
+			actual.push_str(&text(item.into()));
+			actual.push_str(";\n");
+			for dependency in db.item_dependencies(item).iter().copied() {
+				actual.push_str("  ");
+				actual.push_str(&text(dependency.into()));
+				actual.push_str(";\n");
+			}
+		}
+		expected.assert_eq(&actual);
+	}
+
 	fn check_toposort(model: &str, expected: Expect) {
 		let mut db = CompilerDatabase::default();
 		db.set_ignore_stdlib(true);
@@ -435,6 +502,25 @@ mod test {
     int: y;
     int: x;
     x = y;
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_item_dependencies() {
+		check_dependencies(
+			r#"
+			enum Foo = {A, B};
+			function int: f(Foo: x) = 1;
+			int: y = f(A);
+		"#,
+			expect!([r#"
+    enum Foo = {A, B};
+    function int: f(Foo: x) = 1;
+      enum Foo = {A, B};
+    int: y = f(A);
+      enum Foo = {A, B};
+      function int: f(Foo: x) = 1;
 "#]),
 		);
 	}
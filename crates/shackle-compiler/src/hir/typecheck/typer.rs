@@ -6,12 +6,13 @@ use super::{PatternTy, TypeContext};
 use crate::{
 	constants::{IdentifierRegistry, TypeRegistry},
 	diagnostics::{
-		AmbiguousCall, BranchMismatch, IllegalType, InvalidArrayLiteral, InvalidFieldAccess,
-		NoMatchingFunction, SyntaxError, TypeInferenceFailure, TypeMismatch, UndefinedIdentifier,
+		AmbiguousCall, BranchMismatch, IllegalType, InfiniteRange, InvalidArrayLiteral,
+		InvalidFieldAccess, NoMatchingFunction, PredicateInParContext, SyntaxError,
+		TypeInferenceFailure, TypeMismatch, UndefinedIdentifier,
 	},
 	hir::{
 		db::Hir,
-		ids::{EntityRef, ExpressionRef, ItemRef, NodeRef, PatternRef},
+		ids::{EntityRef, ExpressionRef, ItemRef, LocalItemRef, NodeRef, PatternRef},
 		ArrayAccess, ArrayComprehension, ArrayLiteral, ArrayLiteral2D, Call, Case, Declaration,
 		Expression, Generator, Identifier, IfThenElse, IndexedArrayLiteral, ItemData, Lambda, Let,
 		LetItem, MaybeIndexSet, Pattern, PrimitiveType, RecordAccess, RecordLiteral,
@@ -91,6 +92,15 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		let db = self.db;
 		let actual = self.collect_expression(expr);
 		if !actual.is_subtype_of(self.db.upcast(), expected) {
+			if actual == self.types.var_bool && expected == self.types.par_bool {
+				if let Some(name) = self.predicate_call(expr) {
+					let (src, span) = NodeRef::from(EntityRef::new(self.db, self.item, expr))
+						.source_span(self.db);
+					self.ctx
+						.add_diagnostic(self.item, PredicateInParContext { src, name, span });
+					return actual;
+				}
+			}
 			let (src, span) =
 				NodeRef::from(EntityRef::new(self.db, self.item, expr)).source_span(self.db);
 			self.ctx.add_diagnostic(
@@ -126,6 +136,7 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		let db = self.db;
 		let result = match &self.data[expr] {
 			Expression::Absent => self.types.bottom.make_opt(db.upcast()),
+			Expression::Anonymous => self.anonymous_type(expr, None),
 			Expression::BooleanLiteral(_) => self.types.par_bool,
 			Expression::IntegerLiteral(_) => self.types.par_int,
 			Expression::FloatLiteral(_) => self.types.par_float,
@@ -283,6 +294,11 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 			}
 		}
 
+		if db.lookup_item_scope(self.item).is_forward_reference(expr) {
+			// Error already emitted during scope collection
+			return self.types.error;
+		}
+
 		let (src, span) = NodeRef::from(EntityRef::new(db, self.item, expr)).source_span(db);
 		self.ctx.add_diagnostic(
 			self.item,
@@ -295,6 +311,46 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		self.types.error
 	}
 
+	/// Get the name of the function if the given expression is a call to a predicate (or other
+	/// function returning `var bool`) declared by the user
+	///
+	/// Calls to operators and functions from the standard library are not included, as these cannot
+	/// be declared as tests by the user.
+	fn predicate_call(&self, expr: ArenaIndex<Expression>) -> Option<String> {
+		let Expression::Call(c) = &self.data[expr] else {
+			return None;
+		};
+		let Expression::Identifier(i) = &self.data[c.function] else {
+			return None;
+		};
+		let resolution = self
+			.ctx
+			.identifier_resolution(ExpressionRef::new(self.item, c.function))?;
+		let item = resolution.item();
+		if !matches!(item.local_item_ref(self.db), LocalItemRef::Function(_)) {
+			return None;
+		}
+		let path = item.model_ref(self.db).path(self.db.upcast());
+		if let (Some(path), Ok(share)) = (path, self.db.share_directory()) {
+			if path.starts_with(share.as_path()) {
+				return None;
+			}
+		}
+		Some(i.pretty_print(self.db))
+	}
+
+	/// Whether the given expression is `infinity` or `-infinity`
+	fn is_infinite(&self, expr: ArenaIndex<Expression>) -> bool {
+		match &self.data[expr] {
+			Expression::Infinity => true,
+			Expression::Call(c) if c.arguments.len() == 1 => {
+				matches!(self.data[c.function], Expression::Identifier(i) if i == self.identifiers.minus)
+					&& matches!(self.data[c.arguments[0]], Expression::Infinity)
+			}
+			_ => false,
+		}
+	}
+
 	fn collect_call(
 		&mut self,
 		expr: ArenaIndex<Expression>,
@@ -302,20 +358,65 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		is_annotation_for: Option<Ty>,
 	) -> Ty {
 		let db = self.db;
-		let args = c
+		// Anonymous variables take their types from the called function, so are left until
+		// its parameter types are known
+		let anonymous = c
+			.arguments
+			.iter()
+			.enumerate()
+			.filter(|(_, e)| matches!(self.data[**e], Expression::Anonymous))
+			.map(|(i, _)| i)
+			.collect::<Vec<_>>();
+		let mut args = c
 			.arguments
 			.iter()
-			.map(|e| self.collect_expression(*e))
+			.map(|e| match self.data[*e] {
+				Expression::Anonymous => self.types.bottom,
+				_ => self.collect_expression(*e),
+			})
 			.collect::<Vec<_>>();
 
 		match self.data[c.function] {
 			Expression::Identifier(i) => {
-				let (op, ret) = self.resolve_overloading(c.function, i, &args, is_annotation_for);
+				if i == self.identifiers.dot_dot
+					&& c.arguments.len() == 2
+					&& c.arguments.iter().all(|arg| self.is_infinite(*arg))
+				{
+					// One endpoint may be infinite, giving an unbounded range, but not both
+					let (src, span) =
+						NodeRef::from(EntityRef::new(db, self.item, expr)).source_span(db);
+					self.ctx
+						.add_diagnostic(self.item, InfiniteRange { src, span });
+					return self.types.error;
+				}
+				if !anonymous.is_empty() {
+					// Take the types of anonymous variables from the parameters of the function
+					// which matches the other arguments
+					let params = if args.iter().any(|t| t.contains_error(db.upcast())) {
+						Some(vec![self.types.error; args.len()])
+					} else {
+						self.match_parameters(c.function, i, &args)
+					};
+					for idx in anonymous.iter() {
+						let expected = params.as_ref().and_then(|ps| ps.get(*idx).copied());
+						args[*idx] = self.collect_anonymous(c.arguments[*idx], expected);
+					}
+				}
+				let (op, ret) =
+					self.resolve_overloading(c.function, i, &c.arguments, &args, is_annotation_for);
 				self.collect_annotations(c.function, op);
 				ret
 			}
 			_ => {
 				let ty = self.collect_expression(c.function);
+				let params = ty.function_params(db.upcast());
+				for idx in anonymous.iter() {
+					let expected = params
+						.as_ref()
+						.and_then(|ps| ps.get(*idx).copied())
+						.unwrap_or(self.types.error);
+					args[*idx] = self.collect_anonymous(c.arguments[*idx], Some(expected));
+				}
 				if let TyData::Function(OptType::NonOpt, f) = ty.lookup(db.upcast()) {
 					if f.matches(db.upcast(), &args).is_err() {
 						let (src, span) =
@@ -356,16 +457,74 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		}
 	}
 
+	/// Collect the types of the members of an array literal.
+	///
+	/// Anonymous variables are given the `var` version of the type of the other members.
+	fn collect_array_members(&mut self, members: &[ArenaIndex<Expression>]) -> Vec<Ty> {
+		let db = self.db;
+		let tys = members
+			.iter()
+			.map(|e| match self.data[*e] {
+				Expression::Anonymous => None,
+				_ => Some(self.collect_expression(*e)),
+			})
+			.collect::<Vec<_>>();
+		if tys.iter().all(|t| t.is_some()) {
+			return tys.into_iter().flatten().collect();
+		}
+		let expected = if tys.iter().any(|t| t.is_some()) {
+			Ty::most_specific_supertype(db.upcast(), tys.iter().flatten().copied())
+		} else {
+			None
+		};
+		members
+			.iter()
+			.zip(tys)
+			.map(|(e, t)| t.unwrap_or_else(|| self.collect_anonymous(*e, expected)))
+			.collect()
+	}
+
+	/// Collect the type of an anonymous variable `_`, which is the `var` version of the type
+	/// expected by its context (or `None` if this is not known).
+	fn collect_anonymous(&mut self, expr: ArenaIndex<Expression>, expected: Option<Ty>) -> Ty {
+		let ty = self.anonymous_type(expr, expected);
+		self.ctx
+			.add_expression(ExpressionRef::new(self.item, expr), ty);
+		self.collect_annotations(expr, ty);
+		ty
+	}
+
+	fn anonymous_type(&mut self, expr: ArenaIndex<Expression>, expected: Option<Ty>) -> Ty {
+		let db = self.db;
+		let msg = match expected {
+			Some(ty) if ty.contains_error(db.upcast()) => return self.types.error,
+			Some(ty) => match ty.make_var(db.upcast()) {
+				Some(t)
+					if !t.contains_bottom(db.upcast())
+						&& !t.contains_type_inst_var(db.upcast()) =>
+				{
+					return t
+				}
+				_ => format!(
+					"Cannot create an anonymous variable of type '{}'",
+					ty.pretty_print(db.upcast())
+				),
+			},
+			None => "Cannot infer the type of this anonymous variable. Consider using an explicit declaration, or an opt type with <> for a missing value".to_owned(),
+		};
+		let (src, span) = NodeRef::from(EntityRef::new(db, self.item, expr)).source_span(db);
+		self.ctx
+			.add_diagnostic(self.item, TypeInferenceFailure { src, span, msg });
+		self.types.error
+	}
+
 	fn collect_array_literal(&mut self, expr: ArenaIndex<Expression>, al: &ArrayLiteral) -> Ty {
 		let db = self.db;
 		if al.members.is_empty() {
 			return self.types.array_of_bottom;
 		}
-		let ty = Ty::most_specific_supertype(
-			db.upcast(),
-			al.members.iter().map(|e| self.collect_expression(*e)),
-		)
-		.unwrap_or_else(|| {
+		let members = self.collect_array_members(&al.members);
+		let ty = Ty::most_specific_supertype(db.upcast(), members).unwrap_or_else(|| {
 			let (src, span) = NodeRef::from(EntityRef::new(db, self.item, expr)).source_span(db);
 			self.ctx.add_diagnostic(
 				self.item,
@@ -427,11 +586,8 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		let el_ty = if al.members.is_empty() {
 			self.types.bottom
 		} else {
-			Ty::most_specific_supertype(
-				db.upcast(),
-				al.members.iter().map(|e| self.collect_expression(*e)),
-			)
-			.unwrap_or_else(|| {
+			let members = self.collect_array_members(&al.members);
+			Ty::most_specific_supertype(db.upcast(), members).unwrap_or_else(|| {
 				let (src, span) =
 					NodeRef::from(EntityRef::new(db, self.item, expr)).source_span(db);
 				self.ctx.add_diagnostic(
@@ -488,11 +644,8 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		let el_ty = if al.members.is_empty() {
 			self.types.bottom
 		} else {
-			Ty::most_specific_supertype(
-				db.upcast(),
-				al.members.iter().map(|e| self.collect_expression(*e)),
-			)
-			.unwrap_or_else(|| {
+			let members = self.collect_array_members(&al.members);
+			Ty::most_specific_supertype(db.upcast(), members).unwrap_or_else(|| {
 				let (src, span) =
 					NodeRef::from(EntityRef::new(db, self.item, expr)).source_span(db);
 				self.ctx.add_diagnostic(
@@ -1520,8 +1673,12 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 			self.ctx
 				.add_declaration(PatternRef::new(self.item, p), PatternTy::Computing);
 		}
+		let complete = self.data[d.declared_type].is_complete(self.data);
 		let ty = if let Some(e) = d.definition {
 			let actual = self.collect_expression(e);
+			if !complete && actual.contains_bottom(self.db.upcast()) {
+				return self.uninferable_declaration(d);
+			}
 			let expected = self
 				.complete_type(d.declared_type, Some(actual), TypeCompletionMode::Default)
 				.ty;
@@ -1542,6 +1699,8 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 				);
 			}
 			expected
+		} else if !complete {
+			return self.uninferable_declaration(d);
 		} else {
 			self.complete_type(d.declared_type, None, TypeCompletionMode::Default)
 				.ty
@@ -1555,6 +1714,31 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		ty
 	}
 
+	/// Report that the type of a declaration with an incomplete type cannot be inferred from its
+	/// definition (or lack thereof)
+	fn uninferable_declaration(&mut self, d: &Declaration) -> Ty {
+		let db = self.db;
+		let names = Pattern::identifiers(d.pattern, self.data)
+			.filter_map(|p| self.data[p].identifier())
+			.map(|i| format!("'{}'", i.pretty_print(db)))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let (src, span) = NodeRef::from(EntityRef::new(db, self.item, d.pattern)).source_span(db);
+		self.ctx.add_diagnostic(
+			self.item,
+			TypeInferenceFailure {
+				src,
+				span,
+				msg: format!("Cannot infer type for {}; add a type annotation", names),
+			},
+		);
+		self.collect_pattern(None, false, d.pattern, self.types.error, false);
+		for ann in d.annotations.iter() {
+			self.typecheck_expression(*ann, self.types.ann);
+		}
+		self.types.error
+	}
+
 	/// Type check a declaration in output mode
 	pub fn collect_output_declaration(&mut self, d: &Declaration) -> Ty {
 		let prev = self.in_output_item;
@@ -1663,6 +1847,7 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 		&mut self,
 		expr: ArenaIndex<Expression>,
 		i: Identifier,
+		arg_exprs: &[ArenaIndex<Expression>],
 		args: &[Ty],
 		is_annotation_for: Option<Ty>,
 	) -> (Ty, Ty) {
@@ -1855,6 +2040,40 @@ impl<'a, T: TypeContext> Typer<'a, T> {
 				error
 			}
 			Err(FunctionResolutionError::NoMatchingFunction(es)) => {
+				// If calling a predicate in place of a par bool would match, report that instead
+				let predicates = arg_exprs
+					.iter()
+					.zip(args)
+					.filter(|(_, t)| **t == self.types.var_bool)
+					.filter_map(|(e, _)| Some((*e, self.predicate_call(*e)?)))
+					.collect::<Vec<_>>();
+				if !predicates.is_empty() {
+					let par_args = arg_exprs
+						.iter()
+						.zip(args)
+						.map(|(e, t)| {
+							if predicates.iter().any(|(p, _)| p == e) {
+								self.types.par_bool
+							} else {
+								*t
+							}
+						})
+						.collect::<Vec<_>>();
+					let overloads = es.iter().map(|(p, f, _)| (*p, f.clone()));
+					if FunctionEntry::match_fn(db.upcast(), overloads, &par_args).is_ok() {
+						for (e, name) in predicates {
+							let (src, span) =
+								NodeRef::from(EntityRef::new(db, self.item, e)).source_span(db);
+							self.ctx.add_diagnostic(
+								self.item,
+								PredicateInParContext { src, name, span },
+							);
+						}
+						self.ctx
+							.add_expression(ExpressionRef::new(self.item, expr), self.types.error);
+						return error;
+					}
+				}
 				let mut msg = String::new();
 				if args.is_empty() {
 					writeln!(
@@ -2763,6 +2982,43 @@ supported in operation types."
 		}
 	}
 
+	/// Get the parameter types of the function which would be called with the given argument
+	/// types, without recording the resolution.
+	///
+	/// Used to find the types of anonymous variables given as arguments.
+	fn match_parameters(
+		&mut self,
+		expr: ArenaIndex<Expression>,
+		i: Identifier,
+		args: &[Ty],
+	) -> Option<Vec<Ty>> {
+		let db = self.db;
+		if let Some(p) = self.find_variable(expr, i) {
+			if let PatternTy::Variable(t) | PatternTy::Argument(t) = self.ctx.type_pattern(db, p) {
+				if let Some(params) = t.function_params(db.upcast()) {
+					return Some(params);
+				}
+			}
+		}
+		let mut overloads = Vec::new();
+		for p in self.find_function(expr, i).iter() {
+			match self.ctx.type_pattern(db, *p) {
+				PatternTy::Function(function)
+				| PatternTy::AnnotationConstructor(function)
+				| PatternTy::AnnotationDestructure(function) => overloads.push((*p, *function.clone())),
+				PatternTy::EnumConstructor(ec) => {
+					overloads.extend(ec.iter().map(|ec| (*p, ec.constructor.clone())))
+				}
+				PatternTy::EnumDestructure(fs) => {
+					overloads.extend(fs.iter().map(|f| (*p, f.clone())))
+				}
+				_ => (),
+			}
+		}
+		let (_, fe, tvs) = FunctionEntry::match_fn(db.upcast(), overloads, args).ok()?;
+		Some(fe.overload.instantiate(db.upcast(), &tvs).params.to_vec())
+	}
+
 	fn find_variable(
 		&self,
 		expression: ArenaIndex<Expression>,
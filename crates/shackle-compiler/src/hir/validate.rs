@@ -6,6 +6,7 @@
 //! - Check for illegal overloading/duplicate definitions
 //! - Check for multiple definitions of variables
 //! - Check for multiple solve items
+//! - Check for constraints which are always true/false
 
 use std::{collections::hash_map::Entry, sync::Arc};
 
@@ -14,17 +15,18 @@ use rustc_hash::FxHashMap;
 use super::{
 	db::Hir,
 	ids::{EntityRef, LocalItemRef},
-	PatternTy,
+	BooleanLiteral, Expression, PatternTy, SolveMethod,
 };
 use crate::{
 	diagnostics::{
-		AdditionalSolveItem, ConstructorAlreadyDefined, DuplicateAssignment, DuplicateConstructor,
-		DuplicateFunction, FunctionAlreadyDefined, IllegalOverload, IllegalOverloading,
-		MultipleAssignments, MultipleSolveItems,
+		AdditionalSolveItem, ConstantConstraint, ConstructorAlreadyDefined, DuplicateAssignment,
+		DuplicateConstructor, DuplicateFunction, FunctionAlreadyDefined, IllegalOverload,
+		IllegalOverloading, MultipleAssignments, MultipleSolveItems, UnsatisfiableConstraint,
 	},
+	file::ModelRef,
 	hir::ids::{ItemRef, NodeRef},
 	ty::{FunctionEntry, OverloadingError},
-	Error,
+	Error, Warning,
 };
 
 /// Validate HIR
@@ -212,8 +214,17 @@ pub fn validate_hir(db: &dyn Hir) -> Arc<Vec<Error>> {
 	}
 
 	// Check for multiple solve items
+	if let Err(e) = db.solve_item() {
+		diagnostics.extend(e.iter().cloned());
+	}
+	Arc::new(diagnostics)
+}
+
+/// Get the solve item across all included models
+pub fn solve_item(db: &dyn Hir) -> Result<Option<ItemRef>, Arc<Vec<Error>>> {
+	let models = db.resolve_includes().map_err(|e| Arc::new(vec![e]))?;
 	let mut solve_items = Vec::new();
-	for m in db.resolve_includes().unwrap().iter() {
+	for m in models.iter() {
 		let model = db.lookup_model(*m);
 		for (i, _) in model.solves.iter() {
 			let item_ref = ItemRef::new(db, *m, i);
@@ -224,19 +235,76 @@ pub fn validate_hir(db: &dyn Hir) -> Arc<Vec<Error>> {
 		let mut iter = solve_items.into_iter();
 		let first = iter.next().unwrap();
 		let (src, span) = NodeRef::from(first).source_span(db);
-		diagnostics.push(
-			MultipleSolveItems {
-				src,
-				span,
-				others: iter
-					.map(|i| {
-						let (src, span) = NodeRef::from(i).source_span(db);
-						AdditionalSolveItem { src, span }
-					})
-					.collect(),
+		return Err(Arc::new(vec![MultipleSolveItems {
+			src,
+			span,
+			others: iter
+				.map(|i| {
+					let (src, span) = NodeRef::from(i).source_span(db);
+					AdditionalSolveItem { src, span }
+				})
+				.collect(),
+		}
+		.into()]));
+	}
+	Ok(solve_items.pop())
+}
+
+/// Get the solve method of the solve item across all included models (if there is one)
+pub fn solve_method(db: &dyn Hir) -> Result<Option<SolveMethod>, Arc<Vec<Error>>> {
+	let Some(item) = db.solve_item()? else {
+		return Ok(None);
+	};
+	let model = item.model(db);
+	match item.local_item_ref(db) {
+		LocalItemRef::Solve(s) => Ok(Some(model[s].goal.method())),
+		_ => unreachable!("Solve item must be a solve"),
+	}
+}
+
+/// Check for constraint items in the given model which are Boolean literals
+///
+/// `constraint true` has no effect, and `constraint false` makes the model unsatisfiable.
+pub fn check_constant_constraints(
+	db: &dyn Hir,
+	model: ModelRef,
+) -> (Arc<Vec<Error>>, Arc<Vec<Warning>>) {
+	let mut errors = Vec::new();
+	let mut warnings = Vec::new();
+	// Don't check the standard library
+	if let (Some(path), Ok(share)) = (model.path(db.upcast()), db.share_directory()) {
+		if path.starts_with(&*share) {
+			return (Arc::new(errors), Arc::new(warnings));
+		}
+	}
+	let m = db.lookup_model(model);
+	for (i, c) in m.constraints.iter() {
+		if let Expression::BooleanLiteral(BooleanLiteral(value)) = c.data[c.expression] {
+			let (src, span) = NodeRef::from(ItemRef::new(db, model, i)).source_span(db);
+			if value {
+				warnings.push(
+					ConstantConstraint {
+						value,
+						effect: "This constraint has no effect".to_owned(),
+						src,
+						span,
+					}
+					.into(),
+				);
+			} else if db.unsatisfiable_constraint_error() {
+				errors.push(UnsatisfiableConstraint { src, span }.into());
+			} else {
+				warnings.push(
+					ConstantConstraint {
+						value,
+						effect: "This constraint makes the model unsatisfiable".to_owned(),
+						src,
+						span,
+					}
+					.into(),
+				);
 			}
-			.into(),
-		);
+		}
 	}
-	Arc::new(diagnostics)
+	(Arc::new(errors), Arc::new(warnings))
 }
@@ -8,7 +8,7 @@ use std::{
 };
 
 use miette::SourceSpan;
-use tree_sitter::{Node, Tree, TreeCursor};
+use tree_sitter::{InputEdit, Node, Parser, Tree, TreeCursor};
 
 use super::db::SourceParser;
 use crate::{
@@ -55,6 +55,40 @@ impl Cst {
 		}
 	}
 
+	/// Create from a source buffer (without any `FileRef`).
+	pub fn from_source(tree: Tree, source: Arc<String>) -> Self {
+		Cst {
+			inner: Arc::new(CstInner {
+				tree,
+				file: None,
+				source,
+			}),
+		}
+	}
+
+	/// Create a CST for the given file which shares the tree and source of this CST.
+	pub fn with_file(&self, file: FileRef) -> Self {
+		Cst::new(self.inner.tree.clone(), file, self.inner.source.clone())
+	}
+
+	/// Incrementally re-parse this CST after an edit.
+	///
+	/// The edit must describe the change from the text of this CST to `source`.
+	/// Parts of the tree which are unaffected by the edit are reused, which is much faster than
+	/// parsing `source` from scratch. The resulting CST is not associated with any file.
+	pub fn reparse(&self, edit: &InputEdit, source: Arc<String>) -> Self {
+		let mut previous = self.inner.tree.clone();
+		previous.edit(edit);
+		let mut parser = Parser::new();
+		parser
+			.set_language(previous.language())
+			.expect("Failed to set Tree Sitter parser language");
+		let tree = parser
+			.parse(source.as_bytes(), Some(&previous))
+			.expect("Tree Sitter parser did not return tree object");
+		Cst::from_source(tree, source)
+	}
+
 	/// Get the underlying source file
 	pub fn file(&self) -> FileRef {
 		self.inner
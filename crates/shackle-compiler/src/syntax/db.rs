@@ -1,35 +1,123 @@
 #![allow(missing_docs)]
 //! Database queries for syntax parsing
 
-use tree_sitter::Parser;
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Mutex},
+};
+
+use rustc_hash::FxHashMap;
+use tree_sitter::{InputEdit, Parser};
 
 use super::{ast::ConstraintModel, cst::Cst, eprime::EPrimeModel, minizinc::MznModel};
 use crate::{
 	db::{FileReader, Upcast},
-	file::{FileRef, InputLang},
+	file::{FileRef, InputLang, ModelRef},
 	Result,
 };
 
+/// Maximum number of re-parsed CSTs kept by [`IncrementalParses`]
+pub const INCREMENTAL_PARSES_CAPACITY: usize = 16;
+
+/// CSTs produced by incrementally re-parsing, which have not been used yet
+///
+/// These are keyed by the source text they were parsed from. Only the most recent
+/// [`INCREMENTAL_PARSES_CAPACITY`] are kept, so re-parses which are never used (e.g. because
+/// another edit was made before the contents were parsed) are eventually dropped.
+#[derive(Clone, Debug, Default)]
+pub struct IncrementalParses(Arc<Mutex<VecDeque<(Arc<String>, Cst)>>>);
+
+impl IncrementalParses {
+	/// Incrementally re-parse `previous` after the given edit.
+	///
+	/// The resulting CST is used the next time `source` is parsed instead of parsing it from
+	/// scratch. Since the result of re-parsing is the same as parsing from scratch, this does
+	/// not need to be tracked by salsa.
+	pub fn reparse(&self, previous: &Cst, edit: &InputEdit, source: Arc<String>) {
+		let cst = previous.reparse(edit, source.clone());
+		let mut parses = self.0.lock().unwrap();
+		parses.retain(|(s, _)| *s != source);
+		if parses.len() == INCREMENTAL_PARSES_CAPACITY {
+			parses.pop_front();
+		}
+		parses.push_back((source, cst));
+	}
+
+	/// Take the CST previously re-parsed for the given source text, if any
+	pub fn take(&self, source: &Arc<String>) -> Option<Cst> {
+		let mut parses = self.0.lock().unwrap();
+		let idx = parses.iter().position(|(s, _)| s == source)?;
+		parses.remove(idx).map(|(_, cst)| cst)
+	}
+
+	/// The number of re-parsed CSTs which have not been used yet
+	pub fn len(&self) -> usize {
+		self.0.lock().unwrap().len()
+	}
+
+	/// Whether there are no re-parsed CSTs which have not been used yet
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// Trait for accessing the incrementally re-parsed CSTs of a database implementation
+pub trait HasIncrementalParses {
+	/// Get the incrementally re-parsed CSTs
+	fn incremental_parses(&self) -> &IncrementalParses;
+}
+
+/// Maximum number of parses of file contents kept by [`SourceParser::parse_contents`]
+pub const PARSE_CONTENTS_LRU_CAPACITY: usize = 128;
+
 /// Syntax parsing queries
 #[salsa::query_group(SourceParserStorage)]
-pub trait SourceParser: FileReader + Upcast<dyn FileReader> {
+pub trait SourceParser: FileReader + HasIncrementalParses + Upcast<dyn FileReader> {
 	/// Produce a CST for the given file.
 	///
 	/// Only gives an `Err` result if getting the file contents failed.
 	/// Otherwise, the error is contained in the CST.
 	fn cst(&self, file: FileRef) -> Result<Cst>;
 
+	/// Parse the given source text using the grammar for `lang`.
+	///
+	/// This is keyed on the contents rather than the file, so that files with identical contents
+	/// (e.g. a file which has been reverted to its previous contents) share the same parse.
+	/// The resulting CST is not associated with any file.
+	///
+	/// If these contents were produced by an edit which has been incrementally re-parsed
+	/// (see [`IncrementalParses::reparse`]), then that CST is used instead.
+	///
+	/// Only the most recently used parses are kept (see [`PARSE_CONTENTS_LRU_CAPACITY`]), since
+	/// otherwise the parse of every version of an edited file would be kept forever.
+	#[salsa::lru]
+	fn parse_contents(&self, lang: InputLang, contents: Arc<String>) -> Cst;
+
 	/// Produce an AST for the given file.
 	///
 	/// Only gives an `Err` result if getting the file contents failed.
 	/// Otherwise, the error is contained in the CST.
 	fn ast(&self, file: FileRef) -> Result<ConstraintModel>;
+
+	/// Count the occurrences of each kind of named node in the CST of the given model.
+	///
+	/// Anonymous nodes (such as keywords and punctuation) are not counted.
+	/// If the model could not be read, then the result is empty.
+	fn node_kind_histogram(&self, model: ModelRef) -> Arc<FxHashMap<String, u32>>;
 }
 
 fn cst(db: &dyn SourceParser, file: FileRef) -> Result<Cst> {
 	let contents = file.contents(db.upcast())?;
+	let parsed = db.parse_contents(file.lang(db.upcast()), contents);
+	Ok(parsed.with_file(file))
+}
 
-	let tree_sitter_lang = match file.lang(db.upcast()) {
+fn parse_contents(db: &dyn SourceParser, lang: InputLang, contents: Arc<String>) -> Cst {
+	if let Some(cst) = db.incremental_parses().take(&contents) {
+		return cst;
+	}
+
+	let tree_sitter_lang = match lang {
 		InputLang::MiniZinc => tree_sitter_minizinc::language(),
 		InputLang::EPrime => tree_sitter_eprime::language(),
 		_ => unreachable!("cst should only be called on model files"),
@@ -43,7 +131,7 @@ fn cst(db: &dyn SourceParser, file: FileRef) -> Result<Cst> {
 		.parse(contents.as_bytes(), None)
 		.expect("Tree Sitter parser did not return tree object");
 
-	Ok(Cst::new(tree, file, contents))
+	Cst::from_source(tree, contents)
 }
 
 fn ast(db: &dyn SourceParser, file: FileRef) -> Result<ConstraintModel> {
@@ -54,3 +142,238 @@ fn ast(db: &dyn SourceParser, file: FileRef) -> Result<ConstraintModel> {
 		_ => unreachable!("ast should only be called on ,odel files"),
 	}
 }
+
+fn node_kind_histogram(db: &dyn SourceParser, model: ModelRef) -> Arc<FxHashMap<String, u32>> {
+	let mut histogram = FxHashMap::default();
+	let Ok(cst) = db.cst(*model) else {
+		return Arc::new(histogram);
+	};
+	let mut cursor = cst.root_node().walk();
+	loop {
+		let node = cursor.node();
+		if node.is_named() {
+			*histogram.entry(node.kind().to_owned()).or_default() += 1;
+		}
+		if !cursor.goto_first_child() {
+			while !cursor.goto_next_sibling() {
+				if !cursor.goto_parent() {
+					return Arc::new(histogram);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{sync::Arc, time::Instant};
+
+	use tree_sitter::{InputEdit, Point};
+
+	use crate::{
+		db::{CompilerDatabase, FileReader, Inputs},
+		file::{InputFile, InputLang},
+		syntax::db::{
+			HasIncrementalParses, ParseContentsQuery, SourceParser, INCREMENTAL_PARSES_CAPACITY,
+		},
+	};
+
+	/// Get the address of the parsed source text (shared by CSTs using the same parse)
+	fn parse_id(db: &CompilerDatabase, idx: usize) -> *const u8 {
+		let file = db.input_file_refs()[idx];
+		db.cst(file).unwrap().text().as_ptr()
+	}
+
+	#[test]
+	fn test_identical_contents_share_parse() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![
+			InputFile::String("int: x = 1;".to_owned(), InputLang::MiniZinc),
+			InputFile::String("int: x = 1;".to_owned(), InputLang::MiniZinc),
+			InputFile::String("int: y = 2;".to_owned(), InputLang::MiniZinc),
+		]));
+		assert_eq!(parse_id(&db, 0), parse_id(&db, 1));
+		assert_ne!(parse_id(&db, 0), parse_id(&db, 2));
+		// Each CST still refers to its own file
+		let files = db.input_file_refs();
+		assert_eq!(db.cst(files[1]).unwrap().file(), files[1]);
+	}
+
+	/// Create an edit which replaces the character at the given byte offset with another
+	fn replace_char(source: &str, offset: usize) -> InputEdit {
+		let row = source[..offset].matches('\n').count();
+		let column = offset - source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+		InputEdit {
+			start_byte: offset,
+			old_end_byte: offset + 1,
+			new_end_byte: offset + 1,
+			start_position: Point::new(row, column),
+			old_end_position: Point::new(row, column + 1),
+			new_end_position: Point::new(row, column + 1),
+		}
+	}
+
+	#[test]
+	fn test_incremental_reparse() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			"int: x = 1;\nconstraint x > 0;".to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		let previous = db.cst(db.input_file_refs()[0]).unwrap();
+
+		let source = Arc::new("int: x = 2;\nconstraint x > 0;".to_owned());
+		db.incremental_parses().reparse(
+			&previous,
+			&replace_char(previous.text(), 9),
+			source.clone(),
+		);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			(*source).clone(),
+			InputLang::MiniZinc,
+		)]));
+		let cst = db.cst(db.input_file_refs()[0]).unwrap();
+		// The re-parsed CST is used instead of parsing from scratch
+		assert_eq!(cst.text().as_ptr(), source.as_ptr());
+
+		let full = CompilerDatabase::default().parse_contents(InputLang::MiniZinc, source);
+		assert_eq!(cst.root_node().to_sexp(), full.root_node().to_sexp());
+	}
+
+	#[test]
+	fn test_incremental_parses_bounded() {
+		let db = CompilerDatabase::default();
+		let source = "int: x = 0;";
+		let previous = db.parse_contents(InputLang::MiniZinc, Arc::new(source.to_owned()));
+		// Re-parse edits whose contents are never parsed
+		let edits = ('a'..='z')
+			.take(INCREMENTAL_PARSES_CAPACITY + 1)
+			.map(|c| {
+				let edited = Arc::new(format!("int: {} = 0;", c));
+				let edit = replace_char(source, 5);
+				db.incremental_parses()
+					.reparse(&previous, &edit, edited.clone());
+				edited
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(db.incremental_parses().len(), INCREMENTAL_PARSES_CAPACITY);
+		// The oldest re-parse has been dropped
+		assert!(db.incremental_parses().take(&edits[0]).is_none());
+		let latest = edits.last().unwrap();
+		assert!(db.incremental_parses().take(latest).is_some());
+	}
+
+	#[test]
+	#[ignore = "benchmark"]
+	fn bench_incremental_reparse() {
+		let source = (0..20_000)
+			.map(|i| format!("var 0..{}: x{};\nconstraint x{} != {};\n", i + 1, i, i, i))
+			.collect::<String>();
+		let db = CompilerDatabase::default();
+		let previous = db.parse_contents(InputLang::MiniZinc, Arc::new(source.clone()));
+
+		let offset = source.find("x10000").unwrap() + 1;
+		let mut edited = source.clone();
+		edited.replace_range(offset..offset + 1, "2");
+		let edited = Arc::new(edited);
+
+		let start = Instant::now();
+		let full = CompilerDatabase::default().parse_contents(InputLang::MiniZinc, edited.clone());
+		let full_time = start.elapsed();
+
+		let start = Instant::now();
+		let incremental = previous.reparse(&replace_char(&source, offset), edited);
+		let incremental_time = start.elapsed();
+
+		assert_eq!(
+			incremental.root_node().to_sexp(),
+			full.root_node().to_sexp()
+		);
+		println!(
+			"Full parse: {:?}, incremental parse: {:?}",
+			full_time, incremental_time
+		);
+	}
+
+	#[test]
+	fn test_node_kind_histogram() {
+		let mut db = CompilerDatabase::default();
+		db.set_input_files(Arc::new(vec![
+			InputFile::String(
+				"int: x = 1 + 2;\nconstraint x > 0;\nconstraint x < 10;".to_owned(),
+				InputLang::MiniZinc,
+			),
+			InputFile::String(
+				"find x : int(1..3)\nsuch that x != 2".to_owned(),
+				InputLang::EPrime,
+			),
+		]));
+		let models = db.input_models();
+		let histogram = db.node_kind_histogram(models[0]);
+		assert_eq!(histogram.get("integer_literal"), Some(&4));
+		assert_eq!(histogram.get("constraint"), Some(&2));
+		assert_eq!(histogram.get("identifier"), Some(&3));
+		let histogram = db.node_kind_histogram(models[1]);
+		assert_eq!(histogram.get("integer_literal"), Some(&3));
+		assert_eq!(histogram.get("constraint"), Some(&1));
+	}
+
+	#[test]
+	fn test_reverted_contents_reuse_parse() {
+		let mut db = CompilerDatabase::default();
+		let original = InputFile::String("int: x = 1;".to_owned(), InputLang::MiniZinc);
+		db.set_input_files(Arc::new(vec![original.clone()]));
+		let before = parse_id(&db, 0);
+		db.set_input_files(Arc::new(vec![InputFile::String(
+			"int: x = 2;".to_owned(),
+			InputLang::MiniZinc,
+		)]));
+		assert_ne!(parse_id(&db, 0), before);
+		db.set_input_files(Arc::new(vec![original]));
+		assert_eq!(parse_id(&db, 0), before);
+	}
+
+	#[test]
+	fn test_parse_contents_lru() {
+		let mut db = CompilerDatabase::default();
+		ParseContentsQuery.in_db_mut(&mut db).set_lru_capacity(1);
+		let parse = |db: &CompilerDatabase, source: &str| {
+			db.parse_contents(InputLang::MiniZinc, Arc::new(source.to_owned()))
+				.text()
+				.as_ptr()
+		};
+		let before = parse(&db, "int: x = 1;");
+		assert_eq!(parse(&db, "int: x = 1;"), before);
+		// Parsing other contents evicts the earlier parse, which must then be parsed again
+		parse(&db, "int: x = 2;");
+		assert_ne!(parse(&db, "int: x = 1;"), before);
+	}
+
+	#[test]
+	#[ignore = "benchmark"]
+	fn bench_reverted_contents() {
+		let source = (0..20_000)
+			.map(|i| format!("var 0..{}: x{};\nconstraint x{} != {};\n", i + 1, i, i, i))
+			.collect::<String>();
+		let mut edited = source.clone();
+		edited.push_str("constraint x0 = 1;\n");
+		let mut db = CompilerDatabase::default();
+		let set_source = |db: &mut CompilerDatabase, source: &str| {
+			db.set_input_files(Arc::new(vec![InputFile::String(
+				source.to_owned(),
+				InputLang::MiniZinc,
+			)]));
+			let start = Instant::now();
+			db.cst(db.input_file_refs()[0]).unwrap();
+			start.elapsed()
+		};
+
+		let initial_time = set_source(&mut db, &source);
+		let edited_time = set_source(&mut db, &edited);
+		let reverted_time = set_source(&mut db, &source);
+		println!(
+			"Initial parse: {:?}, parse after edit: {:?}, parse after revert: {:?}",
+			initial_time, edited_time, reverted_time
+		);
+	}
+}
@@ -203,6 +203,64 @@ impl Constraint {
 	pub fn expressions(&self) -> Children<'_, Expression> {
 		children_with_field_name(self, "expression")
 	}
+
+	/// Get the annotation pragmas directly preceding the given constraint expression
+	pub fn pragmas(&self, expression: &Expression) -> Vec<Pragma> {
+		let node = expression.cst_node();
+		let mut pragmas = Vec::new();
+		let mut sibling = node.as_ref().prev_sibling();
+		while let Some(n) = sibling.filter(|n| n.kind() == "line_comment") {
+			let comment = node.cst().node(n);
+			if comment.text().starts_with(Pragma::PREFIX) {
+				pragmas.push(Pragma::from(comment));
+			}
+			sibling = n.prev_sibling();
+		}
+		pragmas.reverse();
+		pragmas
+	}
+}
+
+ast_node!(
+	/// Annotation pragma (a comment of the form `$:: ann1, ann2`)
+	Pragma,
+	annotations,
+);
+
+impl Pragma {
+	/// The start of a comment which contains annotations
+	pub const PREFIX: &'static str = "$::";
+
+	/// Get the source text of each annotation in the pragma
+	pub fn annotations(&self) -> Vec<&str> {
+		split_top_level(&self.cst_text()[Self::PREFIX.len()..])
+			.into_iter()
+			.map(|a| a.trim())
+			.filter(|a| !a.is_empty())
+			.collect()
+	}
+}
+
+/// Split the given text on commas which are not inside parentheses or string literals
+pub(crate) fn split_top_level(text: &str) -> Vec<&str> {
+	let mut parts = Vec::new();
+	let mut depth = 0;
+	let mut in_string = false;
+	let mut start = 0;
+	for (i, c) in text.char_indices() {
+		match c {
+			'"' => in_string = !in_string,
+			'(' if !in_string => depth += 1,
+			')' if !in_string => depth -= 1,
+			',' if !in_string && depth == 0 => {
+				parts.push(&text[start..i]);
+				start = i + 1;
+			}
+			_ => (),
+		}
+	}
+	parts.push(&text[start..]);
+	parts
 }
 
 ast_node!(
@@ -264,6 +264,71 @@ impl InfixOperator {
 	}
 }
 
+/// Associativity of an infix operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+	/// `a op b op c` is `(a op b) op c`
+	Left,
+	/// `a op b op c` is parsed as `(a op b) op c`, but operands using the operator are always
+	/// parenthesised to make the grouping explicit
+	NonAssociative,
+}
+
+/// Precedence and associativity of an infix operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OperatorInfo {
+	/// The precedence of the operator (higher binds tighter)
+	pub precedence: u8,
+	/// The associativity of the operator
+	pub associativity: Associativity,
+}
+
+impl OperatorInfo {
+	/// Whether an operand using the operator `child` requires parentheses when it is the
+	/// left or right operand of this operator
+	pub fn needs_parentheses(&self, child: &OperatorInfo, is_right: bool) -> bool {
+		if child.precedence != self.precedence {
+			return child.precedence < self.precedence;
+		}
+		match self.associativity {
+			Associativity::Left => is_right,
+			Associativity::NonAssociative => true,
+		}
+	}
+}
+
+/// Get the precedence and associativity of the infix operator with the given name
+///
+/// Precedences follow the MiniZinc grammar. The grammar parses chains of comparison and range
+/// operators as left associative, and some of these chains are valid (e.g. `a = b = true` for
+/// Booleans `a` and `b` is `(a = b) = true`). They are still treated as non-associative so that
+/// such chains are given parentheses, since the grouping is easily misread.
+pub fn operator_info(name: &str) -> Option<OperatorInfo> {
+	let (precedence, associativity) = match name {
+		"<->" | "⟷" | "⇔" => (1, Associativity::Left),
+		"->" | "→" | "⇒" | "<-" | "←" | "⇐" => (2, Associativity::Left),
+		"\\/" | "∨" => (3, Associativity::Left),
+		"xor" | "⊻" => (4, Associativity::Left),
+		"/\\" | "∧" => (5, Associativity::Left),
+		"=" | "==" | "!=" | "≠" | "<" | "<=" | "≤" | ">" | ">=" | "≥" | "in" | "∈" | "subset"
+		| "⊆" | "superset" | "⊇" | "~=" | "~!=" => (6, Associativity::NonAssociative),
+		"union" | "∪" => (7, Associativity::Left),
+		"diff" | "∖" => (8, Associativity::Left),
+		"symdiff" => (9, Associativity::Left),
+		".." | "<.." | "..<" | "<..<" => (10, Associativity::NonAssociative),
+		"intersect" | "∩" => (11, Associativity::Left),
+		"+" | "-" | "++" | "~+" | "~-" => (12, Associativity::Left),
+		"*" | "/" | "div" | "mod" | "~*" | "~div" | "~/" => (13, Associativity::Left),
+		"^" => (14, Associativity::Left),
+		"default" => (17, Associativity::Left),
+		_ => return None,
+	};
+	Some(OperatorInfo {
+		precedence,
+		associativity,
+	})
+}
+
 ast_node!(
 	/// Postfix operator
 	PostfixOperator,
@@ -503,6 +568,7 @@ impl Lambda {
 mod test {
 	use expect_test::expect;
 
+	use super::operator_info;
 	use crate::syntax::ast::test::*;
 
 	#[test]
@@ -1198,6 +1264,28 @@ MznModel(
 		);
 	}
 
+	#[test]
+	fn test_operator_info() {
+		let info = |name| operator_info(name).unwrap();
+		assert!(info("*").precedence > info("+").precedence);
+		assert!(info("+").precedence > info("..").precedence);
+		assert!(info("..").precedence > info("=").precedence);
+		assert!(info("/\\").precedence > info("\\/").precedence);
+		assert!(info("->").precedence > info("<->").precedence);
+		assert_eq!(info("∧"), info("/\\"));
+		assert_eq!(operator_info("foo"), None);
+
+		// (a + b) * c
+		assert!(info("*").needs_parentheses(&info("+"), false));
+		// a * b + c
+		assert!(!info("+").needs_parentheses(&info("*"), false));
+		// (a - b) - c vs a - (b - c)
+		assert!(!info("-").needs_parentheses(&info("-"), false));
+		assert!(info("-").needs_parentheses(&info("-"), true));
+		// (a = b) = c
+		assert!(info("=").needs_parentheses(&info("="), false));
+	}
+
 	#[test]
 	fn test_postfix_operator() {
 		check_ast(
@@ -0,0 +1,760 @@
+//! Formatting of MiniZinc models
+//!
+//! Reproduces MiniZinc source from the AST of a model, normalising whitespace, operator spacing,
+//! and the indentation of `let` expressions, `if-then-else` expressions, and comprehensions.
+//! Constructs which are too long to fit on a line are broken over multiple lines.
+//!
+//! Comments are not part of the AST, so top-level comments are kept as they are, and items which
+//! contain comments are reproduced verbatim. Types and patterns are reproduced with their whitespace
+//! normalised.
+
+use tree_sitter::Node;
+
+use super::{
+	ArrayIndex, ArrayLiteral2D, Case, EnumerationCase, Expression, Generator, Goal, IfThenElse,
+	Item, Let, LetItem, MznModel, Parameter, PredicateType,
+};
+use crate::syntax::ast::AstNode;
+
+/// Indentation used for nested blocks
+const INDENT: &str = "  ";
+
+/// Maximum width of a construct before it is broken over multiple lines
+const WIDTH: usize = 80;
+
+/// Format the given model
+///
+/// Models containing syntax errors are returned unchanged.
+pub fn format_model(model: &MznModel) -> String {
+	let cst = model.cst();
+	let root = cst.root_node();
+	if root.has_error() {
+		return cst.text().to_owned();
+	}
+	let mut out = String::new();
+	let mut last_row = None;
+	let mut cursor = root.walk();
+	let mut more = cursor.goto_first_child();
+	while more {
+		let node = cursor.node();
+		let is_item = cursor.field_name() == Some("item");
+		more = cursor.goto_next_sibling();
+		if !is_item && !is_comment(node) {
+			continue;
+		}
+		let text = if is_item {
+			let item = Item::new(cst.node(node));
+			if contains_comment(node) {
+				format!("{};", item.cst_text())
+			} else {
+				format!("{};", format_item(&item))
+			}
+		} else {
+			cst.node(node).text().to_owned()
+		};
+		let start = node.start_position().row;
+		match last_row {
+			// Trailing comment on the same line as the previous item
+			Some(row) if row == start && !is_item => out.push(' '),
+			Some(row) => {
+				out.push('\n');
+				if start > row + 1 {
+					out.push('\n');
+				}
+			}
+			None => (),
+		}
+		out.push_str(&text);
+		last_row = Some(node.end_position().row);
+	}
+	if !out.is_empty() {
+		out.push('\n');
+	}
+	out
+}
+
+fn is_comment(node: Node) -> bool {
+	matches!(node.kind(), "line_comment" | "block_comment")
+}
+
+/// Whether the given node has a comment as a descendant
+fn contains_comment(node: Node) -> bool {
+	let mut cursor = node.walk();
+	let mut more = cursor.goto_first_child();
+	while more {
+		if is_comment(cursor.node()) || contains_comment(cursor.node()) {
+			return true;
+		}
+		more = cursor.goto_next_sibling();
+	}
+	false
+}
+
+/// Format an item (without the trailing `;`)
+pub fn format_item(item: &Item) -> String {
+	match item {
+		Item::Include(i) => format!("include {}", i.file().cst_text()),
+		Item::Declaration(d) => {
+			let mut buf = format!(
+				"{}: {}",
+				normalise(d.declared_type().cst_text()),
+				normalise(d.pattern().cst_text())
+			);
+			buf.push_str(&annotations(d.annotations()));
+			if let Some(def) = d.definition() {
+				buf.push_str(" = ");
+				buf.push_str(&format_expression(&def));
+			}
+			buf
+		}
+		Item::Enumeration(e) => {
+			let mut buf = format!("enum {}", e.id().cst_text());
+			buf.push_str(&annotations(e.annotations()));
+			let cases = e
+				.cases()
+				.map(|c| match c {
+					EnumerationCase::Members(m) => {
+						list("{", m.members().map(|m| m.cst_text().to_owned()), "}")
+					}
+					EnumerationCase::Anonymous(a) => list(
+						&format!("{}(", a.anonymous().cst_text()),
+						a.parameters().map(|t| normalise(t.cst_text())),
+						")",
+					),
+					EnumerationCase::Constructor(c) => list(
+						&format!("{}(", c.id().cst_text()),
+						c.parameters().map(|t| normalise(t.cst_text())),
+						")",
+					),
+				})
+				.collect::<Vec<_>>();
+			if !cases.is_empty() {
+				buf.push_str(" = ");
+				buf.push_str(&cases.join(" ++ "));
+			}
+			buf
+		}
+		Item::Assignment(a) => format!(
+			"{} = {}",
+			format_expression(&a.assignee()),
+			format_expression(&a.definition())
+		),
+		Item::Constraint(c) => format!(
+			"constraint{} {}",
+			annotations(c.annotations()),
+			format_expression(&c.expression())
+		),
+		Item::Solve(s) => {
+			let goal = match s.goal() {
+				Goal::Satisfy => "satisfy".to_owned(),
+				Goal::Maximize(e) => format!("maximize {}", format_expression(&e)),
+				Goal::Minimize(e) => format!("minimize {}", format_expression(&e)),
+			};
+			format!("solve{} {}", annotations(s.annotations()), goal)
+		}
+		Item::Output(o) => match o.section() {
+			Some(s) => format!(
+				"output :: {} {}",
+				s.cst_text(),
+				format_expression(&o.expression())
+			),
+			None => format!("output {}", format_expression(&o.expression())),
+		},
+		Item::Function(f) => {
+			let header = format!(
+				"function {}: {}{}{}",
+				normalise(f.return_type().cst_text()),
+				f.id().cst_text(),
+				parameters(f.parameters()),
+				annotations(f.annotations())
+			);
+			body(header, f.body())
+		}
+		Item::Predicate(p) => {
+			let keyword = match p.declared_type() {
+				PredicateType::Predicate => "predicate",
+				PredicateType::Test => "test",
+			};
+			let header = format!(
+				"{} {}{}{}",
+				keyword,
+				p.id().cst_text(),
+				parameters(p.parameters()),
+				annotations(p.annotations())
+			);
+			body(header, p.body())
+		}
+		Item::Annotation(a) => {
+			let mut buf = format!("annotation {}", a.id().cst_text());
+			if let Some(ps) = a.parameters() {
+				buf.push_str(&parameters(ps.iter()));
+			}
+			buf
+		}
+		Item::TypeAlias(t) => format!(
+			"type {} = {}",
+			t.name().cst_text(),
+			normalise(t.aliased_type().cst_text())
+		),
+	}
+}
+
+/// Format an expression
+pub fn format_expression(expression: &Expression) -> String {
+	let formatted = match expression {
+		Expression::IntegerLiteral(_)
+		| Expression::FloatLiteral(_)
+		| Expression::BooleanLiteral(_)
+		| Expression::StringLiteral(_)
+		| Expression::StringInterpolation(_)
+		| Expression::Identifier(_)
+		| Expression::Absent(_)
+		| Expression::Infinity(_)
+		| Expression::Anonymous(_) => expression.cst_text().to_owned(),
+		Expression::TupleLiteral(t) => {
+			let members = t
+				.members()
+				.map(|m| format_expression(&m))
+				.collect::<Vec<_>>();
+			if members.len() == 1 {
+				format!("({},)", members[0])
+			} else {
+				list("(", members, ")")
+			}
+		}
+		Expression::RecordLiteral(r) => list(
+			"(",
+			r.members()
+				.map(|m| format!("{}: {}", m.name().cst_text(), format_expression(&m.value()))),
+			")",
+		),
+		Expression::SetLiteral(s) => list("{", s.members().map(|m| format_expression(&m)), "}"),
+		Expression::ArrayLiteral(a) => list(
+			"[",
+			a.members().map(|m| match m.indices() {
+				Some(i) => format!(
+					"{}: {}",
+					format_expression(&i),
+					format_expression(&m.value())
+				),
+				None => format_expression(&m.value()),
+			}),
+			"]",
+		),
+		Expression::ArrayLiteral2D(a) => array_2d(a),
+		Expression::ArrayAccess(a) => format!(
+			"{}[{}]",
+			format_expression(&a.collection()),
+			a.indices()
+				.map(|i| match i {
+					ArrayIndex::IndexSlice(s) => s.operator().to_owned(),
+					ArrayIndex::Expression(e) => format_expression(&e),
+				})
+				.collect::<Vec<_>>()
+				.join(", ")
+		),
+		Expression::ArrayComprehension(c) => {
+			let template = match c.indices() {
+				Some(i) => format!(
+					"{}: {}",
+					format_expression(&i),
+					format_expression(&c.template())
+				),
+				None => format_expression(&c.template()),
+			};
+			comprehension("[", template, c.generators(), "]")
+		}
+		Expression::SetComprehension(c) => {
+			comprehension("{", format_expression(&c.template()), c.generators(), "}")
+		}
+		Expression::IfThenElse(i) => if_then_else(i),
+		Expression::Call(c) => format!(
+			"{}{}",
+			format_expression(&c.function()),
+			list("(", c.arguments().map(|a| format_expression(&a)), ")")
+		),
+		Expression::PrefixOperator(o) => {
+			let operator = o.operator();
+			let operand = format_expression(&o.operand());
+			if operator.name() == "not" || operand.starts_with(operator.name()) {
+				format!("{} {}", operator.name(), operand)
+			} else {
+				format!("{}{}", operator.name(), operand)
+			}
+		}
+		Expression::InfixOperator(o) => {
+			let operator = o.operator();
+			let left = format_expression(&o.left());
+			let right = format_expression(&o.right());
+			if is_range_operator(operator.name()) {
+				format!("{}{}{}", left, operator.name(), right)
+			} else {
+				let inline = format!("{} {} {}", left, operator.name(), right);
+				if !fits(&inline) && is_logical_operator(operator.name()) {
+					format!("{} {}\n{}", left, operator.name(), right)
+				} else {
+					inline
+				}
+			}
+		}
+		Expression::PostfixOperator(o) => {
+			format!("{}{}", format_expression(&o.operand()), o.operator().name())
+		}
+		Expression::GeneratorCall(c) => {
+			let function = format_expression(&c.function());
+			let generators = c.generators().map(|g| generator(&g)).collect::<Vec<_>>();
+			let template = format_expression(&c.template());
+			let inline = format!("{} ({}) ({})", function, generators.join(", "), template);
+			if fits(&inline) {
+				inline
+			} else {
+				format!(
+					"{} ({}) (\n{}{}\n)",
+					function,
+					generators.join(", "),
+					INDENT,
+					indent(&template)
+				)
+			}
+		}
+		Expression::Case(c) => case(c),
+		Expression::Let(l) => let_expression(l),
+		Expression::TupleAccess(t) => {
+			format!("{}.{}", format_expression(&t.tuple()), t.field().cst_text())
+		}
+		Expression::RecordAccess(r) => format!(
+			"{}.{}",
+			format_expression(&r.record()),
+			r.field().cst_text()
+		),
+		Expression::Lambda(l) => {
+			let return_type = l
+				.return_type()
+				.map(|t| format!(" {}:", normalise(t.cst_text())))
+				.unwrap_or_default();
+			format!(
+				"lambda{} {} => {}",
+				return_type,
+				parameters(l.parameters()),
+				format_expression(&l.body())
+			)
+		}
+		Expression::AnnotatedExpression(e) => format!(
+			"{}{}",
+			format_expression(&e.expression()),
+			annotations(e.annotations())
+		),
+	};
+	let parenthesised = expression
+		.cst_node()
+		.as_ref()
+		.parent()
+		.map(|p| p.kind() == "parenthesised_expression")
+		.unwrap_or_default();
+	if parenthesised {
+		format!("({})", formatted)
+	} else {
+		formatted
+	}
+}
+
+/// Whether the construct fits on a single line
+fn fits(s: &str) -> bool {
+	s.chars().count() <= WIDTH && !s.contains('\n')
+}
+
+/// Indent all lines after the first
+fn indent(s: &str) -> String {
+	s.replace('\n', &format!("\n{}", INDENT))
+}
+
+/// Collapse whitespace (outside of string literals) into single spaces
+fn normalise(s: &str) -> String {
+	let mut buf = String::with_capacity(s.len());
+	let mut in_string = false;
+	let mut escaped = false;
+	let mut space = false;
+	for c in s.chars() {
+		if in_string {
+			buf.push(c);
+			if escaped {
+				escaped = false;
+			} else if c == '\\' {
+				escaped = true;
+			} else if c == '"' {
+				in_string = false;
+			}
+		} else if c.is_whitespace() {
+			space = true;
+		} else {
+			if space {
+				buf.push(' ');
+				space = false;
+			}
+			in_string = c == '"';
+			buf.push(c);
+		}
+	}
+	buf
+}
+
+fn is_range_operator(operator: &str) -> bool {
+	matches!(operator, ".." | "..<" | "<.." | "<..<")
+}
+
+fn is_logical_operator(operator: &str) -> bool {
+	matches!(
+		operator,
+		"/\\" | "∧" | "\\/" | "∨" | "->" | "→" | "<-" | "←" | "<->" | "↔" | "xor" | "⊻"
+	)
+}
+
+/// Comma separated list, placing each member on its own line if it does not fit on one line
+fn list(open: &str, members: impl IntoIterator<Item = String>, close: &str) -> String {
+	let members = members.into_iter().collect::<Vec<_>>();
+	let inline = format!("{}{}{}", open, members.join(", "), close);
+	if fits(&inline) || members.is_empty() {
+		inline
+	} else {
+		format!(
+			"{}\n{}{}\n{}",
+			open,
+			INDENT,
+			indent(&members.join(",\n")),
+			close
+		)
+	}
+}
+
+fn annotations(anns: impl Iterator<Item = Expression>) -> String {
+	anns.map(|a| format!(" :: {}", format_expression(&a)))
+		.collect()
+}
+
+fn parameters(params: impl Iterator<Item = Parameter>) -> String {
+	list(
+		"(",
+		params.map(|p| {
+			let ty = normalise(p.declared_type().cst_text());
+			match p.pattern() {
+				Some(pattern) => format!(
+					"{}: {}{}",
+					ty,
+					normalise(pattern.cst_text()),
+					annotations(p.annotations())
+				),
+				None => ty,
+			}
+		}),
+		")",
+	)
+}
+
+/// Function or predicate with an optional body
+fn body(header: String, body: Option<Expression>) -> String {
+	match body {
+		Some(b) => {
+			let b = format_expression(&b);
+			let inline = format!("{} = {}", header, b);
+			if fits(&inline) {
+				inline
+			} else {
+				format!("{} =\n{}{}", header, INDENT, indent(&b))
+			}
+		}
+		None => header,
+	}
+}
+
+fn generator(g: &Generator) -> String {
+	let (mut buf, where_clause) = match g {
+		Generator::IteratorGenerator(g) => (
+			format!(
+				"{} in {}",
+				g.patterns()
+					.map(|p| normalise(p.cst_text()))
+					.collect::<Vec<_>>()
+					.join(", "),
+				format_expression(&g.collection())
+			),
+			g.where_clause(),
+		),
+		Generator::AssignmentGenerator(g) => (
+			format!(
+				"{} = {}",
+				normalise(g.pattern().cst_text()),
+				format_expression(&g.value())
+			),
+			g.where_clause(),
+		),
+	};
+	if let Some(w) = where_clause {
+		buf.push_str(" where ");
+		buf.push_str(&format_expression(&w));
+	}
+	buf
+}
+
+fn comprehension(
+	open: &str,
+	template: String,
+	generators: impl Iterator<Item = Generator>,
+	close: &str,
+) -> String {
+	let generators = generators.map(|g| generator(&g)).collect::<Vec<_>>();
+	let inline = format!("{}{} | {}{}", open, template, generators.join(", "), close);
+	if fits(&inline) {
+		inline
+	} else {
+		format!(
+			"{}\n{}{}\n{}| {}\n{}",
+			open,
+			INDENT,
+			indent(&template),
+			INDENT,
+			indent(&generators.join(",\n")),
+			close
+		)
+	}
+}
+
+fn if_then_else(i: &IfThenElse) -> String {
+	let branches = i
+		.branches()
+		.map(|b| {
+			(
+				format_expression(&b.condition),
+				format_expression(&b.result),
+			)
+		})
+		.collect::<Vec<_>>();
+	let else_result = i.else_result().map(|e| format_expression(&e));
+	let mut inline = String::new();
+	for (idx, (condition, result)) in branches.iter().enumerate() {
+		let keyword = if idx == 0 { "if" } else { " elseif" };
+		inline.push_str(&format!("{} {} then {}", keyword, condition, result));
+	}
+	if let Some(e) = &else_result {
+		inline.push_str(&format!(" else {}", e));
+	}
+	inline.push_str(" endif");
+	if fits(&inline) {
+		return inline;
+	}
+	let mut buf = String::new();
+	for (idx, (condition, result)) in branches.iter().enumerate() {
+		let keyword = if idx == 0 { "if" } else { "elseif" };
+		buf.push_str(&format!(
+			"{} {} then\n{}{}\n",
+			keyword,
+			condition,
+			INDENT,
+			indent(result)
+		));
+	}
+	if let Some(e) = &else_result {
+		buf.push_str(&format!("else\n{}{}\n", INDENT, indent(e)));
+	}
+	buf.push_str("endif");
+	buf
+}
+
+fn case(c: &Case) -> String {
+	let expression = format_expression(&c.expression());
+	let cases = c
+		.cases()
+		.map(|i| {
+			format!(
+				"{} => {}",
+				normalise(i.pattern().cst_text()),
+				format_expression(&i.value())
+			)
+		})
+		.collect::<Vec<_>>();
+	let inline = format!("case {} of {} endcase", expression, cases.join(", "));
+	if fits(&inline) {
+		inline
+	} else {
+		format!(
+			"case {} of\n{}{}\nendcase",
+			expression,
+			INDENT,
+			indent(&cases.join(",\n"))
+		)
+	}
+}
+
+fn let_expression(l: &Let) -> String {
+	let items = l
+		.items()
+		.map(|i| {
+			let item = match i {
+				LetItem::Declaration(d) => format_item(&Item::Declaration(d)),
+				LetItem::Constraint(c) => format_item(&Item::Constraint(c)),
+			};
+			format!("{}{};\n", INDENT, indent(&item))
+		})
+		.collect::<String>();
+	let in_expression = format_expression(&l.in_expression());
+	if items.is_empty() {
+		format!("let {{}} in {}", in_expression)
+	} else {
+		format!("let {{\n{}}} in {}", items, in_expression)
+	}
+}
+
+fn array_2d(a: &ArrayLiteral2D) -> String {
+	let mut parts = Vec::new();
+	let column_indices = a
+		.column_indices()
+		.map(|i| format!("{}:", format_expression(&i)))
+		.collect::<Vec<_>>();
+	if !column_indices.is_empty() {
+		parts.push(column_indices.join(" "));
+	}
+	for row in a.rows() {
+		let members = row
+			.members()
+			.map(|m| format_expression(&m))
+			.collect::<Vec<_>>()
+			.join(", ");
+		match row.index() {
+			Some(i) => parts.push(format!("{}: {}", format_expression(&i), members)),
+			None => parts.push(members),
+		}
+	}
+	if parts.is_empty() {
+		return "[||]".to_owned();
+	}
+	let inline = format!("[| {} |]", parts.join(" | "));
+	if fits(&inline) {
+		inline
+	} else {
+		format!("[| {} |]", parts.join("\n | "))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use expect_test::{expect, Expect};
+	use tree_sitter::Parser;
+
+	use super::format_model;
+	use crate::syntax::{cst::Cst, minizinc::MznModel};
+
+	fn check_format(source: &str, expected: Expect) {
+		let parse = |source: &str| {
+			let mut parser = Parser::new();
+			parser
+				.set_language(tree_sitter_minizinc::language())
+				.unwrap();
+			let tree = parser.parse(source.as_bytes(), None).unwrap();
+			MznModel::new(Cst::from_str(tree, source))
+		};
+		let formatted = format_model(&parse(source));
+		expected.assert_eq(&formatted);
+		// Formatting is idempotent
+		assert_eq!(format_model(&parse(&formatted)), formatted);
+	}
+
+	#[test]
+	fn test_format_items() {
+		check_format(
+			r#"include   "globals.mzn";
+int:n=3;   var 1..n  :  x::add_to_output;
+enum  Foo={A,B}++C(1..n);
+constraint x>1/\x<n;constraint ::"c" x!=2;
+predicate  p(var int:y)=y>0;
+function int:f(int:y);
+annotation my_ann(int: i);
+solve::int_search([x],input_order,indomain_min)minimize x;
+output::"section"["\(x)"];"#,
+			expect!([r#"
+include "globals.mzn";
+int: n = 3;
+var 1..n: x :: add_to_output;
+enum Foo = {A, B} ++ C(1..n);
+constraint x > 1 /\ x < n;
+constraint :: "c" x != 2;
+predicate p(var int: y) = y > 0;
+function int: f(int: y);
+annotation my_ann(int: i);
+solve :: int_search([x], input_order, indomain_min) minimize x;
+output :: "section" ["\(x)"];
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_format_expressions() {
+		check_format(
+			r#"any: a = (1+2)*3;
+any: b = [ i*j | i,j in 1..3 where i<j ];
+any: c = forall(i in 1..3)(x[i]>0);
+any: d = if a>b then   a elseif a<b then b else 0 endif;
+any: e = let {int:y=1;constraint y>0} in y;
+any: f = case a of 1 => true, _ => false endcase;
+any: g = [| 1,2 | 3,4 |];
+any: h = (a: 1, b: (2,));
+any: i = lambda int: (int: x) => -x;"#,
+			expect!([r#"
+any: a = (1 + 2) * 3;
+any: b = [i * j | i, j in 1..3 where i < j];
+any: c = forall (i in 1..3) (x[i] > 0);
+any: d = if a > b then a elseif a < b then b else 0 endif;
+any: e = let {
+  int: y = 1;
+  constraint y > 0;
+} in y;
+any: f = case a of 1 => true, _ => false endcase;
+any: g = [| 1, 2 | 3, 4 |];
+any: h = (a: 1, b: (2,));
+any: i = lambda int: (int: x) => -x;
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_format_long_expressions() {
+		check_format(
+			r#"function var int: long_function_name(var int: first_argument, var int: second_argument) = if first_argument > second_argument then first_argument else second_argument endif;
+constraint forall (i in 1..100) (some_very_long_predicate_name(i, another_long_argument_name, yet_another_argument));"#,
+			expect!([r#"
+function var int: long_function_name(var int: first_argument, var int: second_argument) =
+  if first_argument > second_argument then
+    first_argument
+  else
+    second_argument
+  endif;
+constraint forall (i in 1..100) (
+  some_very_long_predicate_name(i, another_long_argument_name, yet_another_argument)
+);
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_format_comments() {
+		check_format(
+			r#"% Model
+% Parameters
+int:n=3; % Size
+
+
+constraint n>  /* inline comment */ 0;
+/* Block
+   comment */
+solve satisfy;"#,
+			expect!([r#"
+% Model
+% Parameters
+int: n = 3; % Size
+
+constraint n>  /* inline comment */ 0;
+/* Block
+   comment */
+solve satisfy;
+"#]),
+		);
+	}
+}
@@ -8,6 +8,7 @@ use std::{fmt::Debug, marker::PhantomData};
 
 pub mod container;
 pub mod expression;
+pub mod format;
 pub mod item;
 pub mod pattern;
 pub mod primitive;
@@ -15,6 +16,7 @@ pub mod types;
 
 pub use container::*;
 pub use expression::*;
+pub use format::*;
 pub use item::*;
 pub use pattern::*;
 pub use primitive::*;
@@ -693,6 +693,22 @@ impl<'a, 'b> ExpressionCollector<'a, 'b> {
 		let origin = EntityRef::new(db.upcast(), self.item, idx);
 		let mut result = match &self.data[idx] {
 			hir::Expression::Absent => alloc_expression(Absent, self, origin),
+			hir::Expression::Anonymous => {
+				// Introduce a fresh variable with the type taken from the context
+				let declaration = Declaration::new(false, Domain::unbounded(db, origin, ty));
+				let idx = self
+					.parent
+					.model
+					.add_declaration(Item::new(declaration, origin));
+				alloc_expression(
+					Let {
+						items: vec![LetItem::Declaration(idx)],
+						in_expression: Box::new(alloc_expression(idx, self, origin)),
+					},
+					self,
+					origin,
+				)
+			}
 			hir::Expression::ArrayAccess(aa) => {
 				let is_slice = match self.types[aa.indices].lookup(db.upcast()) {
 					TyData::Tuple(_, fs) => fs.iter().any(|f| f.is_set(db.upcast())),
@@ -618,6 +618,24 @@ mod test {
     } in _DECL_1 else let {
       var opt int: _DECL_2 = <>;
     } in _DECL_2 endif | i in ub(S)]);
+"#]),
+		)
+	}
+	#[test]
+	fn test_desugar_comprehension_assignment_where() {
+		check(
+			desugar_comprehension,
+			r#"
+				set of int: S;
+				function int: foo(int: x);
+				any: x = [a | i in S, a = foo(i) where a > 0];
+				any: y = {a | i in S, a = foo(i) where a > 0};
+				"#,
+			expect!([r#"
+    set of int: S;
+    function int: foo(int: x);
+    array [int] of int: x = [a | i in S, a = foo(i) where '>'(a, 0)];
+    set of int: y = array2set([a | i in S, a = foo(i) where '>'(a, 0)]);
 "#]),
 		)
 	}
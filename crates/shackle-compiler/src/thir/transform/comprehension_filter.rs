@@ -0,0 +1,315 @@
+//! Partially evaluates comprehension generators with par where clauses
+//! - Generators over a fixed integer set (a literal range or set literal) whose where clause
+//!   only depends on the generator variable are filtered at compile time
+//! - The collection is replaced with a set literal of the values satisfying the where clause,
+//!   and the where clause is removed
+//!
+//! Should be run after comprehension desugaring so that where clauses have been moved to the
+//! earliest possible generator, and var where clauses have been removed.
+
+use std::sync::Arc;
+
+use crate::{
+	constants::IdentifierRegistry,
+	hir::{BooleanLiteral, IntegerLiteral, VarType},
+	thir::{
+		db::Thir,
+		traverse::{fold_generator, Folder, ReplacementMap},
+		Callable, DeclarationId, Expression, ExpressionData, Generator, Marker, Model,
+		ResolvedIdentifier, SetLiteral,
+	},
+	Result,
+};
+
+/// Maximum number of values in a generator collection which will be filtered at compile time
+const MAX_FILTERED_VALUES: i64 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+	Bool(bool),
+	Int(i64),
+}
+
+impl Value {
+	fn int(self) -> Option<i64> {
+		match self {
+			Value::Int(i) => Some(i),
+			_ => None,
+		}
+	}
+
+	fn bool(self) -> Option<bool> {
+		match self {
+			Value::Bool(b) => Some(b),
+			_ => None,
+		}
+	}
+}
+
+struct ComprehensionFilter<Dst: Marker> {
+	result: Model<Dst>,
+	replacement_map: ReplacementMap<Dst>,
+	ids: Arc<IdentifierRegistry>,
+}
+
+impl<Dst: Marker> Folder<'_, Dst> for ComprehensionFilter<Dst> {
+	fn model(&mut self) -> &mut Model<Dst> {
+		&mut self.result
+	}
+
+	fn replacement_map(&mut self) -> &mut ReplacementMap<Dst> {
+		&mut self.replacement_map
+	}
+
+	fn fold_generator(
+		&mut self,
+		db: &dyn Thir,
+		model: &Model,
+		generator: &Generator,
+	) -> Generator<Dst> {
+		let mut folded = fold_generator(self, db, model, generator);
+		if let Some(values) = self.filter_generator(db, model, generator) {
+			if let Generator::Iterator {
+				collection,
+				where_clause,
+				..
+			} = &mut folded
+			{
+				let origin = collection.origin();
+				let elements = values
+					.into_iter()
+					.map(|v| Expression::new(db, &self.result, origin, IntegerLiteral(v)))
+					.collect();
+				// Keep the original type so that an empty result is still a set of int
+				*collection =
+					Expression::new_unchecked(collection.ty(), SetLiteral(elements), origin);
+				*where_clause = None;
+			}
+		}
+		folded
+	}
+}
+
+impl<Dst: Marker> ComprehensionFilter<Dst> {
+	/// Get the values of the generator which satisfy its where clause if they can be computed
+	/// at compile time
+	fn filter_generator(
+		&self,
+		db: &dyn Thir,
+		model: &Model,
+		generator: &Generator,
+	) -> Option<Vec<i64>> {
+		let Generator::Iterator {
+			declarations,
+			collection,
+			where_clause: Some(w),
+		} = generator
+		else {
+			return None;
+		};
+		if declarations.len() != 1
+			|| collection.ty().inst(db.upcast()) != Some(VarType::Par)
+			|| w.ty().inst(db.upcast()) != Some(VarType::Par)
+		{
+			return None;
+		}
+		let decl = declarations[0];
+		let values = self.collection_values(model, collection)?;
+		let mut filtered = Vec::new();
+		for v in values {
+			if self.evaluate(model, w, Some((decl, v)))?.bool()? {
+				filtered.push(v);
+			}
+		}
+		Some(filtered)
+	}
+
+	/// Get the sorted values of a fixed integer set
+	fn collection_values(&self, model: &Model, collection: &Expression) -> Option<Vec<i64>> {
+		match &**collection {
+			ExpressionData::SetLiteral(sl) => {
+				let mut values = sl
+					.iter()
+					.map(|e| self.evaluate(model, e, None)?.int())
+					.collect::<Option<Vec<_>>>()?;
+				values.sort_unstable();
+				values.dedup();
+				Some(values)
+			}
+			ExpressionData::Call(c) => match &c.function {
+				Callable::Function(f)
+					if model[*f].name() == self.ids.dot_dot && c.arguments.len() == 2 =>
+				{
+					let lb = self.evaluate(model, &c.arguments[0], None)?.int()?;
+					let ub = self.evaluate(model, &c.arguments[1], None)?.int()?;
+					if ub.checked_sub(lb)? >= MAX_FILTERED_VALUES {
+						return None;
+					}
+					Some((lb..=ub).collect())
+				}
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	/// Evaluate a par expression, given the value of the generator declaration (if any)
+	fn evaluate(
+		&self,
+		model: &Model,
+		expression: &Expression,
+		binding: Option<(DeclarationId, i64)>,
+	) -> Option<Value> {
+		match &**expression {
+			ExpressionData::IntegerLiteral(IntegerLiteral(i)) => Some(Value::Int(*i)),
+			ExpressionData::BooleanLiteral(BooleanLiteral(b)) => Some(Value::Bool(*b)),
+			ExpressionData::Identifier(ResolvedIdentifier::Declaration(d)) => match binding {
+				Some((decl, value)) if *d == decl => Some(Value::Int(value)),
+				_ => None,
+			},
+			ExpressionData::Call(c) => {
+				let Callable::Function(f) = &c.function else {
+					return None;
+				};
+				let name = model[*f].name();
+				if name == self.ids.forall && c.arguments.len() == 1 {
+					let ExpressionData::ArrayLiteral(al) = &*c.arguments[0] else {
+						return None;
+					};
+					for e in al.iter() {
+						if !self.evaluate(model, e, binding)?.bool()? {
+							return Some(Value::Bool(false));
+						}
+					}
+					return Some(Value::Bool(true));
+				}
+				let args = c
+					.arguments
+					.iter()
+					.map(|arg| self.evaluate(model, arg, binding))
+					.collect::<Option<Vec<_>>>()?;
+				match args[..] {
+					[a] if name == self.ids.minus => Some(Value::Int(a.int()?.checked_neg()?)),
+					[a] if name == self.ids.not => Some(Value::Bool(!a.bool()?)),
+					[a, b] if name == self.ids.eq => Some(Value::Bool(a == b)),
+					[a, b] if name == self.ids.ne => Some(Value::Bool(a != b)),
+					[Value::Bool(a), Value::Bool(b)] if name == self.ids.conj => {
+						Some(Value::Bool(a && b))
+					}
+					[Value::Bool(a), Value::Bool(b)] if name == self.ids.disj => {
+						Some(Value::Bool(a || b))
+					}
+					[Value::Int(a), Value::Int(b)] => {
+						if name == self.ids.plus {
+							Some(Value::Int(a.checked_add(b)?))
+						} else if name == self.ids.minus {
+							Some(Value::Int(a.checked_sub(b)?))
+						} else if name == self.ids.times {
+							Some(Value::Int(a.checked_mul(b)?))
+						} else if name == self.ids.div {
+							Some(Value::Int(a.checked_div(b)?))
+						} else if name == self.ids.mod_ {
+							Some(Value::Int(a.checked_rem(b)?))
+						} else if name == self.ids.lt {
+							Some(Value::Bool(a < b))
+						} else if name == self.ids.le {
+							Some(Value::Bool(a <= b))
+						} else if name == self.ids.gt {
+							Some(Value::Bool(a > b))
+						} else if name == self.ids.ge {
+							Some(Value::Bool(a >= b))
+						} else {
+							None
+						}
+					}
+					_ => None,
+				}
+			}
+			_ => None,
+		}
+	}
+}
+
+/// Filter generators with par where clauses over fixed integer sets at compile time
+pub fn filter_comprehensions(db: &dyn Thir, model: Model) -> Result<Model> {
+	log::info!("Filtering comprehensions with par where clauses");
+	let mut r = ComprehensionFilter {
+		ids: db.identifier_registry(),
+		replacement_map: ReplacementMap::default(),
+		result: Model::default(),
+	};
+	r.add_model(db, &model);
+	Ok(r.result)
+}
+
+#[cfg(test)]
+mod test {
+	use expect_test::expect;
+
+	use super::filter_comprehensions;
+	use crate::thir::transform::{comprehension::desugar_comprehension, test::check, transformer};
+
+	#[test]
+	fn test_filter_comprehension_par_where() {
+		check(
+			filter_comprehensions,
+			r#"
+				any: x = [i | i in 1..10 where i mod 3 = 0];
+				any: y = [i | i in {5, 1, 3, 1} where i > 1 /\ i != 4];
+			"#,
+			expect!([r#"
+    array [int] of int: x = [i | i in {3, 6, 9}];
+    array [int] of int: y = [i | i in {3, 5}];
+"#]),
+		)
+	}
+
+	#[test]
+	fn test_filter_comprehension_size() {
+		// Without filtering, the generator iterates over all 100 values
+		check(
+			desugar_comprehension,
+			r#"
+				any: x = sum (i in 1..100 where i mod 25 = 0) (i);
+			"#,
+			expect!([r#"
+    int: x = sum([i | i in '..'(1, 100) where '='('mod'(i, 25), 0)]);
+"#]),
+		);
+		// With filtering, only the 4 values satisfying the where clause remain
+		check(
+			transformer(vec![desugar_comprehension, filter_comprehensions]),
+			r#"
+				any: x = sum (i in 1..100 where i mod 25 = 0) (i);
+			"#,
+			expect!([r#"
+    int: x = sum([i | i in {25, 50, 75, 100}]);
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_filter_comprehension_unchanged() {
+		check(
+			transformer(vec![desugar_comprehension, filter_comprehensions]),
+			r#"
+				int: n;
+				predicate foo(var int: x);
+				any: x = [i | i in 1..n where i > 2];
+				any: y = [i | i in 1..3 where foo(i)];
+				any: z = [i | i in 1..3, j in 1..3 where i < j];
+			"#,
+			expect!([r#"
+    int: n;
+    function var bool: foo(var int: x);
+    array [int] of int: x = [i | i in '..'(1, n) where '>'(i, 2)];
+    array [int] of var opt int: y = [if foo(i) then let {
+      opt int: _DECL_1 = i;
+    } in _DECL_1 else let {
+      opt int: _DECL_2 = <>;
+    } in _DECL_2 endif | i in '..'(1, 3)];
+    array [int] of int: z = [i | i in '..'(1, 3), j in '..'(1, 3) where '<'(i, j)];
+"#]),
+		)
+	}
+}
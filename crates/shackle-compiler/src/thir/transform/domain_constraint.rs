@@ -90,6 +90,12 @@ impl<Dst: Marker, Src: Marker> Folder<'_, Dst, Src> for DomainRewriter<Dst, Src>
 				self.model[idx].set_domain(unbounded);
 				return;
 			}
+		} else if model[d].top_level() && model[d].annotations().has(model, self.ids.output_only) {
+			// Output only declarations are not part of the problem, so their domains are not checked
+			let idx = add_declaration(self, db, model, d);
+			let unbounded = Domain::unbounded(db, self.model[idx].origin(), self.model[idx].ty());
+			self.model[idx].set_domain(unbounded);
+			return;
 		} else if model[d].top_level() {
 			// Ignore local declarations, they're processed separately
 			if let Some(dom) =
@@ -1007,6 +1013,23 @@ mod test {
 		)
 	}
 
+	#[test]
+	fn test_output_only_domain() {
+		check(
+			rewrite_domains,
+			r#"
+				1..3: x :: output_only = 2;
+				1..3: y = 2;
+			"#,
+			expect!([r#"
+    int: x :: (output_only) = 2;
+    set of int: _DECL_1 = '..'(1, 3);
+    int: y = 2;
+    constraint mzn_domain_constraint("y", y, _DECL_1);
+"#]),
+		)
+	}
+
 	#[test]
 	fn test_non_domain_constraint() {
 		check(
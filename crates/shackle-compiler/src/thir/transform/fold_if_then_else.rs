@@ -0,0 +1,140 @@
+//! Replace if-then-else expressions whose conditions are boolean literals with the taken branch
+//! (if the `fold_constants` setting is enabled).
+//!
+//! This is done after typechecking so that errors in the other branches are still reported.
+//! The if-then-else is kept if its type differs from the taken branch (e.g. if another branch
+//! is `float` or `var`), since the branch types are unified.
+
+use crate::{
+	hir::BooleanLiteral,
+	thir::{
+		db::Thir,
+		traverse::{fold_expression, Folder, ReplacementMap},
+		Expression, ExpressionData, IfThenElse, Marker, Model,
+	},
+	utils::maybe_grow_stack,
+	Result,
+};
+
+struct IfThenElseFolder<Dst: Marker, Src: Marker = ()> {
+	model: Model<Dst>,
+	replacement_map: ReplacementMap<Dst, Src>,
+}
+
+impl<Dst: Marker, Src: Marker> Folder<'_, Dst, Src> for IfThenElseFolder<Dst, Src> {
+	fn model(&mut self) -> &mut Model<Dst> {
+		&mut self.model
+	}
+
+	fn replacement_map(&mut self) -> &mut ReplacementMap<Dst, Src> {
+		&mut self.replacement_map
+	}
+
+	fn fold_expression(
+		&mut self,
+		db: &dyn Thir,
+		model: &Model<Src>,
+		expression: &Expression<Src>,
+	) -> Expression<Dst> {
+		maybe_grow_stack(|| {
+			if let ExpressionData::IfThenElse(ite) = &**expression {
+				match taken_branch(ite) {
+					Some(result) if result.ty() == expression.ty() => {
+						let mut e = self.fold_expression(db, model, result);
+						e.annotations_mut().extend(
+							expression
+								.annotations()
+								.iter()
+								.map(|ann| self.fold_expression(db, model, ann)),
+						);
+						return e;
+					}
+					_ => (),
+				}
+			}
+			fold_expression(self, db, model, expression)
+		})
+	}
+}
+
+/// Get the result of the branch of an if-then-else which is known to be taken from its boolean
+/// literal conditions
+fn taken_branch<T: Marker>(ite: &IfThenElse<T>) -> Option<&Expression<T>> {
+	for b in ite.branches.iter() {
+		match &*b.condition {
+			ExpressionData::BooleanLiteral(BooleanLiteral(true)) => return Some(&b.result),
+			ExpressionData::BooleanLiteral(_) => (),
+			// Any other condition may not be known until later, so must be kept
+			_ => return None,
+		}
+	}
+	Some(&ite.else_result)
+}
+
+/// Replace if-then-else expressions with boolean literal conditions by their taken branch
+pub fn fold_if_then_else(db: &dyn Thir, model: Model) -> Result<Model> {
+	if !db.fold_constants() {
+		return Ok(model);
+	}
+	log::info!("Folding if-then-else expressions with literal conditions");
+	let mut f = IfThenElseFolder {
+		model: Model::with_capacities(&model.entity_counts()),
+		replacement_map: ReplacementMap::default(),
+	};
+	f.add_model(db, &model);
+	Ok(f.model)
+}
+
+#[cfg(test)]
+mod test {
+	use expect_test::expect;
+
+	use super::fold_if_then_else;
+	use crate::thir::transform::test::check_no_stdlib_folded;
+
+	#[test]
+	fn test_fold_if_then_else() {
+		check_no_stdlib_folded(
+			fold_if_then_else,
+			r#"
+				int: a;
+				int: b;
+				int: c;
+				int: x = if true then a else b endif;
+				int: y = if false then a elseif false then b else c endif;
+				int: z = if false then a elseif true then b else c endif;
+			"#,
+			expect!([r#"
+    int: a;
+    int: b;
+    int: c;
+    int: x = a;
+    int: y = c;
+    int: z = b;
+    solve satisfy;
+"#]),
+		);
+	}
+
+	#[test]
+	fn test_fold_if_then_else_unchanged() {
+		check_no_stdlib_folded(
+			fold_if_then_else,
+			r#"
+				bool: p;
+				var int: v;
+				float: x = if true then 1 else 2.5 endif;
+				var int: y = if true then 1 else v endif;
+				int: z = if p then 1 else 2 endif;
+			"#,
+			expect!([r#"
+    bool: p;
+    var int: v;
+    float: x = if true then 1 else 2.5 endif;
+    var int: y = if true then 1 else v endif;
+    int: z = if p then 1 else 2 endif;
+    solve satisfy;
+"#]),
+		);
+	}
+}
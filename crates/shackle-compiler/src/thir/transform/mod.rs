@@ -6,8 +6,9 @@
 
 use self::{
 	call_by_name::inline_call_by_name, capturing_fn::decapture_model,
-	comprehension::desugar_comprehension, domain_constraint::rewrite_domains,
-	erase_enum::erase_enum, erase_opt::erase_opt, erase_record::erase_record,
+	comprehension::desugar_comprehension, comprehension_filter::filter_comprehensions,
+	domain_constraint::rewrite_domains, erase_enum::erase_enum, erase_opt::erase_opt,
+	erase_record::erase_record, fold_if_then_else::fold_if_then_else,
 	function_dispatch::function_dispatch, name_mangle::mangle_names, output::generate_output,
 	top_down_type::top_down_type, type_specialise::type_specialise,
 };
@@ -17,10 +18,12 @@ use crate::Result;
 pub mod call_by_name;
 pub mod capturing_fn;
 pub mod comprehension;
+pub mod comprehension_filter;
 pub mod domain_constraint;
 pub mod erase_enum;
 pub mod erase_opt;
 pub mod erase_record;
+pub mod fold_if_then_else;
 pub mod function_dispatch;
 pub mod name_mangle;
 pub mod output;
@@ -42,6 +45,7 @@ pub fn transformer(transforms: Vec<TransformFn>) -> impl FnMut(&dyn Thir, Model)
 /// Get the default THIR transformer
 pub fn thir_transforms() -> impl FnMut(&dyn Thir, Model) -> Result<Model> {
 	transformer(vec![
+		fold_if_then_else,
 		generate_output,
 		rewrite_domains,
 		top_down_type,
@@ -51,6 +55,7 @@ pub fn thir_transforms() -> impl FnMut(&dyn Thir, Model) -> Result<Model> {
 		erase_record,
 		erase_enum,
 		desugar_comprehension,
+		filter_comprehensions,
 		erase_opt,
 		inline_call_by_name,
 		decapture_model,
@@ -65,7 +70,7 @@ pub mod test {
 	use rustc_hash::FxHashMap;
 
 	use crate::{
-		db::{CompilerDatabase, FileReader, Inputs},
+		db::{CompilerDatabase, CompilerSettings, FileReader, Inputs},
 		file::{InputFile, InputLang, ModelRef},
 		hir::{ids::NodeRef, Identifier},
 		thir::{
@@ -115,8 +120,29 @@ pub mod test {
 		transform: impl FnOnce(&dyn Thir, Model) -> Result<Model>,
 		source: &str,
 		expected: Expect,
+	) {
+		check_no_stdlib_with_db(CompilerDatabase::default(), transform, source, expected);
+	}
+
+	/// Perform a transform on the THIR, and verify the result matches an expected value.
+	///
+	/// Turns off stdlib inclusion, and turns on constant folding.
+	pub fn check_no_stdlib_folded(
+		transform: impl FnOnce(&dyn Thir, Model) -> Result<Model>,
+		source: &str,
+		expected: Expect,
 	) {
 		let mut db = CompilerDatabase::default();
+		db.set_fold_constants(true);
+		check_no_stdlib_with_db(db, transform, source, expected);
+	}
+
+	fn check_no_stdlib_with_db(
+		mut db: CompilerDatabase,
+		transform: impl FnOnce(&dyn Thir, Model) -> Result<Model>,
+		source: &str,
+		expected: Expect,
+	) {
 		db.set_ignore_stdlib(true);
 		db.set_input_files(Arc::new(vec![InputFile::String(
 			source.to_owned(),
@@ -8,69 +8,31 @@ use crate::{
 	hir::{Identifier, StringLiteral},
 	thir::{
 		db::Thir, source::Origin, Declaration, Domain, Expression, ExpressionData, Item,
-		LookupCall, LookupIdentifier, Model,
+		LookupCall, LookupIdentifier, Model, OutputItem,
 	},
 	Result,
 };
 
 /// Generate the output
-pub fn generate_output(db: &dyn Thir, mut model: Model) -> Result<Model> {
+pub fn generate_output(db: &dyn Thir, model: Model) -> Result<Model> {
+	generate_section_output(db, model, |_| true)
+}
+
+/// Generate the output, only keeping the output items belonging to sections accepted by `filter`.
+///
+/// Output items in other sections are removed without generating any declarations for them.
+pub fn generate_section_output(
+	db: &dyn Thir,
+	mut model: Model,
+	filter: impl FnMut(&str) -> bool,
+) -> Result<Model> {
 	log::info!("Generating output");
 
 	let ids = db.identifier_registry();
 	let tys = db.type_registry();
 	let origin = Origin::Introduced("<generated-output>");
-	let mut sections: FxHashMap<StringLiteral, Vec<Expression>> = FxHashMap::default();
 	let outputs = model.take_outputs();
-	for output in outputs {
-		let (_, output) = output.into_inner();
-		let (section, expression) = output.into_inner();
-		if let Some(s) = section {
-			let section = match &*s {
-				ExpressionData::StringLiteral(sl) => sl.clone(),
-				_ => unreachable!(),
-			};
-			sections.entry(section).or_default().push(expression)
-		} else {
-			sections
-				.entry(ids.default.into())
-				.or_default()
-				.push(expression)
-		}
-	}
-	let mut sections = sections
-		.into_iter()
-		.map(|(k, v)| (k.value(db.upcast()), v))
-		.collect::<Vec<_>>();
-	sections.sort_by(|(a, _), (b, _)| a.cmp(b));
-	for (section, expressions) in sections {
-		let definition = expressions
-			.into_iter()
-			.reduce(|acc, e| {
-				Expression::new(
-					db,
-					&model,
-					origin,
-					LookupCall {
-						function: ids.plus_plus.into(),
-						arguments: vec![acc, e],
-					},
-				)
-			})
-			.map(|arg| {
-				Expression::new(
-					db,
-					&model,
-					origin,
-					LookupCall {
-						function: ids.concat.into(),
-						arguments: vec![arg],
-					},
-				)
-			})
-			.unwrap_or_else(|| {
-				Expression::new(db, &model, origin, StringLiteral::from(ids.empty_string))
-			});
+	for (section, definition) in section_outputs(db, &model, outputs, filter) {
 		let mut declaration = Declaration::new(true, Domain::unbounded(db, origin, tys.string));
 		declaration.set_name(Identifier::new(
 			format!("mzn_output_{}", section),
@@ -111,11 +73,82 @@ pub fn generate_output(db: &dyn Thir, mut model: Model) -> Result<Model> {
 	Ok(model)
 }
 
+/// Concatenate the given output items into a single string expression for each section accepted
+/// by `filter`, sorted by section name.
+///
+/// Output items without a section belong to the `default` section. Output items in sections which
+/// are not accepted are discarded.
+pub fn section_outputs(
+	db: &dyn Thir,
+	model: &Model,
+	outputs: Vec<OutputItem>,
+	mut filter: impl FnMut(&str) -> bool,
+) -> Vec<(String, Expression)> {
+	let ids = db.identifier_registry();
+	let origin = Origin::Introduced("<generated-output>");
+	let mut sections: FxHashMap<StringLiteral, Vec<Expression>> = FxHashMap::default();
+	for output in outputs {
+		let (_, output) = output.into_inner();
+		let (section, expression) = output.into_inner();
+		if let Some(s) = section {
+			let section = match &*s {
+				ExpressionData::StringLiteral(sl) => sl.clone(),
+				_ => unreachable!(),
+			};
+			sections.entry(section).or_default().push(expression)
+		} else {
+			sections
+				.entry(ids.default.into())
+				.or_default()
+				.push(expression)
+		}
+	}
+	let mut sections = sections
+		.into_iter()
+		.map(|(k, v)| (k.value(db.upcast()), v))
+		.filter(|(k, _)| filter(k))
+		.collect::<Vec<_>>();
+	sections.sort_by(|(a, _), (b, _)| a.cmp(b));
+	sections
+		.into_iter()
+		.map(|(section, expressions)| {
+			let definition = expressions
+				.into_iter()
+				.reduce(|acc, e| {
+					Expression::new(
+						db,
+						model,
+						origin,
+						LookupCall {
+							function: ids.plus_plus.into(),
+							arguments: vec![acc, e],
+						},
+					)
+				})
+				.map(|arg| {
+					Expression::new(
+						db,
+						model,
+						origin,
+						LookupCall {
+							function: ids.concat.into(),
+							arguments: vec![arg],
+						},
+					)
+				})
+				.unwrap_or_else(|| {
+					Expression::new(db, model, origin, StringLiteral::from(ids.empty_string))
+				});
+			(section, definition)
+		})
+		.collect()
+}
+
 #[cfg(test)]
 mod test {
 	use expect_test::expect;
 
-	use super::generate_output;
+	use super::{generate_output, generate_section_output};
 	use crate::thir::transform::test::check;
 
 	#[test]
@@ -136,6 +169,22 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn test_section_output_filter() {
+		check(
+			|db, model| generate_section_output(db, model, |s| s == "one"),
+			r#"
+				output ["Hello, world"];
+				output :: "one" ["A"];
+				output :: "two" ["B"];
+				output :: "one" ["C"];
+            "#,
+			expect!([r#"
+    string: mzn_output_one :: (output_only) = concat('++'(["A"], ["C"]));
+"#]),
+		);
+	}
+
 	#[test]
 	fn test_implicit_output_vars() {
 		check(
@@ -73,6 +73,16 @@ impl<T> ArenaIndex<T> {
 			phantom: PhantomData,
 		}
 	}
+
+	/// Create an index from its raw value (as given by converting to `u32`)
+	///
+	/// Returns `None` if the raw value is not a valid index.
+	pub fn from_raw(raw: u32) -> Option<Self> {
+		NonZeroU32::new(raw).map(|index| Self {
+			index,
+			phantom: PhantomData,
+		})
+	}
 }
 
 impl<T> From<ArenaIndex<T>> for u32 {
@@ -5,7 +5,7 @@ use shackle_compiler::{
 	file::ModelRef,
 	hir::{
 		db::Hir,
-		ids::{LocalEntityRef, NodeRef, PatternRef},
+		ids::NodeRef,
 		source::{find_node, Point},
 	},
 };
@@ -34,34 +34,14 @@ impl RequestHandler<GotoDefinition, (ModelRef, Point)> for GotoDefinitionHandler
 	) -> Result<Option<GotoDefinitionResponse>, ResponseError> {
 		let found = find_node(db, *model_ref, start, start);
 		Ok((|| {
-			let node = found?;
-			match node {
-				NodeRef::Entity(e) => {
-					let item = e.item(db);
-					match e.entity(db) {
-						LocalEntityRef::Expression(e) => {
-							let types = db.lookup_item_types(item);
-							let resolution = types.name_resolution(e)?;
-							Some(GotoDefinitionResponse::Scalar(node_ref_to_location(
-								db,
-								resolution.into_entity(db),
-							)?))
-						}
-						LocalEntityRef::Pattern(p) => {
-							let types = db.lookup_item_types(item);
-							let resolution = types
-								.pattern_resolution(p)
-								.unwrap_or_else(|| PatternRef::new(item, p));
-							Some(GotoDefinitionResponse::Scalar(node_ref_to_location(
-								db,
-								resolution.into_entity(db),
-							)?))
-						}
-						_ => None,
-					}
-				}
-				_ => None,
-			}
+			let NodeRef::Entity(e) = found? else {
+				return None;
+			};
+			let resolution = db.definition_site(e)?;
+			Some(GotoDefinitionResponse::Scalar(node_ref_to_location(
+				db,
+				resolution.into_entity(db),
+			)?))
 		})())
 	}
 }
@@ -3,22 +3,30 @@
 //! This module contains a parser for the DataZinc format (i.e., `.dzn`) files.
 //! These files are often used to provide data for MiniZinc models.
 
-use std::sync::Arc;
+use std::{borrow::Cow, io::BufRead, path::PathBuf, sync::Arc};
 
 use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
 use shackle_compiler::{
-	diagnostics::{Error, InvalidArrayLiteral, InvalidNumericLiteral, SyntaxError, TypeMismatch},
+	diagnostics::{
+		Error, FileError, IdentifierAlreadyDefined, IgnoredDataItem, InvalidArrayLiteral,
+		InvalidNumericLiteral, InvalidSetBound, SyntaxError, TypeMismatch, UndefinedIdentifier,
+		Warning,
+	},
 	file::SourceFile,
 	syntax::{
 		ast::{AstNode, Children},
 		cst::{Cst, CstNode},
-		minizinc::{Assignment, Expression, Identifier, InfixOperator, RecordLiteralMember},
+		minizinc::{
+			Assignment, Expression, Identifier, InfixOperator, Item, MznModel, RecordLiteralMember,
+			StringLiteral,
+		},
 	},
 };
 use tree_sitter::Parser;
 
 use crate::{
-	data::ParserVal,
+	data::{AnnotationParameters, DataAssignment, ParserVal},
 	value::{EnumInner, Index, Polarity, Set},
 	Enum, OptType, Type, Value,
 };
@@ -46,12 +54,213 @@ pub(crate) fn parse_dzn(src: &SourceFile) -> Result<Vec<Assignment>, Error> {
 	Ok(it.collect())
 }
 
+/// Parses a MiniZinc model file used as data, returning its assignment items.
+///
+/// Other kinds of items cannot be used as data, so they are ignored and a warning is added to
+/// `warnings` for each of them.
+pub(crate) fn parse_mzn_data(
+	src: &SourceFile,
+	warnings: &mut Vec<Warning>,
+) -> Result<Vec<Assignment>, Error> {
+	let mut parser = Parser::new();
+	parser
+		.set_language(tree_sitter_minizinc::language())
+		.expect("Failed to set Tree Sitter parser language");
+	let tree = parser
+		.parse(src.contents().as_bytes(), None)
+		.expect("MiniZinc Tree Sitter parser did not return tree object");
+
+	let cst = Cst::from_str(tree, src.contents());
+	cst.error(|_| src.clone())?; // Check for any syntax errors
+
+	let model = MznModel::new(cst);
+	let mut assignments = Vec::new();
+	for item in model.items() {
+		match item {
+			Item::Assignment(asg) => assignments.push(asg),
+			_ => warnings.push(
+				IgnoredDataItem {
+					src: src.clone(),
+					span: item.cst_node().as_ref().byte_range().into(),
+				}
+				.into(),
+			),
+		}
+	}
+	Ok(assignments)
+}
+
+/// Parses a DataZinc file from a reader one assignment at a time, collecting the values of the
+/// assignments to the given input declarations.
+///
+/// Unlike [`parse_dzn`], only the text of the current assignment is kept in memory while it is
+/// parsed, and it is discarded before the next assignment is read. The memory used is therefore
+/// bounded by the size of the largest assignment (and the values yielded so far, if the caller keeps
+/// them). Values of enumerated types must be resolved against definitions given elsewhere, as the
+/// definitions of enumerated types cannot be given in the stream.
+///
+/// Errors are reported relative to the assignment in which they occur. The iterator stops after
+/// yielding the first error.
+pub(crate) fn parse_dzn_stream<'a, R: BufRead>(
+	reader: R,
+	input_types: &'a FxHashMap<Arc<str>, Type>,
+	annotations: &'a AnnotationParameters,
+	defined: &'a FxHashMap<Arc<str>, Value>,
+) -> DznStream<'a, R> {
+	DznStream {
+		reader,
+		input_types,
+		annotations,
+		defined,
+		seen: FxHashSet::default(),
+		done: false,
+	}
+}
+
+/// Iterator over the assignments in a DataZinc stream, created by [`parse_dzn_stream`]
+pub(crate) struct DznStream<'a, R> {
+	reader: R,
+	input_types: &'a FxHashMap<Arc<str>, Type>,
+	annotations: &'a AnnotationParameters,
+	defined: &'a FxHashMap<Arc<str>, Value>,
+	seen: FxHashSet<&'a Arc<str>>,
+	done: bool,
+}
+
+impl<'a, R: BufRead> DznStream<'a, R> {
+	fn next_assignment(&mut self) -> Result<Option<DataAssignment<'a, 'static>>, Error> {
+		let io_err = |message: String| FileError {
+			file: PathBuf::new(),
+			message,
+			other: Vec::new(),
+		};
+		loop {
+			let mut buf = Vec::new();
+			read_assignment(&mut self.reader, &mut buf).map_err(|e| io_err(e.to_string()))?;
+			if buf.is_empty() {
+				return Ok(None);
+			}
+			let src = SourceFile::from(Arc::new(
+				String::from_utf8(buf).map_err(|e| io_err(e.to_string()))?,
+			));
+			let mut assignments = parse_dzn(&src)?;
+			debug_assert!(assignments.len() <= 1);
+			let Some(asg) = assignments.pop() else {
+				// Only whitespace or comments
+				continue;
+			};
+
+			let ident = asg.assignee().cast::<Identifier>().unwrap();
+			let input_types: &'a FxHashMap<Arc<str>, Type> = self.input_types;
+			let Some((k, ty)) = input_types.get_key_value::<str>(&ident.name()) else {
+				return Err(UndefinedIdentifier {
+					src: src.clone(),
+					span: ident.cst_node().as_ref().byte_range().into(),
+					identifier: ident.name().to_string(),
+				}
+				.into());
+			};
+			if !self.seen.insert(k) || self.defined.contains_key(k) {
+				return Err(IdentifierAlreadyDefined {
+					src: src.clone(),
+					span: asg.cst_node().as_ref().byte_range().into(),
+					identifier: k.to_string(),
+				}
+				.into());
+			}
+			let span = asg.definition().cst_node().as_ref().byte_range();
+			let val = collect_dzn_value(&src, &asg.definition(), ty)?;
+			val.check_annotations(self.annotations)
+				.map_err(|msg| TypeMismatch {
+					src: src.clone(),
+					msg,
+					span: span.clone().into(),
+				})?;
+			let value = val.into_owned();
+			return Ok(Some(DataAssignment {
+				name: k,
+				ty,
+				value,
+				src,
+				span,
+			}));
+		}
+	}
+}
+
+impl<'a, R: BufRead> Iterator for DznStream<'a, R> {
+	type Item = Result<DataAssignment<'a, 'static>, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		let next = self.next_assignment().transpose();
+		self.done = !matches!(next, Some(Ok(_)));
+		next
+	}
+}
+
+/// Read the text of the next assignment (up to and including the `;` that ends it) into `buf`
+///
+/// Semicolons in string literals, quoted identifiers, and comments do not end the assignment.
+fn read_assignment<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> std::io::Result<()> {
+	#[derive(Clone, Copy)]
+	enum State {
+		Code,
+		Slash,
+		String,
+		StringEscape,
+		QuotedIdentifier,
+		LineComment,
+		BlockComment,
+		BlockCommentStar,
+	}
+	let mut state = State::Code;
+	loop {
+		let available = reader.fill_buf()?;
+		if available.is_empty() {
+			return Ok(());
+		}
+		let mut used = 0;
+		let mut end = false;
+		for &c in available {
+			used += 1;
+			state = match (state, c) {
+				(State::Code | State::Slash, b';') => {
+					end = true;
+					break;
+				}
+				(State::Code | State::Slash, b'/') => State::Slash,
+				(State::Slash, b'*') => State::BlockComment,
+				(State::Code | State::Slash, b'"') => State::String,
+				(State::Code | State::Slash, b'\'') => State::QuotedIdentifier,
+				(State::Code | State::Slash, b'%') => State::LineComment,
+				(State::Code | State::Slash, _) => State::Code,
+				(State::String, b'\\') => State::StringEscape,
+				(State::StringEscape, _) => State::String,
+				(State::String, b'"') | (State::QuotedIdentifier, b'\'') => State::Code,
+				(State::LineComment, b'\n') => State::Code,
+				(State::BlockComment | State::BlockCommentStar, b'*') => State::BlockCommentStar,
+				(State::BlockCommentStar, b'/') => State::Code,
+				(State::BlockCommentStar, _) => State::BlockComment,
+				(s, _) => s,
+			};
+		}
+		buf.extend_from_slice(&available[..used]);
+		reader.consume(used);
+		if end {
+			return Ok(());
+		}
+	}
+}
+
 /// Convert an DZN AST expression into a internal value of the given type
-pub(crate) fn collect_dzn_value(
-	file: &SourceFile,
+pub(crate) fn collect_dzn_value<'a>(
+	file: &'a SourceFile,
 	val: &Expression,
 	ty: &Type,
-) -> Result<ParserVal, Error> {
+) -> Result<ParserVal<'a>, Error> {
 	let type_err = |val_kind| {
 		Err(TypeMismatch {
 			src: file.clone(),
@@ -106,44 +315,37 @@ pub(crate) fn collect_dzn_value(
 		}
 		Expression::RecordLiteral(r) => match ty {
 			Type::Record(_, elem_tys) => {
-				// Assume that the list of types is already sorted based on the identifiers
-				debug_assert!(elem_tys.iter().tuple_windows().all(|(a, b)| a.0 <= b.0));
-				// Sort the AST record literal based on the identifiers
-				let exprs: Vec<RecordLiteralMember> = r
-					.members()
-					.sorted_by_key(|x| x.name().name().to_string())
-					.collect();
-				// Now walk the types and expressions together, if there are less expressions or if
-				// the names do not match, then one of the keys is missing from the data
+				// Walk the fields in the order of the record type, finding the member of the literal
+				// with the same name. If there is no such member, then the key is missing.
+				let mut exprs: Vec<RecordLiteralMember> = r.members().collect();
 				let mut vals = Vec::with_capacity(elem_tys.len());
-				for i in 0..elem_tys.len() {
-					if exprs.len() <= i || exprs[i].name().name().as_ref() != elem_tys[i].0.as_ref()
-					{
+				for (name, field_ty) in elem_tys.iter() {
+					let Some(i) = exprs
+						.iter()
+						.position(|m| m.name().name().as_ref() == name.as_ref())
+					else {
 						return Err(TypeMismatch {
 							src: file.clone(),
-							msg: format!(
-								"Expected '{}', but key '{}' was not found",
-								ty, elem_tys[i].0
-							),
+							msg: format!("Expected '{}', but key '{}' was not found", ty, name),
 							span: val.cst_node().as_ref().byte_range().into(),
 						}
 						.into());
-					}
+					};
+					let member = exprs.remove(i);
 					vals.push((
-						elem_tys[i].0.clone(),
-						collect_dzn_value(file, &exprs[i].value(), &elem_tys[i].1)?,
+						name.clone(),
+						collect_dzn_value(file, &member.value(), field_ty)?,
 					))
 				}
 				// Check whether there are any additional remaining keys
-				if exprs.len() > elem_tys.len() {
-					let additional = &exprs[elem_tys.len()..exprs.len()];
+				if !exprs.is_empty() {
 					return Err(TypeMismatch {
 						src: file.clone(),
 						msg: format!(
 							"Expected '{}', but found the addition key{} {}",
 							ty,
-							if additional.len() > 1 { "s" } else { "" },
-							additional.iter().format_with(", ", |key, f| {
+							if exprs.len() > 1 { "s" } else { "" },
+							exprs.iter().format_with(", ", |key, f| {
 								f(&format_args!("'{}'", key.name().name()))
 							})
 						),
@@ -173,14 +375,14 @@ pub(crate) fn collect_dzn_value(
 		},
 		Expression::StringLiteral(s) => {
 			if matches!(ty, Type::String(_)) {
-				Ok(ParserVal::String(s.value()))
+				Ok(ParserVal::String(string_value(file, s)))
 			} else {
 				type_err("a string literal")
 			}
 		}
 		Expression::Identifier(ident) => match ty {
-			Type::Enum(_, _) => Ok(ParserVal::Enum(ident.name().to_string(), Vec::new())),
-			Type::Annotation(_) => Ok(ParserVal::Ann(ident.name().to_string(), Vec::new())),
+			Type::Enum(_, _) => Ok(ParserVal::Enum(identifier_name(file, ident), Vec::new())),
+			Type::Annotation(_) => Ok(ParserVal::Ann(identifier_name(file, ident), Vec::new())),
 			_ => type_err("an identifier"),
 		},
 		Expression::Absent(_) => {
@@ -418,20 +620,84 @@ pub(crate) fn collect_dzn_value(
 						}.into())
 					}
 				).collect::<Result<Vec<_>, _>>()?;
-				Ok(ParserVal::Enum(ident.name().to_string(), args))
+				Ok(ParserVal::Enum(identifier_name(file, &ident), args))
+			}
+			Type::Annotation(_) => {
+				let ident: Identifier = c.function().cast().unwrap();
+				// The declaration of the annotation is not known until the value is resolved, so
+				// literals are collected using their own type, and identifiers as annotations (which
+				// may later be resolved as values of enumerated types)
+				let args = c
+					.arguments()
+					.map(|expr| {
+						let arg_ty = match expr {
+							Expression::IntegerLiteral(_) => Type::Integer(OptType::NonOpt),
+							Expression::FloatLiteral(_) => Type::Float(OptType::NonOpt),
+							Expression::BooleanLiteral(_) => Type::Boolean(OptType::NonOpt),
+							Expression::StringLiteral(_) => Type::String(OptType::NonOpt),
+							Expression::Identifier(_) | Expression::Call(_) => ty.clone(),
+							_ => {
+								return Err(TypeMismatch {
+									src: file.clone(),
+									msg: "Annotation arguments in DZN must be literals, values of enumerated types, or annotations"
+										.to_string(),
+									span: expr.cst_node().as_ref().byte_range().into(),
+								}
+								.into())
+							}
+						};
+						collect_dzn_value(file, &expr, &arg_ty)
+					})
+					.collect::<Result<Vec<_>, _>>()?;
+				Ok(ParserVal::Ann(identifier_name(file, &ident), args))
 			}
-			Type::Annotation(_) => todo!(),
 			Type::Array {
 				opt: _,
 				dim: _,
 				element: _,
-			} => todo!(),
+			} => Err(TypeMismatch {
+				src: file.clone(),
+				msg: "array functions are not allowed as part of a DZN value, use a 2D array literal or an indexed array literal instead".to_string(),
+				span: c.cst_node().as_ref().byte_range().into(),
+			}
+			.into()),
 			_ => type_err("a call"),
 		},
 		Expression::InfixOperator(op) => {
+			let extract_bound = |e: &Expression, ty: &Type| {
+				// Set bounds must be literals of the element type
+				let literal = match ty {
+					Type::Integer(_) => matches!(
+						e,
+						Expression::IntegerLiteral(_)
+							| Expression::BooleanLiteral(_)
+							| Expression::Infinity(_)
+					),
+					Type::Float(_) => matches!(
+						e,
+						Expression::IntegerLiteral(_)
+							| Expression::FloatLiteral(_)
+							| Expression::BooleanLiteral(_)
+							| Expression::Infinity(_)
+					),
+					Type::Enum(_, _) => {
+						matches!(e, Expression::Identifier(_) | Expression::Call(_))
+					}
+					_ => true,
+				};
+				if !literal {
+					return Err(InvalidSetBound {
+						src: file.clone(),
+						ty: ty.to_string(),
+						span: e.cst_node().as_ref().byte_range().into(),
+					}
+					.into());
+				}
+				collect_dzn_value(file, e, ty)
+			};
 			let extract_range = |op: &InfixOperator, ty| {
-				let left = collect_dzn_value(file, &op.left(), ty)?;
-				let right = collect_dzn_value(file, &op.right(), ty)?;
+				let left = extract_bound(&op.left(), ty)?;
+				let right = extract_bound(&op.right(), ty)?;
 				Ok::<_, Error>((left, right))
 			};
 			match op.operator().name() {
@@ -495,6 +761,155 @@ pub(crate) fn collect_dzn_value(
 	}
 }
 
+/// Infer the type of a DZN value from its syntax alone, for parsing data without a model.
+///
+/// Identifiers and calls are taken to be values of an unknown enumerated type, integers are widened
+/// to floats when mixed with them, and element types are made optional if any of the elements are
+/// `<>`. Types which cannot be determined (e.g. of `<>` or `[]`) default to `int`.
+pub(crate) fn infer_dzn_type(val: &Expression) -> Type {
+	infer_dzn_element_type([val.clone()])
+}
+
+/// Infer the type of a single DZN value, or `None` if it is `<>`
+fn infer_dzn_value_type(val: &Expression) -> Option<Type> {
+	let int = Type::Integer(OptType::NonOpt);
+	Some(match val {
+		Expression::IntegerLiteral(_) | Expression::Infinity(_) => int,
+		Expression::FloatLiteral(_) => Type::Float(OptType::NonOpt),
+		Expression::BooleanLiteral(_) => Type::Boolean(OptType::NonOpt),
+		Expression::StringLiteral(_) => Type::String(OptType::NonOpt),
+		Expression::Identifier(_) | Expression::Call(_) => {
+			Type::Enum(OptType::NonOpt, Arc::new(Enum::from_data("".into())))
+		}
+		Expression::TupleLiteral(t) => Type::Tuple(
+			OptType::NonOpt,
+			t.members().map(|m| infer_dzn_element_type([m])).collect(),
+		),
+		Expression::RecordLiteral(r) => Type::Record(
+			OptType::NonOpt,
+			r.members()
+				.map(|m| {
+					(
+						Arc::from(&*m.name().name()),
+						infer_dzn_element_type([m.value()]),
+					)
+				})
+				.collect(),
+		),
+		Expression::SetLiteral(sl) => Type::Set(
+			OptType::NonOpt,
+			Box::new(infer_dzn_element_type(sl.members())),
+		),
+		Expression::InfixOperator(op) => match op.operator().name() {
+			".." => Type::Set(
+				OptType::NonOpt,
+				Box::new(infer_dzn_element_type([op.left(), op.right()])),
+			),
+			// The element type is taken from the first range
+			_ => return infer_dzn_value_type(&op.left()),
+		},
+		Expression::ArrayLiteral(al) => {
+			let members = al.members().collect::<Vec<_>>();
+			let index_ty = |i: Expression| infer_dzn_value_type(&i).unwrap_or(int.clone());
+			let dim = match members.first().and_then(|m| m.indices()) {
+				// Fully indexed using tuples
+				Some(Expression::TupleLiteral(t))
+					if members.len() > 1 && members[1].indices().is_some() =>
+				{
+					t.members().map(index_ty).collect()
+				}
+				Some(i) => vec![index_ty(i)].into_boxed_slice(),
+				None => vec![int.clone()].into_boxed_slice(),
+			};
+			Type::Array {
+				opt: OptType::NonOpt,
+				dim,
+				element: Box::new(infer_dzn_element_type(members.iter().map(|m| m.value()))),
+			}
+		}
+		Expression::ArrayLiteral2D(al) => {
+			let row_ty = al
+				.rows()
+				.find_map(|r| r.index())
+				.and_then(|i| infer_dzn_value_type(&i))
+				.unwrap_or(int.clone());
+			let col_ty = al
+				.column_indices()
+				.next()
+				.and_then(|i| infer_dzn_value_type(&i))
+				.unwrap_or(int);
+			Type::Array {
+				opt: OptType::NonOpt,
+				dim: vec![row_ty, col_ty].into_boxed_slice(),
+				element: Box::new(infer_dzn_element_type(
+					al.rows().flat_map(|r| r.members().collect::<Vec<_>>()),
+				)),
+			}
+		}
+		_ => return None,
+	})
+}
+
+/// Infer the common type of the given DZN values (see [`infer_dzn_type`])
+fn infer_dzn_element_type(vals: impl IntoIterator<Item = Expression>) -> Type {
+	let mut ty = None;
+	let mut opt = false;
+	for val in vals {
+		match infer_dzn_value_type(&val) {
+			Some(t) => {
+				if ty.is_none() || matches!((&t, &ty), (Type::Float(_), Some(Type::Integer(_)))) {
+					ty = Some(t);
+				}
+			}
+			None => opt = true,
+		}
+	}
+	let ty = ty.unwrap_or(Type::Integer(OptType::NonOpt));
+	if !opt {
+		return ty;
+	}
+	match ty {
+		Type::Boolean(_) => Type::Boolean(OptType::Opt),
+		Type::Integer(_) => Type::Integer(OptType::Opt),
+		Type::Float(_) => Type::Float(OptType::Opt),
+		Type::Enum(_, e) => Type::Enum(OptType::Opt, e),
+		Type::String(_) => Type::String(OptType::Opt),
+		Type::Annotation(_) => Type::Annotation(OptType::Opt),
+		Type::Array { dim, element, .. } => Type::Array {
+			opt: OptType::Opt,
+			dim,
+			element,
+		},
+		Type::Set(_, e) => Type::Set(OptType::Opt, e),
+		Type::Tuple(_, fs) => Type::Tuple(OptType::Opt, fs),
+		Type::Record(_, fs) => Type::Record(OptType::Opt, fs),
+	}
+}
+
+/// Get the name of an identifier, borrowing it from the source file where possible
+fn identifier_name<'a>(file: &'a SourceFile, ident: &Identifier) -> Cow<'a, str> {
+	let range = ident.cst_node().as_ref().byte_range();
+	match ident {
+		Identifier::UnquotedIdentifier(_) => Cow::Borrowed(&file.contents()[range]),
+		Identifier::QuotedIdentifier(_) => {
+			Cow::Borrowed(&file.contents()[range.start + 1..range.end - 1])
+		}
+		Identifier::InversedIdentifier(i) => Cow::Owned(i.name()),
+	}
+}
+
+/// Get the value of a string literal, borrowing it from the source file if it contains no escape
+/// sequences
+fn string_value<'a>(file: &'a SourceFile, s: &StringLiteral) -> Cow<'a, str> {
+	let range = s.cst_node().as_ref().byte_range();
+	let text = &file.contents()[range.start + 1..range.end - 1];
+	if text.contains('\\') {
+		Cow::Owned(s.value())
+	} else {
+		Cow::Borrowed(text)
+	}
+}
+
 impl EnumInner {
 	pub(crate) fn collect_definition(
 		&mut self,
@@ -527,7 +942,8 @@ impl EnumInner {
 					let name: Arc<str> = c.function().cast::<Identifier>().unwrap().name().into();
 
 					let mut args = Vec::new();
-					let mut len = 0;
+					// The constructor has a member for every combination of arguments
+					let mut len = 1;
 					for arg in c.arguments() {
 						let int_set_ty =
 							Type::Set(OptType::NonOpt, Box::new(Type::Integer(OptType::NonOpt)));
@@ -540,7 +956,7 @@ impl EnumInner {
 							todo!("handle non-continuous (and empty) integer sets for constructors")
 						}
 						args.push(Index::Integer(x[0].clone()));
-						len += args.last().unwrap().len();
+						len *= args.last().unwrap().len();
 					}
 					ctors.push((name, args.into_boxed_slice(), len))
 				}
@@ -590,13 +1006,22 @@ impl EnumInner {
 }
 #[cfg(test)]
 mod tests {
-	use std::sync::Arc;
+	use std::{borrow::Cow, sync::Arc};
 
 	use expect_test::{expect, Expect};
-	use shackle_compiler::file::SourceFile;
+	use itertools::Itertools;
+	use rustc_hash::FxHashMap;
+	use shackle_compiler::{
+		diagnostics::{Error, Warning},
+		file::SourceFile,
+		syntax::{ast::AstNode, minizinc::Identifier},
+	};
 
-	use super::parse_dzn;
-	use crate::{data::dzn::collect_dzn_value, Enum, OptType, Type};
+	use super::{parse_dzn, parse_dzn_stream};
+	use crate::{
+		data::{dzn::collect_dzn_value, AnnotationParameters, DataAssignment, ParserVal},
+		Enum, OptType, Type,
+	};
 
 	fn check_serialization(input: &str, ty: &Type, expected: &Expect) {
 		let src = SourceFile::from(Arc::new(format!("x = {input};")));
@@ -659,6 +1084,29 @@ mod tests {
 	#[test]
 	fn test_parse_absent() {
 		check_serialization("<>", &Type::Integer(OptType::Opt), &expect!("<>"));
+
+		let array = |element| Type::Array {
+			opt: OptType::NonOpt,
+			dim: [Type::Integer(OptType::NonOpt)].into(),
+			element: Box::new(element),
+		};
+		check_serialization(
+			"[1, <>, 3]",
+			&array(Type::Integer(OptType::Opt)),
+			&expect!("[1, <>, 3]"),
+		);
+
+		// Absent members are rejected for arrays of non-optional values
+		let src = SourceFile::from(Arc::new("x = [1, <>, 3];".to_owned()));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let Err(Error::TypeMismatch(e)) = collect_dzn_value(
+			&src,
+			&assignments[0].definition(),
+			&array(Type::Integer(OptType::NonOpt)),
+		) else {
+			panic!("expected type mismatch")
+		};
+		expect!("Expected 'int' but found absent").assert_eq(&e.msg);
 	}
 
 	#[test]
@@ -767,7 +1215,7 @@ mod tests {
 					Type::Integer(OptType::NonOpt),
 				]),
 			),
-			&expect!("([1, 2], 3..3 ∪ 4..4, 5)"),
+			&expect!("([1, 2], 3..4, 5)"),
 		);
 		check_serialization(
 			"(1, (2, (4, 5)), 6)",
@@ -810,12 +1258,12 @@ mod tests {
 		check_serialization(
 			"{1.0}",
 			&Type::Set(OptType::NonOpt, Box::new(Type::Float(OptType::NonOpt))),
-			&expect!("1..1"),
+			&expect!("{1}"),
 		);
 		check_serialization(
 			"{1,2.2}",
 			&Type::Set(OptType::NonOpt, Box::new(Type::Float(OptType::NonOpt))),
-			&expect!("1..1 ∪ 2.2..2.2"),
+			&expect!("{1} ∪ {2.2}"),
 		);
 		check_serialization(
 			"1..3",
@@ -878,7 +1326,20 @@ mod tests {
 					),
 				]),
 			),
-			&expect!("(a: 1..1 ∪ 2..2, b: (3.5, true), c: [<>])"),
+			&expect!("(a: 1..2, b: (3.5, true), c: [<>])"),
+		);
+		// Fields keep the order of the record type, which need not be sorted by name
+		check_serialization(
+			"(a: 1, b: 2.5, c: true)",
+			&Type::Record(
+				OptType::NonOpt,
+				Arc::new([
+					(c.clone(), Type::Boolean(OptType::NonOpt)),
+					(a.clone(), Type::Integer(OptType::NonOpt)),
+					(b.clone(), Type::Float(OptType::NonOpt)),
+				]),
+			),
+			&expect!("(c: true, a: 1, b: 2.5)"),
 		);
 
 		check_serialization(
@@ -952,6 +1413,402 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_parse_string_input() {
+		let src = SourceFile::from(Arc::new("x = 1; y = [1,2,3];".to_owned()));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let int = Type::Integer(OptType::NonOpt);
+		let array = Type::Array {
+			opt: OptType::NonOpt,
+			dim: [Type::Integer(OptType::NonOpt)].into(),
+			element: Type::Integer(OptType::NonOpt).into(),
+		};
+		let values = assignments
+			.iter()
+			.zip_eq([&int, &array])
+			.map(|(asg, ty)| {
+				let name = asg.assignee().cast::<Identifier>().unwrap().name();
+				let val = collect_dzn_value(&src, &asg.definition(), ty)
+					.expect("unexpected type error")
+					.resolve_value(ty)
+					.expect("unexpected resolve error");
+				format!("{name} = {val};")
+			})
+			.join(" ");
+		expect!("x = 1; y = [1, 2, 3];").assert_eq(&values);
+	}
+
+	#[test]
+	fn test_parse_stream() {
+		// Synthetic data file with many assignments, and semicolons in strings and comments
+		let n = 10_000;
+		let mut data = String::new();
+		for i in 0..n {
+			data.push_str(&format!(
+				"x{i} = [{i}, {}]; % comment; with a semicolon\n",
+				i + 1
+			));
+		}
+		data.push_str("/* block; comment */ s = \"a;b\\\";\";\n");
+		let array = Type::Array {
+			opt: OptType::NonOpt,
+			dim: [Type::Integer(OptType::NonOpt)].into(),
+			element: Type::Integer(OptType::NonOpt).into(),
+		};
+		let mut input_types = (0..n)
+			.map(|i| (Arc::from(format!("x{i}")), array.clone()))
+			.collect::<FxHashMap<_, _>>();
+		input_types.insert("s".into(), Type::String(OptType::NonOpt));
+		let annotations = FxHashMap::default();
+		let defined = FxHashMap::default();
+
+		let mut count = 0;
+		for asg in parse_dzn_stream(data.as_bytes(), &input_types, &annotations, &defined) {
+			let asg = asg.expect("unexpected error");
+			let name = asg.name;
+			let val = asg
+				.value
+				.resolve_value(asg.ty)
+				.expect("unexpected resolve error");
+			if count < n {
+				assert_eq!(name.as_ref(), format!("x{count}"));
+				assert_eq!(val.to_string(), format!("[{count}, {}]", count + 1));
+			} else {
+				expect!([r#""a;b\";""#]).assert_eq(&val.to_string());
+			}
+			count += 1;
+		}
+		assert_eq!(count, n + 1);
+	}
+
+	#[test]
+	fn test_parse_stream_error() {
+		let int = Type::Integer(OptType::NonOpt);
+		let input_types = ["x", "y", "z"]
+			.into_iter()
+			.map(|n| (Arc::from(n), int.clone()))
+			.collect::<FxHashMap<_, _>>();
+		let annotations = FxHashMap::default();
+		let defined = FxHashMap::default();
+		let data = "x = 1; y = \"a\"; z = 3;";
+		let mut stream = parse_dzn_stream(data.as_bytes(), &input_types, &annotations, &defined);
+		let Some(Ok(asg)) = stream.next() else {
+			panic!("expected first assignment")
+		};
+		assert_eq!(asg.name.as_ref(), "x");
+		assert_eq!(asg.value, ParserVal::Integer(1));
+		assert!(matches!(stream.next(), Some(Err(Error::TypeMismatch(_)))));
+		// Iteration stops after the first error
+		assert!(stream.next().is_none());
+	}
+
+	#[test]
+	fn test_parse_syntax_error_spans() {
+		let syntax_error = |data: &str| {
+			let src = SourceFile::from(Arc::new(data.to_owned()));
+			match parse_dzn(&src) {
+				Err(Error::SyntaxError(e)) => {
+					assert_eq!(e.src, src);
+					e.span
+				}
+				r => panic!("expected syntax error, found {:?}", r),
+			}
+		};
+
+		// Unterminated array: the error is located in the second assignment
+		let data = "a = 1;\nx = [1, 2,";
+		let span = syntax_error(data);
+		assert!(span.offset() >= data.find('x').unwrap());
+		assert!(span.offset() + span.len() <= data.len());
+
+		// Stray token: the error covers the token
+		let data = "a = 1; ) b = 2;";
+		let span = syntax_error(data);
+		let stray = data.find(')').unwrap();
+		assert!(span.offset() <= stray && stray < span.offset() + span.len());
+	}
+
+	#[test]
+	fn test_reversed_ranges() {
+		let color = Arc::new(Enum::from_data("Color".into()));
+		let src = SourceFile::from(Arc::new("Color = {Red, Green, Blue};".to_owned()));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		color
+			.state
+			.lock()
+			.unwrap()
+			.collect_definition(&src, &assignments[0].definition())
+			.expect("unexpected error defining enum");
+
+		let int_set = Type::Set(OptType::NonOpt, Type::Integer(OptType::NonOpt).into());
+		let enum_set = Type::Set(OptType::NonOpt, Type::Enum(OptType::NonOpt, color).into());
+		let name: Arc<str> = "x".into();
+		let src = SourceFile::from(Arc::new(
+			"a = 5..1; b = {1..3, 6..4}; c = -3..3; d = Blue..Red; e = {Red..Red, Blue..Green};"
+				.to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let resolve = |allow_reversed| {
+			let mut warnings = Vec::new();
+			let values = assignments
+				.iter()
+				.zip_eq([&int_set, &int_set, &int_set, &enum_set, &enum_set])
+				.map(|(asg, ty)| {
+					let value = collect_dzn_value(&src, &asg.definition(), ty)
+						.expect("unexpected type error");
+					DataAssignment {
+						name: &name,
+						ty,
+						value,
+						src: src.clone(),
+						span: asg.definition().cst_node().as_ref().byte_range(),
+					}
+					.resolve(
+						&AnnotationParameters::default(),
+						allow_reversed,
+						&mut warnings,
+					)
+					.map(|v| v.to_string())
+				})
+				.collect_vec();
+			(values, warnings)
+		};
+
+		// Reversed ranges are empty sets, with a warning
+		let (values, warnings) = resolve(true);
+		let values = values
+			.into_iter()
+			.map(|v| v.expect("unexpected resolve error"))
+			.collect_vec();
+		expect!([r#"
+    [
+        "∅",
+        "1..3",
+        "-3..3",
+        "∅",
+        "{Red}",
+    ]
+"#])
+		.assert_debug_eq(&values);
+		let ranges = warnings
+			.iter()
+			.map(|w| match w {
+				Warning::EmptyRange(w) => w.range.as_str(),
+				_ => unreachable!(),
+			})
+			.collect_vec();
+		assert_eq!(ranges, ["5..1", "6..4", "Blue..Red", "Blue..Green"]);
+
+		// Reversed ranges are errors
+		let (values, warnings) = resolve(false);
+		assert!(warnings.is_empty());
+		let ranges = values
+			.iter()
+			.map(|v| match v {
+				Ok(_) => None,
+				Err(Error::ReversedRange(e)) => Some(e.range.as_str()),
+				Err(e) => panic!("unexpected error {e}"),
+			})
+			.collect_vec();
+		assert_eq!(
+			ranges,
+			[
+				Some("5..1"),
+				Some("6..4"),
+				None,
+				Some("Blue..Red"),
+				Some("Blue..Green")
+			]
+		);
+	}
+
+	#[test]
+	fn test_parse_infinite_set_bounds() {
+		let src = SourceFile::from(Arc::new(
+			"a = 1..infinity; b = -infinity..10; c = 1..3 union 5..infinity;".to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let int_set = Type::Set(OptType::NonOpt, Type::Integer(OptType::NonOpt).into());
+		let float_set = Type::Set(OptType::NonOpt, Type::Float(OptType::NonOpt).into());
+		for asg in assignments.iter() {
+			// Unbounded integer sets cannot be represented
+			let val = collect_dzn_value(&src, &asg.definition(), &int_set)
+				.expect("unexpected type error");
+			expect!("Sets of type 'set of int' with infinite bounds are not supported").assert_eq(
+				&val.resolve_value_with(
+					&int_set,
+					&AnnotationParameters::default(),
+					&mut Vec::new(),
+				)
+				.unwrap_err(),
+			);
+		}
+		let values = assignments
+			.iter()
+			.map(|asg| {
+				let val = collect_dzn_value(&src, &asg.definition(), &float_set)
+					.expect("unexpected type error");
+				val.resolve_value(&float_set)
+					.expect("unexpected resolve error")
+					.to_string()
+			})
+			.collect_vec();
+		expect!([r#"
+    [
+        "1..inf",
+        "-inf..10",
+        "1..3 ∪ 5..inf",
+    ]
+"#])
+		.assert_debug_eq(&values);
+	}
+
+	#[test]
+	fn test_invalid_set_bounds() {
+		let int_set = Type::Set(OptType::NonOpt, Type::Integer(OptType::NonOpt).into());
+		let float_set = Type::Set(OptType::NonOpt, Type::Float(OptType::NonOpt).into());
+		let enum_set = Type::Set(
+			OptType::NonOpt,
+			Type::Enum(OptType::NonOpt, Arc::new(Enum::from_data("Color".into()))).into(),
+		);
+		let src = SourceFile::from(Arc::new(
+			"a = 1..3 union 5..n; b = 1.0..2.5 union Red..3.0; c = Red..Red union Green..3;"
+				.to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let errors = assignments
+			.iter()
+			.zip_eq([&int_set, &float_set, &enum_set])
+			.map(|(asg, ty)| {
+				let Err(Error::InvalidSetBound(e)) = collect_dzn_value(&src, &asg.definition(), ty)
+				else {
+					panic!("expected invalid set bound error");
+				};
+				(
+					e.ty,
+					src.contents()[e.span.offset()..e.span.offset() + e.span.len()].to_owned(),
+				)
+			})
+			.collect_vec();
+		expect!([r#"
+    [
+        (
+            "int",
+            "n",
+        ),
+        (
+            "float",
+            "Red",
+        ),
+        (
+            "Color",
+            "3",
+        ),
+    ]
+"#])
+		.assert_debug_eq(&errors);
+	}
+
+	#[test]
+	fn test_borrowed_strings() {
+		let src = SourceFile::from(Arc::new(
+			"x = A; y = 'B c'; z = \"d\"; w = \"e\\n\";".to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let e = Type::Enum(OptType::NonOpt, Arc::new(Enum::from_data("E".into())));
+		let s = Type::String(OptType::NonOpt);
+		let values = assignments
+			.iter()
+			.zip_eq([&e, &e, &s, &s])
+			.map(|(asg, ty)| {
+				collect_dzn_value(&src, &asg.definition(), ty).expect("unexpected type error")
+			})
+			.collect_vec();
+		assert!(matches!(&values[0], ParserVal::Enum(Cow::Borrowed("A"), _)));
+		assert!(matches!(
+			&values[1],
+			ParserVal::Enum(Cow::Borrowed("B c"), _)
+		));
+		assert!(matches!(&values[2], ParserVal::String(Cow::Borrowed("d"))));
+		// Strings containing escape sequences must be decoded
+		assert_eq!(&values[3], &ParserVal::String(Cow::Owned("e\n".into())));
+	}
+
+	#[test]
+	fn test_parse_annotation() {
+		let ty = Type::Annotation(OptType::NonOpt);
+		let int = Type::Integer(OptType::NonOpt);
+		let mut annotations = AnnotationParameters::default();
+		annotations.insert("foo".into(), vec![[].into()]);
+		annotations.insert("bar".into(), vec![[Some(int), Some(ty.clone())].into()]);
+		annotations.insert("baz".into(), vec![[Some(ty.clone())].into()]);
+		let name: Arc<str> = "x".into();
+		let src = SourceFile::from(Arc::new(
+			"a = foo; b = bar(1, baz(foo)); c = qux; d = bar(foo, 1);".to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let values = assignments
+			.iter()
+			.map(|asg| {
+				let value =
+					collect_dzn_value(&src, &asg.definition(), &ty).expect("unexpected type error");
+				DataAssignment {
+					name: &name,
+					ty: &ty,
+					value,
+					src: src.clone(),
+					span: asg.definition().cst_node().as_ref().byte_range(),
+				}
+				.resolve(&annotations, true, &mut Vec::new())
+				.map(|v| v.to_string())
+				.map_err(|e| match e {
+					Error::TypeMismatch(e) => e.msg,
+					e => panic!("unexpected error {e}"),
+				})
+			})
+			.collect_vec();
+		expect![[r#"
+    [
+        Ok(
+            "foo",
+        ),
+        Ok(
+            "bar(1, baz(foo))",
+        ),
+        Err(
+            "Unknown annotation 'qux'",
+        ),
+        Err(
+            "No declaration of annotation 'bar' matches the given arguments",
+        ),
+    ]
+"#]]
+		.assert_debug_eq(&values);
+	}
+
+	#[test]
+	fn test_parse_2d_array() {
+		let ty = Type::Array {
+			opt: OptType::NonOpt,
+			dim: [
+				Type::Integer(OptType::NonOpt),
+				Type::Integer(OptType::NonOpt),
+			]
+			.into(),
+			element: Type::Integer(OptType::NonOpt).into(),
+		};
+		let src = SourceFile::from(Arc::new("x = [| 1, 2, 3 | 4, 5, 6 |];".to_owned()));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		assert_eq!(assignments.len(), 1);
+		let val = collect_dzn_value(&src, &assignments[0].definition(), &ty)
+			.expect("unexpected type error");
+		expect!("SimpleArray([(Integer(1), Integer(2)), (Integer(1), Integer(3))], [Integer(1), Integer(2), Integer(3), Integer(4), Integer(5), Integer(6)])")
+			.assert_eq(&format!("{:?}", val));
+		let val = val.resolve_value(&ty).expect("unexpected resolve error");
+		expect!("[(1, 1): 1, (1, 2): 2, (1, 3): 3, (2, 1): 4, (2, 2): 5, (2, 3): 6]")
+			.assert_eq(&val.to_string());
+	}
+
 	#[test]
 	fn test_enum_list_definition() {
 		check_enum_serialization("{}", [], &[expect!("A = {}")]);
@@ -971,6 +1828,126 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_enum_constructor_arguments() {
+		let a = Arc::new(Enum::from_data("A".into()));
+		let src = SourceFile::from(Arc::new("A = Rect(1..3, 1..4) ++ {B};".to_owned()));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		a.state
+			.lock()
+			.unwrap()
+			.collect_definition(&src, &assignments[0].definition())
+			.expect("unexpected error defining enum");
+
+		let ty = Type::Enum(OptType::NonOpt, a);
+		let name: Arc<str> = "x".into();
+		let src = SourceFile::from(Arc::new(
+			"a = Rect(2, 3); b = B; c = Rect(1, 2, 3); d = Rect(B, 1); e = Rect(4, 1);".to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let values = assignments
+			.iter()
+			.map(|asg| {
+				let value =
+					collect_dzn_value(&src, &asg.definition(), &ty).expect("unexpected type error");
+				DataAssignment {
+					name: &name,
+					ty: &ty,
+					value,
+					src: src.clone(),
+					span: asg.definition().cst_node().as_ref().byte_range(),
+				}
+				.resolve(&AnnotationParameters::default(), true, &mut Vec::new())
+				.map(|v| v.to_string())
+				.map_err(|e| match e {
+					Error::TypeMismatch(e) => e.msg,
+					e => panic!("unexpected error {e}"),
+				})
+			})
+			.collect_vec();
+		expect![[r#"
+    [
+        Ok(
+            "Rect(2,3)",
+        ),
+        Ok(
+            "B",
+        ),
+        Err(
+            "Constructor 'Rect' expects 2 arguments but found 3",
+        ),
+        Err(
+            "Expected 'int' but found a value of an enumerated type",
+        ),
+        Err(
+            "Argument 4 of constructor 'Rect' is not in its domain 1..3",
+        ),
+    ]
+"#]]
+		.assert_debug_eq(&values);
+	}
+
+	#[test]
+	fn test_enum_indexed_array_bounds() {
+		let color = Arc::new(Enum::from_data("Color".into()));
+		let src = SourceFile::from(Arc::new("Color = {Red, Green, Blue};".to_owned()));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		color
+			.state
+			.lock()
+			.unwrap()
+			.collect_definition(&src, &assignments[0].definition())
+			.expect("unexpected error defining enum");
+
+		let ty = Type::Array {
+			opt: OptType::NonOpt,
+			dim: [Type::Enum(OptType::NonOpt, color)].into(),
+			element: Box::new(Type::Integer(OptType::NonOpt)),
+		};
+		let name: Arc<str> = "x".into();
+		let src = SourceFile::from(Arc::new(
+			"a = []; b = [Blue: 1]; c = [Green: 1, 2]; d = [Green: 1, 2, 3];".to_owned(),
+		));
+		let assignments = parse_dzn(&src).expect("unexpected syntax error");
+		let values = assignments
+			.iter()
+			.map(|asg| {
+				let value =
+					collect_dzn_value(&src, &asg.definition(), &ty).expect("unexpected type error");
+				DataAssignment {
+					name: &name,
+					ty: &ty,
+					value,
+					src: src.clone(),
+					span: asg.definition().cst_node().as_ref().byte_range(),
+				}
+				.resolve(&AnnotationParameters::default(), true, &mut Vec::new())
+				.map(|v| v.to_string())
+				.map_err(|e| match e {
+					Error::TypeMismatch(e) => e.msg,
+					e => panic!("unexpected error {e}"),
+				})
+			})
+			.collect_vec();
+		expect![[r#"
+    [
+        Ok(
+            "[]",
+        ),
+        Ok(
+            "[Blue: 1]",
+        ),
+        Ok(
+            "[Green: 1, Blue: 2]",
+        ),
+        Err(
+            "Array literal cannot start at value Green. There are only 2 values from Green onwards in its enumerated type, but the array literal has 3 members",
+        ),
+    ]
+"#]]
+		.assert_debug_eq(&values);
+	}
+
 	#[test]
 	fn test_parse_enum_generators() {
 		check_enum_serialization(
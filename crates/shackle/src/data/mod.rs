@@ -3,23 +3,116 @@
 pub(crate) mod dzn;
 pub(crate) mod serde;
 
-use std::sync::Arc;
+use std::{
+	borrow::Cow,
+	ops::{Add, Range},
+	sync::Arc,
+};
 
 use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use shackle_compiler::{
+	diagnostics::{EmptyRange, ReversedRange, TypeMismatch},
+	file::SourceFile,
+	syntax::{ast::AstNode, minizinc::Identifier},
+	Warning,
+};
 
 use crate::{
 	value::{Array, EnumRangeInclusive, EnumValue, Index, Polarity, Record, Set, Value},
-	Error, OptType, Result, Type,
+	OptType, Result, Type,
 };
 
+/// Parse DataZinc assignments from a string, without a model and without touching the filesystem.
+///
+/// As there are no declarations to parse the values against, the type of each value is inferred
+/// from its syntax. Errors refer to an unnamed source file containing `input`.
+pub fn parse_dzn_str(input: &str) -> Result<Vec<(Arc<str>, ParserVal<'static>)>> {
+	let src = SourceFile::from(Arc::new(input.to_owned()));
+	dzn::parse_dzn(&src)?
+		.into_iter()
+		.map(|asg| {
+			let ident = asg.assignee().cast::<Identifier>().unwrap();
+			let definition = asg.definition();
+			let ty = dzn::infer_dzn_type(&definition);
+			let value = dzn::collect_dzn_value(&src, &definition, &ty)?;
+			Ok((Arc::from(&*ident.name()), value.into_owned()))
+		})
+		.collect()
+}
+
+/// Parameter types for the overloads of each annotation declared in the model
+///
+/// Parameters with types which cannot be given as part of a data file are `None`.
+pub(crate) type AnnotationParameters = FxHashMap<Arc<str>, Vec<Box<[Option<Type>]>>>;
+
+/// Value assigned to an input declaration in a data file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DataAssignment<'a, 'b> {
+	/// Name of the input declaration
+	pub(crate) name: &'a Arc<str>,
+	/// Type of the input declaration
+	pub(crate) ty: &'a Type,
+	/// The value assigned
+	pub(crate) value: ParserVal<'b>,
+	/// The data file containing the assignment
+	pub(crate) src: SourceFile,
+	/// The byte range of the value in the data file
+	pub(crate) span: Range<usize>,
+}
+
+impl DataAssignment<'_, '_> {
+	/// Resolve the assigned value (see [`ParserVal::resolve_value`])
+	///
+	/// The arguments of annotations are resolved against the parameters of their declaration in
+	/// `annotations`. Reversed ranges (e.g. `5..1`) are empty sets. If `allow_reversed` is set, a
+	/// warning is added to `warnings` for each reversed range, otherwise the first reversed range is
+	/// an error.
+	pub(crate) fn resolve(
+		self,
+		annotations: &AnnotationParameters,
+		allow_reversed: bool,
+		warnings: &mut Vec<Warning>,
+	) -> Result<Value> {
+		let mut reversed = Vec::new();
+		let value = self
+			.value
+			.resolve_value_with(self.ty, annotations, &mut reversed)
+			.map_err(|msg| TypeMismatch {
+				src: self.src.clone(),
+				msg,
+				span: self.span.clone().into(),
+			})?;
+		if allow_reversed {
+			warnings.extend(reversed.into_iter().map(|range| {
+				EmptyRange {
+					src: self.src.clone(),
+					range,
+					span: self.span.clone().into(),
+				}
+				.into()
+			}));
+		} else if let Some(range) = reversed.into_iter().next() {
+			return Err(ReversedRange {
+				src: self.src,
+				range,
+				span: self.span.into(),
+			}
+			.into());
+		}
+		Ok(value)
+	}
+}
+
 /// Value parsed in a data file.
 ///
 /// These values can still contain unmatched enum values or enum constructors,
 /// for which the internal value has not yet been determined.
 ///
-/// TODO: Can we avoid copying the actual strings and use Cow/&str
+/// Strings and identifiers borrow from the source of the data file when possible, and are only
+/// copied when they are resolved into a [`Value`].
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum ParserVal {
+pub enum ParserVal<'a> {
 	/// Absence of an optional value
 	Absent,
 	/// Infinity (+∞ or -∞)
@@ -31,74 +124,344 @@ pub(crate) enum ParserVal {
 	/// Floating point
 	Float(f64),
 	/// String
-	String(String),
+	String(Cow<'a, str>),
 	/// Identifier of a value of an enumerated type
-	Enum(String, Vec<ParserVal>),
+	Enum(Cow<'a, str>, Vec<ParserVal<'a>>),
 	/// Annotation
-	Ann(String, Vec<ParserVal>),
-	/// An array of values
-	SimpleArray(Vec<(ParserVal, ParserVal)>, Vec<ParserVal>),
-	IndexedArray(usize, Vec<ParserVal>),
+	Ann(Cow<'a, str>, Vec<ParserVal<'a>>),
+	/// An array of values, with the bounds of each dimension
+	SimpleArray(Vec<(ParserVal<'a>, ParserVal<'a>)>, Vec<ParserVal<'a>>),
+	/// An array with the given number of dimensions, given as the indices of each element followed
+	/// by its value
+	IndexedArray(usize, Vec<ParserVal<'a>>),
 	/// A set of values
-	SetList(Vec<ParserVal>),
-	SetRangeList(Vec<(ParserVal, ParserVal)>),
-	Range(Box<(ParserVal, ParserVal)>),
+	SetList(Vec<ParserVal<'a>>),
+	/// A set given as a union of ranges
+	SetRangeList(Vec<(ParserVal<'a>, ParserVal<'a>)>),
+	/// A set given as a range
+	Range(Box<(ParserVal<'a>, ParserVal<'a>)>),
 	/// A tuple of values
-	Tuple(Vec<ParserVal>),
-	/// A record of values
-	Record(Vec<(Arc<str>, ParserVal)>),
+	Tuple(Vec<ParserVal<'a>>),
+	/// A record of values, with its fields in the order of the record type
+	Record(Vec<(Arc<str>, ParserVal<'a>)>),
 }
 
-impl ParserVal {
+impl<'a> ParserVal<'a> {
+	/// Check that the shape of the parsed data value matches the expected type
+	///
+	/// Values parsed from DZN and JSON are already collected against their declared type, so this is
+	/// only needed for values parsed without one (e.g. the arguments of annotations and enum
+	/// constructors). Returns a message describing the mismatch if it does not match.
+	pub(crate) fn check_type(&self, ty: &Type) -> Result<(), String> {
+		let mismatch = |found: &str| Err(type_mismatch(ty, found));
+		match self {
+			ParserVal::Absent => {
+				if ty.is_opt() {
+					Ok(())
+				} else {
+					mismatch("absent")
+				}
+			}
+			ParserVal::Infinity(_) => match ty {
+				Type::Integer(_) | Type::Float(_) => Ok(()),
+				_ => mismatch("infinity"),
+			},
+			ParserVal::Boolean(_) => match ty {
+				Type::Boolean(_) => Ok(()),
+				_ => mismatch("a Boolean"),
+			},
+			ParserVal::Integer(_) => match ty {
+				Type::Integer(_) | Type::Float(_) => Ok(()),
+				_ => mismatch("an integer"),
+			},
+			ParserVal::Float(_) => match ty {
+				Type::Float(_) => Ok(()),
+				_ => mismatch("a floating point number"),
+			},
+			ParserVal::String(_) => match ty {
+				Type::String(_) => Ok(()),
+				_ => mismatch("a string"),
+			},
+			ParserVal::Enum(_, _) => match ty {
+				Type::Enum(_, _) => Ok(()),
+				_ => mismatch("a value of an enumerated type"),
+			},
+			ParserVal::Ann(_, _) => match ty {
+				Type::Annotation(_) => Ok(()),
+				_ => mismatch("an annotation"),
+			},
+			ParserVal::SimpleArray(ranges, elements) => match ty {
+				Type::Array { dim, element, .. } => {
+					if !ranges.is_empty() && ranges.len() != dim.len() {
+						return mismatch(&format!("an array with {} dimensions", ranges.len()));
+					}
+					elements.iter().try_for_each(|e| e.check_type(element))
+				}
+				_ => mismatch("an array"),
+			},
+			ParserVal::IndexedArray(n, elements) => match ty {
+				Type::Array { dim, element, .. } => {
+					if *n != dim.len() {
+						return mismatch(&format!("an array with {} dimensions", n));
+					}
+					// Each value is preceded by its indices
+					elements
+						.iter()
+						.skip(*n)
+						.step_by(n + 1)
+						.try_for_each(|e| e.check_type(element))
+				}
+				_ => mismatch("an array"),
+			},
+			ParserVal::SetList(members) => check_set_type(ty, members.iter()),
+			ParserVal::SetRangeList(ranges) => {
+				check_set_type(ty, ranges.iter().flat_map(|(a, b)| [a, b]))
+			}
+			ParserVal::Range(range) => check_set_type(ty, [&range.0, &range.1]),
+			ParserVal::Tuple(members) => match ty {
+				Type::Tuple(_, tys) => {
+					if members.len() != tys.len() {
+						return mismatch(&format!("a tuple of length {}", members.len()));
+					}
+					members
+						.iter()
+						.zip(tys.iter())
+						.try_for_each(|(m, ty)| m.check_type(ty))
+				}
+				_ => mismatch("a tuple"),
+			},
+			ParserVal::Record(fields) => match ty {
+				Type::Record(_, tys) => {
+					if fields.len() != tys.len()
+						|| fields
+							.iter()
+							.zip(tys.iter())
+							.any(|((n, _), (name, _))| n != name)
+					{
+						return mismatch(&format!(
+							"a record with fields {}",
+							fields
+								.iter()
+								.format_with(", ", |(n, _), f| f(&format_args!("'{}'", n)))
+						));
+					}
+					fields
+						.iter()
+						.zip(tys.iter())
+						.try_for_each(|((_, v), (_, ty))| v.check_type(ty))
+				}
+				_ => mismatch("a record"),
+			},
+		}
+	}
+
+	/// Convert into a value which no longer borrows from the source of the data file
+	pub(crate) fn into_owned(self) -> ParserVal<'static> {
+		let owned = |v: Vec<ParserVal<'a>>| v.into_iter().map(ParserVal::into_owned).collect();
+		let owned_pairs = |v: Vec<(ParserVal<'a>, ParserVal<'a>)>| {
+			v.into_iter()
+				.map(|(a, b)| (a.into_owned(), b.into_owned()))
+				.collect()
+		};
+		match self {
+			ParserVal::Absent => ParserVal::Absent,
+			ParserVal::Infinity(v) => ParserVal::Infinity(v),
+			ParserVal::Boolean(v) => ParserVal::Boolean(v),
+			ParserVal::Integer(v) => ParserVal::Integer(v),
+			ParserVal::Float(v) => ParserVal::Float(v),
+			ParserVal::String(v) => ParserVal::String(Cow::Owned(v.into_owned())),
+			ParserVal::Enum(name, args) => {
+				ParserVal::Enum(Cow::Owned(name.into_owned()), owned(args))
+			}
+			ParserVal::Ann(name, args) => {
+				ParserVal::Ann(Cow::Owned(name.into_owned()), owned(args))
+			}
+			ParserVal::SimpleArray(ranges, elements) => {
+				ParserVal::SimpleArray(owned_pairs(ranges), owned(elements))
+			}
+			ParserVal::IndexedArray(n, elements) => ParserVal::IndexedArray(n, owned(elements)),
+			ParserVal::SetList(members) => ParserVal::SetList(owned(members)),
+			ParserVal::SetRangeList(ranges) => ParserVal::SetRangeList(owned_pairs(ranges)),
+			ParserVal::Range(range) => {
+				let (a, b) = *range;
+				ParserVal::Range(Box::new((a.into_owned(), b.into_owned())))
+			}
+			ParserVal::Tuple(members) => ParserVal::Tuple(owned(members)),
+			ParserVal::Record(fields) => ParserVal::Record(
+				fields
+					.into_iter()
+					.map(|(k, v)| (k, v.into_owned()))
+					.collect(),
+			),
+		}
+	}
+
+	/// Check that the annotations in the parsed data value are declared, and that their arguments
+	/// match the parameters of one of the declarations
+	///
+	/// Returns a message describing the problem if they do not.
+	pub(crate) fn check_annotations(
+		&self,
+		annotations: &AnnotationParameters,
+	) -> Result<(), String> {
+		match self {
+			ParserVal::Ann(name, args) => {
+				let params = annotation_parameters(annotations, name, args)?;
+				args.iter()
+					.zip(params.iter())
+					// Arguments for enum parameters are not annotations
+					.filter(|(_, ty)| !matches!(ty, Some(Type::Enum(_, _))))
+					.try_for_each(|(arg, _)| arg.check_annotations(annotations))
+			}
+			ParserVal::Enum(_, vs)
+			| ParserVal::SimpleArray(_, vs)
+			| ParserVal::IndexedArray(_, vs)
+			| ParserVal::SetList(vs)
+			| ParserVal::Tuple(vs) => vs.iter().try_for_each(|v| v.check_annotations(annotations)),
+			ParserVal::Record(fields) => fields
+				.iter()
+				.try_for_each(|(_, v)| v.check_annotations(annotations)),
+			_ => Ok(()),
+		}
+	}
+
+	/// Whether the parsed value can be given as an argument for an annotation parameter of type `ty`
+	fn is_annotation_argument(&self, ty: &Type) -> bool {
+		match (self, ty) {
+			// Identifiers and calls in annotation arguments are parsed as annotations, but may be
+			// values of enumerated types (see `ParserVal::into_annotation_argument`)
+			(ParserVal::Ann(_, _), Type::Enum(_, _)) => true,
+			_ => self.check_type(ty).is_ok(),
+		}
+	}
+
+	/// Convert an argument of an annotation into a value of the type `ty` of its parameter
+	fn into_annotation_argument(self, ty: &Type) -> ParserVal<'a> {
+		match (self, ty) {
+			(ParserVal::Integer(i), Type::Float(_)) => ParserVal::Float(i as f64),
+			(v @ ParserVal::Ann(_, _), Type::Enum(_, _)) => v.into_enum(),
+			(v, _) => v,
+		}
+	}
+
+	/// Reinterpret an identifier or call parsed as an annotation as a value of an enumerated type
+	fn into_enum(self) -> ParserVal<'a> {
+		match self {
+			ParserVal::Ann(name, args) => {
+				ParserVal::Enum(name, args.into_iter().map(ParserVal::into_enum).collect())
+			}
+			v => v,
+		}
+	}
+
 	/// Resolve parsed data value into final value for users and the interpreter
 	///
-	/// This is the final step in the parsing of data files, resolving enumerated types and creating
-	pub(crate) fn resolve_value(self, ty: &Type) -> Result<Value> {
+	/// This is the final step in the parsing of data files, resolving the values of enumerated types
+	/// and creating the arrays and sets described by the parsed value.
+	///
+	/// Returns a message describing the problem if the value does not match `ty`, which the caller
+	/// should report with the location of the value. Reversed ranges are resolved to empty sets. No
+	/// annotations are declared, so the value cannot contain annotations (see
+	/// [`DataAssignment::resolve`]).
+	pub(crate) fn resolve_value(self, ty: &Type) -> Result<Value, String> {
+		self.resolve_value_with(ty, &AnnotationParameters::default(), &mut Vec::new())
+	}
+
+	/// Resolve the value (see [`ParserVal::resolve_value`]) using the declarations in `annotations`,
+	/// adding any reversed ranges to `reversed`
+	///
+	/// Returns a message describing the problem if the value cannot be resolved (e.g. when it does
+	/// not match `ty`, or when the arguments of an enum constructor do not match its definition).
+	fn resolve_value_with(
+		self,
+		ty: &Type,
+		annotations: &AnnotationParameters,
+		reversed: &mut Vec<String>,
+	) -> Result<Value, String> {
 		match self {
-			ParserVal::Absent => Ok(Value::Absent),
+			ParserVal::Absent => {
+				debug_assert!(ty.is_opt(), "absent value for non-optional type '{}'", ty);
+				Ok(Value::Absent)
+			}
 			ParserVal::Infinity(v) => Ok(Value::Infinity(v)),
 			ParserVal::Boolean(v) => Ok(Value::Boolean(v)),
 			ParserVal::Integer(v) => Ok(Value::Integer(v)),
 			ParserVal::Float(v) => Ok(Value::Float(v)),
 			ParserVal::String(v) => Ok(Value::String(v.into())),
 			ParserVal::Enum(name, args) => {
-				let Type::Enum(_, e) = ty else { unreachable!() };
+				let Type::Enum(_, e) = ty else {
+					return Err(type_mismatch(ty, "a value of an enumerated type"));
+				};
 				let Some((offset, doms)) = e.get(&name) else {
-					todo!("add location to throw error for undefined constructor")
+					return Err(format!(
+						"Unknown constructor '{}' of enumerated type '{}'",
+						name,
+						e.name()
+					));
 				};
 				if args.len() != doms.len() {
-					todo!("add error for non-matching constructor call");
+					return Err(format!(
+						"Constructor '{}' expects {} arguments but found {}",
+						name,
+						doms.len(),
+						args.len()
+					));
 				}
-				let mut offset = offset;
+				// The position within the constructor, with the last argument varying fastest
+				let mut pos = 0;
 				for (arg, dom) in args.into_iter().zip_eq(doms.iter()) {
-					match dom {
+					let not_in_domain = |arg: &dyn std::fmt::Display| {
+						Err(format!(
+							"Argument {} of constructor '{}' is not in its domain {}",
+							arg, name, dom
+						))
+					};
+					let arg_pos = match dom {
 						Index::Integer(r) => {
-							let Value::Integer(arg) =
-								arg.resolve_value(&Type::Integer(OptType::NonOpt))?
-							else {
-								unreachable!()
-							};
-							if !r.contains(&arg) {
-								todo!("invalid argument - out of domain")
+							let arg_ty = Type::Integer(OptType::NonOpt);
+							arg.check_type(&arg_ty)?;
+							let arg = arg.resolve_value_with(&arg_ty, annotations, reversed)?;
+							match &arg {
+								Value::Integer(i) if r.contains(i) => (i - r.start()) as usize,
+								_ => return not_in_domain(&arg),
 							}
-							offset += (arg - r.start()) as usize;
 						}
 						Index::Enum(r) => {
-							let Value::Enum(arg) =
-								arg.resolve_value(&Type::Enum(OptType::NonOpt, r.enum_type()))?
-							else {
-								unreachable!()
-							};
-							if !r.contains(&arg) {
-								todo!("invalid argument - out of domain")
+							let arg_ty = Type::Enum(OptType::NonOpt, r.enum_type());
+							arg.check_type(&arg_ty)?;
+							let arg = arg.resolve_value_with(&arg_ty, annotations, reversed)?;
+							match &arg {
+								Value::Enum(v) if r.contains(v) => {
+									v.int_val() - r.start().int_val()
+								}
+								_ => return not_in_domain(&arg),
 							}
-							offset += r.start().int_val() - arg.int_val();
 						}
-					}
+					};
+					pos = pos * dom.len() + arg_pos;
 				}
-				Ok(Value::Enum(EnumValue::from_enum_and_pos(e.clone(), offset)))
+				Ok(Value::Enum(EnumValue::from_enum_and_pos(
+					e.clone(),
+					offset + pos,
+				)))
+			}
+			ParserVal::Ann(name, args) => {
+				let params = annotation_parameters(annotations, &name, &args)?;
+				let args = args
+					.into_iter()
+					.zip(params.iter())
+					.map(|(arg, param)| match param {
+						Some(param) => arg.into_annotation_argument(param).resolve_value_with(
+							param,
+							annotations,
+							reversed,
+						),
+						// Only annotations are given for parameters which cannot be given in data
+						None => arg.resolve_value_with(ty, annotations, reversed),
+					})
+					.collect::<Result<Vec<_>, _>>()?;
+				Ok(Value::Ann(name.into(), args))
 			}
-			ParserVal::Ann(_, _) => todo!(),
 			ParserVal::SimpleArray(ranges, elements) => {
 				let Type::Array {
 					opt: _,
@@ -106,172 +469,634 @@ impl ParserVal {
 					element,
 				} = ty
 				else {
-					unreachable!()
+					return Err(type_mismatch(ty, "an array"));
 				};
 				let elements = elements
 					.into_iter()
-					.map(|el| el.resolve_value(element))
+					.map(|el| el.resolve_value_with(element, annotations, reversed))
 					.collect::<Result<Vec<_>, _>>()?;
 				if elements.is_empty() {
 					return Ok(Array::empty().into());
 				}
+				if ranges.len() != dim.len() {
+					return Err(type_mismatch(
+						ty,
+						format!("an array with {} dimensions", ranges.len()),
+					));
+				}
 				let indices = ranges
 					.into_iter()
 					.zip_eq(dim.iter())
 					.map(|(range, ty)| match range {
 						(ParserVal::Integer(start), ParserVal::Integer(end)) => {
-							Ok::<_, Error>(Index::Integer(start..=end))
+							Ok::<_, String>(Index::Integer(start..=end))
 						}
 						(start @ ParserVal::Enum(_, _), ParserVal::Infinity(Polarity::Pos)) => {
 							debug_assert_eq!(dim.len(), 1);
-							let Value::Enum(start) = start.resolve_value(ty)? else {
-								unreachable!()
+							let Value::Enum(start) = start.resolve_value_with(ty, annotations, reversed)? else {
+								return Err(invalid_index_set(ty));
 							};
-							if start.int_val() + elements.len() > start.enum_type().len() {
-								todo!()
-							// Err(InvalidArrayLiteral {
-							// 	msg: format!("Array literal cannot start at value {start}. There are only {} higher values in its enumerated type, but the array literal has {} members", start.enum_type().len() + 1 - start.int_val(), elements.len()),
-							// 	src: todo!(),
-							// 	span: todo!(),
-							// }
-							// .into())
+							// Positions are 1-based, and there is at least one element
+							let last = start.int_val() + elements.len() - 1;
+							let available = start.enum_type().len() + 1 - start.int_val();
+							if last > start.enum_type().len() {
+								Err(format!(
+									"Array literal cannot start at value {start}. There are only {available} values from {start} onwards in its enumerated type, but the array literal has {} members",
+									elements.len()
+								))
 							} else {
 								Ok(Index::Enum(EnumRangeInclusive::from_enum_and_positions(
 									start.enum_type(),
 									start.int_val(),
-									start.int_val() + elements.len(),
+									last,
 								)))
 							}
 						}
 						(start @ ParserVal::Enum(_, _), end @ ParserVal::Enum(_, _)) => {
-							let Value::Enum(start) = start.resolve_value(ty)? else {
-								unreachable!()
-							};
-							let Value::Enum(end) = end.resolve_value(ty)? else {
-								unreachable!()
+							let (Value::Enum(start), Value::Enum(end)) = (
+								start.resolve_value_with(ty, annotations, reversed)?,
+								end.resolve_value_with(ty, annotations, reversed)?,
+							) else {
+								return Err(invalid_index_set(ty));
 							};
 							Ok(Index::Enum((start, end).into()))
 						}
-						_ => unreachable!("invalid index range parsed"),
+						_ => Err(invalid_index_set(ty)),
 					})
 					.collect::<Result<Vec<_>, _>>()?;
 				Ok(Array::new(indices, elements).into())
 			}
-			ParserVal::IndexedArray(_, _) => todo!(),
+			ParserVal::IndexedArray(n, elements) => {
+				let Type::Array {
+					opt: _,
+					dim,
+					element,
+				} = ty
+				else {
+					return Err(type_mismatch(ty, "an array"));
+				};
+				if n != dim.len() {
+					return Err(type_mismatch(ty, format!("an array with {} dimensions", n)));
+				}
+				// Resolve the indices of each element as positions, finding the bounds of each
+				// dimension to create the index sets
+				let mut entries = Vec::with_capacity(elements.len() / (n + 1));
+				let mut bounds = vec![(i64::MAX, i64::MIN); n];
+				let mut enums = vec![None; n];
+				for chunk in &elements.into_iter().chunks(n + 1) {
+					let mut chunk = chunk.collect::<Vec<_>>();
+					let value =
+						chunk
+							.pop()
+							.unwrap()
+							.resolve_value_with(element, annotations, reversed)?;
+					let mut position = Vec::with_capacity(n);
+					for (i, (idx, idx_ty)) in chunk.into_iter().zip_eq(dim.iter()).enumerate() {
+						let pos = match idx.resolve_value_with(idx_ty, annotations, reversed)? {
+							Value::Integer(v) => v,
+							Value::Enum(v) => {
+								enums[i] = Some(v.enum_type());
+								v.int_val() as i64
+							}
+							_ => return Err(invalid_index_set(idx_ty)),
+						};
+						bounds[i] = (bounds[i].0.min(pos), bounds[i].1.max(pos));
+						position.push(pos);
+					}
+					entries.push((position, value));
+				}
+				if entries.is_empty() {
+					return Ok(Array::empty().into());
+				}
+				let indices = bounds
+					.iter()
+					.zip(enums)
+					.map(|(&(lo, hi), e)| match e {
+						Some(e) => Index::Enum(EnumRangeInclusive::from_enum_and_positions(
+							e,
+							lo as usize,
+							hi as usize,
+						)),
+						None => Index::Integer(lo..=hi),
+					})
+					.collect::<Vec<_>>();
+				// Place the values in order, checking that every index is given exactly once
+				let len = indices.iter().map(|i| i.len()).product::<usize>();
+				if len != entries.len() {
+					return Err(format!(
+						"Indexed array literal has {} members, but its index sets {} have {} indices",
+						entries.len(),
+						indices.iter().format(", "),
+						len
+					));
+				}
+				let mut members = (0..len).map(|_| None).collect::<Vec<_>>();
+				for (position, value) in entries {
+					let offset = position
+						.iter()
+						.zip(bounds.iter())
+						.zip(indices.iter())
+						.fold(0, |acc, ((pos, (lo, _)), index)| {
+							acc * index.len() + (pos - lo) as usize
+						});
+					if members[offset].replace(value).is_some() {
+						return Err(
+							"Indexed array literal has multiple members with the same index"
+								.to_owned(),
+						);
+					}
+				}
+				Ok(Array::new(indices, members.into_iter().flatten().collect()).into())
+			}
 			ParserVal::SetList(li) => {
-				let Type::Set(_, ty) = ty else { unreachable!() };
+				let Type::Set(_, element) = ty else {
+					return Err(type_mismatch(ty, "a set"));
+				};
 				let members = li
 					.into_iter()
-					.map(|m| m.resolve_value(ty))
+					.map(|m| m.resolve_value_with(element, annotations, reversed))
 					.collect::<Result<Vec<_>, _>>()?;
-				// TODO: This could likely be optimised to not create ranges first
-				match **ty {
-					Type::Integer(_) => Ok(Value::Set(
-						members
+				// Consecutive integer and enum values are coalesced into a single range, so the
+				// ranges are already sorted and non-overlapping
+				match &**element {
+					Type::Integer(_) => {
+						let members = members
 							.into_iter()
-							.map(|m| {
-								let Value::Integer(i) = m else { unreachable!() };
-								i..=i
+							.map(|m| match m {
+								Value::Integer(i) => Ok(i),
+								_ => Err(unbounded_int_set(element)),
 							})
-							.collect(),
-					)),
-					Type::Float(_) => Ok(Value::Set(
+							.collect::<Result<Vec<_>, _>>()?;
+						Ok(Value::Set(Set::Int(
+							consecutive_ranges(members).map(|(a, b)| a..=b).collect(),
+						)))
+					}
+					Type::Float(_) => Ok(Value::Set(Set::from_iter(
 						members
 							.into_iter()
 							.map(|m| {
-								let Value::Float(i) = m else { unreachable!() };
-								i..=i
+								let i = match m {
+									Value::Float(i) => i,
+									Value::Infinity(Polarity::Pos) => f64::INFINITY,
+									Value::Infinity(Polarity::Neg) => f64::NEG_INFINITY,
+									m => return Err(type_mismatch(element, m)),
+								};
+								Ok(i..=i)
 							})
-							.collect(),
-					)),
-					Type::Enum(_, _) => Ok(Value::Set(
-						members
+							.collect::<Result<Vec<_>, _>>()?,
+					))),
+					Type::Enum(_, e) => {
+						let members = members
 							.into_iter()
-							.map(|m| {
-								let Value::Enum(i) = m else { unreachable!() };
-								EnumRangeInclusive::new(i.clone(), i)
+							.map(|m| match m {
+								Value::Enum(i) => Ok(i.int_val()),
+								m => Err(type_mismatch(element, m)),
 							})
-							.collect(),
-					)),
-					_ => unreachable!("invalid set type"),
+							.collect::<Result<Vec<_>, _>>()?;
+						Ok(Value::Set(Set::Enum(
+							consecutive_ranges(members)
+								.map(|(a, b)| {
+									EnumRangeInclusive::from_enum_and_positions(e.clone(), a, b)
+								})
+								.collect(),
+						)))
+					}
+					_ => Err(unsupported_set(ty)),
 				}
 			}
 			ParserVal::SetRangeList(li) => {
-				let Type::Set(_, ty) = ty else { unreachable!() };
-				Ok(match &**ty {
-					Type::Integer(OptType::NonOpt) => Set::from_iter(li.into_iter().map(|r| {
-						let (ParserVal::Integer(a), ParserVal::Integer(b)) = r else {
-							unreachable!("invalid integer set")
-						};
-						a..=b
-					}))
+				let Type::Set(_, element) = ty else {
+					return Err(type_mismatch(ty, "a set"));
+				};
+				let invalid_bound = || {
+					format!(
+						"Set bounds in data must be constant literals of the element type '{}'",
+						element
+					)
+				};
+				Ok(match &**element {
+					Type::Integer(OptType::NonOpt) => Set::from_iter(
+						li.into_iter()
+							.map(|r| match r {
+								(ParserVal::Integer(a), ParserVal::Integer(b)) => {
+									if a > b {
+										reversed.push(format!("{}..{}", a, b));
+									}
+									Ok(a..=b)
+								}
+								(ParserVal::Infinity(_), _) | (_, ParserVal::Infinity(_)) => {
+									Err(unbounded_int_set(element))
+								}
+								_ => Err(invalid_bound()),
+							})
+							.collect::<Result<Vec<_>, _>>()?,
+					)
 					.into(),
-					Type::Float(OptType::NonOpt) => Set::from_iter(li.into_iter().map(|r| {
-						let (ParserVal::Float(a), ParserVal::Float(b)) = r else {
-							unreachable!("invalid integer set")
-						};
-						a..=b
-					}))
+					Type::Float(OptType::NonOpt) => Set::from_iter(
+						li.into_iter()
+							.map(|(a, b)| {
+								let (Some(a), Some(b)) = (float_bound(&a), float_bound(&b)) else {
+									return Err(invalid_bound());
+								};
+								if a > b {
+									reversed.push(format!("{}..{}", a, b));
+								}
+								Ok(a..=b)
+							})
+							.collect::<Result<Vec<_>, _>>()?,
+					)
 					.into(),
 					e @ Type::Enum(OptType::NonOpt, _) => Set::from_iter(
 						li.into_iter()
-							.map(|(a, b)| match a.resolve_value(e) {
-								Ok(a) => match b.resolve_value(e) {
-									Ok(b) => {
-										let (Value::Enum(a), Value::Enum(b)) = (a, b) else {
-											unreachable!("invalid enum set")
-										};
-										Ok(EnumRangeInclusive::new(a, b))
-									}
-									Err(e) => Err(e),
-								},
-								Err(e) => Err(e),
+							.map(|(a, b)| -> Result<EnumRangeInclusive, String> {
+								let (Value::Enum(a), Value::Enum(b)) = (
+									a.resolve_value_with(e, annotations, reversed)?,
+									b.resolve_value_with(e, annotations, reversed)?,
+								) else {
+									return Err(invalid_bound());
+								};
+								if a.int_val() > b.int_val() {
+									reversed.push(format!("{}..{}", a, b));
+								}
+								Ok(EnumRangeInclusive::new(a, b))
 							})
 							.collect::<Result<Vec<EnumRangeInclusive>, _>>()?,
 					)
 					.into(),
-					_ => unreachable!("invalid set type"),
+					_ => return Err(unsupported_set(ty)),
 				})
 			}
-			ParserVal::Range(range) => Ok(Value::Set(match *range {
-				(ParserVal::Float(start), ParserVal::Float(end)) => (start..=end).into(),
-				(ParserVal::Integer(start), ParserVal::Integer(end)) => (start..=end).into(),
-				(from @ ParserVal::Enum(_, _), to @ ParserVal::Enum(_, _)) => {
-					let Value::Enum(a) = from.resolve_value(ty)? else {
-						unreachable!()
-					};
-					let Value::Enum(b) = to.resolve_value(ty)? else {
-						unreachable!()
-					};
-					EnumRangeInclusive::new(a, b).into()
-				}
-				_ => unreachable!("invalid ParserVal::Range arguments"),
-			})),
+			// A range is resolved in the same way as a set containing only that range
+			ParserVal::Range(range) => {
+				ParserVal::SetRangeList(vec![*range]).resolve_value_with(ty, annotations, reversed)
+			}
 			ParserVal::Tuple(v) => {
-				let Type::Tuple(_, ty) = ty else {
-					unreachable!()
+				let Type::Tuple(_, tys) = ty else {
+					return Err(type_mismatch(ty, "a tuple"));
 				};
+				if v.len() != tys.len() {
+					return Err(type_mismatch(ty, format!("a tuple of length {}", v.len())));
+				}
 				let members = v
 					.into_iter()
-					.zip_eq(ty.iter())
-					.map(|(m, ty)| m.resolve_value(ty))
+					.zip_eq(tys.iter())
+					.map(|(m, ty)| m.resolve_value_with(ty, annotations, reversed))
 					.collect::<Result<Vec<_>, _>>()?;
 				Ok(Value::Tuple(members))
 			}
 			ParserVal::Record(v) => {
-				let Type::Record(_, ty) = ty else {
-					unreachable!()
+				let Type::Record(_, tys) = ty else {
+					return Err(type_mismatch(ty, "a record"));
 				};
+				if v.len() != tys.len()
+					|| v.iter()
+						.zip(tys.iter())
+						.any(|((n, _), (name, _))| n != name)
+				{
+					return Err(type_mismatch(
+						ty,
+						format!(
+							"a record with fields {}",
+							v.iter()
+								.format_with(", ", |(n, _), f| f(&format_args!("'{}'", n)))
+						),
+					));
+				}
 				let rec = v
 					.into_iter()
-					.zip_eq(ty.iter())
-					.map(|((n, v), (name, ty))| {
-						debug_assert_eq!(&n, name);
-						Ok((name.clone(), v.resolve_value(ty)?))
+					.zip_eq(tys.iter())
+					.map(|((_, v), (name, ty))| {
+						Ok((
+							name.clone(),
+							v.resolve_value_with(ty, annotations, reversed)?,
+						))
 					})
-					.collect::<Result<Record>>()?;
+					.collect::<Result<Record, String>>()?;
 				Ok(Value::Record(rec))
 			}
 		}
 	}
 }
+
+/// Get the parameter types of the first declaration of the annotation `name` in `annotations` which
+/// accepts the arguments `args`
+fn annotation_parameters<'p>(
+	annotations: &'p AnnotationParameters,
+	name: &str,
+	args: &[ParserVal],
+) -> Result<&'p [Option<Type>], String> {
+	let Some(overloads) = annotations.get(name) else {
+		return Err(format!("Unknown annotation '{}'", name));
+	};
+	overloads
+		.iter()
+		.find(|params| {
+			params.len() == args.len()
+				&& args.iter().zip(params.iter()).all(|(arg, ty)| {
+					ty.as_ref()
+						.map(|ty| arg.is_annotation_argument(ty))
+						.unwrap_or(true)
+				})
+		})
+		.map(|params| &**params)
+		.ok_or_else(|| {
+			format!(
+				"No declaration of annotation '{}' matches the given arguments",
+				name
+			)
+		})
+}
+
+/// Check that a parsed set with the given members or range bounds has the expected set type `ty`
+fn check_set_type<'a: 'b, 'b>(
+	ty: &Type,
+	members: impl IntoIterator<Item = &'b ParserVal<'a>>,
+) -> Result<(), String> {
+	let Type::Set(_, element) = ty else {
+		return Err(type_mismatch(ty, "a set"));
+	};
+	if !matches!(
+		**element,
+		Type::Integer(_) | Type::Float(_) | Type::Enum(_, _)
+	) {
+		return Err(unsupported_set(ty));
+	}
+	members.into_iter().try_for_each(|m| m.check_type(element))
+}
+
+/// Message for a data value which does not have the expected type `ty`
+fn type_mismatch(ty: &Type, found: impl std::fmt::Display) -> String {
+	format!("Expected '{}' but found {}", ty, found)
+}
+
+/// Message for a set in data which has an unsupported element type
+fn unsupported_set(ty: &Type) -> String {
+	format!("Sets of type '{}' are not supported", ty)
+}
+
+/// Message for an integer set in data with an infinite bound
+///
+/// Integer sets are represented using bounded ranges, so these cannot be resolved.
+fn unbounded_int_set(element: &Type) -> String {
+	format!(
+		"Sets of type 'set of {}' with infinite bounds are not supported",
+		element
+	)
+}
+
+/// Message for an array dimension in data whose index set does not match the type `ty`
+fn invalid_index_set(ty: &Type) -> String {
+	format!("Invalid index set for an array dimension of type '{}'", ty)
+}
+
+/// Get the value of a bound of a floating point set
+fn float_bound(v: &ParserVal) -> Option<f64> {
+	match v {
+		ParserVal::Float(f) => Some(*f),
+		ParserVal::Infinity(Polarity::Pos) => Some(f64::INFINITY),
+		ParserVal::Infinity(Polarity::Neg) => Some(f64::NEG_INFINITY),
+		_ => None,
+	}
+}
+/// Sort the given values, and combine consecutive values into inclusive ranges
+pub(crate) fn consecutive_ranges<T>(
+	values: impl IntoIterator<Item = T>,
+) -> impl Iterator<Item = (T, T)>
+where
+	T: Copy + Ord + Add<Output = T> + From<u8>,
+{
+	values
+		.into_iter()
+		.sorted_unstable()
+		.dedup()
+		.map(|v| (v, v))
+		.coalesce(|a, b| {
+			if a.1 + T::from(1) == b.0 {
+				Ok((a.0, b.1))
+			} else {
+				Err((a, b))
+			}
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use expect_test::expect;
+
+	use super::{parse_dzn_str, AnnotationParameters, ParserVal};
+	use crate::{
+		value::{Array, Index, Set},
+		Error, OptType, Type, Value,
+	};
+
+	#[test]
+	fn test_check_type() {
+		let int = Type::Integer(OptType::NonOpt);
+		let set_of_int = Type::Set(OptType::NonOpt, int.clone().into());
+		let tuple = Type::Tuple(OptType::NonOpt, vec![int.clone(), int.clone()].into());
+		let record = Type::Record(
+			OptType::NonOpt,
+			vec![("a".into(), int.clone()), ("b".into(), int.clone())].into(),
+		);
+
+		// Set vs scalar
+		let set = ParserVal::SetList(vec![ParserVal::Integer(1), ParserVal::Integer(2)]);
+		expect!("Err(\"Expected 'int' but found a set\")")
+			.assert_eq(&format!("{:?}", set.check_type(&int)));
+		assert_eq!(set.check_type(&set_of_int), Ok(()));
+		expect!("Err(\"Expected 'set of int' but found an integer\")").assert_eq(&format!(
+			"{:?}",
+			ParserVal::Integer(1).check_type(&set_of_int)
+		));
+
+		// Tuple arity
+		let pair = ParserVal::Tuple(vec![ParserVal::Integer(1), ParserVal::Integer(2)]);
+		let triple = ParserVal::Tuple(vec![
+			ParserVal::Integer(1),
+			ParserVal::Integer(2),
+			ParserVal::Integer(3),
+		]);
+		assert_eq!(pair.check_type(&tuple), Ok(()));
+		expect!("Err(\"Expected 'tuple(int, int)' but found a tuple of length 3\")")
+			.assert_eq(&format!("{:?}", triple.check_type(&tuple)));
+
+		// Record field names
+		let rec = |a: &str, b: &str| {
+			ParserVal::Record(vec![
+				(Arc::from(a), ParserVal::Integer(1)),
+				(Arc::from(b), ParserVal::Integer(2)),
+			])
+		};
+		assert_eq!(rec("a", "b").check_type(&record), Ok(()));
+		expect!(
+			"Err(\"Expected 'record(int: a, int: b)' but found a record with fields 'a', 'c'\")"
+		)
+		.assert_eq(&format!("{:?}", rec("a", "c").check_type(&record)));
+	}
+
+	#[test]
+	fn test_parse_dzn_str() {
+		let values = parse_dzn_str("x = 1; y = [1,2,3];").unwrap();
+		assert_eq!(
+			values,
+			vec![
+				(Arc::from("x"), ParserVal::Integer(1)),
+				(
+					Arc::from("y"),
+					ParserVal::SimpleArray(
+						vec![(ParserVal::Integer(1), ParserVal::Integer(3))],
+						vec![
+							ParserVal::Integer(1),
+							ParserVal::Integer(2),
+							ParserVal::Integer(3)
+						]
+					)
+				),
+			]
+		);
+
+		// Types are inferred from the values
+		let values = parse_dzn_str("z = [<>, 1, 2.5]; s = 1..3; e = {A, B};").unwrap();
+		expect!([r#"[("z", SimpleArray([(Integer(1), Integer(3))], [Absent, Float(1.0), Float(2.5)])), ("s", Range((Integer(1), Integer(3)))), ("e", SetList([Enum("A", []), Enum("B", [])]))]"#])
+			.assert_eq(&format!("{:?}", values));
+
+		// Errors refer to the input string
+		match parse_dzn_str("x = 1;\ny = \"a\" ++ \"b\";") {
+			Err(Error::TypeMismatch(e)) => {
+				assert_eq!(e.src.contents(), "x = 1;\ny = \"a\" ++ \"b\";");
+				assert_eq!(e.span.offset(), 11);
+			}
+			result => panic!("Expected type mismatch, got {:?}", result),
+		}
+	}
+
+	#[test]
+	fn test_resolve_mismatch() {
+		let int = Type::Integer(OptType::NonOpt);
+		let tuple = Type::Tuple(OptType::NonOpt, vec![int.clone(), int.clone()].into());
+		let set_of_bool = Type::Set(OptType::NonOpt, Type::Boolean(OptType::NonOpt).into());
+		let resolve = |v: ParserVal, ty: &Type| {
+			v.resolve_value_with(ty, &AnnotationParameters::default(), &mut Vec::new())
+		};
+
+		expect!("Err(\"Expected 'int' but found a set\")").assert_eq(&format!(
+			"{:?}",
+			resolve(ParserVal::SetList(vec![ParserVal::Integer(1)]), &int)
+		));
+		expect!("Err(\"Expected 'tuple(int, int)' but found a tuple of length 1\")").assert_eq(
+			&format!(
+				"{:?}",
+				resolve(ParserVal::Tuple(vec![ParserVal::Integer(1)]), &tuple)
+			),
+		);
+		expect!("Err(\"Sets of type 'set of bool' are not supported\")").assert_eq(&format!(
+			"{:?}",
+			resolve(
+				ParserVal::Range(Box::new((
+					ParserVal::Boolean(false),
+					ParserVal::Boolean(true)
+				))),
+				&set_of_bool
+			)
+		));
+	}
+
+	#[test]
+	fn test_resolve_indexed_array() {
+		let int = Type::Integer(OptType::NonOpt);
+		let ty = Type::Array {
+			opt: OptType::NonOpt,
+			dim: vec![int.clone(), int.clone()].into_boxed_slice(),
+			element: Box::new(int.clone()),
+		};
+		let indexed = |elements: &[i64]| {
+			ParserVal::IndexedArray(2, elements.iter().map(|v| ParserVal::Integer(*v)).collect())
+		};
+
+		// Members are placed by their indices, not the order they are given in
+		assert_eq!(
+			indexed(&[2, 0, 3, 1, 0, 1, 2, 1, 4, 1, 1, 2]).resolve_value(&ty),
+			Ok(Array::new(
+				vec![Index::Integer(1..=2), Index::Integer(0..=1)],
+				vec![
+					Value::Integer(1),
+					Value::Integer(2),
+					Value::Integer(3),
+					Value::Integer(4)
+				]
+			)
+			.into())
+		);
+		expect!("Err(\"Indexed array literal has 3 members, but its index sets 1..2, 0..1 have 4 indices\")")
+			.assert_eq(&format!(
+				"{:?}",
+				indexed(&[2, 0, 3, 1, 0, 1, 1, 1, 2]).resolve_value(&ty)
+			));
+		expect!("Err(\"Indexed array literal has multiple members with the same index\")")
+			.assert_eq(&format!(
+				"{:?}",
+				indexed(&[1, 1, 1, 1, 1, 2, 2, 2, 3, 2, 1, 4]).resolve_value(&ty)
+			));
+	}
+
+	#[test]
+	fn test_check_annotations() {
+		let mut annotations = AnnotationParameters::default();
+		annotations.insert("foo".into(), vec![Vec::new().into_boxed_slice()]);
+		annotations.insert(
+			"bar".into(),
+			vec![vec![
+				Some(Type::Integer(OptType::NonOpt)),
+				Some(Type::Annotation(OptType::NonOpt)),
+			]
+			.into_boxed_slice()],
+		);
+		let ann = |name: &'static str, args| ParserVal::Ann(name.into(), args);
+
+		assert_eq!(ann("foo", vec![]).check_annotations(&annotations), Ok(()));
+		assert_eq!(
+			ann("bar", vec![ParserVal::Integer(1), ann("foo", vec![])])
+				.check_annotations(&annotations),
+			Ok(())
+		);
+		expect!("Err(\"Unknown annotation 'baz'\")").assert_eq(&format!(
+			"{:?}",
+			ParserVal::SetList(vec![ann("baz", vec![])]).check_annotations(&annotations)
+		));
+		expect!("Err(\"No declaration of annotation 'bar' matches the given arguments\")")
+			.assert_eq(&format!(
+				"{:?}",
+				ann("bar", vec![ann("foo", vec![]), ParserVal::Integer(1)])
+					.check_annotations(&annotations)
+			));
+		expect!("Err(\"Unknown annotation 'baz'\")").assert_eq(&format!(
+			"{:?}",
+			ann("bar", vec![ParserVal::Integer(1), ann("baz", vec![])])
+				.check_annotations(&annotations)
+		));
+	}
+
+	#[test]
+	fn test_set_list_ranges() {
+		let set_of_int = Type::Set(OptType::NonOpt, Type::Integer(OptType::NonOpt).into());
+		let resolve = |members: &[i64]| {
+			ParserVal::SetList(members.iter().copied().map(ParserVal::Integer).collect())
+				.resolve_value(&set_of_int)
+				.unwrap()
+		};
+
+		// Dense sets are coalesced into a single range
+		let dense = (1..=100_000).rev().collect::<Vec<_>>();
+		assert_eq!(resolve(&dense), Value::Set(Set::Int(vec![1..=100_000])));
+		expect!("1..3 ∪ {5} ∪ 7..8").assert_eq(&resolve(&[8, 1, 3, 2, 7, 5, 2]).to_string());
+
+		// Non-contiguous sets are the same as a set constructed from singleton ranges
+		let sparse = [1, 3, 5, -7];
+		assert_eq!(
+			resolve(&sparse),
+			Value::Set(sparse.iter().map(|&i| i..=i).collect())
+		);
+	}
+}
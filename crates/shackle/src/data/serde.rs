@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{borrow::Cow, ops::Range, sync::Arc};
 
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
@@ -17,8 +17,20 @@ use crate::{
 #[derive(Clone)]
 pub(crate) struct SerdeValueVisitor<'a>(pub &'a Type);
 
+impl<'a> SerdeValueVisitor<'a> {
+	fn visit_cow<'de, E: Error>(self, v: Cow<'de, str>) -> Result<ParserVal<'de>, E> {
+		let ty = self.0;
+		match ty {
+			Type::Enum(_, _) => Ok(ParserVal::Enum(v, Vec::new())),
+			Type::String(_) => Ok(ParserVal::String(v)),
+			Type::Annotation(_) => Ok(ParserVal::Ann(v, Vec::new())),
+			_ => Err(Error::invalid_type(Unexpected::Str(&v), &self)),
+		}
+	}
+}
+
 impl<'de, 'a> Visitor<'de> for SerdeValueVisitor<'a> {
-	type Value = ParserVal;
+	type Value = ParserVal<'de>;
 
 	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(formatter, "a value of type \"{}\"", self.0)
@@ -59,20 +71,14 @@ impl<'de, 'a> Visitor<'de> for SerdeValueVisitor<'a> {
 	}
 
 	fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
-		self.visit_string(v.to_string())
+		self.visit_cow(Cow::Owned(v.to_string()))
 	}
 	fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
-		self.visit_str(v) // TODO: avoid copying when possible
+		self.visit_cow(Cow::Borrowed(v))
 	}
 
 	fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
-		let ty = self.0;
-		match ty {
-			Type::Enum(_, _) => Ok(ParserVal::Enum(v, Vec::new())),
-			Type::String(_) => Ok(ParserVal::String(v)),
-			Type::Annotation(_) => Ok(ParserVal::Ann(v, Vec::new())),
-			_ => Err(Error::invalid_type(Unexpected::Str(v.as_str()), &self)),
-		}
+		self.visit_cow(Cow::Owned(v))
 	}
 
 	fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
@@ -178,7 +184,8 @@ impl<'de, 'a> Visitor<'de> for SerdeValueVisitor<'a> {
 					let field = types.into_iter().next().unwrap().0.to_string();
 					return Err(Error::missing_field(Box::leak(field.into_boxed_str()))); // TODO: Can we avoid leaking memory here?
 				}
-				rec.sort_by(|x, y| x.0.cmp(&y.0));
+				// Fields are given in the order of the record type
+				rec.sort_by_key(|(k, _)| ty.iter().position(|(name, _)| name == k));
 				Ok(ParserVal::Record(rec))
 			}
 			Type::Set(_, ty) => {
@@ -223,7 +230,7 @@ impl<'de, 'a> Visitor<'de> for SerdeValueVisitor<'a> {
 }
 
 impl<'a, 'de> DeserializeSeed<'de> for SerdeValueVisitor<'a> {
-	type Value = ParserVal;
+	type Value = ParserVal<'de>;
 
 	fn deserialize<D: serde::Deserializer<'de>>(
 		self,
@@ -237,7 +244,7 @@ impl<'a, 'de> DeserializeSeed<'de> for SerdeValueVisitor<'a> {
 struct SerdeEnumVisitor;
 
 impl<'de> DeserializeSeed<'de> for SerdeEnumVisitor {
-	type Value = ParserVal;
+	type Value = ParserVal<'de>;
 
 	fn deserialize<D: serde::Deserializer<'de>>(
 		self,
@@ -248,7 +255,7 @@ impl<'de> DeserializeSeed<'de> for SerdeEnumVisitor {
 }
 
 impl<'de> Visitor<'de> for SerdeEnumVisitor {
-	type Value = ParserVal;
+	type Value = ParserVal<'de>;
 
 	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(formatter, "an enumerated type argument")
@@ -265,10 +272,13 @@ impl<'de> Visitor<'de> for SerdeEnumVisitor {
 	}
 
 	fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
-		Ok(ParserVal::Enum(v, Vec::new()))
+		Ok(ParserVal::Enum(Cow::Owned(v), Vec::new()))
 	}
 	fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
-		Ok(ParserVal::Enum(v.into(), Vec::new()))
+		Ok(ParserVal::Enum(Cow::Owned(v.into()), Vec::new()))
+	}
+	fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+		Ok(ParserVal::Enum(Cow::Borrowed(v), Vec::new()))
 	}
 
 	fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
@@ -302,15 +312,15 @@ impl<'de> Visitor<'de> for SerdeEnumVisitor {
 	}
 }
 
-struct SerdeArrayVisitor<'a> {
-	data: &'a mut Vec<ParserVal>,
+struct SerdeArrayVisitor<'a, 'de> {
+	data: &'a mut Vec<ParserVal<'de>>,
 	size: &'a mut Vec<i64>,
 	element: &'a Type,
 	dim: u8,
 	depth: u8,
 }
 
-impl<'a, 'de> DeserializeSeed<'de> for SerdeArrayVisitor<'a> {
+impl<'a, 'de> DeserializeSeed<'de> for SerdeArrayVisitor<'a, 'de> {
 	type Value = ();
 
 	fn deserialize<D: serde::Deserializer<'de>>(
@@ -321,7 +331,7 @@ impl<'a, 'de> DeserializeSeed<'de> for SerdeArrayVisitor<'a> {
 	}
 }
 
-impl<'de, 'a> Visitor<'de> for SerdeArrayVisitor<'a> {
+impl<'de, 'a> Visitor<'de> for SerdeArrayVisitor<'a, 'de> {
 	type Value = ();
 
 	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -477,7 +487,7 @@ pub(crate) struct SerdeFileVisitor<'a> {
 	pub(crate) enum_types: &'a FxHashMap<Arc<str>, Arc<Enum>>,
 }
 impl<'de, 'a> Visitor<'de> for SerdeFileVisitor<'a> {
-	type Value = Vec<(&'a Arc<str>, &'a Type, ParserVal)>;
+	type Value = Vec<(&'a Arc<str>, &'a Type, ParserVal<'de>)>;
 
 	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(formatter, "assignment mapping")
@@ -505,6 +515,92 @@ impl<'de, 'a> Visitor<'de> for SerdeFileVisitor<'a> {
 	}
 }
 
+/// Find the keys of the top-level object of a JSON data file, in the order in which they appear
+///
+/// Returns the name of each key, the byte range of its assignment (from the key to the end of the
+/// value), and the byte range of its value. The file must already have been parsed successfully.
+pub(crate) fn json_assignment_spans(src: &str) -> Vec<(String, Range<usize>, Range<usize>)> {
+	let bytes = src.as_bytes();
+	let skip_whitespace = |mut i: usize| {
+		while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+			i += 1;
+		}
+		i
+	};
+	// Get the index after the end of the string starting at `i`
+	let skip_string = |mut i: usize| {
+		i += 1;
+		while i < bytes.len() {
+			match bytes[i] {
+				b'\\' => i += 2,
+				b'"' => return i + 1,
+				_ => i += 1,
+			}
+		}
+		i
+	};
+	// Get the index after the end of the value starting at `i`
+	let skip_value = |mut i: usize| match bytes.get(i) {
+		Some(b'"') => skip_string(i),
+		Some(b'{' | b'[') => {
+			let mut depth = 0;
+			while i < bytes.len() {
+				match bytes[i] {
+					b'"' => {
+						i = skip_string(i);
+						continue;
+					}
+					b'{' | b'[' => depth += 1,
+					b'}' | b']' => {
+						depth -= 1;
+						if depth == 0 {
+							return i + 1;
+						}
+					}
+					_ => (),
+				}
+				i += 1;
+			}
+			i
+		}
+		_ => {
+			while i < bytes.len()
+				&& !matches!(bytes[i], b',' | b'}' | b']')
+				&& !bytes[i].is_ascii_whitespace()
+			{
+				i += 1;
+			}
+			i
+		}
+	};
+
+	let mut spans = Vec::new();
+	let mut i = skip_whitespace(0);
+	if bytes.get(i) != Some(&b'{') {
+		return spans;
+	}
+	i = skip_whitespace(i + 1);
+	while bytes.get(i) == Some(&b'"') {
+		let start = i;
+		i = skip_string(i);
+		let Ok(name) = serde_json::from_str::<String>(&src[start..i]) else {
+			break;
+		};
+		i = skip_whitespace(i);
+		if bytes.get(i) != Some(&b':') {
+			break;
+		}
+		let value_start = skip_whitespace(i + 1);
+		i = skip_value(value_start);
+		spans.push((name, start..i, value_start..i));
+		i = skip_whitespace(i);
+		if bytes.get(i) == Some(&b',') {
+			i = skip_whitespace(i + 1);
+		}
+	}
+	spans
+}
+
 struct SerdeSeqVisitor<X: Clone>(X);
 impl<'de, X: DeserializeSeed<'de> + Clone> DeserializeSeed<'de> for SerdeSeqVisitor<X> {
 	type Value = Vec<X::Value>;
@@ -836,7 +932,7 @@ mod tests {
 	use serde::Deserializer;
 	use shackle_compiler::file::SourceFile;
 
-	use super::SerdeFileVisitor;
+	use super::{json_assignment_spans, SerdeFileVisitor};
 	use crate::{Enum, Error, OptType, Type};
 
 	fn check_serialization(input: &str, ty: &Type, expected: &Expect) {
@@ -929,6 +1025,15 @@ mod tests {
 	#[test]
 	fn test_parse_absent() {
 		check_serialization("null", &Type::Integer(OptType::Opt), &expect!("<>"));
+		check_serialization(
+			"[1, null, 3]",
+			&Type::Array {
+				opt: OptType::NonOpt,
+				dim: [Type::Integer(OptType::NonOpt)].into(),
+				element: Box::new(Type::Integer(OptType::Opt)),
+			},
+			&expect!("[1, <>, 3]"),
+		);
 	}
 
 	#[test]
@@ -1026,7 +1131,7 @@ mod tests {
 					Type::Integer(OptType::NonOpt),
 				]),
 			),
-			&expect!("([1, 2], 3..3 ∪ 4..4, 5)"),
+			&expect!("([1, 2], 3..4, 5)"),
 		);
 		check_serialization(
 			"[1, [2, [4, 5]], 6]",
@@ -1064,12 +1169,12 @@ mod tests {
 		check_serialization(
 			"{\"set\": [1.0]}",
 			&Type::Set(OptType::NonOpt, Box::new(Type::Float(OptType::NonOpt))),
-			&expect!("1..1"),
+			&expect!("{1}"),
 		);
 		check_serialization(
 			"{\"set\": [1, 1.0]}",
 			&Type::Set(OptType::NonOpt, Box::new(Type::Float(OptType::NonOpt))),
-			&expect!("1..1"),
+			&expect!("{1}"),
 		);
 		check_serialization(
 			"{\"set\": [[1, 3]]}",
@@ -1132,7 +1237,20 @@ mod tests {
 					),
 				]),
 			),
-			&expect!("(a: 1..1 ∪ 2..2, b: (3.5, true), c: [<>])"),
+			&expect!("(a: 1..2, b: (3.5, true), c: [<>])"),
+		);
+		// Fields keep the order of the record type, which need not be sorted by name
+		check_serialization(
+			"{\"a\": 1, \"b\": 2.5, \"c\": true}",
+			&Type::Record(
+				OptType::NonOpt,
+				Arc::new([
+					(c.clone(), Type::Boolean(OptType::NonOpt)),
+					(a.clone(), Type::Integer(OptType::NonOpt)),
+					(b.clone(), Type::Float(OptType::NonOpt)),
+				]),
+			),
+			&expect!("(c: true, a: 1, b: 2.5)"),
 		);
 
 		check_serialization(
@@ -1274,4 +1392,26 @@ mod tests {
 			],
 		);
 	}
+
+	#[test]
+	fn test_json_assignment_spans() {
+		let src = r#"{ "a": 1, "b" : {"set": [[1, 3]]}, "c\"d": "x,}" , "e": [1, [2]] }"#;
+		let spans: Vec<_> = json_assignment_spans(src)
+			.into_iter()
+			.map(|(name, asg, value)| (name, &src[asg], &src[value]))
+			.collect();
+		assert_eq!(
+			spans,
+			[
+				("a".to_owned(), r#""a": 1"#, "1"),
+				(
+					"b".to_owned(),
+					r#""b" : {"set": [[1, 3]]}"#,
+					r#"{"set": [[1, 3]]}"#
+				),
+				("c\"d".to_owned(), r#""c\"d": "x,}""#, r#""x,}""#),
+				("e".to_owned(), r#""e": [1, [2]]"#, "[1, [2]]"),
+			]
+		);
+	}
 }
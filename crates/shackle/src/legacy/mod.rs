@@ -396,13 +396,10 @@ impl<'de, 'a> Visitor<'de> for SerdeMessageVisitor<'a> {
 					if solution.is_some() {
 						return Err(SerdeError::duplicate_field("output"));
 					}
-					match map.next_value_seed(SerdeWrappedName {
+					solution = Some(map.next_value_seed(SerdeWrappedName {
 						name: "json",
 						seed: SerdeOutputVisitor(type_map),
-					})? {
-						Ok(sol) => solution = Some(sol),
-						Err(e) => return Ok(LegacyOutput::Error(e)),
-					}
+					})?);
 				}
 				"statistics" => {
 					if statistics.is_some() {
@@ -486,7 +483,7 @@ impl<'de, 'a> Visitor<'de> for SerdeMessageVisitor<'a> {
 struct SerdeOutputVisitor<'a>(pub &'a FxHashMap<Arc<str>, Type>);
 
 impl<'de, 'a> Visitor<'de> for SerdeOutputVisitor<'a> {
-	type Value = Result<FxHashMap<&'de str, Value>, Error>;
+	type Value = FxHashMap<&'de str, Value>;
 
 	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(formatter, "minizinc output assignment")
@@ -501,23 +498,19 @@ impl<'de, 'a> Visitor<'de> for SerdeOutputVisitor<'a> {
 			if let Some(ty) = type_map.get(k) {
 				let out_type = ty.type_erase();
 				let v = map.next_value_seed(SerdeValueVisitor(&out_type))?;
-				match v.resolve_value(&out_type) {
-					Ok(v) => {
-						let v = v.reverse_type_erase(ty);
-						sol.insert(k, v);
-					}
-					Err(e) => return Ok(Err(e)),
-				}
+				// Report values which do not match their type as a data error at this position
+				let v = v.resolve_value(&out_type).map_err(A::Error::custom)?;
+				sol.insert(k, v.reverse_type_erase(ty));
 			} else {
 				map.next_value::<IgnoredAny>()?; // Ignore unknown
 			}
 		}
-		Ok(Ok(sol))
+		Ok(sol)
 	}
 }
 
 impl<'a, 'de> DeserializeSeed<'de> for SerdeOutputVisitor<'a> {
-	type Value = Result<FxHashMap<&'de str, Value>, Error>;
+	type Value = FxHashMap<&'de str, Value>;
 
 	fn deserialize<D: serde::Deserializer<'de>>(
 		self,
@@ -4,45 +4,55 @@
 #![warn(unused_crate_dependencies, unused_extern_crates)]
 #![warn(variant_size_differences)]
 
-mod data;
+pub mod data;
 mod legacy;
 mod value;
 
 use std::{
 	ffi::OsStr,
 	fmt::Display,
-	io::Write,
+	io::{BufRead, Read, Write},
 	ops::Deref,
 	path::{Path, PathBuf},
-	sync::Arc,
+	sync::{Arc, Mutex},
 	time::Duration,
 };
 
 use data::{
-	dzn::{collect_dzn_value, parse_dzn},
-	serde::SerdeFileVisitor,
+	dzn::{collect_dzn_value, parse_dzn, parse_dzn_stream, parse_mzn_data},
+	serde::{json_assignment_spans, SerdeFileVisitor},
+	AnnotationParameters, DataAssignment,
 };
 // Result type for Shackle operations
 pub use error::{Error, Result};
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::Deserializer;
+use serde::{de::IgnoredAny, Deserializer};
 // Export OptType enumeration used in [`Type`]
 pub use shackle_compiler::ty::OptType;
 use shackle_compiler::{
-	db::{CompilerDatabase, Inputs, InternedString, Interner},
+	db::{CompilerDatabase, CompilerSettings, Inputs, InternedString, Interner},
 	file::{InputFile, InputLang, SourceFile},
-	hir::db::Hir,
+	hir::{
+		self,
+		db::Hir,
+		ids::{ItemRef, LocalItemRef},
+		PrimitiveType,
+	},
 	syntax::{ast::AstNode, minizinc::Identifier},
 	thir::{self, db::Thir, pretty_print::PrettyPrinter, Declaration},
 	ty::{Ty, TyData},
+	utils::arena::ArenaIndex,
 };
-use value::EnumInner;
-pub use value::{Enum, Value};
+pub use value::{Enum, Index, Value, ValueBuilder};
+use value::{EnumInner, EnumRangeInclusive};
 
 /// Shackle errors
 pub mod error {
-	pub use shackle_compiler::{diagnostics::error::*, Result};
+	pub use shackle_compiler::{
+		diagnostics::{error::*, Diagnostics},
+		Result,
+	};
 }
 
 /// Shackle warnings
@@ -53,6 +63,8 @@ pub mod warning {
 /// Structure used to build a shackle model
 pub struct Model {
 	db: CompilerDatabase,
+	// Data given using the `-` path
+	stdin: StdinData,
 }
 
 impl Model {
@@ -61,23 +73,138 @@ impl Model {
 		let mut db = CompilerDatabase::default();
 		let l = InputLang::from_extension(path.extension());
 		db.set_input_files(Arc::new(vec![InputFile::Path(path, l)]));
-		Model { db }
+		Model {
+			db,
+			stdin: StdinData::default(),
+		}
+	}
+
+	/// Create a Model from the files at the given paths
+	pub fn from_files(paths: impl IntoIterator<Item = PathBuf>) -> Model {
+		let mut db = CompilerDatabase::default();
+		let files = paths
+			.into_iter()
+			.map(|path| {
+				let l = InputLang::from_extension(path.extension());
+				InputFile::Path(path, l)
+			})
+			.collect();
+		db.set_input_files(Arc::new(files));
+		Model {
+			db,
+			stdin: StdinData::default(),
+		}
 	}
 
 	/// Create a Model from the given string
 	pub fn from_string(m: String, l: InputLang) -> Model {
 		let mut db = CompilerDatabase::default();
 		db.set_input_files(Arc::new(vec![InputFile::String(m, l)]));
-		Model { db }
+		Model {
+			db,
+			stdin: StdinData::default(),
+		}
+	}
+
+	/// Set the language of the data read from the standard input stream when the [`STDIN_PATH`]
+	/// is given as a data file (DataZinc by default).
+	///
+	/// Only [`InputLang::DataZinc`] and [`InputLang::Json`] are supported. Other languages give an
+	/// error when the data is read.
+	pub fn with_stdin_lang(mut self, lang: InputLang) -> Self {
+		self.stdin.lang = lang;
+		self
+	}
+
+	/// Read the data for the [`STDIN_PATH`] from `reader` instead of the standard input stream
+	pub fn with_stdin_reader(mut self, reader: impl Read + Send + 'static) -> Self {
+		self.stdin.set_reader(reader);
+		self
 	}
 
 	/// Check whether a model contains any (non-runtime) errors
-	pub fn check(&self, _slv: &Solver, _data: &[PathBuf], _complete: bool) -> Vec<Error> {
-		// TODO: Check data files
-		self.db
-			.run_hir_phase()
-			.map(|_| Vec::new())
-			.unwrap_or_else(|e| e.iter().cloned().collect())
+	///
+	/// If `complete` is set, then enumerated types used as array index sets must also be defined
+	/// in the model or in the given data.
+	pub fn check(&self, _slv: &Solver, data: &[PathBuf], complete: bool) -> Vec<Error> {
+		self.check_with_data(data, complete)
+			.iter()
+			.cloned()
+			.collect()
+	}
+
+	/// Check that the given data files can be bound to the parameters of the model, without
+	/// compiling the model into a [`Program`].
+	///
+	/// The returned diagnostics contain both the errors in the model and the errors in the data.
+	/// If the model contains errors, then the data files are only checked for syntax errors.
+	/// Enumerated types used as array index sets must be defined in the model or in the given data.
+	/// The [`STDIN_PATH`] refers to the data read from the standard input stream (see
+	/// [`Model::with_stdin_lang`]).
+	pub fn check_data(&self, data: &[PathBuf]) -> Diagnostics<Error> {
+		self.check_with_data(data, true)
+	}
+
+	fn check_with_data(&self, data: &[PathBuf], complete: bool) -> Diagnostics<Error> {
+		let model_errors = self.db.run_hir_phase().err();
+		let mut diagnostics = model_errors.as_deref().cloned().unwrap_or_default();
+		let mut sources = Vec::with_capacity(data.len());
+		for path in data {
+			let source = if path.as_path() == Path::new(STDIN_PATH) {
+				self.stdin.source()
+			} else {
+				data_file_lang(path)
+					.and_then(|lang| Ok((SourceFile::try_from(path.as_path())?, lang)))
+			};
+			match source {
+				Ok(source) => sources.push(source),
+				Err(e) => diagnostics.push(e),
+			}
+		}
+
+		if model_errors.is_some() {
+			// The parameter types are unknown, so only the syntax of the data can be checked
+			for (src, lang) in sources {
+				let result = match lang {
+					InputLang::DataZinc => parse_dzn(&src).map(|_| ()),
+					InputLang::MiniZinc => parse_mzn_data(&src, &mut Vec::new()).map(|_| ()),
+					_ => serde_json::from_str::<IgnoredAny>(src.contents())
+						.map(|_| ())
+						.map_err(|err| Error::from_serde_json(err, &src)),
+				};
+				if let Err(e) = result {
+					diagnostics.push(e);
+				}
+			}
+			return diagnostics;
+		}
+
+		let ModelIoInterface {
+			input,
+			enums,
+			annotations,
+			..
+		} = ModelIoInterface::new(&self.db);
+		let mut input_data = FxHashMap::default();
+		let mut warnings = Vec::new();
+		for source in sources {
+			// Bind each file separately so an error in one file does not hide errors in the others
+			if let Err(e) = bind_data(
+				vec![source],
+				&input,
+				&enums,
+				&annotations,
+				&mut input_data,
+				&mut warnings,
+				self.db.allow_reversed_ranges(),
+			) {
+				diagnostics.push(e);
+			}
+		}
+		if complete && diagnostics.is_empty() {
+			diagnostics.extend(undefined_enum_index_sets(&self.db, &enums, &input_data));
+		}
+		diagnostics
 	}
 
 	/// Compile current model into a [`Program`] that can be used by the Shackle interpreter
@@ -90,6 +217,7 @@ impl Model {
 			input,
 			output,
 			enums,
+			annotations,
 		} = ModelIoInterface::new(&self.db);
 		let legacy_enums = enums
 			.iter()
@@ -111,14 +239,38 @@ impl Model {
 			input_types: input,
 			input_data: FxHashMap::default(),
 			enum_types: enums,
+			annotation_types: annotations,
 			legacy_enums,
 			output_types: output,
+			warnings: Vec::new(),
+			stdin: self.stdin,
 			enable_stats: false,
 			time_limit: None,
 		})
 	}
 }
 
+/// Compile the model made up of `model_paths` and bind the data in `data_paths` to its parameters.
+///
+/// Any errors in the model, or in the data (e.g. values that do not match the type of their
+/// declaration), are returned as [`Error`]. The bound values can be retrieved using
+/// [`Program::input_value`], and any warnings using [`Program::warnings`].
+pub fn compile_with_data(
+	model_paths: &[PathBuf],
+	data_paths: &[PathBuf],
+	slv: &Solver,
+) -> Result<Program> {
+	let model = Model::from_files(model_paths.iter().cloned());
+	let mut program = model.compile(slv)?;
+	program.add_data_files(data_paths.iter().map(|f| f.as_path()))?;
+	let errors = undefined_enum_index_sets(&program.db, &program.enum_types, &program.input_data);
+	// Converting fails only if there are no errors
+	if let Ok(e) = Error::try_from(errors.to_vec()) {
+		return Err(e);
+	}
+	Ok(program)
+}
+
 /// Solver specification to compile and solve Model instances.
 #[derive(Clone)]
 pub struct Solver {
@@ -147,16 +299,73 @@ pub struct Program {
 	input_types: FxHashMap<Arc<str>, Type>,
 	input_data: FxHashMap<Arc<str>, Value>,
 	enum_types: FxHashMap<Arc<str>, Arc<Enum>>,
+	annotation_types: AnnotationParameters,
 
 	// LEGACY: names of the enumerated types that have to be given to the legacy interpreter
 	legacy_enums: Vec<Arc<Enum>>,
 
 	output_types: FxHashMap<Arc<str>, Type>,
+	// Warnings emitted while adding data
+	warnings: Vec<warning::Warning>,
+	// Data given using the `-` path
+	stdin: StdinData,
 	// run() options
 	enable_stats: bool,
 	time_limit: Option<Duration>,
 }
 
+/// Path which refers to the standard input stream when given as a data file
+pub const STDIN_PATH: &str = "-";
+
+/// The standard input stream (or a stand-in for it), which is only read once
+struct StdinData {
+	// Reader used instead of the standard input stream
+	reader: Mutex<Option<Box<dyn Read + Send>>>,
+	// The contents of the stream once it has been read
+	contents: Mutex<Option<Arc<String>>>,
+	// The language used to parse the contents
+	lang: InputLang,
+}
+
+impl Default for StdinData {
+	fn default() -> Self {
+		Self {
+			reader: Mutex::new(None),
+			contents: Mutex::new(None),
+			lang: InputLang::DataZinc,
+		}
+	}
+}
+
+impl StdinData {
+	/// Read the data from `reader` instead of the standard input stream
+	fn set_reader(&mut self, reader: impl Read + Send + 'static) {
+		*self.reader.get_mut().unwrap() = Some(Box::new(reader));
+		*self.contents.get_mut().unwrap() = None;
+	}
+
+	/// Get the source file and language of the data, reading it on first use
+	fn source(&self) -> Result<(SourceFile, InputLang), Error> {
+		if !matches!(self.lang, InputLang::DataZinc | InputLang::Json) {
+			return Err(error::UnsupportedDataLanguage {
+				lang: format!("{:?}", self.lang),
+				input: "data read from the standard input stream".to_owned(),
+				supported: "DataZinc and JSON".to_owned(),
+			}
+			.into());
+		}
+		let mut contents = self.contents.lock().unwrap();
+		if contents.is_none() {
+			*contents = Some(match self.reader.lock().unwrap().as_mut() {
+				Some(reader) => read_stdin(reader)?,
+				None => read_stdin(&mut std::io::stdin())?,
+			});
+		}
+		let contents = contents.clone().unwrap();
+		Ok((SourceFile::stdin(contents), self.lang))
+	}
+}
+
 /// Status of running and solving a [`Program`]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Status {
@@ -312,6 +521,81 @@ impl Type {
 		}
 	}
 
+	/// Check whether values of type `found` can be assigned to a declaration of this type
+	///
+	/// Non-optional values can be assigned to optional declarations, Booleans and integers can be
+	/// coerced to floating point numbers (and Booleans to integers), and values of enumerated types
+	/// must belong to the same enumerated type. Returns the first incompatibility found if the
+	/// values cannot be assigned.
+	pub fn is_assignable_from(&self, found: &Type) -> Result<(), AssignmentMismatch> {
+		let mismatch = || {
+			Err(AssignmentMismatch {
+				expected: self.clone(),
+				found: found.clone(),
+			})
+		};
+		if found.is_opt() && !self.is_opt() {
+			return mismatch();
+		}
+		match (self, found) {
+			(Type::Boolean(_), Type::Boolean(_))
+			| (Type::Integer(_), Type::Boolean(_) | Type::Integer(_))
+			| (Type::Float(_), Type::Boolean(_) | Type::Integer(_) | Type::Float(_))
+			| (Type::String(_), Type::String(_))
+			| (Type::Annotation(_), Type::Annotation(_)) => Ok(()),
+			(Type::Enum(_, a), Type::Enum(_, b)) => {
+				if a.name() == b.name() {
+					Ok(())
+				} else {
+					mismatch()
+				}
+			}
+			(
+				Type::Array { dim, element, .. },
+				Type::Array {
+					dim: found_dim,
+					element: found_element,
+					..
+				},
+			) => {
+				if dim.len() != found_dim.len() {
+					return mismatch();
+				}
+				for (d, fd) in dim.iter().zip(found_dim.iter()) {
+					d.is_assignable_from(fd)?;
+				}
+				element.is_assignable_from(found_element)
+			}
+			(Type::Set(_, element), Type::Set(_, found_element)) => {
+				element.is_assignable_from(found_element)
+			}
+			(Type::Tuple(_, members), Type::Tuple(_, found_members)) => {
+				if members.len() != found_members.len() {
+					return mismatch();
+				}
+				members
+					.iter()
+					.zip(found_members.iter())
+					.try_for_each(|(m, fm)| m.is_assignable_from(fm))
+			}
+			(Type::Record(_, fields), Type::Record(_, found_fields)) => {
+				if fields.len() != found_fields.len()
+					|| fields
+						.iter()
+						.zip(found_fields.iter())
+						.any(|((a, _), (b, _))| a != b)
+				{
+					return mismatch();
+				}
+				fields
+					.iter()
+					.zip(found_fields.iter())
+					.try_for_each(|((_, f), (_, ff))| f.is_assignable_from(ff))
+			}
+			_ => mismatch(),
+		}
+	}
+
 	fn is_opt(&self) -> bool {
 		matches!(
 			self,
@@ -364,6 +648,21 @@ impl Display for Type {
 	}
 }
 
+/// The first incompatibility found by [`Type::is_assignable_from`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssignmentMismatch {
+	/// The type that was expected at the point of the incompatibility
+	pub expected: Type,
+	/// The type that was found instead
+	pub found: Type,
+}
+
+impl Display for AssignmentMismatch {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Expected '{}' but found '{}'", self.expected, self.found)
+	}
+}
+
 /// Intermediate messages emitted by shackle in processing and solving a program
 #[derive(Debug)]
 pub enum Message<'a> {
@@ -409,6 +708,178 @@ impl Program {
 		self.time_limit = Some(dur);
 		self
 	}
+	/// Set whether reversed ranges (e.g. `5..1`) are allowed in data, in which case they are empty
+	/// sets and a warning is emitted (the default), or whether they are an error
+	pub fn with_reversed_ranges(mut self, allow: bool) -> Self {
+		self.db.set_allow_reversed_ranges(allow);
+		self
+	}
+	/// Set the language of the data read from the standard input stream when the [`STDIN_PATH`]
+	/// is given as a data file (see [`Model::with_stdin_lang`]).
+	pub fn with_stdin_lang(mut self, lang: InputLang) -> Self {
+		self.stdin.lang = lang;
+		self
+	}
+	/// Read the data for the [`STDIN_PATH`] from `reader` instead of the standard input stream
+	pub fn with_stdin_reader(mut self, reader: impl Read + Send + 'static) -> Self {
+		self.stdin.set_reader(reader);
+		self
+	}
+	/// Warnings emitted while adding data to the program
+	pub fn warnings(&self) -> &[warning::Warning] {
+		&self.warnings
+	}
+	/// Get the value bound to the parameter with the given name, if any
+	pub fn input_value(&self, name: &str) -> Option<&Value> {
+		self.input_data.get(name)
+	}
+	/// Get the declared type of the top-level variable with the given name
+	pub fn global_variable_type(&self, name: &str) -> Option<Type> {
+		let ty = self
+			.db
+			.global_variable_type(shackle_compiler::hir::Identifier::new(name, &self.db))?;
+		let mut resolve_name = |s: InternedString| -> Arc<str> { Arc::from(s.value(&self.db)) };
+		Some(Type::from_compiler(
+			&self.db,
+			&mut resolve_name,
+			&mut FxHashMap::default(),
+			&self.enum_types,
+			ty,
+		))
+	}
+	/// Get the names and types of the top-level decision variables, in declaration order
+	///
+	/// This includes every top-level `var` declaration (whether or not it has a definition), but
+	/// not parameters or functions.
+	pub fn decision_variables(&self) -> Vec<(Arc<str>, Type)> {
+		let mut resolve_name = |s: InternedString| -> Arc<str> { Arc::from(s.value(&self.db)) };
+		let mut type_map = FxHashMap::default();
+		self.db
+			.decision_variables()
+			.iter()
+			.map(|(i, ty)| {
+				(
+					Arc::from(i.lookup(&self.db)),
+					Type::from_compiler(
+						&self.db,
+						&mut resolve_name,
+						&mut type_map,
+						&self.enum_types,
+						*ty,
+					),
+				)
+			})
+			.collect()
+	}
+	/// Get the declared domain of the top-level variable with the given name as a set
+	///
+	/// Parameters used in the domain are resolved using the bound data, so this should be called
+	/// after adding data. Unbounded `int` and `float` variables give the full range
+	/// `i64::MIN..=i64::MAX` or `-∞..=+∞` respectively, as sets cannot have infinite integer
+	/// bounds.
+	///
+	/// The domain is not evaluated by the compiler. Only literals, sets of integer literals,
+	/// ranges (`a..b`), negation, enumerated types and parameters are supported, where the
+	/// parameters must be bound by data or defined using these same expressions. Returns `None`
+	/// if the domain uses any other expression (e.g. the addition in `1..n+1`), if it depends on a
+	/// parameter which has not been bound, or if there is no such variable.
+	pub fn variable_domain(&self, name: &str) -> Option<Value> {
+		let pattern = self
+			.db
+			.lookup_global_variable(hir::Identifier::new(name, &self.db))?;
+		let item = pattern.item();
+		let LocalItemRef::Declaration(d) = item.local_item_ref(&self.db) else {
+			return None;
+		};
+		let model = item.model(&self.db);
+		let data = item.local_item_ref(&self.db).data(&model);
+		match &data[model[d].declared_type] {
+			hir::Type::Primitive {
+				primitive_type: PrimitiveType::Int,
+				..
+			} => Some(Value::Set((i64::MIN..=i64::MAX).into())),
+			hir::Type::Primitive {
+				primitive_type: PrimitiveType::Float,
+				..
+			} => Some(Value::Set((f64::NEG_INFINITY..=f64::INFINITY).into())),
+			hir::Type::Bounded { domain, .. } => match self.evaluate_domain(item, *domain)? {
+				v @ Value::Set(_) => Some(v),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+	/// Evaluate a par expression used in the domain of a top-level declaration, giving `None` for
+	/// unsupported expressions (see [`Program::variable_domain`])
+	fn evaluate_domain(
+		&self,
+		item: ItemRef,
+		expression: ArenaIndex<hir::Expression>,
+	) -> Option<Value> {
+		let model = item.model(&self.db);
+		let data = item.local_item_ref(&self.db).data(&model);
+		match &data[expression] {
+			hir::Expression::IntegerLiteral(i) => Some(Value::Integer(i.0)),
+			hir::Expression::FloatLiteral(f) => Some(Value::Float(f.value())),
+			hir::Expression::SetLiteral(sl) => {
+				let members = sl
+					.members
+					.iter()
+					.map(|m| match self.evaluate_domain(item, *m)? {
+						Value::Integer(i) => Some(i),
+						_ => None,
+					})
+					.collect::<Option<Vec<_>>>()?;
+				Some(ValueBuilder::set_of_ints(members))
+			}
+			hir::Expression::Identifier(i) => {
+				let name = i.lookup(&self.db);
+				let p = self
+					.db
+					.lookup_item_types(item)
+					.name_resolution(expression)?;
+				match p.item().local_item_ref(&self.db) {
+					LocalItemRef::Enumeration(_) => {
+						let e = self.enum_types.get(name.as_str())?;
+						if !matches!(*e.state.lock().unwrap(), EnumInner::Constructors(_)) {
+							return None;
+						}
+						let range =
+							EnumRangeInclusive::from_enum_and_positions(e.clone(), 1, e.len());
+						Some(Value::Set(range.into()))
+					}
+					LocalItemRef::Declaration(d) => match p.item().model(&self.db)[d].definition {
+						Some(definition) => self.evaluate_domain(p.item(), definition),
+						None => self.input_data.get(name.as_str()).cloned(),
+					},
+					_ => None,
+				}
+			}
+			hir::Expression::Call(c) => {
+				let ids = self.db.identifier_registry();
+				let hir::Expression::Identifier(f) = data[c.function] else {
+					return None;
+				};
+				let args = c
+					.arguments
+					.iter()
+					.map(|a| self.evaluate_domain(item, *a))
+					.collect::<Option<Vec<_>>>()?;
+				match args[..] {
+					[Value::Integer(v)] if f == ids.minus => Some(Value::Integer(v.checked_neg()?)),
+					[Value::Float(v)] if f == ids.minus => Some(Value::Float(-v)),
+					[Value::Integer(a), Value::Integer(b)] if f == ids.dot_dot => {
+						Some(Value::Set((a..=b).into()))
+					}
+					[Value::Float(a), Value::Float(b)] if f == ids.dot_dot => {
+						Some(Value::Set((a..=b).into()))
+					}
+					_ => None,
+				}
+			}
+			_ => None,
+		}
+	}
 	/// Output the [`Program`] using the given output interface, using the [`Write`] trait
 	pub fn write<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
 		let printer = PrettyPrinter::new_compat(&self.db, &self.code);
@@ -416,114 +887,280 @@ impl Program {
 	}
 
 	/// Add and parse data to be used by the program.
+	///
+	/// Only the assignment items of MiniZinc (`.mzn`) files are used, and a warning is emitted for
+	/// any other items. The [`STDIN_PATH`] refers to the whole of the standard input stream (see
+	/// [`Program::with_stdin_lang`]).
 	pub fn add_data_files<'a>(
 		&mut self,
 		files: impl Iterator<Item = &'a Path>,
 	) -> Result<(), Error> {
-		// First parse all files:
-		// - most values will be simple values that can be directly assigned
-		// - some values will be values of enumerated types, possible part of tuples, records, or indices.
-		// - files can also contain the constructors for enumerated types.
-		let mut data = Vec::new();
-		let mut names = FxHashSet::default();
+		let mut sources = Vec::new();
 		for f in files {
-			let src = SourceFile::try_from(f)?;
-			match f.extension().and_then(OsStr::to_str) {
-				Some("dzn") => {
-					// Parse the DZN file
-					let assignments = parse_dzn(&src)?;
-					data.reserve(assignments.len());
-					names.reserve(assignments.len());
-					// Match the parser
-					for asg in assignments {
-						let ident = asg.assignee().cast::<Identifier>().unwrap();
-						if let Some((k, ty)) = self.input_types.get_key_value::<str>(&ident.name())
-						{
-							let val = collect_dzn_value(&src, &asg.definition(), ty)?;
-							data.push((k, ty, val));
-							// Identifier already seen
-							if names.contains(k) || self.input_data.contains_key(k) {
-								return Err(error::IdentifierAlreadyDefined {
-									src,
-									span: asg.cst_node().as_ref().byte_range().into(),
-									identifier: k.to_string(),
-								}
-								.into());
-							}
-							names.insert(k);
-						} else if let Some((k, e)) =
-							self.enum_types.get_key_value::<str>(&ident.name())
-						{
-							let mut inner = e.state.lock().unwrap();
-							if matches!(*inner, EnumInner::NoDefinition) {
-								(*inner).collect_definition(&src, &asg.definition())?
-							} else {
-								return Err(error::IdentifierAlreadyDefined {
-									src,
-									span: asg.cst_node().as_ref().byte_range().into(),
-									identifier: k.to_string(),
-								}
-								.into());
+			if f == Path::new(STDIN_PATH) {
+				sources.push(self.stdin.source()?);
+			} else {
+				let lang = data_file_lang(f)?;
+				sources.push((SourceFile::try_from(f)?, lang));
+			}
+		}
+		self.add_data(sources)
+	}
+
+	/// Add and parse data given as a string to be used by the program.
+	///
+	/// Gives an error if `lang` is not [`InputLang::DataZinc`], [`InputLang::Json`], or
+	/// [`InputLang::MiniZinc`] (of which only the assignment items are used).
+	pub fn add_data_str(&mut self, data: &str, lang: InputLang) -> Result<(), Error> {
+		if !matches!(
+			lang,
+			InputLang::DataZinc | InputLang::Json | InputLang::MiniZinc
+		) {
+			return Err(error::UnsupportedDataLanguage {
+				lang: format!("{:?}", lang),
+				input: "data".to_owned(),
+				supported: "DataZinc, JSON and MiniZinc".to_owned(),
+			}
+			.into());
+		}
+		self.add_data(vec![(SourceFile::from(Arc::new(data.to_owned())), lang)])
+	}
+
+	/// Add and parse DataZinc data read from `reader`, one assignment at a time.
+	///
+	/// This avoids holding the text of (very) large data files in memory. Each value is resolved as
+	/// soon as its assignment has been parsed, so any enumerated types used must already be defined.
+	pub fn add_data_stream<R: BufRead>(&mut self, reader: R) -> Result<(), Error> {
+		let allow_reversed = self.db.allow_reversed_ranges();
+		let mut values = Vec::new();
+		for asg in parse_dzn_stream(
+			reader,
+			&self.input_types,
+			&self.annotation_types,
+			&self.input_data,
+		) {
+			let asg = asg?;
+			let key = asg.name.clone();
+			values.push((
+				key,
+				asg.resolve(&self.annotation_types, allow_reversed, &mut self.warnings)?,
+			));
+		}
+		self.input_data.extend(values);
+		Ok(())
+	}
+
+	fn add_data(&mut self, sources: Vec<(SourceFile, InputLang)>) -> Result<(), Error> {
+		bind_data(
+			sources,
+			&self.input_types,
+			&self.enum_types,
+			&self.annotation_types,
+			&mut self.input_data,
+			&mut self.warnings,
+			self.db.allow_reversed_ranges(),
+		)
+	}
+}
+
+/// Read the whole of `reader` as the data given on the standard input stream
+fn read_stdin(reader: &mut dyn Read) -> Result<Arc<String>, Error> {
+	let mut contents = String::new();
+	reader
+		.read_to_string(&mut contents)
+		.map_err(|err| error::FileError {
+			file: STDIN_PATH.into(),
+			message: err.to_string(),
+			other: vec![],
+		})?;
+	Ok(Arc::new(contents))
+}
+
+/// Get the input language of a data file from its extension
+fn data_file_lang(path: &Path) -> Result<InputLang, Error> {
+	let mut extension = path.extension();
+	if extension == Some(OsStr::new("gz")) {
+		// Compressed data files use the extension of the uncompressed file (e.g. `data.dzn.gz`)
+		extension = path
+			.file_stem()
+			.and_then(|stem| Path::new(stem).extension());
+	}
+	match extension.and_then(OsStr::to_str) {
+		Some("dzn") => Ok(InputLang::DataZinc),
+		Some("json") => Ok(InputLang::Json),
+		Some("mzn") => Ok(InputLang::MiniZinc),
+		_ => Err(error::FileError {
+			file: path.into(),
+			message: format!(
+				"Attempting to read data file using unknown extension \"{}\"",
+				path.display()
+			),
+			other: vec![],
+		}
+		.into()),
+	}
+}
+
+/// Get errors for the enumerated types used as array index sets which are defined neither in the
+/// model, nor by the bound data.
+fn undefined_enum_index_sets(
+	db: &CompilerDatabase,
+	enum_types: &FxHashMap<Arc<str>, Arc<Enum>>,
+	input_data: &FxHashMap<Arc<str>, Value>,
+) -> Arc<Vec<Error>> {
+	let defined_enums = enum_types
+		.iter()
+		.filter(|(_, e)| e.state.lock().unwrap().deref() != &EnumInner::NoDefinition)
+		.map(|(name, _)| name);
+	let assigned = input_data
+		.keys()
+		.chain(defined_enums)
+		.map(|name| hir::Identifier::new(name.as_ref(), db))
+		.collect();
+	db.undefined_enum_index_sets(Arc::new(assigned))
+}
+
+/// Parse the given data sources, and bind their values to the parameters in `input_types`.
+///
+/// Enumerated types defined in the data are added to their entry in `enum_types`, and warnings
+/// for any ignored items are added to `warnings`.
+fn bind_data(
+	sources: Vec<(SourceFile, InputLang)>,
+	input_types: &FxHashMap<Arc<str>, Type>,
+	enum_types: &FxHashMap<Arc<str>, Arc<Enum>>,
+	annotation_types: &AnnotationParameters,
+	input_data: &mut FxHashMap<Arc<str>, Value>,
+	warnings: &mut Vec<warning::Warning>,
+	allow_reversed: bool,
+) -> Result<(), Error> {
+	// First parse all files:
+	// - most values will be simple values that can be directly assigned
+	// - some values will be values of enumerated types, possible part of tuples, records, or indices.
+	// - files can also contain the constructors for enumerated types.
+	let mut data = Vec::new();
+	let mut names = FxHashSet::default();
+	for (src, lang) in sources.iter() {
+		match lang {
+			InputLang::DataZinc | InputLang::MiniZinc => {
+				// Parse the DZN file, or the assignment items of the MZN file
+				let assignments = if *lang == InputLang::DataZinc {
+					parse_dzn(src)?
+				} else {
+					parse_mzn_data(src, warnings)?
+				};
+				data.reserve(assignments.len());
+				names.reserve(assignments.len());
+				// Match the parser
+				for asg in assignments {
+					let ident = asg.assignee().cast::<Identifier>().unwrap();
+					if let Some((k, ty)) = input_types.get_key_value::<str>(&ident.name()) {
+						let span = asg.definition().cst_node().as_ref().byte_range();
+						let val = collect_dzn_value(src, &asg.definition(), ty)?;
+						val.check_annotations(annotation_types).map_err(|msg| {
+							error::TypeMismatch {
+								src: src.clone(),
+								msg,
+								span: span.clone().into(),
 							}
-						} else {
-							// Unknown identifier
-							return Err(error::UndefinedIdentifier {
-								src,
-								span: ident.cst_node().as_ref().byte_range().into(),
-								identifier: ident.name().to_string(),
+						})?;
+						data.push(DataAssignment {
+							name: k,
+							ty,
+							value: val,
+							src: src.clone(),
+							span,
+						});
+						// Identifier already seen
+						if names.contains(k) || input_data.contains_key(k) {
+							return Err(error::IdentifierAlreadyDefined {
+								src: src.clone(),
+								span: asg.cst_node().as_ref().byte_range().into(),
+								identifier: k.to_string(),
 							}
 							.into());
 						}
-					}
-				}
-				Some("json") => {
-					let assignments = serde_json::Deserializer::from_str(src.contents())
-						.deserialize_map(SerdeFileVisitor {
-							input_types: &self.input_types,
-							enum_types: &self.enum_types,
-						})
-						.map_err(|err| Error::from_serde_json(err, &src))?;
-
-					data.reserve(assignments.len());
-					names.reserve(assignments.len());
-					for asg in assignments {
-						// Identifier already seen
-						if names.contains(asg.0) || self.input_data.contains_key(asg.0) {
+						names.insert(k);
+					} else if let Some((k, e)) = enum_types.get_key_value::<str>(&ident.name()) {
+						let mut inner = e.state.lock().unwrap();
+						if matches!(*inner, EnumInner::NoDefinition) {
+							(*inner).collect_definition(src, &asg.definition())?
+						} else {
 							return Err(error::IdentifierAlreadyDefined {
-								src,
-								span: (0, 0).into(), // TODO: actual byte range
-								identifier: asg.0.to_string(),
+								src: src.clone(),
+								span: asg.cst_node().as_ref().byte_range().into(),
+								identifier: k.to_string(),
 							}
 							.into());
 						}
-						names.insert(asg.0);
-						data.push(asg);
+					} else {
+						// Unknown identifier
+						return Err(error::UndefinedIdentifier {
+							src: src.clone(),
+							span: ident.cst_node().as_ref().byte_range().into(),
+							identifier: ident.name().to_string(),
+						}
+						.into());
 					}
 				}
-				_ => {
-					return Err(error::FileError {
-						file: f.into(),
-						message: format!(
-							"Attempting to read data file using unknown extension \"{}\"",
-							f.display()
-						),
-						other: vec![],
+			}
+			InputLang::Json => {
+				let assignments = serde_json::Deserializer::from_str(src.contents())
+					.deserialize_map(SerdeFileVisitor {
+						input_types,
+						enum_types,
+					})
+					.map_err(|err| Error::from_serde_json(err, src))?;
+
+				// The assignments are in the same order as the keys of the file
+				let mut spans = json_assignment_spans(src.contents()).into_iter();
+				data.reserve(assignments.len());
+				names.reserve(assignments.len());
+				for asg in assignments {
+					let (asg_span, span) = spans
+						.find(|(name, _, _)| name.as_str() == &**asg.0)
+						.map(|(_, asg_span, span)| (asg_span, span))
+						.unwrap_or_default();
+					asg.2.check_annotations(annotation_types).map_err(|msg| {
+						error::TypeMismatch {
+							src: src.clone(),
+							msg,
+							span: span.clone().into(),
+						}
+					})?;
+					// Identifier already seen
+					if names.contains(asg.0) || input_data.contains_key(asg.0) {
+						return Err(error::IdentifierAlreadyDefined {
+							src: src.clone(),
+							span: asg_span.into(),
+							identifier: asg.0.to_string(),
+						}
+						.into());
 					}
-					.into());
+					names.insert(asg.0);
+					data.push(DataAssignment {
+						name: asg.0,
+						ty: asg.1,
+						value: asg.2,
+						src: src.clone(),
+						span,
+					});
 				}
-			};
-		}
-		// Topologically sort the constructors to allow us to resolve the dependencies
-		// data.sort_by(|_a, _b| todo!());
-
-		// Itererate between initializing the enumerated types and creating the final values for the interpreter
-		for (key, ty, val) in data {
-			let _none = self.input_data.insert(key.clone(), val.resolve_value(ty)?);
-			debug_assert_eq!(_none, None);
-		}
+			}
+			_ => unreachable!("{:?} is not a data input language", lang),
+		};
+	}
+	// Topologically sort the constructors to allow us to resolve the dependencies
+	// data.sort_by(|_a, _b| todo!());
 
-		Ok(())
+	// Itererate between initializing the enumerated types and creating the final values for the interpreter
+	for asg in data {
+		let key = asg.name.clone();
+		let val = asg.resolve(annotation_types, allow_reversed, warnings)?;
+		let _none = input_data.insert(key, val);
+		debug_assert_eq!(_none, None);
 	}
+
+	Ok(())
 }
 
 /// Get a mapping from input/output identifiers to their computed types or enumerated type declaration
@@ -532,6 +1169,7 @@ struct ModelIoInterface {
 	pub input: FxHashMap<Arc<str>, crate::Type>,
 	pub output: FxHashMap<Arc<str>, crate::Type>,
 	pub enums: FxHashMap<Arc<str>, Arc<crate::Enum>>,
+	pub annotations: AnnotationParameters,
 }
 
 impl ModelIoInterface {
@@ -607,13 +1245,462 @@ impl ModelIoInterface {
 			}
 		}
 
+		// Collect the parameter types of the annotations which can be given in data
+		let mut annotations: AnnotationParameters = FxHashMap::default();
+		for (_, ann) in model.annotations() {
+			let Some(name) = ann.name else { continue };
+			let params = ann
+				.parameters
+				.iter()
+				.flatten()
+				.map(|p| {
+					let ty = model[*p].domain().ty();
+					// Only simple parameter types can be given in data (not type-inst vars)
+					matches!(
+						ty.lookup(db.upcast()),
+						TyData::Boolean(..)
+							| TyData::Integer(..) | TyData::Float(..)
+							| TyData::String(..) | TyData::Annotation(..)
+							| TyData::Enum(..)
+					)
+					.then(|| {
+						crate::Type::from_compiler(
+							db.upcast(),
+							&mut resolve_name,
+							&mut type_map,
+							&enums,
+							ty,
+						)
+					})
+				})
+				.collect();
+			annotations
+				.entry(resolve_name(name.0))
+				.or_default()
+				.push(params);
+		}
+
 		ModelIoInterface {
 			input,
 			output,
 			enums,
+			annotations,
 		}
 	}
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+	use std::{
+		fs,
+		io::{Cursor, Write},
+		path::{Path, PathBuf},
+	};
+
+	use expect_test::expect;
+	use flate2::{write::GzEncoder, Compression};
+
+	use shackle_compiler::file::InputLang;
+
+	use crate::{
+		compile_with_data, warning::Warning, Error, Model, OptType, Solver, Type, Value, STDIN_PATH,
+	};
+
+	#[test]
+	fn test_is_assignable_from() {
+		let int = Type::Integer(OptType::NonOpt);
+		let float = Type::Float(OptType::NonOpt);
+		let array = |element: &Type| Type::Array {
+			opt: OptType::NonOpt,
+			dim: [int.clone()].into(),
+			element: Box::new(element.clone()),
+		};
+		let set = |element: &Type| Type::Set(OptType::NonOpt, Box::new(element.clone()));
+
+		// Coercions
+		assert_eq!(array(&float).is_assignable_from(&array(&int)), Ok(()));
+		assert_eq!(set(&float).is_assignable_from(&set(&int)), Ok(()));
+		assert_eq!(Type::Integer(OptType::Opt).is_assignable_from(&int), Ok(()));
+
+		// Rejections
+		let err = int.is_assignable_from(&set(&int)).unwrap_err();
+		expect!("Expected 'int' but found 'set of int'").assert_eq(&err.to_string());
+		let err = array(&int).is_assignable_from(&array(&float)).unwrap_err();
+		expect!("Expected 'int' but found 'float'").assert_eq(&err.to_string());
+		let err = int
+			.is_assignable_from(&Type::Integer(OptType::Opt))
+			.unwrap_err();
+		expect!("Expected 'int' but found 'opt int'").assert_eq(&err.to_string());
+	}
+
+	#[test]
+	fn test_global_variable_type() {
+		let model = Model::from_string(
+			"array [1..3] of var int: x;".to_owned(),
+			InputLang::MiniZinc,
+		);
+		let program = model.compile(&Solver::lookup("gecode").unwrap()).unwrap();
+		assert_eq!(
+			program.global_variable_type("x"),
+			Some(Type::Array {
+				opt: OptType::NonOpt,
+				dim: [Type::Integer(OptType::NonOpt)].into(),
+				element: Box::new(Type::Integer(OptType::NonOpt)),
+			})
+		);
+		assert_eq!(program.global_variable_type("y"), None);
+	}
+
+	#[test]
+	fn test_decision_variables() {
+		let model = Model::from_string(
+			r#"
+			int: n;
+			var 1..n: x;
+			bool: b = true;
+			var bool: c = x > 1;
+			function var int: f(var int: a) = a;
+			"#
+			.to_owned(),
+			InputLang::MiniZinc,
+		);
+		let program = model.compile(&Solver::lookup("gecode").unwrap()).unwrap();
+		assert_eq!(
+			program.decision_variables(),
+			[
+				("x".into(), Type::Integer(OptType::NonOpt)),
+				("c".into(), Type::Boolean(OptType::NonOpt)),
+			]
+		);
+	}
+
+	#[test]
+	fn test_variable_domain() {
+		let model = Model::from_string(
+			r#"
+			int: n;
+			int: m = -n;
+			var 1..n: x;
+			var m..n: y;
+			var {1, 3, 4}: z;
+			var int: w;
+			var float: g;
+			var 1..n+1: u;
+			enum E;
+			var E: e;
+			var bool: b;
+			"#
+			.to_owned(),
+			InputLang::MiniZinc,
+		);
+		let mut program = model.compile(&Solver::lookup("gecode").unwrap()).unwrap();
+		assert_eq!(program.variable_domain("x"), None);
+		program
+			.add_data_str("n = 10; E = {A, B, C};", InputLang::DataZinc)
+			.unwrap();
+		let domain = |name: &str| program.variable_domain(name).unwrap().to_string();
+		assert_eq!(domain("x"), "1..10");
+		assert_eq!(domain("y"), "-10..10");
+		assert_eq!(domain("z"), "{1} ∪ 3..4");
+		assert_eq!(domain("e"), "A..C");
+		assert_eq!(
+			program.variable_domain("w"),
+			Some(Value::Set((i64::MIN..=i64::MAX).into()))
+		);
+		assert_eq!(
+			program.variable_domain("g"),
+			Some(Value::Set((f64::NEG_INFINITY..=f64::INFINITY).into()))
+		);
+		// Arithmetic is not evaluated
+		assert_eq!(program.variable_domain("u"), None);
+		assert_eq!(program.variable_domain("b"), None);
+		assert_eq!(program.variable_domain("f"), None);
+	}
+
+	#[test]
+	fn test_compile_with_data() {
+		let dir = tempfile::tempdir().unwrap();
+		let model = dir.path().join("model.mzn");
+		let data = dir.path().join("data.dzn");
+		let bad_data = dir.path().join("bad_data.dzn");
+		fs::write(&model, "int: n;\narray [1..n] of var 0..n: x;").unwrap();
+		fs::write(&data, "n = 3;").unwrap();
+		fs::write(&bad_data, "n = 1.5;").unwrap();
+
+		let slv = Solver::lookup("gecode").unwrap();
+		let program = compile_with_data(&[model.clone()], &[data], &slv).unwrap();
+		assert_eq!(program.input_value("n"), Some(&Value::Integer(3)));
+		assert_eq!(program.input_value("x"), None);
+		assert!(program.warnings().is_empty());
+
+		let err = compile_with_data(&[model], &[bad_data], &slv)
+			.err()
+			.unwrap();
+		assert!(matches!(err, Error::TypeMismatch(_)));
+	}
+
+	#[test]
+	fn test_undefined_enum_index_sets() {
+		let dir = tempfile::tempdir().unwrap();
+		let model = dir.path().join("model.mzn");
+		let data = dir.path().join("data.dzn");
+		fs::write(&model, "enum E;\narray [E] of int: a;").unwrap();
+		fs::write(&data, "E = {A, B};\na = [1, 2];").unwrap();
+
+		let diagnostics = Model::from_file(model.clone()).check_data(&[data.clone()]);
+		assert!(diagnostics.is_empty());
+		let diagnostics = Model::from_file(model.clone()).check_data(&[]);
+		assert_eq!(diagnostics.len(), 1);
+		assert!(matches!(
+			diagnostics.iter().next(),
+			Some(Error::UndefinedEnumIndexSet(_))
+		));
+
+		// The data may be given later if it is not complete
+		let slv = Solver::lookup("gecode").unwrap();
+		assert!(Model::from_file(model.clone())
+			.check(&slv, &[], false)
+			.is_empty());
+
+		compile_with_data(&[model.clone()], &[data], &slv).unwrap();
+		let err = compile_with_data(&[model], &[], &slv).err().unwrap();
+		assert!(matches!(err, Error::UndefinedEnumIndexSet(_)));
+	}
+
+	#[test]
+	fn test_add_data_str() {
+		let model = Model::from_string(
+			"int: n;\nbool: b;\nfloat: f;".to_owned(),
+			InputLang::MiniZinc,
+		);
+		let mut program = model.compile(&Solver::lookup("gecode").unwrap()).unwrap();
+		program.add_data_str("n = 3;", InputLang::DataZinc).unwrap();
+		program
+			.add_data_str(r#"{"b": true}"#, InputLang::Json)
+			.unwrap();
+		program
+			.add_data_str("f = 1.5;", InputLang::MiniZinc)
+			.unwrap();
+		assert_eq!(program.input_value("n"), Some(&Value::Integer(3)));
+		assert_eq!(program.input_value("b"), Some(&Value::Boolean(true)));
+		assert_eq!(program.input_value("f"), Some(&Value::Float(1.5)));
+
+		let Err(Error::UnsupportedDataLanguage(err)) =
+			program.add_data_str("letting n = 3", InputLang::EPrime)
+		else {
+			panic!("expected unsupported data language error")
+		};
+		assert_eq!(err.lang, "EPrime");
+		let err = program
+			.add_data_str("n = 1.5;", InputLang::DataZinc)
+			.err()
+			.unwrap();
+		assert!(matches!(err, Error::TypeMismatch(_)));
+	}
+
+	#[test]
+	fn test_annotation_data() {
+		let model = || {
+			Model::from_string(
+				"enum Color = {Red, Green};
+				annotation a_enum(Color: c);
+				annotation a_bool(bool: b);
+				annotation a_float(float: f);
+				annotation a_string(string: s);
+				annotation a_ann(ann: a);
+				annotation a_int(int: i);
+				ann: e; ann: b; ann: f; ann: s; ann: a;"
+					.to_owned(),
+				InputLang::MiniZinc,
+			)
+		};
+		let slv = Solver::lookup("gecode").unwrap();
+		let mut program = model().compile(&slv).unwrap();
+		program
+			.add_data_str(
+				r#"e = a_enum(Green); b = a_bool(true); f = a_float(2); s = a_string("x"); a = a_ann(a_int(1));"#,
+				InputLang::DataZinc,
+			)
+			.unwrap();
+		let value = |name| program.input_value(name).unwrap().to_string();
+		assert_eq!(value("e"), "a_enum(Green)");
+		assert_eq!(value("b"), "a_bool(true)");
+		assert_eq!(value("s"), r#"a_string("x")"#);
+		assert_eq!(value("a"), "a_ann(a_int(1))");
+		// Integers are coerced to the float parameter
+		let Some(Value::Ann(_, args)) = program.input_value("f") else {
+			panic!("expected annotation value")
+		};
+		assert_eq!(args, &[Value::Float(2.0)]);
+
+		let mut program = model().compile(&slv).unwrap();
+		let Err(Error::TypeMismatch(err)) =
+			program.add_data_str("b = a_bool(1);", InputLang::DataZinc)
+		else {
+			panic!("expected type mismatch")
+		};
+		assert_eq!(
+			err.msg,
+			"No declaration of annotation 'a_bool' matches the given arguments"
+		);
+
+		// Errors in JSON data refer to the assigned value
+		let src = r#"{"b": "a_int"}"#;
+		let Err(Error::TypeMismatch(err)) = program.add_data_str(src, InputLang::Json) else {
+			panic!("expected type mismatch")
+		};
+		let span = err.span.offset()..err.span.offset() + err.span.len();
+		assert_eq!(&src[span], r#""a_int""#);
+	}
+
+	#[test]
+	fn test_stdin_data() {
+		let dir = tempfile::tempdir().unwrap();
+		let model = dir.path().join("model.mzn");
+		fs::write(&model, "int: n;\narray [1..n] of var 0..n: x;").unwrap();
+		let stdin = [Path::new(STDIN_PATH)];
+
+		let slv = Solver::lookup("gecode").unwrap();
+		let mut program = Model::from_file(model.clone())
+			.compile(&slv)
+			.unwrap()
+			.with_stdin_reader(Cursor::new("n = 3;"));
+		program.add_data_files(stdin.into_iter()).unwrap();
+		assert_eq!(program.input_value("n"), Some(&Value::Integer(3)));
+
+		let mut program = Model::from_file(model.clone())
+			.compile(&slv)
+			.unwrap()
+			.with_stdin_lang(InputLang::Json)
+			.with_stdin_reader(Cursor::new(r#"{"n": 2}"#));
+		program.add_data_files(stdin.into_iter()).unwrap();
+		assert_eq!(program.input_value("n"), Some(&Value::Integer(2)));
+
+		let mut program = Model::from_file(model.clone())
+			.compile(&slv)
+			.unwrap()
+			.with_stdin_reader(Cursor::new("n = 1.5;"));
+		let Err(Error::TypeMismatch(err)) = program.add_data_files(stdin.into_iter()) else {
+			panic!("expected type mismatch")
+		};
+		assert_eq!(err.src.name().as_deref(), Some("<stdin>"));
+
+		let mut program = Model::from_file(model.clone())
+			.compile(&slv)
+			.unwrap()
+			.with_stdin_lang(InputLang::EPrime)
+			.with_stdin_reader(Cursor::new("letting n = 3"));
+		let Err(Error::UnsupportedDataLanguage(err)) = program.add_data_files(stdin.into_iter())
+		else {
+			panic!("expected unsupported data language error")
+		};
+		assert_eq!(err.lang, "EPrime");
+
+		// The data read while checking is used again when compiling
+		let model = Model::from_file(model)
+			.with_stdin_lang(InputLang::Json)
+			.with_stdin_reader(Cursor::new(r#"{"n": 1.5}"#));
+		let diagnostics = model.check_data(&[PathBuf::from(STDIN_PATH)]);
+		assert!(matches!(
+			diagnostics.iter().next(),
+			Some(Error::TypeMismatch(_))
+		));
+		let mut program = model.compile(&slv).unwrap();
+		assert!(program.add_data_files(stdin.into_iter()).is_err());
+	}
+
+	#[test]
+	fn test_check_data() {
+		let dir = tempfile::tempdir().unwrap();
+		let model = dir.path().join("model.mzn");
+		let bad_model = dir.path().join("bad_model.mzn");
+		let data = dir.path().join("data.dzn");
+		let bad_data = dir.path().join("bad_data.dzn");
+		let invalid_data = dir.path().join("invalid_data.dzn");
+		fs::write(&model, "int: n;\nbool: b;\narray [1..n] of var 0..n: x;").unwrap();
+		fs::write(&bad_model, "int: n;\nconstraint m;").unwrap();
+		fs::write(&data, "n = 3;").unwrap();
+		fs::write(&bad_data, "b = 1;").unwrap();
+		fs::write(&invalid_data, "n = ;").unwrap();
+
+		let diagnostics = Model::from_file(model.clone()).check_data(&[data.clone()]);
+		assert!(diagnostics.is_empty());
+
+		let diagnostics = Model::from_file(model).check_data(&[data.clone(), bad_data]);
+		assert_eq!(diagnostics.len(), 1);
+		assert!(matches!(
+			diagnostics.iter().next(),
+			Some(Error::TypeMismatch(_))
+		));
+
+		// Both model and data errors are reported
+		let diagnostics = Model::from_file(bad_model).check_data(&[data, invalid_data]);
+		assert!(diagnostics
+			.iter()
+			.any(|e| matches!(e, Error::UndefinedIdentifier(_))));
+		assert!(diagnostics
+			.iter()
+			.any(|e| matches!(e, Error::SyntaxError(_))));
+	}
+
+	#[test]
+	fn test_gzip_data_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let model = dir.path().join("model.mzn");
+		fs::write(
+			&model,
+			"int: n;\narray [1..n] of int: a;\narray [1..n] of var 0..n: x;",
+		)
+		.unwrap();
+		let gzip = |contents: &str| {
+			let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+			encoder.write_all(contents.as_bytes()).unwrap();
+			encoder.finish().unwrap()
+		};
+		let data = dir.path().join("data.dzn");
+		let compressed = dir.path().join("data.dzn.gz");
+		let multi_member = dir.path().join("multi_member.dzn.gz");
+		let bad_data = dir.path().join("bad_data.dzn.gz");
+		fs::write(&data, "n = 3;\na = [1, 2, 3];").unwrap();
+		fs::write(&compressed, gzip("n = 3;\na = [1, 2, 3];")).unwrap();
+		// Concatenated gzip files (e.g. as written by pigz) are decompressed as a whole
+		fs::write(
+			&multi_member,
+			[gzip("n = 3;\n"), gzip("a = [1, 2, 3];")].concat(),
+		)
+		.unwrap();
+		fs::write(&bad_data, gzip("n = 1.5;\na = [];")).unwrap();
+
+		let slv = Solver::lookup("gecode").unwrap();
+		let expected = compile_with_data(&[model.clone()], &[data], &slv).unwrap();
+		for file in [compressed, multi_member] {
+			let program = compile_with_data(&[model.clone()], &[file], &slv).unwrap();
+			for name in ["n", "a"] {
+				assert!(program.input_value(name).is_some());
+				assert_eq!(program.input_value(name), expected.input_value(name));
+			}
+		}
+
+		// Diagnostics refer to the decompressed contents
+		let Err(Error::TypeMismatch(err)) = compile_with_data(&[model], &[bad_data], &slv) else {
+			panic!("expected type mismatch")
+		};
+		assert_eq!(err.src.contents(), "n = 1.5;\na = [];");
+	}
+
+	#[test]
+	fn test_mzn_data_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let model = dir.path().join("model.mzn");
+		let data = dir.path().join("data.mzn");
+		fs::write(&model, "int: n;\nbool: b;\narray [1..n] of var 0..n: x;").unwrap();
+		fs::write(&data, "n = 3;\nconstraint true;\nb = false;").unwrap();
+
+		let slv = Solver::lookup("gecode").unwrap();
+		let program = compile_with_data(&[model], &[data], &slv).unwrap();
+		assert_eq!(program.input_value("n"), Some(&Value::Integer(3)));
+		assert_eq!(program.input_value("b"), Some(&Value::Boolean(false)));
+		assert_eq!(program.warnings().len(), 1);
+		assert!(matches!(program.warnings()[0], Warning::IgnoredDataItem(_)));
+	}
+}
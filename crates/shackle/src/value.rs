@@ -3,13 +3,17 @@
 use std::{
 	cmp::max,
 	fmt::{self, Display},
+	hash::{Hash, Hasher},
 	iter::FusedIterator,
 	ops::{Deref, RangeInclusive},
 	rc::Rc,
 	sync::{Arc, Mutex, MutexGuard},
 };
 
-use itertools::Itertools;
+use itertools::{EitherOrBoth, Itertools};
+use shackle_compiler::diagnostics::{InvalidValue, ValueConversionError};
+
+use crate::{data::consecutive_ranges, Error};
 
 /// Value types that can be part of a Solution
 #[derive(Debug, Clone, PartialEq)]
@@ -78,6 +82,79 @@ impl From<Record> for Value {
 	}
 }
 
+/// Implement conversion from a [`Value`] of the given variant, and from an
+/// optional value where [`Value::Absent`] becomes `None`
+macro_rules! impl_try_from_value {
+	($ty:ty, $expected:literal, $pat:pat => $conv:expr) => {
+		impl TryFrom<Value> for $ty {
+			type Error = Error;
+			fn try_from(value: Value) -> Result<Self, Self::Error> {
+				match value {
+					$pat => Ok($conv),
+					_ => Err(ValueConversionError {
+						value: value.to_string(),
+						expected: $expected.to_owned(),
+					}
+					.into()),
+				}
+			}
+		}
+		impl TryFrom<Value> for Option<$ty> {
+			type Error = Error;
+			fn try_from(value: Value) -> Result<Self, Self::Error> {
+				match value {
+					Value::Absent => Ok(None),
+					_ => value.try_into().map(Some),
+				}
+			}
+		}
+	};
+}
+
+impl_try_from_value!(bool, "bool", Value::Boolean(v) => v);
+impl_try_from_value!(i64, "int", Value::Integer(v) => v);
+impl_try_from_value!(f64, "float", Value::Float(v) => v);
+impl_try_from_value!(String, "string", Value::String(v) => v.to_string());
+impl_try_from_value!(Array, "array", Value::Array(v) => v);
+impl_try_from_value!(Set, "set", Value::Set(v) => v);
+impl_try_from_value!(Vec<Value>, "tuple", Value::Tuple(v) => v);
+impl_try_from_value!(Record, "record", Value::Record(v) => v);
+
+impl Value {
+	/// Returns whether this value is equal to `other`, where floating point numbers (including
+	/// the bounds of float sets) are considered equal if they differ by at most `epsilon`.
+	///
+	/// All other values are compared structurally, in the same way as [`PartialEq`].
+	pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+		let all_approx_eq = |a: &[Value], b: &[Value]| {
+			a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+		};
+		match (self, other) {
+			(Value::Float(a), Value::Float(b)) => float_approx_eq(*a, *b, epsilon),
+			(Value::Ann(a, xs), Value::Ann(b, ys)) => a == b && all_approx_eq(xs, ys),
+			(Value::Array(a), Value::Array(b)) => {
+				a.indices == b.indices && all_approx_eq(&a.members, &b.members)
+			}
+			(Value::Set(a), Value::Set(b)) => a.approx_eq(b, epsilon),
+			(Value::Tuple(a), Value::Tuple(b)) => all_approx_eq(a, b),
+			(Value::Record(a), Value::Record(b)) => {
+				a.len() == b.len()
+					&& a.fields.iter().all(|(k, v)| {
+						b.fields
+							.iter()
+							.any(|(k2, v2)| k == k2 && v.approx_eq(v2, epsilon))
+					})
+			}
+			_ => self == other,
+		}
+	}
+}
+
+/// Whether two floats differ by at most `epsilon` (infinities are only equal to themselves)
+fn float_approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+	a == b || (a - b).abs() <= epsilon
+}
+
 /// Whether an value is negative or positive
 ///
 /// For example, used for the constant infinity
@@ -175,7 +252,9 @@ impl Array {
 
 	/// Returns an iterator over the array and its indices.
 	///
-	/// The iterator yields all items from start to end.
+	/// The iterator yields all items in row-major order (i.e., the last index
+	/// changes fastest), together with the integer or enum index values at
+	/// which each item is found.
 	pub fn iter(&self) -> impl Iterator<Item = (Vec<Value>, &Value)> {
 		self.indices
 			.iter()
@@ -189,9 +268,8 @@ impl std::ops::Index<&[Value]> for Array {
 	type Output = Value;
 	fn index(&self, index: &[Value]) -> &Self::Output {
 		let mut idx = 0;
-		let mut mult = 1;
 		for (ii, ctx) in index.iter().zip_eq(self.indices.iter()) {
-			idx *= mult;
+			idx *= ctx.len();
 			match ctx {
 				Index::Integer(r) => {
 					if let Value::Integer(ii) = ii {
@@ -209,7 +287,11 @@ impl std::ops::Index<&[Value]> for Array {
 				Index::Enum(e) => {
 					if let Value::Enum(val) = ii {
 						if e.ty == val.ty {
-							idx += val.pos
+							assert!(
+								e.start <= val.pos && val.pos <= e.end,
+								"index out of bounds: the index set is {e} but the index is {val}"
+							);
+							idx += val.pos - e.start
 						} else {
 							panic!("incorrect index type: using value of type {} for an index of type {}", 
 							e.ty.name,
@@ -223,7 +305,6 @@ impl std::ops::Index<&[Value]> for Array {
 					}
 				}
 			}
-			mult *= ctx.len();
 		}
 		&self.members[idx]
 	}
@@ -317,7 +398,7 @@ impl Display for Index {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
 			Index::Integer(v) => write!(f, "{}..{}", v.start(), v.end()),
-			Index::Enum(v) => write!(f, "{v}"),
+			Index::Enum(v) => write!(f, "{}..{}", v.start(), v.end()),
 		}
 	}
 }
@@ -400,6 +481,16 @@ impl Enum {
 		self.lock().iter().next().is_none()
 	}
 
+	/// Returns the names of the members of the enumerated type, in order
+	///
+	/// The names are the same as those given by [`EnumValue::name`].
+	///
+	/// ## Warning
+	/// This function will panic if Enum type is uninitialized
+	pub fn members(self: &Arc<Self>) -> impl Iterator<Item = Arc<str>> + '_ {
+		(1..=self.len()).map(|pos| EnumValue::from_enum_and_pos(self.clone(), pos).name())
+	}
+
 	pub(crate) fn lock(&self) -> CtorLock {
 		CtorLock {
 			lock: self.state.lock().unwrap(),
@@ -529,6 +620,22 @@ impl EnumValue {
 		self.constructor_and_args().0
 	}
 
+	/// Returns the name of the value: its constructor, applied to its arguments if it has any
+	///
+	/// Values created by an anonymous constructor (`_`) are given the synthetic name
+	/// `<enum>_<position>`, which is stable as long as the definition of the enumerated type does
+	/// not change.
+	pub fn name(&self) -> Arc<str> {
+		let (c, args) = self.constructor_and_args();
+		if &*c == "_" {
+			format!("{}_{}", self.ty.name(), self.pos).into()
+		} else if args.is_empty() {
+			c
+		} else {
+			format!("{c}({})", args.iter().format(",")).into()
+		}
+	}
+
 	/// Returns the argument used to construct the value of the enumerated type
 	///
 	/// This method resturns [`None`] if no argument was used to construct the
@@ -677,18 +784,11 @@ impl From<(&EnumValue, &EnumValue)> for EnumRangeInclusive {
 
 impl Display for EnumRangeInclusive {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(
-			f,
-			"{}..{}",
-			EnumValue {
-				ty: self.ty.clone(),
-				pos: self.start,
-			},
-			EnumValue {
-				ty: self.ty.clone(),
-				pos: self.end,
-			},
-		)
+		if self.start == self.end {
+			// Singleton ranges are written as their only value
+			return write!(f, "{}", self.start());
+		}
+		write!(f, "{}..{}", self.start(), self.end())
 	}
 }
 
@@ -744,13 +844,19 @@ impl DoubleEndedIterator for EnumRangeInclusive {
 }
 impl ExactSizeIterator for EnumRangeInclusive {
 	fn len(&self) -> usize {
-		self.end - self.start + 1
+		(self.end + 1).saturating_sub(self.start)
 	}
 }
 impl FusedIterator for EnumRangeInclusive {}
 
 /// Different representations used to represent sets in [`Value`]
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Sets are compared (and hashed) by their members, regardless of how their ranges are
+/// segmented (e.g. `1..3` is equal to `1..2 ∪ {3}`). The ranges of each representation must be
+/// sorted by their start. Sets collected from an iterator of ranges are sorted when they are
+/// created, with empty ranges removed and overlapping (or for integer and enum sets, adjacent)
+/// ranges combined.
+#[derive(Debug, Clone)]
 pub enum Set {
 	/// Set that spans all members of an enumerated type
 	Enum(Vec<EnumRangeInclusive>),
@@ -774,11 +880,11 @@ impl FromIterator<EnumRangeInclusive> for Set {
 			.sorted_by_key(|r| r.start);
 		if let Some(r) = iter.next() {
 			let mut ranges = vec![r];
-			// Combine overlapping ranges
+			// Combine overlapping and adjacent ranges
 			for r in iter {
-				let last = ranges.last().unwrap();
-				if last.end >= r.start {
-					ranges.last_mut().unwrap().end = r.end
+				let last = ranges.last_mut().unwrap();
+				if last.end + 1 >= r.start {
+					last.end = max(last.end, r.end)
 				} else {
 					ranges.push(r)
 				}
@@ -808,7 +914,7 @@ impl FromIterator<RangeInclusive<f64>> for Set {
 			for r in iter {
 				let last = ranges.last().unwrap();
 				if last.end() >= r.start() {
-					*ranges.last_mut().unwrap() = *last.start()..=*r.end()
+					*ranges.last_mut().unwrap() = *last.start()..=r.end().max(*last.end())
 				} else {
 					ranges.push(r)
 				}
@@ -834,11 +940,11 @@ impl FromIterator<RangeInclusive<i64>> for Set {
 			.sorted_by_key(|r| *r.start());
 		if let Some(r) = iter.next() {
 			let mut ranges = vec![r];
-			// Combine overlapping ranges
+			// Combine overlapping and adjacent ranges
 			for r in iter {
 				let last = ranges.last().unwrap();
-				if last.end() >= r.start() {
-					*ranges.last_mut().unwrap() = *last.start()..=*r.end()
+				if last.end().saturating_add(1) >= *r.start() {
+					*ranges.last_mut().unwrap() = *last.start()..=max(*last.end(), *r.end())
 				} else {
 					ranges.push(r)
 				}
@@ -850,58 +956,178 @@ impl FromIterator<RangeInclusive<i64>> for Set {
 	}
 }
 
-impl Display for Set {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Set {
+	/// Returns the enum ranges with empty ranges removed and overlapping or adjacent ranges
+	/// combined, without allocating (the ranges must be sorted by their start)
+	fn coalesced_enums(
+		ranges: &[EnumRangeInclusive],
+	) -> impl Iterator<Item = EnumRangeInclusive> + '_ {
+		ranges
+			.iter()
+			.filter(|r| r.start <= r.end)
+			.cloned()
+			.coalesce(|a, b| {
+				if a.end + 1 >= b.start {
+					Ok(EnumRangeInclusive {
+						end: max(a.end, b.end),
+						..a
+					})
+				} else {
+					Err((a, b))
+				}
+			})
+	}
+
+	/// Returns the float ranges with empty ranges removed and overlapping ranges combined, without
+	/// allocating (the ranges must be sorted by their start)
+	fn coalesced_floats(
+		ranges: &[RangeInclusive<f64>],
+	) -> impl Iterator<Item = RangeInclusive<f64>> + '_ {
+		ranges
+			.iter()
+			.filter(|r| r.start() <= r.end())
+			.cloned()
+			.coalesce(|a, b| {
+				if a.end() >= b.start() {
+					Ok(*a.start()..=a.end().max(*b.end()))
+				} else {
+					Err((a, b))
+				}
+			})
+	}
+
+	/// Returns the integer ranges with empty ranges removed and overlapping or adjacent ranges
+	/// combined, without allocating (the ranges must be sorted by their start)
+	fn coalesced_ints(
+		ranges: &[RangeInclusive<i64>],
+	) -> impl Iterator<Item = RangeInclusive<i64>> + '_ {
+		ranges
+			.iter()
+			.filter(|r| r.start() <= r.end())
+			.cloned()
+			.coalesce(|a, b| {
+				if a.end().saturating_add(1) >= *b.start() {
+					Ok(*a.start()..=max(*a.end(), *b.end()))
+				} else {
+					Err((a, b))
+				}
+			})
+	}
+
+	/// Returns whether this set is equal to `other`, where the bounds of float ranges are
+	/// considered equal if they differ by at most `epsilon`.
+	fn approx_eq(&self, other: &Set, epsilon: f64) -> bool {
+		match (self, other) {
+			(Set::Float(a), Set::Float(b)) => Set::coalesced_floats(a)
+				.zip_longest(Set::coalesced_floats(b))
+				.all(|pair| match pair {
+					EitherOrBoth::Both(a, b) => {
+						float_approx_eq(*a.start(), *b.start(), epsilon)
+							&& float_approx_eq(*a.end(), *b.end(), epsilon)
+					}
+					_ => false,
+				}),
+			(a, b) => a == b,
+		}
+	}
+}
+
+impl PartialEq for Set {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Set::Enum(a), Set::Enum(b)) => Set::coalesced_enums(a).eq(Set::coalesced_enums(b)),
+			(Set::Float(a), Set::Float(b)) => Set::coalesced_floats(a).eq(Set::coalesced_floats(b)),
+			(Set::Int(a), Set::Int(b)) => Set::coalesced_ints(a).eq(Set::coalesced_ints(b)),
+			_ => false,
+		}
+	}
+}
+
+impl Hash for Set {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		// Ensure 0.0 and -0.0 (which are equal) hash the same
+		let float_bits = |f: f64| if f == 0.0 { 0 } else { f.to_bits() };
+		std::mem::discriminant(self).hash(state);
 		match self {
 			Set::Enum(ranges) => {
-				if ranges.is_empty() || (ranges.len() == 1 && ranges.last().unwrap().is_empty()) {
-					return write!(f, "∅");
+				for r in Set::coalesced_enums(ranges) {
+					r.ty.name.hash(state);
+					r.start.hash(state);
+					r.end.hash(state);
+				}
+			}
+			Set::Float(ranges) => {
+				for r in Set::coalesced_floats(ranges) {
+					float_bits(*r.start()).hash(state);
+					float_bits(*r.end()).hash(state);
 				}
-				write!(f, "{}", ranges.iter().format(" ∪ "))
 			}
 			Set::Int(ranges) => {
-				if ranges.is_empty() || (ranges.len() == 1 && ranges.last().unwrap().is_empty()) {
-					return write!(f, "∅");
+				for r in Set::coalesced_ints(ranges) {
+					r.hash(state);
 				}
-				write!(
-					f,
-					"{}",
-					ranges.iter().format_with(" ∪ ", |range, f| f(&format_args!(
-						"{}..{}",
-						range.start(),
-						range.end()
-					)))
-				)
+			}
+		}
+	}
+}
+
+impl Display for Set {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// Singleton ranges of every kind of set are written as set literals (e.g. `{5}`)
+		fn write_ranges<T: PartialEq + Display>(
+			f: &mut fmt::Formatter<'_>,
+			ranges: impl Iterator<Item = (T, T)>,
+		) -> fmt::Result {
+			write!(
+				f,
+				"{}",
+				ranges.format_with(" ∪ ", |(start, end), f| {
+					if start == end {
+						f(&format_args!("{{{}}}", start))
+					} else {
+						f(&format_args!("{}..{}", start, end))
+					}
+				})
+			)
+		}
+
+		let empty = match self {
+			Set::Enum(ranges) => {
+				ranges.is_empty() || (ranges.len() == 1 && ranges.last().unwrap().is_empty())
+			}
+			Set::Int(ranges) => {
+				ranges.is_empty() || (ranges.len() == 1 && ranges.last().unwrap().is_empty())
 			}
 			Set::Float(ranges) => {
-				if ranges.is_empty() || (ranges.len() == 1 && ranges.last().unwrap().is_empty()) {
-					return write!(f, "∅");
-				}
-				write!(
-					f,
-					"{}",
-					ranges.iter().format_with(" ∪ ", |range, f| f(&format_args!(
-						"{}..{}",
-						range.start(),
-						range.end()
-					)))
-				)
+				ranges.is_empty() || (ranges.len() == 1 && ranges.last().unwrap().is_empty())
 			}
+		};
+		if empty {
+			return write!(f, "∅");
+		}
+		match self {
+			Set::Enum(ranges) => write_ranges(f, ranges.iter().map(|r| (r.start(), r.end()))),
+			Set::Int(ranges) => write_ranges(f, ranges.iter().map(|r| (r.start(), r.end()))),
+			Set::Float(ranges) => write_ranges(f, ranges.iter().map(|r| (r.start(), r.end()))),
 		}
 	}
 }
 
 /// A value of a record type
-#[derive(Default, Debug, Clone, PartialEq)]
+///
+/// Fields are kept in the order in which they were given. Values resolved from data have their
+/// fields in the order of the fields of the record [`Type`](crate::Type), regardless of the order
+/// used in the data.
+#[derive(Default, Debug, Clone)]
 pub struct Record {
 	// fields are hidden to possibly replace inner implementation in the future
 	fields: Vec<(Arc<str>, Value)>,
 }
 
 impl Record {
-	/// Returns an iterator over the array and its indices.
+	/// Returns an iterator over the fields of the record.
 	///
-	/// The iterator yields all items from start to end.
+	/// The iterator yields the name and value of each field, in the order the fields were given.
 	pub fn iter(&self) -> impl Iterator<Item = (Arc<str>, &Value)> {
 		self.fields.iter().map(|(k, v)| (k.clone(), v))
 	}
@@ -919,8 +1145,7 @@ impl Record {
 
 impl FromIterator<(Arc<str>, Value)> for Record {
 	fn from_iter<T: IntoIterator<Item = (Arc<str>, Value)>>(iter: T) -> Self {
-		let mut fields: Vec<(Arc<str>, Value)> = iter.into_iter().collect();
-		fields.sort_by(|(k1, _), (k2, _)| k1.as_ref().cmp(k2.as_ref()));
+		let fields: Vec<(Arc<str>, Value)> = iter.into_iter().collect();
 		assert!(!fields.is_empty(), "empty record literals are not allowed");
 		Self { fields }
 	}
@@ -939,28 +1164,436 @@ impl std::ops::Index<&str> for Record {
 	}
 }
 
+impl PartialEq for Record {
+	fn eq(&self, other: &Self) -> bool {
+		// Records are equal regardless of the order of their fields
+		self.len() == other.len()
+			&& self
+				.fields
+				.iter()
+				.all(|(k, v)| other.fields.iter().any(|(k2, v2)| k == k2 && v == v2))
+	}
+}
+
 impl Display for Record {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(
 			f,
 			"({})",
-			&self
-				.fields
+			self.fields
 				.iter()
-				.map(|(k, v)| format!("{}: {}", *k, v))
-				.format(", ")
+				.format_with(", ", |(k, v), f| f(&format_args!("{k}: {v}")))
 		)
 	}
 }
 
+/// Helper for constructing [`Value`]s, which checks the invariants of arrays, records and
+/// enumerated type members.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueBuilder;
+
+impl ValueBuilder {
+	/// Create a Boolean value
+	pub fn bool(v: bool) -> Value {
+		Value::Boolean(v)
+	}
+
+	/// Create an integer value
+	pub fn int(v: i64) -> Value {
+		Value::Integer(v)
+	}
+
+	/// Create a floating point value
+	///
+	/// Returns an error if `v` is NaN.
+	pub fn float(v: f64) -> Result<Value, Error> {
+		if v.is_nan() {
+			return Err(InvalidValue {
+				msg: "NaN is not a valid float value".to_owned(),
+			}
+			.into());
+		}
+		Ok(Value::Float(v))
+	}
+
+	/// Create a string value
+	pub fn string(v: &str) -> Value {
+		Value::String(v.into())
+	}
+
+	/// Create an array with the given index sets and members (in row-major order)
+	///
+	/// Returns an error if the number of members does not match the size of the index sets, or if
+	/// the (non-absent) members are not all of the same kind.
+	pub fn array(indices: Vec<Index>, members: Vec<Value>) -> Result<Value, Error> {
+		let size = indices.iter().map(|i| i.len()).product::<usize>();
+		if size != members.len() {
+			return Err(InvalidValue {
+				msg: format!(
+					"the index sets {} have {} elements, but {} members were given",
+					indices.iter().format(", "),
+					size,
+					members.len()
+				),
+			}
+			.into());
+		}
+		let mut kinds = members
+			.iter()
+			.filter(|m| !matches!(m, Value::Absent))
+			.map(std::mem::discriminant);
+		if let Some(first) = kinds.next() {
+			if kinds.any(|k| k != first) {
+				return Err(InvalidValue {
+					msg: "array members must all be of the same type".to_owned(),
+				}
+				.into());
+			}
+		}
+		Ok(Value::Array(Array::new(indices, members)))
+	}
+
+	/// Create a set of integers containing the given members
+	pub fn set_of_ints(members: impl IntoIterator<Item = i64>) -> Value {
+		Value::Set(Set::Int(
+			consecutive_ranges(members).map(|(a, b)| a..=b).collect(),
+		))
+	}
+
+	/// Create a record with the given fields
+	///
+	/// Returns an error if there are no fields, or if a field name is used more than once.
+	pub fn record<S: Into<Arc<str>>>(
+		fields: impl IntoIterator<Item = (S, Value)>,
+	) -> Result<Value, Error> {
+		let fields = fields
+			.into_iter()
+			.map(|(k, v)| (k.into(), v))
+			.collect::<Vec<(Arc<str>, Value)>>();
+		if fields.is_empty() {
+			return Err(InvalidValue {
+				msg: "records must have at least one field".to_owned(),
+			}
+			.into());
+		}
+		if let Some((name, _)) = fields.iter().duplicates_by(|(k, _)| k.clone()).next() {
+			return Err(InvalidValue {
+				msg: format!("the field '{}' is given more than once", name),
+			}
+			.into());
+		}
+		Ok(Value::Record(fields.into_iter().collect()))
+	}
+
+	/// Create the member of the enumerated type `ty` with the given name
+	///
+	/// Returns an error if the enumerated type has not been defined, or if it has no member with
+	/// the given name.
+	pub fn enum_member(ty: &Arc<Enum>, name: &str) -> Result<Value, Error> {
+		if !matches!(*ty.state.lock().unwrap(), EnumInner::Constructors(_)) {
+			return Err(InvalidValue {
+				msg: format!("the enumerated type '{}' has not been defined", ty.name()),
+			}
+			.into());
+		}
+		let pos = ty.members().position(|m| &*m == name).ok_or_else(|| {
+			Error::from(InvalidValue {
+				msg: format!(
+					"'{}' is not a member of the enumerated type '{}'",
+					name,
+					ty.name()
+				),
+			})
+		})?;
+		Ok(Value::Enum(EnumValue::from_enum_and_pos(
+			ty.clone(),
+			pos + 1,
+		)))
+	}
+}
+
 #[cfg(test)]
 mod tests {
+	use std::sync::Arc;
+
+	use expect_test::expect;
 	use itertools::Itertools;
 
-	use crate::value::Array;
+	use crate::value::{
+		Array, Enum, EnumInner, EnumRangeInclusive, EnumValue, Index, Record, Set, Value,
+		ValueBuilder,
+	};
 
 	#[test]
 	fn test_array_iter() {
 		assert_eq!(Array::empty().iter().collect_vec(), Vec::new());
+
+		let color = Arc::new(Enum::from_data("Color".into()));
+		*color.state.lock().unwrap() = EnumInner::Constructors(
+			["Red", "Green", "Blue"]
+				.into_iter()
+				.map(|c| (c.into(), Vec::new().into_boxed_slice(), 1))
+				.collect(),
+		);
+		let value = |pos| EnumValue::from_enum_and_pos(color.clone(), pos);
+		let arr = Array::new(
+			vec![
+				Index::Enum(EnumRangeInclusive::new(value(1), value(2))),
+				Index::Enum(EnumRangeInclusive::new(value(2), value(3))),
+			],
+			(1..=4).map(Value::Integer).collect(),
+		);
+		let members = arr.iter().collect_vec();
+		expect!("(Red, Green): 1, (Red, Blue): 2, (Green, Green): 3, (Green, Blue): 4").assert_eq(
+			&members
+				.iter()
+				.map(|(ii, x)| format!("({}): {x}", ii.iter().format(", ")))
+				.join(", "),
+		);
+		for (ii, x) in members {
+			assert_eq!(&arr[&ii[..]], x);
+		}
+	}
+
+	#[test]
+	fn test_set_equality() {
+		use std::{
+			collections::hash_map::DefaultHasher,
+			hash::{Hash, Hasher},
+		};
+
+		let hash = |s: &Set| {
+			let mut hasher = DefaultHasher::new();
+			s.hash(&mut hasher);
+			hasher.finish()
+		};
+
+		let a = Set::Int(vec![1..=3]);
+		let b = Set::Int(vec![1..=2, 3..=3]);
+		// Unsorted ranges are normalized when the set is created
+		let c = Set::from_iter([3..=3, 5..=4, 1..=2, 2..=2]);
+		assert!(matches!(&c, Set::Int(r) if r == &[1..=3]));
+		assert_eq!(a, b);
+		assert_eq!(a, c);
+		assert_eq!(hash(&a), hash(&b));
+		assert_eq!(hash(&a), hash(&c));
+		assert_ne!(a, Set::Int(vec![1..=2, 4..=4]));
+		assert_eq!(Set::from_iter([1..=10, 2..=3]), Set::Int(vec![1..=10]));
+
+		let f = Set::Float(vec![1.0..=2.0, 1.5..=3.0]);
+		let g = Set::Float(vec![1.0..=3.0]);
+		assert_eq!(f, g);
+		assert_eq!(hash(&f), hash(&g));
+		assert_ne!(Set::Float(vec![1.0..=2.0, 2.5..=3.0]), g);
+
+		let color = Arc::new(Enum::from_data("Color".into()));
+		*color.state.lock().unwrap() = EnumInner::Constructors(
+			["Red", "Green", "Blue"]
+				.into_iter()
+				.map(|c| (c.into(), Vec::new().into_boxed_slice(), 1))
+				.collect(),
+		);
+		let value = |pos| EnumValue::from_enum_and_pos(color.clone(), pos);
+		let all = Set::Enum(vec![EnumRangeInclusive::new(value(1), value(3))]);
+		let split = Set::Enum(vec![
+			EnumRangeInclusive::new(value(1), value(2)),
+			EnumRangeInclusive::new(value(3), value(3)),
+		]);
+		assert_eq!(
+			Set::from_iter([
+				EnumRangeInclusive::new(value(3), value(3)),
+				EnumRangeInclusive::new(value(1), value(2)),
+			]),
+			all
+		);
+		assert_eq!(all, split);
+		assert_eq!(hash(&all), hash(&split));
+		assert_eq!(
+			Value::Set(Set::Int(vec![1..=1, 2..=2])),
+			Value::Set(Set::Int(vec![1..=2]))
+		);
+	}
+
+	#[test]
+	fn test_value_builder() {
+		let point = |x, y| {
+			ValueBuilder::record([
+				("x", ValueBuilder::int(x)),
+				("y", ValueBuilder::float(y).unwrap()),
+			])
+			.unwrap()
+		};
+		let row = |i| {
+			ValueBuilder::array(
+				vec![Index::Integer(1..=2)],
+				vec![point(i, 0.5), point(i, 1.5)],
+			)
+			.unwrap()
+		};
+		let arr = ValueBuilder::array(vec![Index::Integer(0..=1)], vec![row(1), row(2)]).unwrap();
+		expect!("[0: [(x: 1, y: 0.5), (x: 1, y: 1.5)], [(x: 2, y: 0.5), (x: 2, y: 1.5)]]")
+			.assert_eq(&arr.to_string());
+		expect!("1..3 ∪ {5}").assert_eq(&ValueBuilder::set_of_ints([5, 1, 2, 3]).to_string());
+
+		let err = ValueBuilder::array(vec![Index::Integer(1..=3)], vec![ValueBuilder::int(1)])
+			.unwrap_err();
+		expect!("Invalid value: the index sets 1..3 have 3 elements, but 1 members were given")
+			.assert_eq(&err.to_string());
+		let err = ValueBuilder::array(
+			vec![Index::Integer(1..=2)],
+			vec![ValueBuilder::int(1), ValueBuilder::bool(true)],
+		)
+		.unwrap_err();
+		expect!("Invalid value: array members must all be of the same type")
+			.assert_eq(&err.to_string());
+		let err = ValueBuilder::record([("a", ValueBuilder::int(1)), ("a", ValueBuilder::int(2))])
+			.unwrap_err();
+		expect!("Invalid value: the field 'a' is given more than once").assert_eq(&err.to_string());
+
+		let color = Arc::new(Enum::from_data("Color".into()));
+		let err = ValueBuilder::enum_member(&color, "Red").unwrap_err();
+		expect!("Invalid value: the enumerated type 'Color' has not been defined")
+			.assert_eq(&err.to_string());
+		*color.state.lock().unwrap() = EnumInner::Constructors(
+			["Red", "Green", "Blue"]
+				.into_iter()
+				.map(|c| (c.into(), Vec::new().into_boxed_slice(), 1))
+				.collect(),
+		);
+		assert_eq!(
+			ValueBuilder::enum_member(&color, "Green").unwrap(),
+			Value::Enum(EnumValue::from_enum_and_pos(color.clone(), 2))
+		);
+		let err = ValueBuilder::enum_member(&color, "Purple").unwrap_err();
+		expect!("Invalid value: 'Purple' is not a member of the enumerated type 'Color'")
+			.assert_eq(&err.to_string());
+	}
+
+	#[test]
+	fn test_try_from_value() {
+		let n: i64 = Value::Integer(3).try_into().unwrap();
+		assert_eq!(n, 3);
+		let b: Option<bool> = Value::Absent.try_into().unwrap();
+		assert_eq!(b, None);
+		let b: Option<bool> = Value::Boolean(true).try_into().unwrap();
+		assert_eq!(b, Some(true));
+		let t: Vec<Value> = Value::Tuple(vec![Value::Integer(1), Value::Float(2.5)])
+			.try_into()
+			.unwrap();
+		assert_eq!(t, vec![Value::Integer(1), Value::Float(2.5)]);
+
+		let err = f64::try_from(Value::Integer(1)).unwrap_err();
+		expect!("Expected float but found 1").assert_eq(&err.to_string());
+		let err = i64::try_from(Value::Absent).unwrap_err();
+		expect!("Expected int but found <>").assert_eq(&err.to_string());
+	}
+
+	#[test]
+	fn test_enum_set_display() {
+		let color = Arc::new(Enum::from_data("Color".into()));
+		*color.state.lock().unwrap() = EnumInner::Constructors(
+			["Red", "Green", "Blue"]
+				.into_iter()
+				.map(|c| (c.into(), Vec::new().into_boxed_slice(), 1))
+				.collect(),
+		);
+		let value = |pos| EnumValue::from_enum_and_pos(color.clone(), pos);
+
+		let range = EnumRangeInclusive::new(value(1), value(2));
+		expect!("Red..Green").assert_eq(&range.to_string());
+		let singleton = EnumRangeInclusive::new(value(3), value(3));
+		expect!("Blue").assert_eq(&singleton.to_string());
+
+		expect!("Red..Green").assert_eq(&Value::Set(range.clone().into()).to_string());
+		expect!("{Blue}").assert_eq(&Value::Set(singleton.clone().into()).to_string());
+		expect!("Red..Green ∪ {Blue}")
+			.assert_eq(&Value::Set(Set::Enum(vec![range, singleton])).to_string());
+	}
+
+	#[test]
+	fn test_record_display() {
+		let rec: Record = [
+			("b".into(), Value::Integer(1)),
+			("a".into(), Value::Float(2.5)),
+		]
+		.into_iter()
+		.collect();
+		expect!("(b: 1, a: 2.5)").assert_eq(&rec.to_string());
+
+		let nested: Record = [
+			("x".into(), Value::Record(rec.clone())),
+			("y".into(), Value::Boolean(true)),
+		]
+		.into_iter()
+		.collect();
+		expect!("(x: (b: 1, a: 2.5), y: true)").assert_eq(&nested.to_string());
+
+		// Field order does not affect equality
+		let fields = rec.iter().map(|(k, v)| (k, v.clone())).collect_vec();
+		let reversed: Record = fields.into_iter().rev().collect();
+		assert_eq!(rec, reversed);
+	}
+
+	#[test]
+	fn test_approx_eq() {
+		let record = |a: f64, b: i64| -> Value {
+			Value::Record(
+				[
+					("a".into(), Value::Float(a)),
+					("b".into(), Value::Integer(b)),
+				]
+				.into_iter()
+				.collect(),
+			)
+		};
+		let expected = record(1.0, 2);
+		assert!(expected.approx_eq(&record(1.0 + 1e-10, 2), 1e-9));
+		assert!(expected.approx_eq(&record(1.0 - 1e-10, 2), 1e-9));
+		assert!(!expected.approx_eq(&record(1.0 + 1e-8, 2), 1e-9));
+		assert!(!expected.approx_eq(&record(1.0, 3), 1e-9));
+
+		// Nested values are compared recursively
+		let nested = |a: f64| -> Value {
+			Value::Tuple(vec![
+				Value::Array(Array::new(
+					vec![Index::Integer(1..=2)],
+					vec![record(a, 2), record(2.0, 2)],
+				)),
+				Value::Set((0.5..=a).into()),
+			])
+		};
+		assert!(nested(1.0).approx_eq(&nested(1.0 + 1e-10), 1e-9));
+		assert!(!nested(1.0).approx_eq(&nested(1.1), 1e-9));
+
+		// Sets are compared regardless of how their ranges are segmented
+		let split = Value::Set(Set::Float(vec![0.5..=0.75, 0.75..=1.0 + 1e-10]));
+		assert!(split.approx_eq(&Value::Set((0.5..=1.0).into()), 1e-9));
+	}
+
+	#[test]
+	fn test_enum_member_names() {
+		let ty = Arc::new(Enum::from_data("E".into()));
+		*ty.state.lock().unwrap() = EnumInner::Constructors(
+			vec![
+				("A".into(), Vec::new().into_boxed_slice(), 1),
+				(
+					"B".into(),
+					vec![Index::Integer(1..=2)].into_boxed_slice(),
+					2,
+				),
+				(
+					"_".into(),
+					vec![Index::Integer(1..=2)].into_boxed_slice(),
+					2,
+				),
+			]
+			.into_boxed_slice(),
+		);
+
+		expect!("A, B(1), B(2), E_4, E_5").assert_eq(&ty.members().join(", "));
+		let value = EnumValue::from_enum_and_pos(ty.clone(), 3);
+		expect!("B(2)").assert_eq(&value.name());
+		let anon = EnumValue::from_enum_and_pos(ty.clone(), 5);
+		expect!("E_5").assert_eq(&anon.name());
 	}
 }